@@ -1,169 +1,225 @@
-use clap::{App, Arg};
+use clap::Parser;
 
 use card_counter::{
-  commands::Command,
-  database::{aws::Aws, azure::Azure, config::Config, json::JSON, Database, DatabaseType, Entry},
-  errors::Result,
+  cli::{write_completions, AuthCommands, Cli, Commands, DbCommands, HistoryCommands, ScoreArgs},
+  commands::{
+    auth,
+    prune::enforce_retention,
+    thresholds::{check_thresholds, exit_code, Threshold, WIP_EXIT_BIT},
+    webhook::{self, WebhookOptions},
+    Command,
+  },
+  database::{config::Config, init_database, Database, Entry},
+  errors::{eyre, AuthError, Result},
+  kanban::init_kanban_board_from_config,
+  score::{filter_decks, ListFilter},
 };
 
-fn cli<'a>() -> clap::ArgMatches<'a> {
-  App::new("card-counter")
-    .version(env!("CARGO_PKG_VERSION"))
-    .author("Justin Barclay <justincbarclay@gmail.com>")
-    .about("A CLI for quickly summarizing story points in Trello lists")
-    .arg(
-      Arg::with_name("kanban")
-        .short("k")
-        .long("kanban")
-        .value_name("KANBAN")
-        .help("The kanban API to get your board and card information from")
-        .possible_values(&["jira", "trello"])
-        .takes_value(true),
-    )
-    .arg(
-      Arg::with_name("board_id")
-        .short("b")
-        .long("board-id")
-        .value_name("ID")
-        .help("The ID of the board where the cards are meant to be counted from")
-        .takes_value(true),
-    )
-    .arg(
-      Arg::with_name("filter")
-        .short("f")
-        .long("filter")
-        .value_name("FILTER")
-        .help("Filters out all lists with a name that contains the substring FILTER")
-        .takes_value(true),
-    )
-    .arg(
-      Arg::with_name("save")
-        .short("s")
-        .long("save")
-        .value_name("SAVE")
-        .help("Save the current entry in the database")
-        .default_value("true")
-        .possible_values(&["true", "false"])
-        .takes_value(true),
-    )
-    .arg(
-      Arg::with_name("database")
-        .short("d")
-        .long("database")
-        .value_name("DATABASE")
-        .help("Choose the database you want to save current request in")
-        .possible_values(&["local", "aws", "azure"])
-        .takes_value(true),
-    )
-    .arg(
-      Arg::with_name("compare")
-        .short("c")
-        .long("compare")
-        .help("Compares the current trello board with a previous entry"),
-    )
-    .subcommand(
-      clap::SubCommand::with_name("config").about("Edit properties associated with card-counter."),
-    )
-    .subcommand(
-      clap::SubCommand::with_name("burndown")
-        .about("Parses data for a board and prints out data to be piped to gnuplot")
-        .arg(
-          Arg::with_name("board_id")
-            .short("b")
-            .long("board-id")
-            .value_name("ID")
-            .help("The ID of the board where the cards are meant to be counted from")
-            .takes_value(true),
-        )
-        .arg(
-          Arg::with_name("start")
-            .short("s")
-            .long("start")
-            .value_name("START-DATE")
-            .required(true)
-            .help("Start of the Date Range for the Burndown Chart (yyyy-mm-dd)")
-            .takes_value(true),
-        )
-        .arg(
-          Arg::with_name("end")
-            .short("e")
-            .long("end")
-            .value_name("END-DATE")
-            .required(true)
-            .help("End of the Date Range for the Burndown Chart (yyyy-mm-dd)")
-            .takes_value(true),
-        )
-        .arg(
-          Arg::with_name("database")
-            .short("d")
-            .long("database")
-            .value_name("DATABASE")
-            .default_value("local")
-            .help("Choose the database you want to save current request in")
-            .possible_values(&["local", "aws", "azure"])
-            .takes_value(true),
-        )
-        .arg(
-          Arg::with_name("filter")
-            .short("f")
-            .long("filter")
-            .value_name("FILTER")
-            .help("Filters out all lists with a name that contains the substring FILTER")
-            .takes_value(true),
-        )
-        .arg(
-          Arg::with_name("output")
-            .short("o")
-            .long("output")
-            .value_name("OUTPUT")
-            .help("Filters out all lists with a name that contains the substring FILTER")
-            .possible_values(&["ascii", "csv", "svg"])
-            .default_value("csv")
-            .takes_value(true),
-        ),
-    )
-    .get_matches()
+// TODO refactor database checking into each command,
+// the command can worry about if and when to open or verify database connection
+async fn open_database(command: &Command, database: Option<&str>) -> Result<Box<dyn Database>> {
+  init_database(&command.check_for_database(database)?, command.profile()).await
 }
 
-// Run all of network code asynchronously using tokio and await
-async fn run() -> Result<()> {
-  // TODO: Pull this out to yaml at some point
-  let matches = cli();
+/// Prints the score for a board and saves it as an entry, exiting with a non-zero status when
+/// it's over any of the thresholds passed in
+async fn score(
+  command: &Command,
+  args: ScoreArgs,
+  ascii: bool,
+  database: Box<dyn Database>,
+) -> Result<()> {
+  let thresholds = Threshold::from_args(&args);
+  let config = Config::init(args.kanban.as_deref(), command.profile())?;
+  let (board, decks, cards) = Command::show_score(&config, &args, ascii, database.as_ref()).await?;
+  let violations = check_thresholds(
+    &thresholds,
+    &filter_decks(&decks, &ListFilter::from_args(&args.filter)?),
+    &config.done_matcher()?,
+  );
+
+  if args.save {
+    database
+      .add_entry(Entry {
+        board_id: board.id.clone(),
+        board_name: board.name,
+        time_stamp: Entry::get_current_timestamp()?,
+        decks,
+        tags: args.tag,
+        cards,
+      })
+      .await?;
+    enforce_retention(database.as_ref(), &config, &board.id).await?;
+  };
 
-  // Setting up config requires little access
-  if matches.subcommand_matches("config").is_some() {
-    Config::init(None)?.update_file()?;
-    std::process::exit(0)
+  if !violations.is_empty() {
+    violations
+      .iter()
+      .for_each(|(_, violation)| eprintln!("{}", violation));
+    std::process::exit(exit_code(
+      violations.iter().map(|(threshold, _)| *threshold),
+    ));
   }
 
-  // TODO refactor database checking into each command,
-  // the command can worry about if and when to open or verify database connection
-  let database: Box<dyn Database> = match Command::check_for_database(matches.value_of("database"))?
-  {
-    DatabaseType::Aws => Box::new(Aws::init(&Config::init(None)?).await?),
-    DatabaseType::Azure => Box::new(Azure::init(&Config::init(None)?).await?),
-    DatabaseType::Local => Box::new(JSON::init()?),
-  };
+  Ok(())
+}
 
-  if let Some(matches) = matches.subcommand_matches("burndown") {
-    Command::output_burndown(matches, database).await?;
-  } else {
-    let (board, decks) = Command::show_score(
-      &Config::init(matches.value_of("kanban"))?,
-      &matches,
-      &database,
-    )
-    .await?;
+/// Commands that save to the global config file would write a profile's settings over it
+fn global_only(command: &Command, name: &str) -> Result<()> {
+  match Config::profile_name(command.profile()) {
+    Some(profile) => Err(eyre!(
+      "{} saves to your global config file and can't be used with the {} profile",
+      name,
+      profile
+    )),
+    None => Ok(()),
+  }
+}
 
-    if matches.is_present("save") && matches.value_of("save").unwrap() == "true" {
-      database
-        .add_entry(Entry {
-          board_id: board.id,
-          time_stamp: Entry::get_current_timestamp()?,
-          decks,
-        })
-        .await?;
-    };
+// Run all of network code asynchronously using tokio and await
+async fn run(cli: Cli) -> Result<()> {
+  let (database, ascii) = (cli.database.clone(), cli.ascii);
+  let database = database.as_deref();
+  let command = Command::new(cli.profile.clone());
+
+  match cli.into_command() {
+    // Setting up config requires little access
+    Commands::Config => {
+      global_only(&command, "config")?;
+      Config::from_global_file_or_default()?.update_file()?
+    }
+    Commands::Completions(args) => write_completions(args.shell, &mut std::io::stdout()),
+    Commands::Auth(AuthCommands::Trello(args)) => {
+      global_only(&command, "auth trello")?;
+      auth::trello(&args).await?
+    }
+    Commands::Boards(args) => command.boards(&args).await?,
+    Commands::Lists(args) => command.lists(&args).await?,
+    Commands::CompareBoards(args) => command.compare_boards(&args).await?,
+    Commands::Cycletime(args) => command.cycletime(&args).await?,
+    Commands::TimeInStatus(args) => command.time_in_status(&args).await?,
+    Commands::Review(args) => command.review(&args).await?,
+    Commands::Retro(args) => command.retro(&args).await?,
+    Commands::Due(args) => command.due(&args).await?,
+    Commands::Wip(args) => {
+      let violations = command.wip(&args).await?;
+      std::process::exit(if violations.is_empty() {
+        0
+      } else {
+        WIP_EXIT_BIT
+      })
+    }
+    Commands::Velocity(args) => command.velocity(&args).await?,
+    Commands::Annotate(args) => {
+      global_only(&command, "annotate")?;
+      command.annotate(&args).await?
+    }
+    Commands::Db(DbCommands::Check(args)) => command.check_database(database, &args).await?,
+    Commands::Burndown(args) => {
+      command
+        .output_burndown(&args, ascii, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Scope(args) => {
+      command
+        .scope(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Report(args) => {
+      command
+        .report(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Forecast(args) => {
+      command
+        .forecast(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Anomalies(args) => {
+      command
+        .anomalies(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Export(args) => {
+      command
+        .export(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Diff(args) => {
+      command
+        .diff(&args, ascii, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Backfill(args) => {
+      command
+        .backfill(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Aging(args) => {
+      command
+        .aging(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Accuracy(args) => {
+      command
+        .accuracy(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Summary(args) => {
+      command
+        .summary(&args, ascii, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Snapshot(args) => {
+      command
+        .snapshot(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Watch(args) => {
+      command
+        .watch(&args, ascii, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Rescore(args) => {
+      command
+        .rescore(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Backup(args) => {
+      command
+        .backup(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Restore(args) => {
+      command
+        .restore(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::History(HistoryCommands::Prune(args)) => {
+      command
+        .prune(&args, open_database(&command, database).await?)
+        .await?
+    }
+    Commands::Webhook(args) => {
+      let config = Config::init(Some("jira"), command.profile())?;
+      let kanban = init_kanban_board_from_config(&config)?;
+      webhook::listen(
+        kanban,
+        open_database(&command, database).await?,
+        &config,
+        WebhookOptions::from_args(&args),
+      )
+      .await?;
+    }
+    Commands::Score(args) => {
+      score(
+        &command,
+        args,
+        ascii,
+        open_database(&command, database).await?,
+      )
+      .await?
+    }
   }
 
   Ok(())
@@ -173,6 +229,17 @@ async fn run() -> Result<()> {
 // formatted.
 #[tokio::main]
 async fn main() -> Result<()> {
-  run().await?;
+  let cli = Cli::parse();
+  let profile = cli.profile.clone();
+  if let Err(err) = run(cli).await {
+    if let Some(AuthError::TrelloExpired { .. }) = err.downcast_ref::<AuthError>() {
+      eprintln!("{}", err);
+      if Config::renew_trello_token(profile.as_deref())? {
+        println!("Your Trello token has been updated, please run card-counter again.");
+      }
+      std::process::exit(1)
+    }
+    return Err(err);
+  }
   Ok(())
 }
@@ -0,0 +1,844 @@
+use crate::database::config::TRELLO_TOKEN_EXPIRATION;
+
+use clap::{
+  builder::PossibleValuesParser, ArgAction, ArgGroup, Args, CommandFactory, Parser, Subcommand,
+};
+use clap_complete::Shell;
+use std::io::Write;
+
+const KANBANS: [&str; 7] = [
+  "jira",
+  "trello",
+  "gitlab",
+  "asana",
+  "notion",
+  "monday",
+  "localfile",
+];
+const DATABASES: [&str; 6] = ["local", "aws", "azure", "azure-blob", "s3", "redis"];
+
+/// The arguments card-counter was run with. Without a subcommand card-counter scores a board, the
+/// same as `card-counter score`, so the score flags can't be mixed with another subcommand.
+#[derive(Parser, Debug)]
+#[command(
+  name = "card-counter",
+  version,
+  author = "Justin Barclay <justincbarclay@gmail.com>",
+  about = "A CLI for quickly summarizing story points in Trello lists",
+  args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Commands>,
+  #[command(flatten)]
+  pub score: ScoreArgs,
+  /// Choose the database you want to save current request in
+  #[arg(short, long, value_name = "DATABASE", value_parser = DATABASES, global = true)]
+  pub database: Option<String>,
+  /// Keeps tables and charts to plain ASCII without colour, for logs, CI, and screen readers
+  #[arg(long, global = true)]
+  pub ascii: bool,
+  /// Uses the settings in the profile NAME from your config, the same as setting CARD_COUNTER_PROFILE
+  #[arg(long, value_name = "NAME", global = true)]
+  pub profile: Option<String>,
+}
+
+impl Cli {
+  /// The subcommand to run, scoring the board when none was given
+  pub fn into_command(self) -> Commands {
+    self.command.unwrap_or(Commands::Score(self.score))
+  }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+  /// Scores the lists on a board, and saves the score as an entry
+  Score(ScoreArgs),
+  /// Edit properties associated with card-counter.
+  Config,
+  /// Authorize card-counter with a kanban provider through your browser
+  #[command(subcommand)]
+  Auth(AuthCommands),
+  /// Records an event, such as "scope cut", that is drawn on burndown charts
+  Annotate(AnnotateArgs),
+  /// Lists the id and name of every board your credentials can see
+  Boards(BoardsArgs),
+  /// Lists the name, id, cards, score, and state of every list on a board
+  Lists(BoardArgs),
+  /// Scores two boards and compares their lists side by side
+  CompareBoards(CompareBoardsArgs),
+  /// Shows the average and median time cards spend in each list, and their lead and cycle times
+  Cycletime(BoardArgs),
+  /// Shows how long cards spend in each status and which ones are stuck
+  TimeInStatus(TimeInStatusArgs),
+  /// Looks through saved entries for scope jumps, stalls, and climbing unscored cards
+  Anomalies(AnomaliesArgs),
+  /// Lists finished cards where the correction was far off from the estimate
+  Review(ReviewArgs),
+  /// Compares estimated to corrected points by list and for the whole board
+  Retro(RetroArgs),
+  /// Shows the points on each list that are overdue, due this week, or have no due date
+  Due(BoardArgs),
+  /// Checks each list against the WIP limits in your config, exiting with 4 when one is over
+  Wip(WipArgs),
+  /// Shows how many points each person finished per sprint
+  Velocity(VelocityArgs),
+  /// Listens for Jira webhooks and saves an entry whenever an issue changes
+  Webhook(WebhookArgs),
+  /// Exports saved entries for a board so they can be loaded into other tools
+  Export(ExportArgs),
+  /// Compares two saved entries for a board, using only the database
+  Diff(DiffArgs),
+  /// Rebuilds a daily entry for each day since a date from the board's history of card moves
+  Backfill(BackfillArgs),
+  /// Lists the cards that haven't reached a done list in several saved entries in a row
+  Aging(AgingArgs),
+  /// Compares estimates to corrections across saved history, showing the mean absolute error and bias of each board
+  Accuracy(AccuracyArgs),
+  /// Prints a row for each of several boards with how much is done and which way it's trending
+  Summary(SummaryArgs),
+  /// Saves an entry for each board and prints a line of JSON for it, without prompting. Made for cron jobs and CI
+  Snapshot(SnapshotArgs),
+  /// Stays running, scoring a board and saving an entry every interval
+  Watch(WatchArgs),
+  /// Scores the cards saved with past entries again, so history follows the current scoring rules
+  Rescore(RescoreArgs),
+  /// Saves every entry in the database to a JSON file that can be restored into any database
+  Backup(BackupArgs),
+  /// Saves every entry in a backup to the database
+  Restore(RestoreArgs),
+  /// Manages the entries saved in the database
+  #[command(subcommand)]
+  History(HistoryCommands),
+  /// Manages the database entries are saved in
+  #[command(subcommand)]
+  Db(DbCommands),
+  /// Parses data for a board and prints out data to be piped to gnuplot
+  Burndown(BurndownArgs),
+  /// Lists when points were added to or removed from a board, from its saved entries
+  Scope(RangeArgs),
+  /// Writes up a sprint review in Markdown or HTML from the entries saved during the sprint
+  Report(ReportArgs),
+  /// Forecasts when the points left on a board will be finished from its saved entries
+  Forecast(ForecastArgs),
+  /// Prints a completion script for bash, zsh, fish, or powershell
+  Completions(CompletionsArgs),
+}
+
+/// --filter and --include-only, shared by every command that leaves lists out
+#[derive(Args, Debug, Default, Clone)]
+pub struct FilterArgs {
+  /// Filters out all lists with a name that contains one of the comma separated terms in FILTER. Wrap a term in slashes to match it as a regex, ex: /^Sprint \d+$/
+  #[arg(short, long, value_name = "FILTER")]
+  pub filter: Option<String>,
+  /// Keeps only the lists FILTER matches, instead of filtering them out
+  #[arg(long, requires = "filter")]
+  pub include_only: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ScoreArgs {
+  /// The kanban API to get your board and card information from
+  #[arg(short, long, value_name = "KANBAN", value_parser = KANBANS)]
+  pub kanban: Option<String>,
+  /// The ID, or alias from boards in your config, of the board where the cards are meant to be counted from
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Chooses from the boards in a Trello workspace, by id or short name, instead of your own
+  #[arg(short, long, value_name = "WORKSPACE")]
+  pub workspace: Option<String>,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+  /// Groups lists by the text before SEPARATOR in their name and prints a subtotal for each group
+  #[arg(short, long, value_name = "SEPARATOR")]
+  pub group_separator: Option<String>,
+  /// Only counts cards that are assigned to the Trello member USERNAME
+  #[arg(short, long, value_name = "USERNAME", conflicts_with = "assignee")]
+  pub member: Option<String>,
+  /// Only counts Jira issues assigned to ACCOUNT, either an account id or display name
+  #[arg(short, long, value_name = "ACCOUNT")]
+  pub assignee: Option<String>,
+  /// Only counts Jira issues matching the JQL query, ex: "sprint in openSprints()"
+  #[arg(short = 'q', long, value_name = "JQL")]
+  pub jql: Option<String>,
+  /// Only counts Jira issues in SPRINT, a sprint id or "active". Asks which sprint when SPRINT is left out
+  #[arg(long, value_name = "SPRINT", num_args = 0..=1)]
+  pub sprint: Option<Option<String>>,
+  /// Save the current entry in the database
+  #[arg(short, long, value_name = "SAVE", default_value_t = true, action = ArgAction::Set)]
+  pub save: bool,
+  /// Labels the saved entry with TAG, ex: sprint-42-end. Can be used more than once
+  #[arg(short, long, value_name = "TAG")]
+  pub tag: Vec<String>,
+  /// Compares the current trello board with a previous entry
+  #[arg(short, long)]
+  pub compare: bool,
+  /// Compares against the last entry saved on DATE (yyyy-mm-dd) or the entry tagged TAG instead of prompting
+  #[arg(long, value_name = "DATE|tag:TAG", requires = "compare")]
+  pub at: Option<String>,
+  /// Compares against the last entry saved on DATE (yyyy-mm-dd) or N days ago, the latest entry, or the entry tagged TAG, without needing --compare or prompting
+  #[arg(
+    long,
+    value_name = "DATE|latest|N-days-ago|tag:TAG",
+    conflicts_with_all = ["at", "against_tag"]
+  )]
+  pub compare_with: Option<String>,
+  /// Compares against the latest entry tagged TAG, the same as --at tag:TAG
+  #[arg(long, value_name = "TAG", requires = "compare", conflicts_with = "at")]
+  pub against_tag: Option<String>,
+  /// With label, also prints the points for each label after the table of lists
+  #[arg(long, value_name = "GROUP", value_parser = ["list", "label"], default_value = "list")]
+  pub group_by: String,
+  /// Prints tables without colour
+  #[arg(long)]
+  pub no_color: bool,
+  /// Also prints every card with the estimate and correction read from its name
+  #[arg(long)]
+  pub detailed: bool,
+  /// Prints a line of bars under the table for the points completed on each of the last DAYS days, 14 by default
+  #[arg(long, value_name = "DAYS", num_args = 0..=1, default_missing_value = "14")]
+  pub sparkline: Option<i64>,
+  /// Also counts archived Trello lists and cards, and Jira issues with a resolution excluded in your config
+  #[arg(long)]
+  pub include_archived: bool,
+  /// Fails, without saving, when a card is estimated at points that aren't allowed in your config
+  #[arg(long)]
+  pub strict: bool,
+  /// Print a table, a row per list with csv, the whole score as one JSON document with json, or one JSON object per list with ndjson
+  #[arg(
+    short,
+    long,
+    value_name = "OUTPUT",
+    value_parser = ["table", "csv", "json", "ndjson"],
+    default_value = "table"
+  )]
+  pub output: String,
+  /// Exits with a non-zero status if there are more than N unscored cards
+  #[arg(long, alias = "fail-if-unscored", value_name = "N")]
+  pub fail_if_unscored_over: Option<i32>,
+  /// Exits with a non-zero status if there are more than N cards in work in progress lists
+  #[arg(long, value_name = "N")]
+  pub fail_if_wip_over: Option<i32>,
+  /// Exits with a non-zero status if there are more than N points in lists that aren't done
+  #[arg(long, alias = "fail-if-incomplete", value_name = "N")]
+  pub fail_if_incomplete_over: Option<i32>,
+  /// Lists with a name containing NAME are counted as work in progress
+  #[arg(long, value_name = "NAME", default_value = "In Progress")]
+  pub wip_list: String,
+}
+
+/// For commands that only need to know which board to look at
+#[derive(Args, Debug)]
+pub struct BoardArgs {
+  /// The ID or alias of the board, defaults to default_board or you'll be asked to pick one
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct AccuracyArgs {
+  /// The ID of a board to check, defaults to every board in the database. Can be used more than once
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct SummaryArgs {
+  /// The ID of a board to summarize, defaults to summary_boards in your config. Can be used more than once
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthCommands {
+  /// Gets a Trello token from the authorize page and saves it to the config file
+  Trello(TrelloArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct TrelloArgs {
+  /// Your Trello API key, defaults to the key in your config or TRELLO_API_KEY
+  #[arg(short, long, value_name = "KEY")]
+  pub key: Option<String>,
+  /// How long the token lasts
+  #[arg(
+    short,
+    long,
+    value_name = "EXPIRATION",
+    value_parser = PossibleValuesParser::new(TRELLO_TOKEN_EXPIRATION.iter().copied()),
+    default_value = "1day"
+  )]
+  pub expiration: String,
+  /// The port to listen for Trello's redirect on
+  #[arg(short, long, value_name = "PORT", default_value_t = 8788)]
+  pub port: u16,
+  /// Print the authorize link instead of opening it in a browser
+  #[arg(long)]
+  pub no_browser: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AnnotateArgs {
+  /// The ID of the board the event happened to
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// The day the event happened on (yyyy-mm-dd), defaults to today
+  #[arg(long, value_name = "DATE")]
+  pub date: Option<String>,
+  /// A short description of the event
+  #[arg(value_name = "LABEL")]
+  pub label: String,
+}
+
+#[derive(Args, Debug)]
+pub struct BoardsArgs {
+  /// Prints the boards as a table or as JSON
+  #[arg(short, long, value_name = "FORMAT", value_parser = ["table", "json"], default_value = "table")]
+  pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareBoardsArgs {
+  /// The ID of the first board
+  #[arg(long, value_name = "ID")]
+  pub a: String,
+  /// The ID of the board to compare it with
+  #[arg(long, value_name = "ID")]
+  pub b: String,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct TimeInStatusArgs {
+  /// The ID of the board to report on
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// The status to look for stuck cards in
+  #[arg(long, value_name = "STATUS", default_value = "In Review")]
+  pub stuck_in: String,
+  /// How many days a card can sit in a status before it's considered stuck
+  #[arg(long, value_name = "DAYS", default_value_t = 3)]
+  pub stuck_after: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct AnomaliesArgs {
+  /// The ID of the board to check
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Only check entries saved on or after this date (yyyy-mm-dd or tag:TAG)
+  #[arg(
+    short,
+    long,
+    alias = "from",
+    value_name = "START-DATE",
+    requires = "end"
+  )]
+  pub start: Option<String>,
+  /// Only check entries saved on or before this date (yyyy-mm-dd or tag:TAG)
+  #[arg(short, long, alias = "to", value_name = "END-DATE", requires = "start")]
+  pub end: Option<String>,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+  /// Flag days where total points grow by more than PERCENT
+  #[arg(long, value_name = "PERCENT")]
+  pub scope_jump: Option<f64>,
+  /// Flag this many days in a row without any points completed
+  #[arg(long, value_name = "DAYS")]
+  pub stalled_days: Option<i64>,
+  /// Flag unscored cards going up this many entries in a row
+  #[arg(long, value_name = "ENTRIES")]
+  pub unscored_climbing: Option<usize>,
+  /// Posts anything found to a Slack incoming webhook
+  #[arg(long, value_name = "URL")]
+  pub slack_webhook: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReviewArgs {
+  /// The ID of the board to review
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// List cards where the correction and estimate differ by at least RATIO times
+  #[arg(short, long, value_name = "RATIO", default_value_t = 1.5)]
+  pub ratio: f64,
+  /// Group cards by the list they're in or by their labels
+  #[arg(long, value_name = "GROUP", value_parser = ["list", "label"], default_value = "list")]
+  pub group_by: String,
+}
+
+#[derive(Args, Debug)]
+pub struct RetroArgs {
+  /// The ID of the board to look back on
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// How many of the cards furthest from their estimates to list
+  #[arg(long, value_name = "COUNT", default_value_t = 5)]
+  pub top: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct WipArgs {
+  /// The ID of the board to check
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct VelocityArgs {
+  /// The ID of the board to report on
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// The day the first sprint started on (yyyy-mm-dd)
+  #[arg(short, long, value_name = "DATE")]
+  pub since: String,
+  /// How many days are in a sprint
+  #[arg(long, value_name = "DAYS", default_value_t = 14)]
+  pub sprint_length: i64,
+  /// The list, or status, that finished cards are moved to
+  #[arg(long, value_name = "LIST", default_value = "Done")]
+  pub done_list: String,
+  /// How many sprints to include in the rolling average
+  #[arg(long, value_name = "SPRINTS", default_value_t = 3)]
+  pub rolling: usize,
+  /// How to print the report
+  #[arg(short, long, value_name = "OUTPUT", value_parser = ["table", "csv", "ascii"], default_value = "table")]
+  pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct WebhookArgs {
+  /// The ID of a board to save entries for. Can be used more than once
+  #[arg(
+    short,
+    long,
+    value_name = "ID",
+    visible_alias = "board",
+    required = true
+  )]
+  pub board_id: Vec<String>,
+  /// The port to listen for webhooks on
+  #[arg(short, long, value_name = "PORT", default_value_t = 8080)]
+  pub port: u16,
+  /// How long to wait after the last webhook before saving entries
+  #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+  pub debounce: u64,
+  /// The longest to wait after the first webhook before saving entries, even if more keep
+  /// arriving
+  #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+  pub max_wait: u64,
+  /// Only accept webhooks sent to a URL ending in ?token=TOKEN
+  #[arg(long, value_name = "TOKEN")]
+  pub token: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+  /// The ID of the board to export entries for
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Only export entries saved on or after this date (yyyy-mm-dd or tag:TAG)
+  #[arg(
+    short,
+    long,
+    alias = "from",
+    value_name = "START-DATE",
+    requires = "end"
+  )]
+  pub start: Option<String>,
+  /// Only export entries saved on or before this date (yyyy-mm-dd or tag:TAG)
+  #[arg(short, long, alias = "to", value_name = "END-DATE", requires = "start")]
+  pub end: Option<String>,
+  /// The file format to export entries as, ndjson writes one entry per line and xlsx writes a workbook with a chart of each list's points
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    value_parser = ["parquet", "ndjson", "xlsx"],
+    default_value = "parquet"
+  )]
+  pub format: String,
+  /// Where to write the exported entries. Required for parquet and xlsx, ndjson is printed to standard out without it
+  #[arg(short, long, value_name = "FILE")]
+  pub output_file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+  /// The ID of the board to compare entries for
+  #[arg(
+    short,
+    long,
+    value_name = "ID",
+    visible_alias = "board",
+    required_unless_present = "board_name"
+  )]
+  pub board_id: Option<String>,
+  /// Finds the board's saved entries by name, even if the board has been recreated
+  #[arg(long, value_name = "NAME", conflicts_with = "board_id")]
+  pub board_name: Option<String>,
+  /// The entry to compare from, the last one saved by DATE (yyyy-mm-dd) or N days ago, the latest, or the one tagged TAG
+  #[arg(long, value_name = "DATE|latest|N-days-ago|tag:TAG")]
+  pub from: String,
+  /// The entry to compare to, in the same form as --from
+  #[arg(long, value_name = "DATE|latest|N-days-ago|tag:TAG")]
+  pub to: String,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct BackfillArgs {
+  /// The ID of the board to backfill entries for
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// The first day to rebuild an entry for (yyyy-mm-dd)
+  #[arg(long, value_name = "DATE")]
+  pub since: String,
+  /// Shows how many entries would be saved without saving anything
+  #[arg(long)]
+  pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AgingArgs {
+  /// The ID of the board to check
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// How many entries in a row a card has to be outside of done lists to count as stale
+  #[arg(short = 'n', long, value_name = "N", default_value_t = 5)]
+  pub entries: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+  /// The ID of a board to save an entry for. Can be used more than once
+  #[arg(
+    short,
+    long,
+    value_name = "ID",
+    visible_alias = "board",
+    required = true
+  )]
+  pub board_id: Vec<String>,
+  /// Labels the saved entries with TAG, ex: sprint-42-end. Can be used more than once
+  #[arg(short, long, value_name = "TAG")]
+  pub tag: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+  /// The ID of the board to watch
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// How long to wait between polls, ex: 90s, 15m, 1h, or 1d
+  #[arg(short, long, value_name = "INTERVAL", default_value = "1h")]
+  pub interval: String,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct RescoreArgs {
+  /// The ID of the board to rescore entries for
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Only rescore entries saved on or after this date (yyyy-mm-dd or tag:TAG)
+  #[arg(
+    short,
+    long,
+    alias = "from",
+    value_name = "START-DATE",
+    requires = "end"
+  )]
+  pub start: Option<String>,
+  /// Only rescore entries saved on or before this date (yyyy-mm-dd or tag:TAG)
+  #[arg(short, long, alias = "to", value_name = "END-DATE", requires = "start")]
+  pub end: Option<String>,
+  /// Shows what would change without saving anything
+  #[arg(long)]
+  pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct BackupArgs {
+  /// Where to write the backup, it's printed to standard out without it
+  #[arg(short, long, value_name = "FILE")]
+  pub output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+  /// The backup to restore
+  #[arg(short, long, value_name = "FILE")]
+  pub input: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommands {
+  /// Removes old entries for a board, entries with tags are always kept
+  Prune(PruneArgs),
+}
+
+#[derive(Args, Debug)]
+#[command(group(
+  ArgGroup::new("rules")
+    .args(["older_than", "daily"])
+    .multiple(true)
+    .required(true)
+))]
+pub struct PruneArgs {
+  /// The ID of the board to remove entries for
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Removes entries saved more than DAYS days ago
+  #[arg(long, value_name = "DAYS")]
+  pub older_than: Option<i64>,
+  /// Keeps only the last entry saved each day
+  #[arg(long)]
+  pub daily: bool,
+  /// Lists the entries that would be removed without removing them
+  #[arg(long)]
+  pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommands {
+  /// Checks that the database can be reached, read from, and written to
+  Check(CheckArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+  /// Skips saving and removing a test entry
+  #[arg(long)]
+  pub read_only: bool,
+}
+
+/// The board and the range of its saved entries that burndowns, reports, and forecasts are drawn
+/// from
+#[derive(Args, Debug)]
+pub struct RangeArgs {
+  /// The ID, or alias from boards in your config, of the board where the cards are meant to be counted from
+  #[arg(short, long, value_name = "ID", visible_alias = "board")]
+  pub board_id: Option<String>,
+  /// Finds the board's saved entries by name, even if the board has been recreated
+  #[arg(long, value_name = "NAME", conflicts_with = "board_id")]
+  pub board_name: Option<String>,
+  /// The first day of saved entries to use (yyyy-mm-dd or tag:TAG)
+  #[arg(short, long, alias = "from", value_name = "START-DATE")]
+  pub start: String,
+  /// The last day of saved entries to use (yyyy-mm-dd or tag:TAG)
+  #[arg(short, long, alias = "to", value_name = "END-DATE")]
+  pub end: String,
+  #[command(flatten)]
+  pub filter: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct BurndownArgs {
+  #[command(flatten)]
+  pub range: RangeArgs,
+  /// How to draw the burndown
+  #[arg(
+    short,
+    long,
+    value_name = "OUTPUT",
+    value_parser = ["ascii", "csv", "svg", "inline", "ndjson", "plotly", "png"],
+    default_value = "csv"
+  )]
+  pub output: String,
+  /// Writes csv, svg, png, or plotly output to PATH instead of standard out, creating any missing directories
+  #[arg(long, value_name = "PATH")]
+  pub output_file: Option<String>,
+  /// Overwrites the file at --output-file if it already exists
+  #[arg(long, requires = "output_file")]
+  pub force: bool,
+  /// Shades weekends on svg and plotly burndowns, so flat stretches over them stand out as days off
+  #[arg(long)]
+  pub skip_weekends: bool,
+  /// Only draws days with a saved entry, instead of carrying the previous day's points over days without one
+  #[arg(long)]
+  pub no_fill: bool,
+  /// The title drawn above the chart
+  #[arg(long, value_name = "TITLE")]
+  pub title: Option<String>,
+  /// A label for the x-axis
+  #[arg(long, value_name = "LABEL")]
+  pub x_label: Option<String>,
+  /// A label for the y-axis
+  #[arg(long, value_name = "LABEL")]
+  pub y_label: Option<String>,
+  /// How wide the chart is, in pixels for svg or dots for ascii
+  #[arg(long, value_name = "WIDTH")]
+  pub width: Option<u32>,
+  /// How tall the chart is, in pixels for svg or dots for ascii
+  #[arg(long, value_name = "HEIGHT")]
+  pub height: Option<u32>,
+  /// The colour of the incomplete line, a CSS colour or, for ascii, a terminal colour like red
+  #[arg(long, value_name = "COLOUR")]
+  pub incomplete_colour: Option<String>,
+  /// The colour of the complete line, a CSS colour or, for ascii, a terminal colour like blue
+  #[arg(long, value_name = "COLOUR")]
+  pub complete_colour: Option<String>,
+  /// A Tera template to draw svg output with, instead of the built in one or svg_template in your config
+  #[arg(long, value_name = "PATH")]
+  pub template: Option<String>,
+  /// Compares the burndown to the average of the previous SPRINTS sprints
+  #[arg(long, value_name = "SPRINTS")]
+  pub average_of: Option<i64>,
+  /// How many days apart sprints start, defaults to the length of the date range
+  #[arg(
+    long,
+    value_name = "DAYS",
+    requires = "average_of",
+    value_parser = clap::value_parser!(i64).range(1..)
+  )]
+  pub sprint_length: Option<i64>,
+  /// Draws the total points on the board on SVG burndowns, so scope changes stand out
+  #[arg(long)]
+  pub scope: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+  #[command(flatten)]
+  pub range: RangeArgs,
+  /// Writes the report as Markdown, or as HTML with the burndown drawn as an SVG
+  #[arg(
+    short,
+    long,
+    value_name = "OUTPUT",
+    value_parser = ["markdown", "html"],
+    default_value = "markdown"
+  )]
+  pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ForecastArgs {
+  #[command(flatten)]
+  pub range: RangeArgs,
+  /// Simulates many futures from the daily throughput and reports the 50th, 85th, and 95th percentile finish dates
+  #[arg(long)]
+  pub monte_carlo: bool,
+  /// How many futures to simulate
+  #[arg(
+    long,
+    value_name = "COUNT",
+    requires = "monte_carlo",
+    default_value_t = 10000
+  )]
+  pub runs: usize,
+  /// Leaves weekends out of the daily throughput and finish dates
+  #[arg(long)]
+  pub skip_weekends: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+  /// The shell to print completions for
+  #[arg(value_enum, value_name = "SHELL")]
+  pub shell: Shell,
+}
+
+/// Writes a completion script for shell to out
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+  clap_complete::generate(shell, &mut Cli::command(), "card-counter", out);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(args).unwrap()
+  }
+
+  #[test]
+  fn it_has_a_valid_cli() {
+    Cli::command().debug_assert();
+  }
+
+  #[test]
+  fn it_scores_without_a_subcommand() {
+    let bare = match parse(&["card-counter", "-b", "board", "--save", "false"]).into_command() {
+      Commands::Score(args) => args,
+      command => panic!("Expected score, got {:?}", command),
+    };
+    let explicit = match parse(&[
+      "card-counter",
+      "score",
+      "--board",
+      "board",
+      "--save",
+      "false",
+    ])
+    .into_command()
+    {
+      Commands::Score(args) => args,
+      command => panic!("Expected score, got {:?}", command),
+    };
+
+    for args in [bare, explicit] {
+      assert_eq!(args.board_id.as_deref(), Some("board"));
+      assert!(!args.save);
+      assert_eq!(args.output, "table");
+    }
+    assert!(Cli::try_parse_from(["card-counter", "--save", "maybe"]).is_err());
+  }
+
+  #[test]
+  fn it_parses_typed_subcommand_arguments() {
+    let cli = parse(&[
+      "card-counter",
+      "burndown",
+      "--start",
+      "2020-04-01",
+      "--end",
+      "tag:sprint-12-end",
+      "--width",
+      "400",
+      "--ascii",
+    ]);
+    assert!(cli.ascii);
+    match cli.into_command() {
+      Commands::Burndown(args) => {
+        assert_eq!(args.range.start, "2020-04-01");
+        assert_eq!(args.range.end, "tag:sprint-12-end");
+        assert_eq!(args.width, Some(400));
+        assert_eq!(args.output, "csv");
+      }
+      command => panic!("Expected burndown, got {:?}", command),
+    }
+
+    assert!(Cli::try_parse_from(["card-counter", "burndown", "--start", "2020-04-01"]).is_err());
+    assert!(Cli::try_parse_from(["card-counter", "retro", "--top", "five"]).is_err());
+    assert!(Cli::try_parse_from(["card-counter", "history", "prune"]).is_err());
+    assert!(Cli::try_parse_from([
+      "card-counter",
+      "burndown",
+      "--start",
+      "2020-04-01",
+      "--end",
+      "2020-04-14",
+      "--average-of",
+      "3",
+      "--sprint-length",
+      "0"
+    ])
+    .is_err());
+  }
+
+  #[test]
+  fn it_writes_completions() {
+    let mut script = vec![];
+    write_completions(Shell::Bash, &mut script);
+    let script = String::from_utf8(script).unwrap();
+
+    assert!(script.contains("burndown"));
+    assert!(script.contains("--board-id"));
+  }
+}
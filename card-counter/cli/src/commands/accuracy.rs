@@ -0,0 +1,157 @@
+use crate::{
+  database::{Database, Entry},
+  errors::*,
+  score::Scorer,
+};
+
+use prettytable::Table;
+use std::collections::HashSet;
+
+/// How close a board's estimates came to their corrections
+#[derive(Debug, PartialEq)]
+pub struct Accuracy {
+  pub board: String,
+  // How many cards had both an estimate and a correction
+  pub cards: usize,
+  // The average distance between estimate and correction, in points
+  pub mean_absolute_error: f64,
+  // The average of correction minus estimate. Above zero means work tends to be underestimated.
+  pub bias: f64,
+}
+
+/// Works out the accuracy for each board, in the order boards first appear in entries. A card is
+/// counted once no matter how many entries it was saved with, and boards without a single
+/// corrected card are left out.
+pub fn accuracy(entries: &[Entry], scorer: &Scorer) -> Vec<Accuracy> {
+  let mut boards: Vec<(String, String, HashSet<&str>)> = vec![];
+  for entry in entries {
+    let index = match boards.iter().position(|(id, _, _)| *id == entry.board_id) {
+      Some(index) => index,
+      None => {
+        boards.push((
+          entry.board_id.clone(),
+          entry.board_name.clone(),
+          HashSet::new(),
+        ));
+        boards.len() - 1
+      }
+    };
+    boards[index]
+      .2
+      .extend(entry.cards.iter().map(|card| card.name.as_str()));
+  }
+
+  boards
+    .into_iter()
+    .filter_map(|(id, name, names)| {
+      let errors: Vec<i32> = names
+        .into_iter()
+        .filter_map(|name| {
+          let score = scorer.score(name)?;
+          Some(score.correction? - score.estimated?)
+        })
+        .collect();
+      if errors.is_empty() {
+        return None;
+      }
+
+      let count = errors.len() as f64;
+      Some(Accuracy {
+        board: match name.is_empty() {
+          true => id,
+          false => name,
+        },
+        cards: errors.len(),
+        mean_absolute_error: errors.iter().map(|error| error.abs() as f64).sum::<f64>() / count,
+        bias: errors.iter().map(|error| *error as f64).sum::<f64>() / count,
+      })
+    })
+    .collect()
+}
+
+pub fn print_accuracy(accuracies: &[Accuracy]) {
+  if accuracies.is_empty() {
+    println!("None of the saved cards have both an estimate and a correction yet");
+    return;
+  }
+
+  let mut table = Table::new();
+  table.set_titles(row!["Board", "cards", "mean absolute error", "bias"]);
+  for accuracy in accuracies {
+    table.add_row(row![
+      accuracy.board,
+      accuracy.cards,
+      format!("{:.2}", accuracy.mean_absolute_error),
+      format!("{:+.2}", accuracy.bias)
+    ]);
+  }
+  table.printstd();
+}
+
+/// Compares the estimate and correction of every card saved with the boards' entries, for each
+/// board passed in or every board in the database
+pub async fn report(
+  client: Box<dyn Database>,
+  scorer: &Scorer,
+  board_ids: &[String],
+) -> Result<()> {
+  let entries = match board_ids {
+    [] => client.all_entries().await?.unwrap_or_default(),
+    board_ids => {
+      let mut entries = vec![];
+      for board_id in board_ids {
+        entries.extend(
+          client
+            .query_entries(board_id.to_string(), None)
+            .await?
+            .unwrap_or_default(),
+        );
+      }
+      entries
+    }
+  };
+
+  print_accuracy(&accuracy(&entries, scorer));
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::CardSnapshot;
+
+  #[test]
+  fn it_measures_estimates_against_corrections() {
+    let entry = |board_id: &str, time_stamp: i64, names: &[&str]| Entry {
+      board_id: board_id.to_string(),
+      board_name: String::new(),
+      time_stamp,
+      decks: vec![],
+      tags: vec![],
+      cards: names
+        .iter()
+        .map(|name| CardSnapshot {
+          name: name.to_string(),
+          list_name: "Done".to_string(),
+        })
+        .collect(),
+    };
+    let entries = vec![
+      entry("a", 1, &["(3)[5] Login", "(2) Signup"]),
+      // The same card saved again only counts once
+      entry("a", 2, &["(3)[5] Login", "(8)[4] Search"]),
+      entry("b", 1, &["(1) Docs"]),
+    ];
+
+    assert_eq!(
+      accuracy(&entries, &Scorer::default()),
+      vec![Accuracy {
+        board: "a".to_string(),
+        cards: 2,
+        mean_absolute_error: 3.,
+        bias: -1.,
+      }]
+    );
+  }
+}
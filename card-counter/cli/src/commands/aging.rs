@@ -0,0 +1,191 @@
+use crate::{
+  cli::AgingArgs,
+  commands::burndown::ListMatcher,
+  database::{config::DateFormat, Database, Entry},
+  errors::*,
+  kanban::{Board, Kanban},
+  score::Scorer,
+};
+
+use chrono::DateTime;
+use prettytable::Table;
+
+/// A card that has sat in lists that aren't done for a run of entries
+#[derive(Debug, PartialEq)]
+pub struct StaleCard {
+  pub name: String,
+  pub list_name: String,
+  // How many entries in a row, up to and including the latest, the card wasn't done in
+  pub entries: usize,
+  // When the first of those entries was saved
+  pub since: i64,
+}
+
+/// The cards in the latest entry that weren't done in at least `min_entries` entries in a row,
+/// stalest first
+pub fn stale_cards(
+  entries: &[Entry],
+  done_lists: &ListMatcher,
+  min_entries: usize,
+) -> Vec<StaleCard> {
+  let mut entries: Vec<&Entry> = entries.iter().collect();
+  entries.sort();
+  let latest = match entries.last() {
+    Some(latest) => latest,
+    None => return vec![],
+  };
+
+  let not_done_in = |entry: &Entry, name: &str| {
+    entry
+      .cards
+      .iter()
+      .any(|card| card.name == name && !done_lists.matches(&card.list_name))
+  };
+
+  let mut stale: Vec<StaleCard> = latest
+    .cards
+    .iter()
+    .filter(|card| !done_lists.matches(&card.list_name))
+    .filter_map(|card| {
+      let run: Vec<&&Entry> = entries
+        .iter()
+        .rev()
+        .take_while(|entry| not_done_in(entry, &card.name))
+        .collect();
+      if run.len() < min_entries {
+        return None;
+      }
+      Some(StaleCard {
+        name: card.name.clone(),
+        list_name: card.list_name.clone(),
+        entries: run.len(),
+        since: run.last()?.time_stamp,
+      })
+    })
+    .collect();
+  stale.sort_by(|a, b| {
+    b.entries
+      .cmp(&a.entries)
+      .then_with(|| a.since.cmp(&b.since))
+  });
+  stale
+}
+
+pub fn print_stale_cards(
+  board_name: &str,
+  stale: &[StaleCard],
+  scorer: &Scorer,
+  format: &DateFormat,
+) {
+  if stale.is_empty() {
+    println!("None of the cards on {} have gone stale", board_name);
+    return;
+  }
+
+  println!("{}", board_name);
+  let mut table = Table::new();
+  table.set_titles(row!["Card", "List", "points", "entries", "since"]);
+  let mut points = 0;
+  for card in stale {
+    let (score, _, _) = scorer.tally(std::iter::once(card.name.as_str()));
+    points += score;
+    let since = DateTime::from_timestamp(card.since, 0).unwrap();
+    table.add_row(row![
+      card.name,
+      card.list_name,
+      score,
+      card.entries,
+      format.format(&since, &format.date)
+    ]);
+  }
+  table.printstd();
+  println!("{} stale cards worth {} points", stale.len(), points);
+}
+
+/// Lists the cards that haven't made it to a done list in the last --entries entries saved for the
+/// board
+pub async fn aging(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  scorer: &Scorer,
+  done_lists: &ListMatcher,
+  format: &DateFormat,
+  args: &AgingArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let min_entries = args.entries;
+
+  let entries = client
+    .query_entries(board.id.clone(), None)
+    .await?
+    .unwrap_or_default();
+
+  print_stale_cards(
+    &board.name,
+    &stale_cards(&entries, done_lists, min_entries),
+    scorer,
+    format,
+  );
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::CardSnapshot;
+
+  #[test]
+  fn it_finds_cards_stuck_outside_done() {
+    let entry = |time_stamp: i64, cards: &[(&str, &str)]| Entry {
+      board_id: "board".to_string(),
+      board_name: "Sprint".to_string(),
+      time_stamp,
+      decks: vec![],
+      tags: vec![],
+      cards: cards
+        .iter()
+        .map(|(name, list_name)| CardSnapshot {
+          name: name.to_string(),
+          list_name: list_name.to_string(),
+        })
+        .collect(),
+    };
+    let entries = vec![
+      entry(3, &[("(3) Login", "Doing"), ("(5) Search", "Doing")]),
+      entry(1, &[("(3) Login", "To Do")]),
+      entry(2, &[("(3) Login", "Doing"), ("(5) Search", "Done")]),
+      entry(
+        4,
+        &[
+          ("(3) Login", "Review"),
+          ("(5) Search", "Doing"),
+          ("(1) Docs", "Done"),
+        ],
+      ),
+    ];
+
+    let stale = stale_cards(&entries, &ListMatcher::default(), 2);
+    assert_eq!(
+      stale,
+      vec![
+        StaleCard {
+          name: "(3) Login".to_string(),
+          list_name: "Review".to_string(),
+          entries: 4,
+          since: 1,
+        },
+        StaleCard {
+          name: "(5) Search".to_string(),
+          list_name: "Doing".to_string(),
+          entries: 2,
+          since: 3,
+        },
+      ]
+    );
+    assert_eq!(stale_cards(&entries, &ListMatcher::default(), 3).len(), 1);
+  }
+}
@@ -0,0 +1,394 @@
+use crate::{
+  cli::AnomaliesArgs,
+  commands::burndown::ListMatcher,
+  database::{
+    config::{AnomalyThresholds, Config, DateFormat},
+    Database, DateRange, Entry,
+  },
+  errors::*,
+  kanban::{Board, Kanban},
+  score::{filter_decks, ListFilter},
+};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+/// Something unusual in a board's history that's worth a human looking at
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+  // The total points on the board grew by more than the allowed percentage in a day
+  ScopeJump {
+    date: DateTime<Utc>,
+    before: i32,
+    after: i32,
+  },
+  // No points were completed between since and until
+  Stalled {
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+  },
+  // The number of unscored cards went up every entry between since and until
+  UnscoredClimbing {
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    from: i32,
+    to: i32,
+  },
+}
+
+impl Anomaly {
+  pub fn describe(&self, format: &DateFormat) -> String {
+    match self {
+      Anomaly::ScopeJump {
+        date,
+        before,
+        after,
+      } => format!(
+        "Scope jumped {:.0}% on {}, from {} to {} points",
+        (after - before) as f64 / *before as f64 * 100.0,
+        format.format(date, &format.date),
+        before,
+        after
+      ),
+      Anomaly::Stalled { since, until } => format!(
+        "No points were completed for {} days, from {} to {}",
+        (*until - *since).num_days(),
+        format.format(since, &format.date),
+        format.format(until, &format.date)
+      ),
+      Anomaly::UnscoredClimbing {
+        since,
+        until,
+        from,
+        to,
+      } => format!(
+        "Unscored cards climbed from {} to {} between {} and {}",
+        from,
+        to,
+        format.format(since, &format.date),
+        format.format(until, &format.date)
+      ),
+    }
+  }
+}
+
+// The numbers we care about for the last entry saved each day
+struct Day {
+  date: DateTime<Utc>,
+  incomplete: i32,
+  complete: i32,
+  unscored: i32,
+}
+
+fn summarize_days(entries: &[Entry], filter: &ListFilter, done_lists: &ListMatcher) -> Vec<Day> {
+  let mut entries = entries.to_vec();
+  entries.sort();
+
+  let mut days: Vec<Day> = Vec::new();
+  for entry in entries {
+    let date = DateTime::from_timestamp(entry.time_stamp, 0).unwrap();
+    let (incomplete, complete) = entry.calculate_score(filter, done_lists);
+    let unscored = filter_decks(&entry.decks, filter)
+      .iter()
+      .map(|deck| deck.unscored)
+      .sum();
+
+    // Only the last entry in a day counts
+    if let Some(day) = days.last() {
+      if day.date.date_naive() == date.date_naive() {
+        days.pop();
+      }
+    }
+    days.push(Day {
+      date,
+      incomplete,
+      complete,
+      unscored,
+    });
+  }
+
+  days
+}
+
+fn scope_jumps(days: &[Day], thresholds: &AnomalyThresholds) -> Vec<Anomaly> {
+  days
+    .windows(2)
+    .filter_map(|pair| {
+      let before = pair[0].incomplete + pair[0].complete;
+      let after = pair[1].incomplete + pair[1].complete;
+      let growth = (after - before) as f64 / before as f64 * 100.0;
+
+      if before > 0 && growth > thresholds.scope_jump_percent {
+        Some(Anomaly::ScopeJump {
+          date: pair[1].date,
+          before,
+          after,
+        })
+      } else {
+        None
+      }
+    })
+    .collect()
+}
+
+fn stalls(days: &[Day], thresholds: &AnomalyThresholds) -> Vec<Anomaly> {
+  let mut anomalies = vec![];
+  let mut start = match days.first() {
+    Some(day) => day,
+    None => return anomalies,
+  };
+  let mut last = start;
+
+  for day in days.iter().skip(1) {
+    if day.complete > start.complete {
+      if (last.date - start.date).num_days() >= thresholds.stalled_days {
+        anomalies.push(Anomaly::Stalled {
+          since: start.date,
+          until: last.date,
+        });
+      }
+      start = day;
+    }
+    last = day;
+  }
+
+  if (last.date - start.date).num_days() >= thresholds.stalled_days {
+    anomalies.push(Anomaly::Stalled {
+      since: start.date,
+      until: last.date,
+    });
+  }
+
+  anomalies
+}
+
+fn unscored_climbing(days: &[Day], thresholds: &AnomalyThresholds) -> Vec<Anomaly> {
+  let mut anomalies = vec![];
+  let mut start = 0;
+
+  for index in 1..=days.len() {
+    let climbing = index < days.len() && days[index].unscored > days[index - 1].unscored;
+    if !climbing {
+      // index - 1 is the end of the current run
+      if thresholds.unscored_climbing > 0 && index - 1 - start >= thresholds.unscored_climbing {
+        anomalies.push(Anomaly::UnscoredClimbing {
+          since: days[start].date,
+          until: days[index - 1].date,
+          from: days[start].unscored,
+          to: days[index - 1].unscored,
+        });
+      }
+      start = index;
+    }
+  }
+
+  anomalies
+}
+
+/// Looks through entries for scope jumps, stretches without progress, and a climbing number of
+/// unscored cards. Entries are reduced to the last one saved each day.
+pub fn detect_anomalies(
+  entries: &[Entry],
+  thresholds: &AnomalyThresholds,
+  filter: &ListFilter,
+  done_lists: &ListMatcher,
+) -> Vec<Anomaly> {
+  let days = summarize_days(entries, filter, done_lists);
+
+  let mut anomalies = scope_jumps(&days, thresholds);
+  anomalies.extend(stalls(&days, thresholds));
+  anomalies.extend(unscored_climbing(&days, thresholds));
+
+  anomalies
+}
+
+/// Posts anomalies to a Slack incoming webhook
+pub async fn notify_slack(webhook: &str, board_name: &str, messages: &[String]) -> Result<()> {
+  let text = format!(
+    "*{}* needs a look:\n{}",
+    board_name,
+    messages
+      .iter()
+      .map(|message| format!("• {}", message))
+      .collect::<Vec<String>>()
+      .join("\n")
+  );
+
+  reqwest::Client::new()
+    .post(webhook)
+    .json(&json!({ "text": text }))
+    .send()
+    .await?
+    .error_for_status()
+    .wrap_err_with(|| "Unable to post anomalies to Slack")?;
+
+  Ok(())
+}
+
+/// Overrides the configured thresholds with anything passed in on the command line
+pub fn thresholds_from_args(config: &Config, args: &AnomaliesArgs) -> AnomalyThresholds {
+  let mut thresholds = config.anomalies.clone();
+
+  if let Some(percent) = args.scope_jump {
+    thresholds.scope_jump_percent = percent;
+  }
+  if let Some(days) = args.stalled_days {
+    thresholds.stalled_days = days;
+  }
+  if let Some(entries) = args.unscored_climbing {
+    thresholds.unscored_climbing = entries;
+  }
+  if let Some(webhook) = &args.slack_webhook {
+    thresholds.slack_webhook = Some(webhook.clone());
+  }
+
+  thresholds
+}
+
+/// Prints every anomaly found in a board's history, and posts them to Slack if a webhook is set
+pub async fn report(
+  config: &Config,
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  args: &AnomaliesArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let thresholds = thresholds_from_args(config, args);
+  let filter = ListFilter::from_args(&args.filter)?;
+
+  let range = match (&args.start, &args.end) {
+    (Some(start), Some(end)) => Some(
+      DateRange::resolve(
+        client.as_ref(),
+        &board.id,
+        start,
+        end,
+        &config.date_format.timezone,
+      )
+      .await?,
+    ),
+    _ => None,
+  };
+  let entries = client
+    .query_entries(board.id.clone(), range)
+    .await?
+    .unwrap_or_default();
+
+  let messages: Vec<String> =
+    detect_anomalies(&entries, &thresholds, &filter, &config.done_matcher()?)
+      .iter()
+      .map(|anomaly| anomaly.describe(&config.date_format))
+      .collect();
+
+  if messages.is_empty() {
+    println!("Nothing unusual found for {}", board.name);
+    return Ok(());
+  }
+
+  println!("{}", board.name);
+  messages
+    .iter()
+    .for_each(|message| println!("  - {}", message));
+
+  if let Some(webhook) = &thresholds.slack_webhook {
+    notify_slack(webhook, &board.name, &messages).await?;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::score::Deck;
+  use chrono::TimeZone;
+
+  fn entry(day: u32, todo: i32, done: i32, unscored: i32) -> Entry {
+    Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp: Utc
+        .with_ymd_and_hms(2021, 3, day, 12, 0, 0)
+        .unwrap()
+        .timestamp(),
+      tags: vec![],
+      cards: vec![],
+      decks: vec![
+        Deck {
+          list_name: "To Do".to_string(),
+          size: 1,
+          score: todo,
+          unscored,
+          estimated: todo,
+        },
+        Deck {
+          list_name: "Done".to_string(),
+          size: 1,
+          score: done,
+          unscored: 0,
+          estimated: done,
+        },
+      ],
+    }
+  }
+
+  fn date(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 3, day, 12, 0, 0).unwrap()
+  }
+
+  #[test]
+  fn it_flags_scope_jumps() {
+    let entries = vec![entry(1, 20, 0, 0), entry(2, 19, 1, 0), entry(3, 30, 2, 0)];
+    let anomalies = detect_anomalies(
+      &entries,
+      &AnomalyThresholds::default(),
+      &ListFilter::default(),
+      &ListMatcher::default(),
+    );
+
+    assert_eq!(
+      anomalies,
+      vec![Anomaly::ScopeJump {
+        date: date(3),
+        before: 20,
+        after: 32
+      }]
+    );
+  }
+
+  #[test]
+  fn it_flags_stalls_and_climbing_unscored_cards() {
+    let entries = vec![
+      entry(1, 20, 0, 0),
+      entry(2, 20, 0, 1),
+      entry(3, 20, 0, 2),
+      entry(4, 20, 0, 3),
+      entry(5, 18, 2, 3),
+    ];
+    let anomalies = detect_anomalies(
+      &entries,
+      &AnomalyThresholds::default(),
+      &ListFilter::default(),
+      &ListMatcher::default(),
+    );
+
+    assert_eq!(
+      anomalies,
+      vec![
+        Anomaly::Stalled {
+          since: date(1),
+          until: date(4)
+        },
+        Anomaly::UnscoredClimbing {
+          since: date(1),
+          until: date(4),
+          from: 0,
+          to: 3
+        }
+      ]
+    );
+  }
+}
@@ -0,0 +1,333 @@
+use crate::{
+  cli::TrelloArgs,
+  database::config::{trello_auth_from_env, Config, KanbanBoard},
+  errors::*,
+  kanban::trello::TrelloAuth,
+};
+
+use std::{convert::Infallible, net::SocketAddr, process};
+
+use chrono::Utc;
+use dialoguer::{Confirm, Input};
+use hyper::{
+  header::ORIGIN,
+  service::{make_service_fn, service_fn},
+  Body, Method, Request, Response, Server, StatusCode,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Trello hands the token back in the URL fragment, which browsers never send to the server, so
+/// the callback page posts it to /token itself along with the state we gave Trello.
+static CALLBACK_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head><title>card-counter</title></head>
+  <body>
+    <p id="message">Saving your token...</p>
+    <script>
+      const token = new URLSearchParams(window.location.hash.slice(1)).get("token");
+      const state = new URLSearchParams(window.location.search).get("state");
+      const message = document.getElementById("message");
+      if (token && state) {
+        fetch("/token", { method: "POST", body: new URLSearchParams({ token, state }) })
+          .then((response) => message.textContent = response.ok
+            ? "card-counter has your token, you can close this tab."
+            : "card-counter didn't accept the token, please try again.");
+      } else {
+        message.textContent = "Trello didn't send a token back, please try again.";
+      }
+    </script>
+  </body>
+</html>"#;
+
+/// A random value that only our callback page knows, so /token only takes tokens coming back from
+/// the authorization we started
+fn new_state() -> String {
+  rand::thread_rng()
+    .sample_iter(&Alphanumeric)
+    .take(32)
+    .map(char::from)
+    .collect()
+}
+
+/// The authorize URL for auth, asking Trello to send the token on to our local callback server
+pub fn callback_url(auth: &TrelloAuth, port: u16, state: &str) -> String {
+  let mut url = Url::parse(&auth.authorize_url()).expect("The authorize URL is always valid");
+  url
+    .query_pairs_mut()
+    .append_pair("callback_method", "fragment")
+    .append_pair(
+      "return_url",
+      &format!("http://localhost:{}/callback?state={}", port, state),
+    );
+  url.to_string()
+}
+
+/// Pulls the token out of the form posted to /token, as long as it came with the expected state
+fn token_from(body: &[u8], state: &str) -> Option<String> {
+  let form = Url::parse(&format!(
+    "http://localhost/?{}",
+    String::from_utf8_lossy(body)
+  ))
+  .ok()?;
+  let value = |key: &str| {
+    form
+      .query_pairs()
+      .find(|(name, _)| name == key)
+      .map(|(_, value)| value.to_string())
+  };
+  if value("state")? != state {
+    return None;
+  }
+  value("token").filter(|token| !token.is_empty())
+}
+
+/// Browsers send an Origin with the callback page's post, anything else came from another site
+fn allowed_origin(origin: Option<&str>, port: u16) -> bool {
+  match origin {
+    Some(origin) => {
+      origin == format!("http://localhost:{}", port)
+        || origin == format!("http://127.0.0.1:{}", port)
+    }
+    None => true,
+  }
+}
+
+fn respond(status: StatusCode, body: &'static str) -> Response<Body> {
+  let mut response = Response::new(Body::from(body));
+  *response.status_mut() = status;
+  response
+}
+
+/// What the callback server needs to check and hand back a token
+#[derive(Clone)]
+struct Callback {
+  port: u16,
+  state: String,
+  sender: UnboundedSender<String>,
+}
+
+async fn handle(request: Request<Body>, callback: Callback) -> Result<Response<Body>, Infallible> {
+  match (request.method(), request.uri().path()) {
+    (&Method::GET, "/callback") => Ok(respond(StatusCode::OK, CALLBACK_PAGE)),
+    (&Method::POST, "/token") => {
+      let origin = request
+        .headers()
+        .get(ORIGIN)
+        .map(|origin| origin.to_str().unwrap_or_default());
+      if !allowed_origin(origin, callback.port) {
+        return Ok(respond(StatusCode::FORBIDDEN, ""));
+      }
+      let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(_) => return Ok(respond(StatusCode::BAD_REQUEST, "")),
+      };
+      match token_from(&body, &callback.state) {
+        Some(token) => {
+          // The receiver only goes away once we have a token
+          callback.sender.send(token).ok();
+          Ok(respond(StatusCode::OK, ""))
+        }
+        None => Ok(respond(StatusCode::BAD_REQUEST, "")),
+      }
+    }
+    (_, "/callback") | (_, "/token") => Ok(respond(StatusCode::METHOD_NOT_ALLOWED, "")),
+    _ => Ok(respond(StatusCode::NOT_FOUND, "")),
+  }
+}
+
+/// Opens url in the user's browser, returning false if we couldn't
+fn open_browser(url: &str) -> bool {
+  let result = if cfg!(target_os = "macos") {
+    process::Command::new("open").arg(url).status()
+  } else if cfg!(target_os = "windows") {
+    // start treats the first quoted argument as the window title
+    process::Command::new("cmd")
+      .args(["/C", "start", "", url])
+      .status()
+  } else {
+    process::Command::new("xdg-open").arg(url).status()
+  };
+  result.map(|status| status.success()).unwrap_or(false)
+}
+
+/// Uses the key passed in, or the one we already know about, before asking for one
+fn api_key(config: &Config, key: Option<&str>) -> Result<String> {
+  if let Some(key) = key {
+    return Ok(key.to_string());
+  }
+  if let KanbanBoard::Trello(auth) = &config.kanban {
+    if !auth.key.is_empty() {
+      return Ok(auth.key.clone());
+    }
+  }
+  if let Ok(auth) = trello_auth_from_env() {
+    return Ok(auth.key);
+  }
+
+  println!("You can find your API key at https://trello.com/app-key");
+  Ok(
+    Input::<String>::new()
+      .with_prompt("Trello API Key")
+      .interact()?,
+  )
+}
+
+/// Sends the user to Trello to authorize card-counter and waits for the token to come back to a
+/// server on localhost, then saves it to the global config.
+pub async fn trello(args: &TrelloArgs) -> Result<()> {
+  let mut config = Config::from_global_file_or_default()?;
+  let port = args.port;
+
+  // Keep any other Trello settings, only the key and token change
+  let existing = match &config.kanban {
+    KanbanBoard::Trello(auth) => auth.clone(),
+    other => {
+      let replace = Confirm::new()
+        .with_prompt(format!(
+          "Your config uses {} for its kanban board, replace it with Trello?",
+          other
+        ))
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+      if !replace {
+        return Err(eyre!(
+          "Kept {} as your kanban board, nothing was saved",
+          other
+        ));
+      }
+      TrelloAuth::default()
+    }
+  };
+  let auth = TrelloAuth {
+    key: api_key(&config, args.key.as_deref())?,
+    expiration: args.expiration.clone(),
+    ..existing
+  };
+
+  let state = new_state();
+  let (sender, mut receiver) = unbounded_channel::<String>();
+  let callback = Callback {
+    port,
+    state: state.clone(),
+    sender,
+  };
+  let make_service = make_service_fn(move |_connection| {
+    let callback = callback.clone();
+    async move { Ok::<_, Infallible>(service_fn(move |request| handle(request, callback.clone()))) }
+  });
+  let address = SocketAddr::from(([127, 0, 0, 1], port));
+  let server = Server::try_bind(&address)
+    .wrap_err_with(|| format!("Unable to listen on port {}", port))?
+    .serve(make_service);
+
+  let url = callback_url(&auth, port, &state);
+  if args.no_browser || !open_browser(&url) {
+    println!("Visit the link below to authorize card-counter:\n{}", url);
+  } else {
+    println!("Waiting for you to authorize card-counter in your browser...");
+  }
+
+  let token = tokio::select! {
+    result = server => {
+      result.wrap_err_with(|| "The callback server stopped unexpectedly")?;
+      return Err(eyre!("The callback server stopped before receiving a token"));
+    }
+    token = receiver.recv() => token.ok_or_else(|| eyre!("Never received a token from Trello"))?,
+  };
+
+  config.kanban = KanbanBoard::Trello(TrelloAuth {
+    token,
+    issued_at: Some(Utc::now().timestamp()),
+    ..auth
+  });
+  config.persist()?;
+  println!("Saved your Trello token.");
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_reads_the_token_from_the_callback() {
+    assert_eq!(
+      token_from(b"token=abc123&state=xyz", "xyz"),
+      Some("abc123".to_string())
+    );
+    assert_eq!(token_from(b"token=&state=xyz", "xyz"), None);
+    assert_eq!(token_from(b"state=xyz", "xyz"), None);
+  }
+
+  #[test]
+  fn it_rejects_tokens_without_our_state() {
+    assert_eq!(token_from(b"token=abc123&state=guess", "xyz"), None);
+    assert_eq!(token_from(b"token=abc123", "xyz"), None);
+  }
+
+  #[test]
+  fn it_only_takes_tokens_posted_from_localhost() {
+    assert!(allowed_origin(Some("http://localhost:8788"), 8788));
+    assert!(allowed_origin(Some("http://127.0.0.1:8788"), 8788));
+    assert!(allowed_origin(None, 8788));
+    assert!(!allowed_origin(Some("https://example.com"), 8788));
+    assert!(!allowed_origin(Some("http://localhost:9999"), 8788));
+  }
+
+  #[tokio::test]
+  async fn it_only_accepts_posts_to_token() {
+    let (sender, mut receiver) = unbounded_channel::<String>();
+    let callback = Callback {
+      port: 8788,
+      state: "xyz".to_string(),
+      sender,
+    };
+    let request = |method: Method, origin: &str| {
+      Request::builder()
+        .method(method)
+        .uri("/token")
+        .header(ORIGIN, origin)
+        .body(Body::from("token=abc123&state=xyz"))
+        .unwrap()
+    };
+
+    let response = handle(
+      request(Method::GET, "http://localhost:8788"),
+      callback.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let response = handle(
+      request(Method::POST, "https://example.com"),
+      callback.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = handle(request(Method::POST, "http://localhost:8788"), callback)
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(receiver.recv().await, Some("abc123".to_string()));
+  }
+
+  #[test]
+  fn it_asks_trello_to_return_to_localhost() {
+    let auth = TrelloAuth {
+      key: "key".to_string(),
+      expiration: "never".to_string(),
+      ..TrelloAuth::default()
+    };
+
+    assert_eq!(
+      callback_url(&auth, 8788, "xyz"),
+      "https://trello.com/1/authorize?expiration=never&name=card-counter&scope=read&response_type=token&key=key&callback_method=fragment&return_url=http%3A%2F%2Flocalhost%3A8788%2Fcallback%3Fstate%3Dxyz"
+    );
+  }
+}
@@ -0,0 +1,240 @@
+use crate::{
+  cli::BackfillArgs,
+  commands::prune::enforce_retention,
+  database::{config::Config, CardSnapshot, Database, DateRange, Entry},
+  errors::*,
+  kanban::{Board, CardHistory, Kanban, List},
+  score::{Deck, Scorer},
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// The name of the list a card was in at `at`, or None if it hadn't been created yet
+pub fn list_at(history: &CardHistory, at: DateTime<Utc>) -> Option<&str> {
+  if history.created > at {
+    return None;
+  }
+
+  let list = match history
+    .transitions
+    .iter()
+    .take_while(|transition| transition.at <= at)
+    .last()
+  {
+    Some(transition) => &transition.to,
+    // Cards start in whatever list they were first moved out of
+    None => history
+      .transitions
+      .first()
+      .and_then(|transition| transition.from.as_ref())
+      .unwrap_or(&history.current_list),
+  };
+  Some(list.as_str())
+}
+
+/// Rebuilds what the board looked like at `at` from where each card was, with a deck for each of
+/// the board's lists. Cards in lists that are no longer on the board are left out.
+pub fn entry_at(
+  board: &Board,
+  lists: &[List],
+  histories: &[CardHistory],
+  scorer: &Scorer,
+  at: DateTime<Utc>,
+) -> Entry {
+  let cards: Vec<CardSnapshot> = histories
+    .iter()
+    .filter_map(|history| {
+      list_at(history, at).map(|list_name| CardSnapshot {
+        name: history.name.clone(),
+        list_name: list_name.to_string(),
+      })
+    })
+    .filter(|card| lists.iter().any(|list| list.name == card.list_name))
+    .collect();
+
+  let decks = lists
+    .iter()
+    .map(|list| {
+      let names: Vec<&str> = cards
+        .iter()
+        .filter(|card| card.list_name == list.name)
+        .map(|card| card.name.as_str())
+        .collect();
+      let (score, unscored, estimated) = scorer.tally(names.iter().copied());
+      Deck {
+        list_name: list.name.clone(),
+        size: names.len(),
+        score,
+        unscored,
+        estimated,
+      }
+    })
+    .collect();
+
+  Entry {
+    board_id: board.id.clone(),
+    board_name: board.name.clone(),
+    time_stamp: at.timestamp(),
+    decks,
+    tags: vec![],
+    cards,
+  }
+}
+
+/// Builds an entry for the end of each day from `since` up until the day before `until`
+pub fn backfill_entries(
+  board: &Board,
+  lists: &[List],
+  histories: &[CardHistory],
+  scorer: &Scorer,
+  since: DateTime<Utc>,
+  until: DateTime<Utc>,
+) -> Vec<Entry> {
+  let mut entries = vec![];
+  let mut day = since;
+  while day + Duration::days(1) <= until {
+    let end_of_day = day + Duration::days(1) - Duration::seconds(1);
+    entries.push(entry_at(board, lists, histories, scorer, end_of_day));
+    day += Duration::days(1);
+  }
+  entries
+}
+
+/// Reconstructs a daily entry for every day since --since from the moves between lists the board
+/// recorded, and saves the ones for days that don't have an entry yet.
+pub async fn backfill(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  config: &Config,
+  args: &BackfillArgs,
+) -> Result<()> {
+  let scorer = &config.scorer()?;
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let since = NaiveDate::parse_from_str(&args.since, "%Y-%m-%d").wrap_err_with(|| {
+    format!(
+      "Unable to parse since date {}, expected yyyy-mm-dd",
+      args.since
+    )
+  })?;
+  let since = DateTime::from_naive_utc_and_offset(since.and_hms_opt(0, 0, 0).unwrap(), Utc);
+  let now = Utc::now();
+
+  let lists = kanban.get_lists(&board.id).await?;
+  let histories = kanban.get_card_histories(&board.id).await?;
+
+  let existing = client
+    .query_entries(
+      board.id.clone(),
+      Some(DateRange {
+        start: since.timestamp(),
+        end: now.timestamp(),
+      }),
+    )
+    .await?
+    .unwrap_or_default();
+  let day_of = |time_stamp: i64| (time_stamp - since.timestamp()).div_euclid(24 * 60 * 60);
+
+  let (entries, skipped): (Vec<Entry>, Vec<Entry>) =
+    backfill_entries(&board, &lists, &histories, scorer, since, now)
+      .into_iter()
+      .partition(|entry| {
+        !existing
+          .iter()
+          .any(|saved| day_of(saved.time_stamp) == day_of(entry.time_stamp))
+      });
+
+  if args.dry_run {
+    println!(
+      "{} entries for {} would be saved",
+      entries.len(),
+      board.name
+    );
+  } else {
+    client.add_entries(&entries).await?;
+    println!("Saved {} entries for {}", entries.len(), board.name);
+    let removed = enforce_retention(client.as_ref(), config, &board.id).await?;
+    if removed > 0 {
+      println!("Removed {} entries past your retention policy", removed);
+    }
+  }
+  if !skipped.is_empty() {
+    println!(
+      "Skipped {} days that already had an entry saved",
+      skipped.len()
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::kanban::Transition;
+  use chrono::TimeZone;
+
+  #[test]
+  fn it_rebuilds_daily_entries_from_card_movement() {
+    let board = Board {
+      id: "board".to_string(),
+      name: "Sprint".to_string(),
+    };
+    let list = |name: &str| List {
+      name: name.to_string(),
+      id: name.to_string(),
+      board_id: "board".to_string(),
+    };
+    let lists = vec![list("To Do"), list("Doing"), list("Done")];
+    let history = |name: &str, created: u32, moves: &[(&str, &str, u32)]| CardHistory {
+      id: name.to_string(),
+      name: name.to_string(),
+      created: Utc.with_ymd_and_hms(2021, 3, created, 9, 0, 0).unwrap(),
+      current_list: moves
+        .last()
+        .map(|(_, to, _)| to.to_string())
+        .unwrap_or_else(|| "To Do".to_string()),
+      members: vec![],
+      transitions: moves
+        .iter()
+        .map(|(from, to, day)| Transition {
+          from: Some(from.to_string()),
+          to: to.to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, *day, 15, 0, 0).unwrap(),
+        })
+        .collect(),
+    };
+    let histories = vec![
+      history(
+        "(3) Login",
+        1,
+        &[("To Do", "Doing", 2), ("Doing", "Done", 3)],
+      ),
+      history("(5) Signup", 2, &[]),
+    ];
+
+    let entries = backfill_entries(
+      &board,
+      &lists,
+      &histories,
+      &Scorer::default(),
+      Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+      Utc.with_ymd_and_hms(2021, 3, 4, 8, 0, 0).unwrap(),
+    );
+    let scores: Vec<Vec<i32>> = entries
+      .iter()
+      .map(|entry| entry.decks.iter().map(|deck| deck.score).collect())
+      .collect();
+    assert_eq!(scores, vec![vec![3, 0, 0], vec![5, 3, 0], vec![5, 0, 3]]);
+    assert_eq!(
+      entries[0].time_stamp,
+      Utc
+        .with_ymd_and_hms(2021, 3, 1, 23, 59, 59)
+        .unwrap()
+        .timestamp()
+    );
+    assert_eq!(entries[2].board_name, "Sprint");
+  }
+}
@@ -0,0 +1,151 @@
+use crate::{
+  cli::{BackupArgs, RestoreArgs},
+  commands::prune::enforce_retention,
+  database::{config::Config, Database, Entries},
+  errors::*,
+};
+
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{self, File},
+  io::{self, BufWriter, Write},
+};
+
+/// Bumped whenever the layout of a backup changes, so older versions of card-counter refuse to
+/// restore backups they don't understand
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Every entry in a database, in a format that doesn't depend on which database it came from
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Backup {
+  pub version: u32,
+  pub entries: Entries,
+}
+
+impl Backup {
+  /// Orders entries by board and then by time, so backing up the same entries twice gives the
+  /// same file
+  pub fn new(mut entries: Entries) -> Self {
+    entries.sort_by(|a, b| {
+      a.board_id
+        .cmp(&b.board_id)
+        .then(a.time_stamp.cmp(&b.time_stamp))
+    });
+    Backup {
+      version: BACKUP_VERSION,
+      entries,
+    }
+  }
+
+  pub fn parse(contents: &str) -> Result<Self> {
+    let backup: Backup =
+      serde_json::from_str(contents).wrap_err_with(|| "Unable to parse backup")?;
+    if backup.version > BACKUP_VERSION {
+      return Err(eyre!(
+        "This backup was made by a newer version of card-counter (version {}), please upgrade to restore it",
+        backup.version
+      ));
+    }
+    Ok(backup)
+  }
+}
+
+/// Writes every entry in the database to --output, or standard out without it
+pub async fn backup(client: Box<dyn Database>, args: &BackupArgs) -> Result<()> {
+  let entries = client.all_entries().await?.ok_or_else(|| {
+    eyre!(
+      "Unable to list every entry in the {} database",
+      client.what_type()
+    )
+  })?;
+  let backup = Backup::new(entries);
+
+  match args.output.as_deref() {
+    Some(path) => {
+      let file = File::create(path).wrap_err_with(|| format!("Unable to create file {}", path))?;
+      let mut writer = BufWriter::new(file);
+      serde_json::to_writer_pretty(&mut writer, &backup)
+        .wrap_err_with(|| "Unable to serialize backup")?;
+      writer.flush()?;
+      println!("Saved {} entries to {}", backup.entries.len(), path);
+    }
+    None => {
+      let stdout = io::stdout();
+      let mut writer = stdout.lock();
+      serde_json::to_writer_pretty(&mut writer, &backup)
+        .wrap_err_with(|| "Unable to serialize backup")?;
+      writer.write_all(b"\n")?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Saves every entry in --input to the database. Entries already in the database are replaced by
+/// the ones in the backup.
+pub async fn restore(client: Box<dyn Database>, config: &Config, args: &RestoreArgs) -> Result<()> {
+  let path = &args.input;
+  let contents =
+    fs::read_to_string(path).wrap_err_with(|| format!("Unable to read backup {}", path))?;
+  let backup = Backup::parse(&contents)?;
+
+  let restored = backup.entries.len();
+  client.add_entries(&backup.entries).await?;
+  println!(
+    "Restored {} entries to the {} database",
+    restored,
+    client.what_type()
+  );
+
+  let mut board_ids: Vec<&str> = backup
+    .entries
+    .iter()
+    .map(|entry| entry.board_id.as_str())
+    .collect();
+  board_ids.sort_unstable();
+  board_ids.dedup();
+  let mut removed = 0;
+  for board_id in board_ids {
+    removed += enforce_retention(client.as_ref(), config, board_id).await?;
+  }
+  if removed > 0 {
+    println!("Removed {} entries past your retention policy", removed);
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::Entry;
+
+  fn entry(board_id: &str, time_stamp: i64) -> Entry {
+    Entry {
+      board_id: board_id.to_string(),
+      time_stamp,
+      ..Entry::default()
+    }
+  }
+
+  #[test]
+  fn it_round_trips_backups() {
+    let backup = Backup::new(vec![entry("b", 1), entry("a", 2), entry("a", 1)]);
+    let contents = serde_json::to_string(&backup).unwrap();
+
+    let restored = Backup::parse(&contents).unwrap();
+    let keys: Vec<(&str, i64)> = restored
+      .entries
+      .iter()
+      .map(|entry| (entry.board_id.as_str(), entry.time_stamp))
+      .collect();
+    assert_eq!(restored.version, BACKUP_VERSION);
+    assert_eq!(keys, vec![("a", 1), ("a", 2), ("b", 1)]);
+  }
+
+  #[test]
+  fn it_refuses_backups_from_newer_versions() {
+    let contents = format!(r#"{{"version": {}, "entries": []}}"#, BACKUP_VERSION + 1);
+    assert!(Backup::parse(&contents).is_err());
+  }
+}
@@ -0,0 +1,73 @@
+use crate::{cli::BoardsArgs, errors::*, kanban::Board};
+
+use prettytable::Table;
+use serde::Serialize;
+
+/// A board as printed by `boards --output json`
+#[derive(Serialize, Debug)]
+pub struct BoardLine<'a> {
+  pub id: &'a str,
+  pub name: &'a str,
+  pub provider: &'a str,
+}
+
+/// Sorts boards by name, so they're listed the same way the board selector shows them
+pub fn sort_boards(mut boards: Vec<Board>) -> Vec<Board> {
+  boards.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+  boards
+}
+
+pub fn print_boards(boards: &[Board], provider: &str) {
+  let mut table = Table::new();
+  table.set_titles(row!["ID", "Name", "Provider"]);
+  for board in boards {
+    table.add_row(row![board.id, board.name, provider]);
+  }
+  table.printstd();
+}
+
+pub fn boards_as_json(boards: &[Board], provider: &str) -> Result<String> {
+  let lines: Vec<BoardLine> = boards
+    .iter()
+    .map(|board| BoardLine {
+      id: &board.id,
+      name: &board.name,
+      provider,
+    })
+    .collect();
+  serde_json::to_string_pretty(&lines).wrap_err_with(|| "Unable to serialize boards as JSON")
+}
+
+/// Prints every board the configured credentials can see, so their ids can be used in scripts
+pub fn list_boards(boards: Vec<Board>, provider: &str, args: &BoardsArgs) -> Result<()> {
+  let boards = sort_boards(boards);
+  match args.output.as_str() {
+    "json" => println!("{}", boards_as_json(&boards, provider)?),
+    _ => print_boards(&boards, provider),
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_lists_boards_as_json() {
+    let board = |id: &str, name: &str| Board {
+      id: id.to_string(),
+      name: name.to_string(),
+    };
+    let boards = sort_boards(vec![board("2", "Sprint"), board("1", "Roadmap")]);
+
+    let json: serde_json::Value =
+      serde_json::from_str(&boards_as_json(&boards, "Trello").unwrap()).unwrap();
+    assert_eq!(
+      json,
+      serde_json::json!([
+        {"id": "1", "name": "Roadmap", "provider": "Trello"},
+        {"id": "2", "name": "Sprint", "provider": "Trello"},
+      ])
+    );
+  }
+}
@@ -1,7 +1,16 @@
 use crate::{
-  database::{Database, DateRange, Entry},
+  cli::RangeArgs,
+  commands::{
+    inline::{detect_protocol, Canvas},
+    ndjson::BurndownLine,
+  },
+  database::{
+    config::{Annotation, ChartStyle, CsvDialect, DateFormat, Timezone},
+    Database, DateRange, Entries, Entry,
+  },
   errors::*,
   kanban::{Board, Kanban},
+  score::ListFilter,
 };
 use core::fmt;
 
@@ -9,7 +18,10 @@ use serde::{Serialize, Serializer};
 
 use pointplots::{Chart, PixelColor, Plot, Point, Shape};
 
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc, Weekday};
+use std::collections::BTreeMap;
 
 use tera::{Context, Tera};
 
@@ -42,7 +54,7 @@ impl<T: TimeZone> From<DateTime<T>> for Timestamp {
 
 impl fmt::Display for Timestamp {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-    let date = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(self.0 as i64, 0), Utc);
+    let date = Utc.timestamp_opt(self.0 as i64, 0).unwrap();
     f.write_fmt(format_args!("{}", date.format("%Y-%m-%d")))
   }
 }
@@ -56,57 +68,244 @@ impl Serialize for Timestamp {
   }
 }
 
+/// Decides which lists a card is in counts towards a state, like done. A list belongs when its name
+/// is one of the patterns, or when one of the patterns is a regex matching its name.
+#[derive(Debug, Clone)]
+pub struct ListMatcher {
+  patterns: Vec<(String, Regex)>,
+}
+
+impl ListMatcher {
+  pub fn new(patterns: &[String]) -> Result<Self> {
+    let patterns = patterns
+      .iter()
+      .map(|pattern| {
+        Regex::new(pattern)
+          .map(|regex| (pattern.clone(), regex))
+          .map_err(|err| eyre!("{} isn't a valid list name regex: {}", pattern, err))
+      })
+      .collect::<Result<Vec<(String, Regex)>>>()?;
+    Ok(ListMatcher { patterns })
+  }
+
+  pub fn matches(&self, list_name: &str) -> bool {
+    self
+      .patterns
+      .iter()
+      .any(|(name, regex)| name == list_name || regex.is_match(list_name))
+  }
+}
+
+/// Any list with Done in its name
+impl Default for ListMatcher {
+  fn default() -> Self {
+    ListMatcher::new(&["Done".to_string()]).expect("Done is a valid regex")
+  }
+}
+
+/// Where the cards in a list are in the team's process
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListState {
+  Backlog,
+  Committed,
+  InProgress,
+  Done,
+}
+
+/// Sorts lists into states. A list matching more than one state is put in the furthest along, and
+/// lists that don't match any are counted as committed.
+#[derive(Debug, Clone)]
+pub struct StateMatcher {
+  pub backlog: ListMatcher,
+  pub committed: ListMatcher,
+  pub in_progress: ListMatcher,
+  pub done: ListMatcher,
+}
+
+impl StateMatcher {
+  pub fn state_of(&self, list_name: &str) -> ListState {
+    if self.done.matches(list_name) {
+      ListState::Done
+    } else if self.in_progress.matches(list_name) {
+      ListState::InProgress
+    } else if self.committed.matches(list_name) {
+      ListState::Committed
+    } else if self.backlog.matches(list_name) {
+      ListState::Backlog
+    } else {
+      ListState::Committed
+    }
+  }
+}
+
+/// Lists with Done in their name are done, everything else is committed
+impl Default for StateMatcher {
+  fn default() -> Self {
+    let none = ListMatcher::new(&[]).expect("No patterns are always valid");
+    StateMatcher {
+      backlog: none.clone(),
+      committed: none.clone(),
+      in_progress: none,
+      done: ListMatcher::default(),
+    }
+  }
+}
+
 pub struct BurndownOptions {
   pub board_id: String,
+  // Set when the board was picked with --board-name, entries are then found by name
+  pub board_name: Option<String>,
   pub client: Box<dyn Database>,
   pub range: DateRange,
-  pub filter: Option<String>,
+  pub filter: ListFilter,
+  // Which lists are backlog, committed, in progress, and done
+  pub states: StateMatcher,
+  // Carries the previous day's points over days without an entry
+  pub fill_missing_days: bool,
+  // The timezone entries are split into days by
+  pub timezone: Timezone,
 }
 
 impl BurndownOptions {
-  pub async fn init_with_matches(
+  pub async fn init_with_args(
     kanban: Box<dyn Kanban>,
     client: Box<dyn Database>,
-    matches: &clap::ArgMatches<'_>,
+    states: StateMatcher,
+    timezone: Timezone,
+    args: &RangeArgs,
   ) -> Result<BurndownOptions> {
-    let start = matches.value_of("start").expect("Missing start argument");
-    let end = matches.value_of("end").expect("Missing end argument");
-
-    let range = DateRange::from_strs(start, end);
-
-    let board: Board = match matches.value_of("board_id") {
-      Some(id) => kanban.get_board(id).await?,
-      None => kanban.select_board().await?,
+    let (board_id, board_name) = match &args.board_name {
+      // The board may not exist anymore, so the newest entry saved under the name stands in for it
+      Some(name) => {
+        let latest = client
+          .query_entries_by_name(name.to_string(), None)
+          .await?
+          .and_then(|entries| entries.into_iter().max())
+          .ok_or_else(|| eyre!("Unable to find any entries for a board named {}", name))?;
+        (latest.board_id, Some(latest.board_name))
+      }
+      None => {
+        let board: Board = match args.board_id.as_deref() {
+          Some(id) => kanban.get_board(id).await?,
+          None => kanban.select_board().await?,
+        };
+        (board.id, None)
+      }
     };
-    let board_id = board.id;
-    let filter: Option<String> = matches.value_of("filter").map(|filter| filter.into());
+
+    let range = DateRange::resolve(
+      client.as_ref(),
+      &board_id,
+      &args.start,
+      &args.end,
+      &timezone,
+    )
+    .await?;
+    let filter = ListFilter::from_args(&args.filter)?;
 
     Ok(Self {
       client,
       board_id,
+      board_name,
       filter,
       range,
+      states,
+      fill_missing_days: true,
+      timezone,
     })
   }
 
+  /// Fetches the entries saved for the board within the date range
+  pub async fn entries(&self) -> Result<Entries> {
+    self.entries_within(self.range.clone()).await
+  }
+
+  async fn entries_within(&self, range: DateRange) -> Result<Entries> {
+    let entries = match &self.board_name {
+      Some(name) => {
+        self
+          .client
+          .query_entries_by_name(name.clone(), Some(range))
+          .await?
+      }
+      None => {
+        self
+          .client
+          .query_entries(self.board_id.clone(), Some(range))
+          .await?
+      }
+    };
+    Ok(entries.unwrap_or_default())
+  }
+
+  /// Builds the burndowns for the `count` sprints before this one, each `sprint_length` days
+  /// long, and averages them by sprint day. Returns None if no earlier entries were saved.
+  pub async fn trailing_average(
+    &self,
+    count: i64,
+    sprint_length: Option<i64>,
+  ) -> Result<Option<Burndown>> {
+    let day = 24 * 60 * 60;
+    let sprint_length = match sprint_length {
+      Some(days) => days
+        .checked_mul(day)
+        .ok_or_else(|| eyre!("--sprint-length {} is too many days", days))?,
+      None => ((self.range.end - self.range.start) + day - 1) / day * day,
+    };
+
+    let mut sprints = Vec::new();
+    for sprint in 1..=count {
+      let offset = sprint
+        .checked_mul(sprint_length)
+        .filter(|offset| self.range.start.checked_sub(*offset).is_some())
+        .ok_or_else(|| eyre!("Unable to go back {} sprints from the date range", sprint))?;
+      let range = DateRange {
+        start: self.range.start - offset,
+        end: self.range.end - offset,
+      };
+      let start = Utc.timestamp_opt(range.start, 0).unwrap();
+      let entries = self.entries_within(range).await?;
+
+      if !entries.is_empty() {
+        sprints.push((
+          start,
+          Burndown::calculate_burndown(
+            &entries,
+            &self.filter,
+            &self.states.done,
+            self.fill_missing_days.then_some(self.timezone),
+          ),
+        ));
+      }
+    }
+
+    let start = Utc.timestamp_opt(self.range.start, 0).unwrap();
+    Ok(Burndown::trailing_average(&sprints, start))
+  }
+
   pub async fn into_burndown(self) -> Result<Burndown> {
-    let entries = self
-      .client
-      .query_entries(self.board_id, Some(self.range))
-      .await?
-      .unwrap();
-    Ok(Burndown::calculate_burndown(&entries, self.filter))
+    let entries = self.entries().await?;
+    Ok(Burndown::calculate_burndown(
+      &entries,
+      &self.filter,
+      &self.states.done,
+      self.fill_missing_days.then_some(self.timezone),
+    ))
   }
 }
 
 impl Entry {
-  /// Calculates a Deck's total score based on the score of the list done vs the other lists.
+  /// Calculates a Deck's total score based on the score of the lists done_lists matches vs the
+  /// other lists.
   /// Ex:
   /// ```
-  /// use card_counter::{database::Entry, score::Deck};
+  /// use card_counter::{commands::burndown::ListMatcher, database::Entry, score::{Deck, ListFilter}};
   /// let entry = Entry {
   ///       board_id: "board-id-1".to_string(),
+  ///       board_name: String::new(),
   ///       time_stamp: 1,
+  ///       tags: vec![],
+  ///       cards: vec![],
   ///       decks: vec![
   ///         Deck {list_name: "listA".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
   ///         Deck {list_name: "listB".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
@@ -114,16 +313,18 @@ impl Entry {
   ///       ],
   ///   };
   ///
-  /// assert_eq!((40, 40), entry.calculate_score(&None));
+  /// assert_eq!((40, 40), entry.calculate_score(&ListFilter::default(), &ListMatcher::default()));
+  /// let shipped = ListMatcher::new(&["listB".to_string()]).unwrap();
+  /// assert_eq!((60, 20), entry.calculate_score(&ListFilter::default(), &shipped));
   /// ```
-  pub fn calculate_score(&self, filter: &Option<String>) -> (i32, i32) {
+  pub fn calculate_score(&self, filter: &ListFilter, done_lists: &ListMatcher) -> (i32, i32) {
     self
       .decks
       .iter()
       .fold((0, 0), |(incomplete, complete), deck| -> (i32, i32) {
-        if filter.is_some() && deck.list_name.contains(filter.as_ref().unwrap()) {
+        if !filter.keeps(&deck.list_name) {
           (incomplete, complete)
-        } else if deck.list_name.contains("Done") {
+        } else if done_lists.matches(&deck.list_name) {
           (incomplete, complete + deck.score)
         } else {
           (incomplete + deck.score, complete)
@@ -132,20 +333,85 @@ impl Entry {
   }
 }
 
+/// The template SVG burndowns are drawn with unless svg_template or --template says otherwise
+pub const SVG_TEMPLATE: &str = include_str!("../template/burndown.svg");
+
+/// The burndown outputs that can be written to a file with --output-file
+pub const FILE_OUTPUTS: [&str; 4] = ["csv", "svg", "png", "plotly"];
+
+/// Where the plotly HTML output loads plotly.js from
+const PLOTLY_SRC: &str = "https://cdn.plot.ly/plotly-2.27.0.min.js";
+
+/// The position and text of an annotation drawn on the SVG chart
+#[derive(Serialize)]
+struct Marker {
+  x: f64,
+  label: String,
+}
+
+/// Where a weekend starts on the SVG chart and how wide it is
+#[derive(Serialize)]
+struct Span {
+  x: f64,
+  width: f64,
+}
+
+/// The terminal colour with the given name, for drawing ASCII charts
+fn pixel_colour(name: &str) -> Result<PixelColor> {
+  match name.to_lowercase().as_str() {
+    "black" => Ok(PixelColor::Black),
+    "red" => Ok(PixelColor::Red),
+    "green" => Ok(PixelColor::Green),
+    "yellow" => Ok(PixelColor::Yellow),
+    "blue" => Ok(PixelColor::Blue),
+    "magenta" => Ok(PixelColor::Magenta),
+    "cyan" => Ok(PixelColor::Cyan),
+    "white" => Ok(PixelColor::White),
+    _ => Err(eyre!(
+      "Unknown terminal colour {}. Expected black, red, green, yellow, blue, magenta, cyan, or white",
+      name
+    )),
+  }
+}
+
+/// Whether the day is a Saturday or Sunday
+pub fn is_weekend(day: &impl Datelike) -> bool {
+  matches!(day.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Burndown(pub Vec<(DateTime<Utc>, i32, i32)>);
 
+/// Points being added to, or removed from, the board between two entries
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeChange {
+  pub date: DateTime<Utc>,
+  pub before: i32,
+  pub after: i32,
+}
+
+impl ScopeChange {
+  pub fn change(&self) -> i32 {
+    self.after - self.before
+  }
+}
+
 impl Burndown {
   /// Aggregates the score of a set of entries into a list of 3-tuples
   /// of [("dd-mm-yyyy", i32, i32)...] for ease in rendering content
-  /// to a human useable form.
+  /// to a human useable form. When fill_days_in is given a timezone, days in that timezone without
+  /// an entry get a point carrying over the previous day's score, so every day in the range shows
+  /// up on the chart.
   /// Ex:
   /// ```
-  /// use card_counter::{database::Entry, score::Deck, commands::burndown::Burndown};
+  /// use card_counter::{database::Entry, score::{Deck, ListFilter}, commands::burndown::{Burndown, ListMatcher}};
   /// use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
   /// let entry = Entry {
   ///       board_id: "board-id-1".to_string(),
+  ///       board_name: String::new(),
   ///       time_stamp: 1,
+  ///       tags: vec![],
+  ///       cards: vec![],
   ///       decks: vec![
   ///         Deck {list_name: "listA".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
   ///         Deck {list_name: "listB".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
@@ -154,7 +420,10 @@ impl Burndown {
   ///   };
   /// let entry2 = Entry {
   ///       board_id: "board-id-1".to_string(),
+  ///       board_name: String::new(),
   ///       time_stamp: 86401,
+  ///       tags: vec![],
+  ///       cards: vec![],
   ///       decks: vec![
   ///         Deck {list_name: "listA".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
   ///         Deck {list_name: "listB".to_string(), size: 5, score: 10, unscored: 0, estimated: 10 },
@@ -162,11 +431,16 @@ impl Burndown {
   ///       ],
   ///   };
   /// let entries = vec![entry, entry2];
-  /// let timestamp = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(1, 0), Utc);
-  /// let timestamp2 = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(86401, 0), Utc);
-  /// assert_eq!(vec![(timestamp, 40, 40), (timestamp2, 30, 50)], Burndown::calculate_burndown(&entries, None).0);
+  /// let timestamp = Utc.timestamp_opt(1, 0).unwrap();
+  /// let timestamp2 = Utc.timestamp_opt(86401, 0).unwrap();
+  /// assert_eq!(vec![(timestamp, 40, 40), (timestamp2, 30, 50)], Burndown::calculate_burndown(&entries, &ListFilter::default(), &ListMatcher::default(), None).0);
   /// ```
-  pub fn calculate_burndown(entries: &[Entry], filter: Option<String>) -> Self {
+  pub fn calculate_burndown(
+    entries: &[Entry],
+    filter: &ListFilter,
+    done_lists: &ListMatcher,
+    fill_days_in: Option<Timezone>,
+  ) -> Self {
     let mut entries = entries.to_vec();
 
     // In some cases, there are going to be multiple entries for a
@@ -175,8 +449,8 @@ impl Burndown {
     entries.sort();
     let mut burndown: Vec<(DateTime<Utc>, i32, i32)> = Vec::new();
     entries.into_iter().for_each(|entry| {
-      let time = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(entry.time_stamp, 0), Utc);
-      let (incomplete, complete) = entry.calculate_score(&filter);
+      let time = Utc.timestamp_opt(entry.time_stamp, 0).unwrap();
+      let (incomplete, complete) = entry.calculate_score(filter, done_lists);
 
       // Remove duplicate entry
       if let Some(entry) = burndown.last() {
@@ -188,17 +462,130 @@ impl Burndown {
       burndown.push((time, incomplete, complete));
     });
 
-    Burndown(burndown)
+    match fill_days_in {
+      Some(timezone) => Burndown(Self::carry_forward(burndown, &timezone)),
+      None => Burndown(burndown),
+    }
+  }
+
+  /// Repeats each point, at the same time of day, on every day in timezone before the next point
+  fn carry_forward(
+    points: Vec<(DateTime<Utc>, i32, i32)>,
+    timezone: &Timezone,
+  ) -> Vec<(DateTime<Utc>, i32, i32)> {
+    let mut filled: Vec<(DateTime<Utc>, i32, i32)> = Vec::with_capacity(points.len());
+    for point in points {
+      if let Some(&(last, incomplete, complete)) = filled.last() {
+        let mut day = last + Duration::days(1);
+        while timezone.day(&day) < timezone.day(&point.0) {
+          filled.push((day, incomplete, complete));
+          day += Duration::days(1);
+        }
+      }
+      filled.push(point);
+    }
+    filled
+  }
+
+  /// Averages several sprints' burndowns by how many days into the sprint each point was saved,
+  /// then dates the averaged points as if they started on `start`. Sprints are given as pairs of
+  /// the day the sprint started and its burndown.
+  /// Ex:
+  /// ```
+  /// use card_counter::commands::burndown::Burndown;
+  /// use chrono::{TimeZone, Utc};
+  /// let first = Burndown(vec![
+  ///   (Utc.with_ymd_and_hms(2021, 3, 1, 9, 0, 0).unwrap(), 20, 0),
+  ///   (Utc.with_ymd_and_hms(2021, 3, 2, 9, 0, 0).unwrap(), 10, 10),
+  /// ]);
+  /// let second = Burndown(vec![
+  ///   (Utc.with_ymd_and_hms(2021, 3, 15, 9, 0, 0).unwrap(), 30, 0),
+  ///   (Utc.with_ymd_and_hms(2021, 3, 16, 9, 0, 0).unwrap(), 20, 10),
+  /// ]);
+  /// let average = Burndown::trailing_average(
+  ///   &[(Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(), first), (Utc.with_ymd_and_hms(2021, 3, 15, 0, 0, 0).unwrap(), second)],
+  ///   Utc.with_ymd_and_hms(2021, 3, 29, 0, 0, 0).unwrap(),
+  /// );
+  /// assert_eq!(
+  ///   Some(Burndown(vec![(Utc.with_ymd_and_hms(2021, 3, 29, 0, 0, 0).unwrap(), 25, 0), (Utc.with_ymd_and_hms(2021, 3, 30, 0, 0, 0).unwrap(), 15, 10)])),
+  ///   average
+  /// );
+  /// ```
+  pub fn trailing_average(
+    sprints: &[(DateTime<Utc>, Burndown)],
+    start: DateTime<Utc>,
+  ) -> Option<Burndown> {
+    // Sums of incomplete and complete points, and how many sprints had a point, for each day
+    let mut days: BTreeMap<i64, (i32, i32, i32)> = BTreeMap::new();
+    for (sprint_start, burndown) in sprints {
+      // The last point saved in a day wins
+      let last_in_day: BTreeMap<i64, (i32, i32)> = burndown
+        .0
+        .iter()
+        .map(|(date, incomplete, complete)| {
+          ((*date - *sprint_start).num_days(), (*incomplete, *complete))
+        })
+        .collect();
+
+      for (day, (incomplete, complete)) in last_in_day {
+        let totals = days.entry(day).or_insert((0, 0, 0));
+        *totals = (totals.0 + incomplete, totals.1 + complete, totals.2 + 1);
+      }
+    }
+
+    if days.is_empty() {
+      return None;
+    }
+
+    Some(Burndown(
+      days
+        .into_iter()
+        .map(|(day, (incomplete, complete, count))| {
+          (
+            start + Duration::days(day),
+            (incomplete as f64 / count as f64).round() as i32,
+            (complete as f64 / count as f64).round() as i32,
+          )
+        })
+        .collect(),
+    ))
+  }
+
+  /// Every time the total points on the board, complete and incomplete, changed between one point
+  /// in the burndown and the next
+  pub fn scope_changes(&self) -> Vec<ScopeChange> {
+    self
+      .0
+      .windows(2)
+      .filter_map(|pair| {
+        let before = pair[0].1 + pair[0].2;
+        let after = pair[1].1 + pair[1].2;
+        match before == after {
+          true => None,
+          false => Some(ScopeChange {
+            date: pair[1].0,
+            before,
+            after,
+          }),
+        }
+      })
+      .collect()
   }
 
   /// Formats a Burndown struct as a vector of csv, with the first row being the header row.
+  /// Dates are formatted using `dialect.date`, or `format.date` when it isn't set, and rows are
+  /// written using `dialect`. When an average burndown is given, its incomplete points for the same
+  /// day are added as a column.
   /// Ex:
   /// ```
-  /// use card_counter::{database::Entry, score::Deck, commands::burndown::Burndown};
+  /// use card_counter::{database::{config::{CsvDialect, DateFormat, Timezone}, Entry}, score::{Deck, ListFilter}, commands::burndown::{Burndown, ListMatcher}};
   /// use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
   /// let entry = Entry {
   ///       board_id: "board-id-1".to_string(),
+  ///       board_name: String::new(),
   ///       time_stamp: 1,
+  ///       tags: vec![],
+  ///       cards: vec![],
   ///       decks: vec![
   ///         Deck {list_name: "listA".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
   ///         Deck {list_name: "listB".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
@@ -207,7 +594,10 @@ impl Burndown {
   ///   };
   /// let entry2 = Entry {
   ///       board_id: "board-id-1".to_string(),
+  ///       board_name: String::new(),
   ///       time_stamp: 86401,
+  ///       tags: vec![],
+  ///       cards: vec![],
   ///       decks: vec![
   ///         Deck {list_name: "listA".to_string(), size: 5, score: 20, unscored: 0, estimated: 20 },
   ///         Deck {list_name: "listB".to_string(), size: 5, score: 10, unscored: 0, estimated: 10 },
@@ -215,83 +605,340 @@ impl Burndown {
   ///       ],
   ///   };
   /// let entries = vec![entry, entry2];
-  /// let timestamp = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(1, 0), Utc);
-  /// let timestamp2 = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(86401, 0), Utc);
-  /// assert_eq!(vec!["Date,Incomplete,Complete", "01-01-1970,40,40", "02-01-1970,30,50"], Burndown::calculate_burndown(&entries, None).as_csv());
+  /// let timestamp = Utc.timestamp_opt(1, 0).unwrap();
+  /// let timestamp2 = Utc.timestamp_opt(86401, 0).unwrap();
+  /// assert_eq!(vec!["Date,Incomplete,Complete", "01-01-1970,40,40", "02-01-1970,30,50"], Burndown::calculate_burndown(&entries, &ListFilter::default(), &ListMatcher::default(), None).as_csv(&DateFormat { timezone: Timezone::Utc, ..DateFormat::default() }, &CsvDialect::default(), None));
   ///```
-  pub fn as_csv(&self) -> Vec<String> {
-    let mut output = vec!["Date,Incomplete,Complete".to_string()];
+  pub fn as_csv(
+    &self,
+    format: &DateFormat,
+    dialect: &CsvDialect,
+    average: Option<&Burndown>,
+  ) -> Vec<String> {
+    let mut header = vec!["Date", "Incomplete", "Complete"];
+    if average.is_some() {
+      header.push("Average Incomplete");
+    }
+
+    let mut output = vec![dialect.row(&header)];
     output.extend(self.0.iter().map(|(time, incomplete, complete)| {
-      format!(
-        "{},{},{}",
-        time.format("%d-%m-%Y").to_string(),
+      let mut row = vec![
+        dialect.date(format, time),
+        dialect.number(incomplete),
+        dialect.number(complete),
+      ];
+      if let Some(average) = average {
+        row.push(
+          average
+            .incomplete_on(time, &format.timezone)
+            .map(|points| dialect.number(points))
+            .unwrap_or_default(),
+        );
+      }
+      dialect.row(&row)
+    }));
+
+    output
+  }
+
+  /// Draws the points completed by the end of each day as a single line of unicode bars, scaled
+  /// from the fewest to the most points completed. With ascii, the bars are drawn with plain
+  /// characters instead, from _ up to #.
+  /// Ex:
+  /// ```
+  /// use card_counter::{commands::burndown::Burndown, database::config::Timezone};
+  /// use chrono::{TimeZone, Utc};
+  /// let burndown = Burndown(vec![
+  ///   (Utc.with_ymd_and_hms(2021, 3, 1, 9, 0, 0).unwrap(), 20, 0),
+  ///   (Utc.with_ymd_and_hms(2021, 3, 2, 9, 0, 0).unwrap(), 16, 4),
+  ///   (Utc.with_ymd_and_hms(2021, 3, 2, 17, 0, 0).unwrap(), 13, 7),
+  ///   (Utc.with_ymd_and_hms(2021, 3, 3, 9, 0, 0).unwrap(), 6, 14),
+  /// ]);
+  /// assert_eq!("▁▄█", burndown.as_sparkline(&Timezone::Utc, false));
+  /// assert_eq!("_:#", burndown.as_sparkline(&Timezone::Utc, true));
+  /// ```
+  pub fn as_sparkline(&self, timezone: &Timezone, ascii: bool) -> String {
+    const UNICODE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const ASCII_BARS: [char; 8] = ['_', '.', '-', ':', '=', '+', '*', '#'];
+    let bars = match ascii {
+      true => ASCII_BARS,
+      false => UNICODE_BARS,
+    };
+
+    // The last point saved in a day wins
+    let days: BTreeMap<_, i32> = self
+      .0
+      .iter()
+      .map(|(date, _, complete)| (timezone.day(date), *complete))
+      .collect();
+    let min = days.values().copied().min().unwrap_or(0);
+    let max = days.values().copied().max().unwrap_or(0);
+
+    days
+      .values()
+      .map(|complete| match max - min {
+        0 => bars[0],
+        range => bars[((complete - min) * (bars.len() as i32 - 1) / range) as usize],
+      })
+      .collect()
+  }
+
+  /// Pairs each day with its points, and the average's points for that day, for `--output ndjson`
+  pub fn as_ndjson(&self, timezone: &Timezone, average: Option<&Burndown>) -> Vec<BurndownLine> {
+    self
+      .0
+      .iter()
+      .map(|(time, incomplete, complete)| BurndownLine {
+        date: *time,
+        incomplete: *incomplete,
+        complete: *complete,
+        average_incomplete: average.and_then(|average| average.incomplete_on(time, timezone)),
+      })
+      .collect()
+  }
+
+  /// Returns the incomplete points for the same day as date in timezone
+  fn incomplete_on(&self, date: &DateTime<Utc>, timezone: &Timezone) -> Option<i32> {
+    self
+      .0
+      .iter()
+      .rev()
+      .find(|(time, _, _)| timezone.day(time) == timezone.day(date))
+      .map(|(_, incomplete, _)| *incomplete)
+  }
+
+  /// Writes the burndown as plain text, a row for each point with a bar of # for the incomplete
+  /// points and = for the complete ones. Unlike the ASCII chart it has no braille or colour, so it
+  /// reads well in logs and screen readers. Bars are half of ascii_width long at most, the same
+  /// number of characters the ASCII chart takes up.
+  pub fn as_text(
+    &self,
+    format: &DateFormat,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+  ) -> Vec<String> {
+    let width = (style.ascii_width / 2).max(1) as i32;
+    let max = self
+      .0
+      .iter()
+      .map(|(_, incomplete, complete)| incomplete + complete)
+      .max()
+      .unwrap_or(0)
+      .max(1);
+
+    let mut output = vec![style.title.clone(), String::new()];
+    output.extend(self.0.iter().map(|(time, incomplete, complete)| {
+      let mut row = format!(
+        "{} |{}{} {} incomplete, {} complete",
+        format.format(time, &format.date),
+        "#".repeat((*incomplete.max(&0) * width / max) as usize),
+        "=".repeat((*complete.max(&0) * width / max) as usize),
         incomplete,
         complete
-      )
+      );
+      if let Some(points) =
+        average.and_then(|average| average.incomplete_on(time, &format.timezone))
+      {
+        row.push_str(&format!(", {} average incomplete", points));
+      }
+      row
     }));
 
+    let annotations = self.annotations_in_range(annotations, &format.timezone);
+    if !annotations.is_empty() {
+      output.push(String::new());
+      output.push("Events".to_string());
+      output.extend(
+        annotations
+          .iter()
+          .map(|(_, annotation)| format!("{}: {}", annotation.date, annotation.label)),
+      );
+    }
+
     output
   }
 
-  /// Generates an ASCII graph of the Burndown struct and prints it to standard out
-  pub fn as_ascii(&self) -> Result<(), ()> {
+  /// Generates an ASCII graph of the Burndown struct and prints it to standard out. Annotations
+  /// that fall within the chart are drawn as vertical lines and listed below the chart, and an
+  /// average burndown is drawn as a third line.
+  pub fn as_ascii(
+    &self,
+    timezone: &Timezone,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+  ) -> Result<()> {
+    let incomplete_colour = pixel_colour(&style.ascii_incomplete_colour)?;
+    let complete_colour = pixel_colour(&style.ascii_complete_colour)?;
+    let average_colour = pixel_colour(&style.ascii_average_colour)?;
+    let annotation_colour = pixel_colour(&style.ascii_annotation_colour)?;
+
     let start_date: DateTime<Utc> = self.0.first().unwrap().0;
-    let end_date: DateTime<Utc> = self.0.last().unwrap().0;
+    let end_date: DateTime<Utc> = match average {
+      Some(average) => self.max_date().max(average.max_date()),
+      None => self.max_date(),
+    };
 
     let max_complete: i32 = self.max_complete();
 
     let max_incomplete: i32 = self.max_incomplete();
 
-    let max_y = max_complete.max(max_incomplete) as f64;
+    let max_y = max_complete
+      .max(max_incomplete)
+      .max(average.map(|average| average.max_incomplete()).unwrap_or(0)) as f64;
 
     let incomplete: Vec<Point<Timestamp, f64>> = self.incomplete_as_points();
 
     let complete: Vec<Point<Timestamp, f64>> = self.complete_as_points();
 
+    let average_points: Vec<Vec<Point<Timestamp, f64>>> = average
+      .map(|average| average.incomplete_as_points())
+      .into_iter()
+      .collect();
+    let average_shapes: Vec<Shape<Timestamp, f64>> = average_points
+      .iter()
+      .map(|points| Shape::Lines(points))
+      .collect();
+
+    let annotations = self.annotations_in_range(annotations, timezone);
+    let markers: Vec<Vec<Point<Timestamp, f64>>> = annotations
+      .iter()
+      .map(|(time_stamp, _)| {
+        vec![
+          Point {
+            x: Timestamp::from(*time_stamp as f64),
+            y: 0.,
+          },
+          Point {
+            x: Timestamp::from(*time_stamp as f64),
+            y: max_y,
+          },
+        ]
+      })
+      .collect();
+    let marker_shapes: Vec<Shape<Timestamp, f64>> =
+      markers.iter().map(|points| Shape::Lines(points)).collect();
+
     println!("Max: {}", max_y);
-    println!("\nBurndown Chart\n");
-    Chart::new(
-      120,
-      60,
-      start_date.timestamp() as f64,
-      end_date.timestamp() as f64,
-    )
-    .lineplot_with_tags(
-      &Shape::Lines(&complete),
-      Some("Complete".to_string()),
-      PixelColor::Blue,
-    )
-    .lineplot_with_tags(
-      &Shape::Lines(&incomplete),
-      Some("Incomplete".to_string()),
-      PixelColor::Red,
-    )
-    .display();
+    println!("\n{}\n", style.title);
+    if let Some(label) = &style.y_label {
+      println!("{}", label);
+    }
+    marker_shapes
+      .iter()
+      .zip(annotations.iter())
+      .fold(
+        average_shapes.iter().fold(
+          Chart::new(
+            style.ascii_width,
+            style.ascii_height,
+            start_date.timestamp() as f64,
+            end_date.timestamp() as f64,
+          )
+          .lineplot_with_tags(
+            &Shape::Lines(&complete),
+            Some("Complete".to_string()),
+            complete_colour,
+          )
+          .lineplot_with_tags(
+            &Shape::Lines(&incomplete),
+            Some("Incomplete".to_string()),
+            incomplete_colour,
+          ),
+          |chart, shape| {
+            chart.lineplot_with_tags(shape, Some("Average".to_string()), average_colour)
+          },
+        ),
+        |chart, (shape, (_, annotation))| {
+          chart.lineplot_with_tags(shape, Some(annotation.label.clone()), annotation_colour)
+        },
+      )
+      .display();
+    if let Some(label) = &style.x_label {
+      println!("{}", label);
+    }
+
+    if !annotations.is_empty() {
+      println!("\nEvents\n");
+      for (_, annotation) in annotations {
+        println!("{}: {}", annotation.date, annotation.label);
+      }
+    }
 
     Ok(())
   }
 
-  /// Generates an SVG graph of the Burndown struct and prints it to standard out
-  pub fn as_svg(&self) -> Result<String> {
+  /// Generates an SVG graph of the Burndown struct and prints it to standard out. An average
+  /// burndown, if given, is drawn as a dashed line of remaining points, and with show_scope the
+  /// total points on the board are drawn as well so scope changes stand out.
+  pub fn as_svg(
+    &self,
+    format: &DateFormat,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+    show_scope: bool,
+    show_weekends: bool,
+  ) -> Result<String> {
+    self.as_svg_with_template(
+      SVG_TEMPLATE,
+      format,
+      style,
+      annotations,
+      average,
+      show_scope,
+      show_weekends,
+    )
+  }
+
+  /// Draws the burndown with a Tera template of your own. The template is given the same paths,
+  /// colours, and labels the built in SVG template uses.
+  #[allow(clippy::too_many_arguments)]
+  pub fn as_svg_with_template(
+    &self,
+    template: &str,
+    format: &DateFormat,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+    show_scope: bool,
+    show_weekends: bool,
+  ) -> Result<String> {
     let mut context = Context::new();
 
     //hardset the padding around the graph
     let padding = 50;
 
     //ensure the viewbox is as per input
-    let width = 900 - padding * 2;
-    let height = 600 - padding * 2;
+    let width = style.width as i32 - padding * 2;
+    let height = style.height as i32 - padding * 2;
 
     let max_complete: i32 = self.max_complete();
     let max_incomplete: i32 = self.max_incomplete();
 
-    let max_y: f64 = max_complete.max(max_incomplete).into();
+    let max_average: i32 = average.map(|average| average.max_incomplete()).unwrap_or(0);
+
+    let max_scope: i32 = match show_scope {
+      true => self.max_scope(),
+      false => 0,
+    };
+
+    let max_y: f64 = max_complete
+      .max(max_incomplete)
+      .max(max_average)
+      .max(max_scope)
+      .into();
     let min_x = self.min_date().timestamp() as f64;
-    let max_x = self.max_date().timestamp() as f64;
+    let max_x = match average {
+      Some(average) => self.max_date().max(average.max_date()),
+      None => self.max_date(),
+    }
+    .timestamp() as f64;
 
     let point_to_path = |index: usize, point: &Point<Timestamp, f64>| -> String {
       let x = (f64::from(&point.x) - min_x) / (max_x - min_x) * width as f64 + padding as f64;
-      let y = point.y / max_y * (height as f64 * -1.0) + height as f64 + padding as f64;
+      let y = point.y / max_y * -(height as f64) + height as f64 + padding as f64;
       if index == 0 {
         format!("M {} {}", x, y)
       } else {
@@ -315,32 +962,446 @@ impl Burndown {
       .collect::<Vec<String>>()
       .join(" ");
 
-    context.insert("name", "Burndown");
+    let average_path = average
+      .map(|average| {
+        average
+          .incomplete_as_points()
+          .iter()
+          .enumerate()
+          .map(|(i, path)| point_to_path(i, path))
+          .collect::<Vec<String>>()
+          .join(" ")
+      })
+      .unwrap_or_default();
+
+    let scope_path = match show_scope {
+      true => self
+        .scope_as_points()
+        .iter()
+        .enumerate()
+        .map(|(i, path)| point_to_path(i, path))
+        .collect::<Vec<String>>()
+        .join(" "),
+      false => String::new(),
+    };
+
+    let markers: Vec<Marker> = self
+      .annotations_in_range(annotations, &format.timezone)
+      .into_iter()
+      .map(|(time_stamp, annotation)| Marker {
+        x: (time_stamp as f64 - min_x) / (max_x - min_x) * width as f64 + padding as f64,
+        label: annotation.label,
+      })
+      .collect();
+
+    let weekends: Vec<Span> = match show_weekends {
+      true => self
+        .weekends(&format.timezone)
+        .into_iter()
+        .map(|(from, to)| {
+          let x = (from.timestamp() as f64 - min_x) / (max_x - min_x) * width as f64;
+          let end = (to.timestamp() as f64 - min_x) / (max_x - min_x) * width as f64;
+          Span {
+            x: x + padding as f64,
+            width: end - x,
+          }
+        })
+        .collect(),
+      false => vec![],
+    };
+
+    context.insert("name", &style.title);
+    context.insert("x_label", &style.x_label);
+    context.insert("y_label", &style.y_label);
+    context.insert("weekends", &weekends);
+    context.insert("weekend_colour", &style.weekend_colour);
+    context.insert("annotations", &markers);
+    context.insert("annotation_colour", &style.annotation_colour);
     context.insert("width", &width);
     context.insert("height", &height);
     context.insert("padding", &padding);
-    context.insert("default_colour", "#74838F");
+    context.insert("default_colour", &style.text_colour);
     context.insert("incomplete_path", &incomplete_path);
-    context.insert("incomplete_colour", "#D2222D");
+    context.insert("incomplete_colour", &style.incomplete_colour);
     context.insert("complete_path", &complete_path);
-    context.insert("complete_colour", "#238823");
+    context.insert("complete_colour", &style.complete_colour);
+    context.insert("average_path", &average_path);
+    context.insert("average_colour", &style.average_colour);
+    context.insert("scope_path", &scope_path);
+    context.insert("scope_colour", &style.scope_colour);
     context.insert("max_y", &max_y);
     context.insert("y_labels", &[0., (max_y / 2.).round(), max_y]);
     context.insert("legend_rect_width", &50);
     context.insert("legend_rect_height", &10);
 
     let mid_date = (max_x - min_x) / 2. + min_x;
-    context.insert(
-      "x_labels",
-      &[
-        Timestamp::from(min_x),
-        Timestamp::from(mid_date),
-        Timestamp::from(max_x),
+    let x_labels: Vec<String> = [min_x, mid_date, max_x]
+      .iter()
+      .map(|x| {
+        let date = Utc.timestamp_opt(*x as i64, 0).unwrap();
+        format.format(&date, &format.chart)
+      })
+      .collect();
+    context.insert("x_labels", &x_labels);
+
+    let graph = Tera::one_off(template, &context, true)?;
+    Ok(graph)
+  }
+
+  /// Builds a standalone HTML page that draws the burndown with plotly.js, so each point can be
+  /// hovered over to see its date and points. Uses the same title, labels, and colours as the SVG.
+  pub fn as_plotly(
+    &self,
+    format: &DateFormat,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+    show_scope: bool,
+    show_weekends: bool,
+  ) -> Result<String> {
+    let trace = |name: &str, colour: &str, points: Vec<(DateTime<Utc>, i32)>| {
+      let (dates, values): (Vec<DateTime<Utc>>, Vec<i32>) = points.into_iter().unzip();
+      serde_json::json!({
+        "name": name,
+        "type": "scatter",
+        "mode": "lines+markers",
+        "x": dates.iter().map(|date| date.to_rfc3339()).collect::<Vec<String>>(),
+        "y": values,
+        "customdata": dates
+          .iter()
+          .map(|date| format.format(date, &format.date))
+          .collect::<Vec<String>>(),
+        "hovertemplate": format!("%{{customdata}}<br>{}: %{{y}} points<extra></extra>", name),
+        "line": { "color": colour },
+      })
+    };
+
+    let mut traces = vec![
+      trace(
+        "Incomplete",
+        &style.incomplete_colour,
+        self
+          .0
+          .iter()
+          .map(|(date, incomplete, _)| (*date, *incomplete))
+          .collect(),
+      ),
+      trace(
+        "Complete",
+        &style.complete_colour,
+        self
+          .0
+          .iter()
+          .map(|(date, _, complete)| (*date, *complete))
+          .collect(),
+      ),
+    ];
+    if let Some(average) = average {
+      traces.push(trace(
+        "Average",
+        &style.average_colour,
+        average
+          .0
+          .iter()
+          .map(|(date, incomplete, _)| (*date, *incomplete))
+          .collect(),
+      ));
+    }
+    if show_scope {
+      traces.push(trace(
+        "Total Scope",
+        &style.scope_colour,
+        self
+          .0
+          .iter()
+          .map(|(date, incomplete, complete)| (*date, incomplete + complete))
+          .collect(),
+      ));
+    }
+
+    let markers = self.annotations_in_range(annotations, &format.timezone);
+    let dates: Vec<String> = markers
+      .iter()
+      .map(|(time_stamp, _)| Utc.timestamp_opt(*time_stamp, 0).unwrap().to_rfc3339())
+      .collect();
+    let weekends: Vec<serde_json::Value> = match show_weekends {
+      true => self
+        .weekends(&format.timezone)
+        .iter()
+        .map(|(from, to)| {
+          serde_json::json!({
+            "type": "rect",
+            "xref": "x",
+            "yref": "paper",
+            "x0": from.to_rfc3339(),
+            "x1": to.to_rfc3339(),
+            "y0": 0,
+            "y1": 1,
+            "fillcolor": style.weekend_colour,
+            "layer": "below",
+            "line": { "width": 0 },
+          })
+        })
+        .collect(),
+      false => vec![],
+    };
+    let layout = serde_json::json!({
+      "title": style.title,
+      "hovermode": "closest",
+      "xaxis": { "type": "date", "title": style.x_label },
+      "yaxis": {
+        "title": style.y_label.as_deref().unwrap_or("Points"),
+        "rangemode": "tozero",
+      },
+      "shapes": dates
+        .iter()
+        .map(|date| serde_json::json!({
+          "type": "line",
+          "xref": "x",
+          "yref": "paper",
+          "x0": date,
+          "x1": date,
+          "y0": 0,
+          "y1": 1,
+          "line": { "color": style.annotation_colour, "dash": "dot" },
+        }))
+        .chain(weekends)
+        .collect::<Vec<serde_json::Value>>(),
+      "annotations": dates
+        .iter()
+        .zip(markers.iter())
+        .map(|(date, (_, annotation))| serde_json::json!({
+          "x": date,
+          "y": 1,
+          "xref": "x",
+          "yref": "paper",
+          "yanchor": "bottom",
+          "showarrow": false,
+          "text": annotation.label,
+          "font": { "color": style.annotation_colour },
+        }))
+        .collect::<Vec<serde_json::Value>>(),
+    });
+
+    // The JSON is dropped straight into a <script>, so keep a label from closing it early
+    let as_script = |value: &serde_json::Value| -> Result<String> {
+      Ok(serde_json::to_string(value)?.replace("</", "<\\/"))
+    };
+
+    let mut context = Context::new();
+    context.insert("name", &style.title);
+    context.insert("plotly_src", PLOTLY_SRC);
+    context.insert("traces", &as_script(&serde_json::Value::from(traces))?);
+    context.insert("layout", &as_script(&layout)?);
+
+    let page = Tera::one_off(include_str!("../template/burndown.html"), &context, true)?;
+    Ok(page)
+  }
+
+  /// Draws the burndown as an image, with the built in SVG layout and colours, and displays it
+  /// inline in terminals that support kitty, iTerm2, or sixel images. Other terminals get the
+  /// ASCII chart instead.
+  pub fn as_inline(
+    &self,
+    format: &DateFormat,
+    style: &ChartStyle,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+  ) -> Result<()> {
+    let protocol = match detect_protocol() {
+      Some(protocol) => protocol,
+      None => {
+        eprintln!("Unable to detect a terminal that can display images, falling back to ascii");
+        return self.as_ascii(&format.timezone, style, annotations, average);
+      }
+    };
+
+    let (canvas, max_y) = self.draw(&format.timezone, annotations, average);
+    let annotations = self.annotations_in_range(annotations, &format.timezone);
+
+    println!("\n{}\n", style.title);
+    canvas.display(protocol)?;
+    println!(
+      "{} to {}, 0 to {} points",
+      format.format(&self.min_date(), &format.chart),
+      format.format(&self.max_date(), &format.chart),
+      max_y
+    );
+    println!("Red: Incomplete, Green: Complete");
+    if average.is_some() {
+      println!("Blue: Typical Points Remaining");
+    }
+
+    if !annotations.is_empty() {
+      println!("\nEvents\n");
+      for (_, annotation) in annotations {
+        println!("{}: {}", annotation.date, annotation.label);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Draws the burndown onto a canvas, with the built in SVG layout and colours. Returns the
+  /// canvas along with the number of points at the top of the chart.
+  fn draw(
+    &self,
+    timezone: &Timezone,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+  ) -> (Canvas, f64) {
+    let padding = 50.;
+    let width = 900. - padding * 2.;
+    let height = 600. - padding * 2.;
+
+    let max_y = self
+      .max_complete()
+      .max(self.max_incomplete())
+      .max(average.map(|average| average.max_incomplete()).unwrap_or(0))
+      .max(1) as f64;
+    let min_x = self.min_date().timestamp() as f64;
+    let max_x = match average {
+      Some(average) => self.max_date().max(average.max_date()),
+      None => self.max_date(),
+    }
+    .timestamp() as f64;
+    let to_x = |time_stamp: f64| (time_stamp - min_x) / (max_x - min_x).max(1.) * width + padding;
+    let to_pixel = |time: &DateTime<Utc>, points: i32| -> (f64, f64) {
+      (
+        to_x(time.timestamp() as f64),
+        height + padding - points as f64 / max_y * height,
+      )
+    };
+
+    // Background, axis, incomplete, complete, average, and annotation colours from the SVG
+    let mut canvas = Canvas::new(
+      900,
+      600,
+      vec![
+        (255, 255, 255),
+        (0x74, 0x83, 0x8F),
+        (0xD2, 0x22, 0x2D),
+        (0x23, 0x88, 0x23),
+        (0x3A, 0x6E, 0xA5),
+        (0xE1, 0xA1, 0x00),
       ],
     );
 
-    let graph = Tera::one_off(include_str!("../template/burndown.svg"), &context, true)?;
-    Ok(graph)
+    canvas.line(
+      (padding, padding + height),
+      (padding + width, padding + height),
+      1,
+      2,
+      None,
+    );
+    canvas.line((padding, padding), (padding, padding + height), 1, 2, None);
+    canvas.line(
+      (padding, padding + height / 2.),
+      (padding + width, padding + height / 2.),
+      1,
+      1,
+      Some((10, 6)),
+    );
+
+    for (time_stamp, _) in self.annotations_in_range(annotations, timezone).iter() {
+      let x = to_x(*time_stamp as f64);
+      canvas.line((x, padding), (x, padding + height), 5, 1, Some((4, 4)));
+    }
+
+    // The average goes first so the current sprint is drawn over it
+    let mut lines = vec![];
+    if let Some(average) = average {
+      lines.push((
+        average
+          .0
+          .iter()
+          .map(|(time, incomplete, _)| to_pixel(time, *incomplete))
+          .collect::<Vec<(f64, f64)>>(),
+        4,
+        Some((6, 4)),
+      ));
+    }
+    lines.push((
+      self
+        .0
+        .iter()
+        .map(|(time, _, complete)| to_pixel(time, *complete))
+        .collect(),
+      3,
+      None,
+    ));
+    lines.push((
+      self
+        .0
+        .iter()
+        .map(|(time, incomplete, _)| to_pixel(time, *incomplete))
+        .collect(),
+      2,
+      None,
+    ));
+    for (points, colour, dash) in lines {
+      for pair in points.windows(2) {
+        canvas.line(pair[0], pair[1], colour, 3, dash);
+      }
+    }
+
+    (canvas, max_y)
+  }
+
+  /// Draws the burndown as a PNG image
+  pub fn as_png(
+    &self,
+    timezone: &Timezone,
+    annotations: &[Annotation],
+    average: Option<&Burndown>,
+  ) -> Vec<u8> {
+    self.draw(timezone, annotations, average).0.to_png()
+  }
+
+  /// The start and end of each weekend in timezone the burndown covers, cut off at its first and
+  /// last points
+  pub fn weekends(&self, timezone: &Timezone) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let (start, end) = (self.min_date(), self.max_date());
+    let mut weekends: Vec<(DateTime<Utc>, DateTime<Utc>)> = vec![];
+    let mut day = timezone.day(&start);
+    while timezone.start_of_day(day) < end {
+      if is_weekend(&day) {
+        let from = timezone.start_of_day(day).max(start);
+        let to = timezone.start_of_day(day.succ_opt().unwrap()).min(end);
+        match weekends.last_mut() {
+          Some((_, last)) if *last == from => *last = to,
+          _ => weekends.push((from, to)),
+        }
+      }
+      day = day.succ_opt().unwrap();
+    }
+    weekends
+  }
+
+  /// Returns the annotations, paired with their timestamp, that fall on the days in timezone
+  /// covered by the burndown chart. Annotations on the first day are moved to the start of the
+  /// chart and annotations with dates that can't be parsed are skipped.
+  fn annotations_in_range(
+    &self,
+    annotations: &[Annotation],
+    timezone: &Timezone,
+  ) -> Vec<(i64, Annotation)> {
+    let first_entry = self.min_date().timestamp();
+    let start = timezone
+      .start_of_day(timezone.day(&self.min_date()))
+      .timestamp();
+    let end = self.max_date().timestamp();
+    annotations
+      .iter()
+      .filter_map(|annotation| {
+        let time_stamp = annotation.time_stamp(timezone).ok()?;
+        if start <= time_stamp && time_stamp <= end {
+          Some((time_stamp.max(first_entry), annotation.clone()))
+        } else {
+          None
+        }
+      })
+      .collect()
   }
 
   /// Returns the date with the highest value
@@ -390,6 +1451,28 @@ impl Burndown {
       .collect()
   }
 
+  /// Returns the highest total of complete and incomplete points
+  fn max_scope(&self) -> i32 {
+    self
+      .0
+      .iter()
+      .map(|(_, incomplete, complete)| incomplete + complete)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// The total points on the board, complete and incomplete, for each date
+  fn scope_as_points(&self) -> Vec<Point<Timestamp, f64>> {
+    self
+      .0
+      .iter()
+      .map(|(date, incomplete, complete)| Point {
+        x: date.to_owned().into(),
+        y: (incomplete + complete) as f64,
+      })
+      .collect()
+  }
+
   /// Extracts the complete and date scores and maps them into a Vec
   /// of pointplots::Point struct.
   fn complete_as_points(&self) -> Vec<Point<Timestamp, f64>> {
@@ -410,12 +1493,19 @@ impl Burndown {
 
 #[cfg(test)]
 mod tests {
-  use crate::{commands::burndown::*, database::Entry, score::Deck};
+  use crate::{
+    commands::burndown::*,
+    database::Entry,
+    score::{Deck, ListFilter},
+  };
   fn gen_burndown() -> Burndown {
     let entries = vec![
       Entry {
         board_id: "board-id-1".to_string(),
+        board_name: String::new(),
         time_stamp: 1,
+        tags: vec![],
+        cards: vec![],
         decks: vec![
           Deck {
             list_name: "listA".to_string(),
@@ -442,7 +1532,10 @@ mod tests {
       },
       Entry {
         board_id: "board-id-1".to_string(),
+        board_name: String::new(),
         time_stamp: 43200,
+        tags: vec![],
+        cards: vec![],
         decks: vec![
           Deck {
             list_name: "listA".to_string(),
@@ -469,7 +1562,10 @@ mod tests {
       },
       Entry {
         board_id: "board-id-1".to_string(),
+        board_name: String::new(),
         time_stamp: 86401,
+        tags: vec![],
+        cards: vec![],
         decks: vec![
           Deck {
             list_name: "listA".to_string(),
@@ -496,7 +1592,12 @@ mod tests {
       },
     ];
 
-    Burndown::calculate_burndown(&entries, None)
+    Burndown::calculate_burndown(
+      &entries,
+      &ListFilter::default(),
+      &ListMatcher::default(),
+      None,
+    )
   }
 
   #[test]
@@ -504,6 +1605,284 @@ mod tests {
     assert_eq!(gen_burndown().max_date().timestamp(), 86401)
   }
 
+  #[test]
+  fn it_draws_svgs_with_custom_templates() {
+    let svg = gen_burndown()
+      .as_svg_with_template(
+        "<svg><title>{{name}}</title><path stroke=\"{{incomplete_colour}}\" d=\"{{incomplete_path}}\"/></svg>",
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        false,
+      )
+      .unwrap();
+
+    assert!(svg.starts_with("<svg><title>Burndown</title><path stroke=\"#D2222D\" d=\"M 50 "));
+    assert!(gen_burndown()
+      .as_svg_with_template(
+        "{{ missing }}",
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        false,
+      )
+      .is_err());
+  }
+
+  #[test]
+  fn it_draws_svgs_with_a_chart_style() {
+    let style = ChartStyle {
+      title: "Sprint 12".to_string(),
+      y_label: Some("Story Points".to_string()),
+      width: 400,
+      height: 300,
+      incomplete_colour: "#000000".to_string(),
+      ..ChartStyle::default()
+    };
+    let svg = gen_burndown()
+      .as_svg(&DateFormat::default(), &style, &[], None, false, false)
+      .unwrap();
+
+    assert!(svg.contains("Sprint 12"));
+    assert!(svg.contains("Story Points"));
+    assert!(svg.contains("stroke=\"#000000\""));
+    assert!(!svg.contains("#D2222D"));
+    assert!(svg.contains("width=\"425\""));
+
+    assert!(pixel_colour("Cyan").is_ok());
+    assert!(pixel_colour("#00FFFF").is_err());
+  }
+
+  #[test]
+  fn it_writes_plain_text_burndowns() {
+    let format = DateFormat {
+      timezone: Timezone::Utc,
+      ..DateFormat::default()
+    };
+    let style = ChartStyle {
+      title: "Sprint 12".to_string(),
+      ascii_width: 20,
+      ..ChartStyle::default()
+    };
+    let text = gen_burndown().as_text(&format, &style, &[], None);
+
+    assert_eq!(text[0], "Sprint 12");
+    assert_eq!(text[2], "01-01-1970 |#####===== 40 incomplete, 40 complete");
+    assert_eq!(
+      text.last().unwrap(),
+      "02-01-1970 |###====== 30 incomplete, 50 complete"
+    );
+    assert!(text.iter().all(|line| line.is_ascii()));
+  }
+
+  #[test]
+  fn it_writes_plotly_html_with_hover_text() {
+    let burndown = gen_burndown();
+    let annotations = vec![Annotation {
+      board_id: "board-id-1".to_string(),
+      date: "1970-01-01".to_string(),
+      label: "</script>".to_string(),
+    }];
+
+    let html = burndown
+      .as_plotly(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &annotations,
+        None,
+        true,
+        false,
+      )
+      .unwrap();
+
+    assert!(html.contains(PLOTLY_SRC));
+    assert!(html.contains("\"name\":\"Incomplete\""));
+    assert!(html.contains("\"name\":\"Total Scope\""));
+    assert!(html.contains("Incomplete: %{y} points"));
+    assert!(!html.contains("\"name\":\"Average\""));
+    assert!(html.contains("<\\/script>"));
+    assert_eq!(html.matches("</script>").count(), 2);
+  }
+
+  #[test]
+  fn it_fills_missing_days() {
+    let entry = |time_stamp: i64, done: i32| Entry {
+      board_id: "board-id-1".to_string(),
+      board_name: String::new(),
+      time_stamp,
+      tags: vec![],
+      cards: vec![],
+      decks: vec![
+        Deck {
+          list_name: "listA".to_string(),
+          size: 1,
+          score: 10 - done,
+          unscored: 0,
+          estimated: 10 - done,
+        },
+        Deck {
+          list_name: "Done".to_string(),
+          size: 1,
+          score: done,
+          unscored: 0,
+          estimated: done,
+        },
+      ],
+    };
+    let day = |day: u32| Utc.with_ymd_and_hms(2021, 3, day, 9, 0, 0).unwrap();
+    let entries = vec![
+      entry(day(1).timestamp(), 0),
+      entry(day(4).timestamp() + 3600, 6),
+    ];
+
+    let filled = Burndown::calculate_burndown(
+      &entries,
+      &ListFilter::default(),
+      &ListMatcher::default(),
+      Some(Timezone::Utc),
+    );
+    assert_eq!(
+      filled.0,
+      vec![
+        (day(1), 10, 0),
+        (day(2), 10, 0),
+        (day(3), 10, 0),
+        (Utc.with_ymd_and_hms(2021, 3, 4, 10, 0, 0).unwrap(), 4, 6),
+      ]
+    );
+
+    let sparse = Burndown::calculate_burndown(
+      &entries,
+      &ListFilter::default(),
+      &ListMatcher::default(),
+      None,
+    );
+    assert_eq!(sparse.0.len(), 2);
+  }
+
+  #[test]
+  fn it_shades_weekends() {
+    // The 5th of March 2021 was a Friday
+    let burndown = Burndown(vec![
+      (Utc.with_ymd_and_hms(2021, 3, 5, 12, 0, 0).unwrap(), 20, 0),
+      (Utc.with_ymd_and_hms(2021, 3, 9, 12, 0, 0).unwrap(), 10, 10),
+    ]);
+    assert_eq!(
+      burndown.weekends(&Timezone::Utc),
+      vec![(
+        Utc.with_ymd_and_hms(2021, 3, 6, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2021, 3, 8, 0, 0, 0).unwrap()
+      )]
+    );
+
+    let weekend_colour = "fill=\"#EEF0F2\"";
+    assert!(!burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        false
+      )
+      .unwrap()
+      .contains(weekend_colour));
+    assert!(burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        true
+      )
+      .unwrap()
+      .contains(weekend_colour));
+  }
+
+  #[test]
+  fn it_draws_the_scope_on_svgs() {
+    let burndown = gen_burndown();
+
+    let without = burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        false,
+      )
+      .unwrap();
+    let with = burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        true,
+        false,
+      )
+      .unwrap();
+
+    assert!(!without.contains("Total Scope"));
+    assert!(with.contains("Total Scope"));
+    assert_eq!(burndown.max_scope(), 80);
+  }
+
+  #[test]
+  fn it_draws_the_average_on_svgs() {
+    let burndown = gen_burndown();
+    let average = Burndown(vec![
+      (Utc.timestamp_opt(0, 0).unwrap(), 50, 0),
+      (Utc.timestamp_opt(86400 * 2, 0).unwrap(), 20, 30),
+    ]);
+
+    let without = burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        None,
+        false,
+        false,
+      )
+      .unwrap();
+    let with = burndown
+      .as_svg(
+        &DateFormat::default(),
+        &ChartStyle::default(),
+        &[],
+        Some(&average),
+        false,
+        false,
+      )
+      .unwrap();
+
+    assert!(!without.contains("Typical Points Remaining"));
+    assert!(with.contains("Typical Points Remaining"));
+    assert_eq!(
+      vec![
+        "Date,Incomplete,Complete,Average Incomplete",
+        "01-01-1970,40,40,50",
+        "01-01-1970,40,40,50",
+        "02-01-1970,30,50,"
+      ],
+      burndown.as_csv(
+        &DateFormat {
+          timezone: Timezone::Utc,
+          ..DateFormat::default()
+        },
+        &CsvDialect::default(),
+        Some(&average)
+      )
+    );
+  }
+
   #[test]
   fn it_calculates_min_date() {
     assert_eq!(gen_burndown().min_date().timestamp(), 1)
@@ -559,4 +1938,45 @@ mod tests {
       ]
     )
   }
+
+  #[test]
+  fn it_keeps_annotations_within_the_chart() {
+    let annotation = |date: &str| Annotation {
+      board_id: "board-id-1".to_string(),
+      date: date.to_string(),
+      label: date.to_string(),
+    };
+    let annotations = vec![
+      annotation("1970-01-01"),
+      annotation("1970-01-02"),
+      annotation("1970-01-03"),
+      annotation("not a date"),
+    ];
+
+    assert_eq!(
+      gen_burndown()
+        .annotations_in_range(&annotations, &Timezone::Utc)
+        .into_iter()
+        .map(|(time_stamp, _)| time_stamp)
+        .collect::<Vec<i64>>(),
+      vec![1, 86400]
+    )
+  }
+
+  #[test]
+  fn it_sorts_lists_into_states() {
+    let pattern = |name: &str| ListMatcher::new(&[name.to_string()]).unwrap();
+    let states = StateMatcher {
+      backlog: pattern("Icebox"),
+      committed: pattern("Sprint"),
+      in_progress: pattern("Doing"),
+      done: pattern("Shipped"),
+    };
+
+    assert_eq!(states.state_of("Icebox"), ListState::Backlog);
+    assert_eq!(states.state_of("Sprint 12"), ListState::Committed);
+    assert_eq!(states.state_of("Doing"), ListState::InProgress);
+    assert_eq!(states.state_of("Shipped"), ListState::Done);
+    assert_eq!(states.state_of("Ideas"), ListState::Committed);
+  }
 }
@@ -0,0 +1,148 @@
+use crate::{
+  cli::CompareBoardsArgs,
+  commands::compile_decks,
+  errors::*,
+  kanban::{Board, Kanban},
+  score::{filter_decks, Deck, ListFilter, ListNames, Scorer},
+};
+
+use prettytable::Table;
+
+/// Lines up the decks of two boards by list name, in the order of the first board's lists followed
+/// by the lists only the second board has
+pub fn align<'a>(
+  a: &'a [Deck],
+  b: &'a [Deck],
+  list_names: &ListNames,
+) -> Vec<(String, Option<&'a Deck>, Option<&'a Deck>)> {
+  let mut rows: Vec<(String, Option<&Deck>, Option<&Deck>)> = a
+    .iter()
+    .map(|deck| {
+      (
+        deck.list_name.clone(),
+        Some(deck),
+        list_names.find_matching(deck, b),
+      )
+    })
+    .collect();
+
+  for deck in b {
+    if !rows.iter().any(|(_, _, matched)| {
+      matched.map(|matched| matched.list_name == deck.list_name) == Some(true)
+    }) {
+      rows.push((deck.list_name.clone(), None, Some(deck)));
+    }
+  }
+
+  rows
+}
+
+pub fn print_comparison(a: &Board, b: &Board, rows: &[(String, Option<&Deck>, Option<&Deck>)]) {
+  let mut table = Table::new();
+  table.set_titles(row![
+    "List",
+    format!("{} cards", a.name),
+    format!("{} score", a.name),
+    format!("{} cards", b.name),
+    format!("{} score", b.name),
+    "Difference"
+  ]);
+
+  let cell = |deck: Option<&Deck>, value: fn(&Deck) -> i32| {
+    deck.map(|deck| value(deck).to_string()).unwrap_or_default()
+  };
+  let (mut a_total, mut b_total) = ((0, 0), (0, 0));
+  for (list_name, a_deck, b_deck) in rows {
+    let (a_score, b_score) = (
+      a_deck.map(|deck| deck.score).unwrap_or(0),
+      b_deck.map(|deck| deck.score).unwrap_or(0),
+    );
+    for (total, deck) in [(&mut a_total, a_deck), (&mut b_total, b_deck)] {
+      if let Some(deck) = deck {
+        total.0 += deck.size as i32;
+        total.1 += deck.score;
+      }
+    }
+    table.add_row(row![
+      list_name,
+      cell(*a_deck, |deck| deck.size as i32),
+      cell(*a_deck, |deck| deck.score),
+      cell(*b_deck, |deck| deck.size as i32),
+      cell(*b_deck, |deck| deck.score),
+      format!("{:+}", b_score - a_score)
+    ]);
+  }
+  table.add_row(row![
+    "TOTAL",
+    a_total.0,
+    a_total.1,
+    b_total.0,
+    b_total.1,
+    format!("{:+}", b_total.1 - a_total.1)
+  ]);
+
+  table.printstd();
+}
+
+/// Scores two boards and prints their lists side by side
+pub async fn compare_boards(
+  kanban: Box<dyn Kanban>,
+  scorer: &Scorer,
+  list_names: &ListNames,
+  args: &CompareBoardsArgs,
+) -> Result<()> {
+  let a = kanban.get_board(&args.a).await?;
+  let b = kanban.get_board(&args.b).await?;
+  let filter = ListFilter::from_args(&args.filter)?;
+
+  let (a_decks, _) = compile_decks(kanban.as_ref(), &a.id, None, scorer).await?;
+  let (b_decks, _) = compile_decks(kanban.as_ref(), &b.id, None, scorer).await?;
+  let (a_decks, b_decks) = (
+    filter_decks(&a_decks, &filter),
+    filter_decks(&b_decks, &filter),
+  );
+
+  print_comparison(&a, &b, &align(&a_decks, &b_decks, list_names));
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  #[test]
+  fn it_lines_up_lists_across_boards() {
+    let deck = |list_name: &str, score: i32| Deck {
+      list_name: list_name.to_string(),
+      size: 1,
+      score,
+      unscored: 0,
+      estimated: score,
+    };
+    let a = vec![deck("To Do", 5), deck("Doing", 3), deck("Done", 8)];
+    let b = vec![deck("Done", 2), deck("Review", 4), deck("todo", 1)];
+
+    let aligned = align(&a, &b, &ListNames::new(&HashMap::new()));
+    let rows: Vec<(&str, Option<i32>, Option<i32>)> = aligned
+      .iter()
+      .map(|(name, a, b)| {
+        (
+          name.as_str(),
+          a.map(|deck| deck.score),
+          b.map(|deck| deck.score),
+        )
+      })
+      .collect();
+    assert_eq!(
+      rows,
+      vec![
+        ("To Do", Some(5), Some(1)),
+        ("Doing", Some(3), None),
+        ("Done", Some(8), Some(2)),
+        ("Review", None, Some(4)),
+      ]
+    );
+  }
+}
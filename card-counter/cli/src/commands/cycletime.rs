@@ -0,0 +1,203 @@
+use crate::{
+  cli::BoardArgs,
+  commands::{
+    burndown::{ListState, StateMatcher},
+    time_in_status::{days, time_in_status},
+  },
+  errors::*,
+  kanban::{Board, CardHistory, Kanban},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use prettytable::Table;
+
+/// The average and median of a set of durations, along with how many there were
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+  pub name: String,
+  pub average: Duration,
+  pub median: Duration,
+  pub count: usize,
+}
+
+impl Summary {
+  fn new(name: String, mut durations: Vec<Duration>) -> Option<Self> {
+    if durations.is_empty() {
+      return None;
+    }
+    durations.sort();
+    let count = durations.len();
+    let median = match count % 2 {
+      0 => (durations[count / 2 - 1] + durations[count / 2]) / 2,
+      _ => durations[count / 2],
+    };
+    let total = durations
+      .into_iter()
+      .fold(Duration::zero(), |total, duration| total + duration);
+
+    Some(Summary {
+      name,
+      average: total / count as i32,
+      median,
+      count,
+    })
+  }
+}
+
+/// Summarizes the time cards spent in each list, in the order the lists were first visited. Time
+/// in a card's current list is counted up until `now`.
+pub fn list_times(histories: &[CardHistory], now: DateTime<Utc>) -> Vec<Summary> {
+  let mut times: Vec<(String, Vec<Duration>)> = Vec::new();
+  for history in histories {
+    for (list, duration) in time_in_status(history, now) {
+      match times.iter_mut().find(|(name, _)| *name == list) {
+        Some((_, durations)) => durations.push(duration),
+        None => times.push((list, vec![duration])),
+      }
+    }
+  }
+
+  times
+    .into_iter()
+    .filter_map(|(list, durations)| Summary::new(list, durations))
+    .collect()
+}
+
+/// The lead time, from when a card was created until it first reached a done list, and the cycle
+/// time, from when work on it started until then. Work starts when the card first moves into an
+/// in progress list, or when it first moves at all if it never went through one. Cards that
+/// haven't been done yet have neither.
+pub fn lead_and_cycle_time(
+  history: &CardHistory,
+  states: &StateMatcher,
+) -> Option<(Duration, Duration)> {
+  let done = history
+    .transitions
+    .iter()
+    .position(|transition| states.state_of(&transition.to) == ListState::Done)?;
+  let done_at = history.transitions[done].at;
+
+  let started = history.transitions[..=done]
+    .iter()
+    .find(|transition| states.state_of(&transition.to) == ListState::InProgress)
+    .unwrap_or(&history.transitions[0])
+    .at;
+
+  Some((done_at - history.created, done_at - started))
+}
+
+fn summary_row(summary: &Summary) -> prettytable::Row {
+  row![
+    summary.name,
+    days(&summary.average),
+    days(&summary.median),
+    summary.count
+  ]
+}
+
+pub fn print_cycle_times(board_name: &str, lists: &[Summary], finished: &[Summary]) {
+  println!("{}", board_name);
+  let mut table = Table::new();
+  table.set_titles(row!["List", "average days", "median days", "cards"]);
+  for summary in lists {
+    table.add_row(summary_row(summary));
+  }
+  table.printstd();
+
+  if finished.is_empty() {
+    println!("None of the cards have reached a done list yet");
+    return;
+  }
+  let mut table = Table::new();
+  table.set_titles(row!["", "average days", "median days", "cards"]);
+  for summary in finished {
+    table.add_row(summary_row(summary));
+  }
+  table.printstd();
+}
+
+/// Reports how long cards spend in each list, and the lead and cycle times of the cards that were
+/// finished, from the moves between lists recorded by the board
+pub async fn report(
+  kanban: Box<dyn Kanban>,
+  states: &StateMatcher,
+  args: &BoardArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let histories = kanban.get_card_histories(&board.id).await?;
+  let (lead_times, cycle_times): (Vec<Duration>, Vec<Duration>) = histories
+    .iter()
+    .filter_map(|history| lead_and_cycle_time(history, states))
+    .unzip();
+  let finished: Vec<Summary> = vec![
+    Summary::new("Lead time".to_string(), lead_times),
+    Summary::new("Cycle time".to_string(), cycle_times),
+  ]
+  .into_iter()
+  .flatten()
+  .collect();
+
+  print_cycle_times(&board.name, &list_times(&histories, Utc::now()), &finished);
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{commands::burndown::ListMatcher, kanban::Transition};
+  use chrono::TimeZone;
+
+  fn history(name: &str, current_list: &str, moves: &[(&str, &str, u32)]) -> CardHistory {
+    CardHistory {
+      id: name.to_string(),
+      name: name.to_string(),
+      created: Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+      current_list: current_list.to_string(),
+      members: vec![],
+      transitions: moves
+        .iter()
+        .map(|(from, to, day)| Transition {
+          from: Some(from.to_string()),
+          to: to.to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, *day, 0, 0, 0).unwrap(),
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn it_measures_lead_and_cycle_times() {
+    let states = StateMatcher {
+      in_progress: ListMatcher::new(&["Doing".to_string()]).unwrap(),
+      ..StateMatcher::default()
+    };
+    let histories = vec![
+      history("a", "Done", &[("To Do", "Doing", 3), ("Doing", "Done", 5)]),
+      history(
+        "b",
+        "Done",
+        &[("To Do", "Review", 2), ("Review", "Done", 9)],
+      ),
+      history("c", "Doing", &[("To Do", "Doing", 4)]),
+    ];
+
+    let times: Vec<(i64, i64)> = histories
+      .iter()
+      .filter_map(|history| lead_and_cycle_time(history, &states))
+      .map(|(lead, cycle)| (lead.num_days(), cycle.num_days()))
+      .collect();
+    assert_eq!(times, vec![(4, 2), (8, 7)]);
+
+    let now = Utc.with_ymd_and_hms(2021, 3, 10, 0, 0, 0).unwrap();
+    let to_do = &list_times(&histories, now)[0];
+    assert_eq!(to_do.name, "To Do");
+    assert_eq!(to_do.count, 3);
+    assert_eq!(to_do.median.num_days(), 2);
+    assert_eq!(to_do.average.num_days(), 2);
+  }
+}
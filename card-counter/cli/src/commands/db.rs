@@ -0,0 +1,168 @@
+use crate::{
+  cli::CheckArgs,
+  database::{connect_database, Database, DatabaseType, Entry},
+  errors::*,
+};
+
+use std::fmt;
+
+/// The board id entries are saved under while checking that the database can be written to
+static CHECK_BOARD_ID: &str = "card-counter-db-check";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+  Connect,
+  Read,
+  Write,
+}
+
+impl fmt::Display for Step {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Step::Connect => write!(f, "Connect"),
+      Step::Read => write!(f, "Read entries"),
+      Step::Write => write!(f, "Save and remove an entry"),
+    }
+  }
+}
+
+/// What to try when step fails for database
+pub fn suggestion(database: &DatabaseType, step: Step) -> &'static str {
+  match (database, step) {
+    (DatabaseType::Local, Step::Connect) => "Make sure $HOME/.card-counter/database.json is valid JSON, or move it aside to start a new database",
+    (DatabaseType::Local, _) => "Make sure you're allowed to write to $HOME/.card-counter",
+    (DatabaseType::Aws, Step::Connect) => "Check your AWS credentials and region, `aws sts get-caller-identity` shows who you're signed in as. `card-counter --save` offers to create a missing table",
+    (DatabaseType::Aws, Step::Read) => "Your AWS user needs dynamodb:Query, dynamodb:Scan, and dynamodb:GetItem on the card-counter table",
+    (DatabaseType::Aws, Step::Write) => "Your AWS user needs dynamodb:PutItem and dynamodb:DeleteItem on the card-counter table",
+    (DatabaseType::Azure, Step::Connect) => "Set COSMOS_ACCOUNT and COSMOS_MASTER_KEY, then run `card-counter config` to set your database and container names. `card-counter --save` offers to create missing ones",
+    (DatabaseType::Azure, _) => "Make sure COSMOS_MASTER_KEY is a read-write key, not a read-only one",
+    (DatabaseType::AzureBlob, Step::Connect) => "Set AZURE_STORAGE_CONNECTION_STRING, or an account, tenant_id, and client_id under azure_blob in your config",
+    (DatabaseType::AzureBlob, _) => "Make sure the container exists and your credentials have the Storage Blob Data Contributor role",
+    (DatabaseType::S3, Step::Connect) => "Set CARD_COUNTER_S3_BUCKET, or bucket under s3 in your config, and check your AWS credentials",
+    (DatabaseType::S3, Step::Read) => "Make sure the bucket exists and your AWS user has s3:GetObject and s3:ListBucket on it",
+    (DatabaseType::S3, Step::Write) => "Your AWS user needs s3:PutObject on the bucket",
+    (DatabaseType::Redis, Step::Connect) => "Check that REDIS_URL, or url under redis in your config, points at a running server",
+    (DatabaseType::Redis, _) => "Make sure your Redis user is allowed to run ZRANGEBYSCORE, ZADD, and ZREMRANGEBYSCORE",
+  }
+}
+
+/// Reads from the database and, unless read_only, saves an entry and removes it again
+pub async fn run_checks(client: &dyn Database, read_only: bool) -> Vec<(Step, Result<()>)> {
+  let mut results = vec![(
+    Step::Read,
+    client
+      .query_entries(CHECK_BOARD_ID.to_string(), None)
+      .await
+      .map(|_| ()),
+  )];
+
+  if !read_only {
+    let write = async {
+      let time_stamp = Entry::get_current_timestamp()?;
+      client
+        .add_entry(Entry {
+          board_id: CHECK_BOARD_ID.to_string(),
+          time_stamp,
+          ..Entry::default()
+        })
+        .await?;
+      client
+        .delete_entries(CHECK_BOARD_ID.to_string(), vec![time_stamp])
+        .await
+    };
+    results.push((Step::Write, write.await));
+  }
+
+  results
+}
+
+fn report(database: &DatabaseType, step: Step, result: &Result<()>) -> bool {
+  match result {
+    Ok(()) => {
+      println!("  {} ... ok", step);
+      true
+    }
+    Err(err) => {
+      println!("  {} ... failed: {:#}", step, err);
+      println!("    {}", suggestion(database, step));
+      false
+    }
+  }
+}
+
+/// Checks that the configured database can be reached, read from, and written to, suggesting
+/// a fix for anything that fails. Nothing is created, a missing table or container fails the
+/// check.
+pub async fn check(database: DatabaseType, profile: Option<&str>, args: &CheckArgs) -> Result<()> {
+  println!("Checking the {} database", database);
+
+  let client = match connect_database(&database, profile).await {
+    Ok(client) => {
+      report(&database, Step::Connect, &Ok(()));
+      client
+    }
+    Err(err) => {
+      report(&database, Step::Connect, &Err(err));
+      return Err(eyre!("The {} database isn't ready to use", database));
+    }
+  };
+
+  let mut healthy = true;
+  for (step, result) in run_checks(client.as_ref(), args.read_only).await {
+    healthy &= report(&database, step, &result);
+  }
+
+  match healthy {
+    true => Ok(()),
+    false => Err(eyre!("The {} database isn't ready to use", database)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::{DateRange, Entries};
+  use async_trait::async_trait;
+
+  /// A database that can be read from but refuses every write
+  struct ReadOnly;
+
+  #[async_trait]
+  impl Database for ReadOnly {
+    async fn add_entry(&self, _entry: Entry) -> Result<()> {
+      Err(eyre!("Access denied"))
+    }
+    async fn all_entries(&self) -> Result<Option<Entries>> {
+      Ok(None)
+    }
+    async fn get_entry(&self, _board_name: String, _time_stamp: i64) -> Result<Option<Entry>> {
+      Ok(None)
+    }
+    async fn query_entries(
+      &self,
+      _board_name: String,
+      _date_range: Option<DateRange>,
+    ) -> Result<Option<Entries>> {
+      Ok(None)
+    }
+    async fn delete_entries(&self, _board_name: String, _time_stamps: Vec<i64>) -> Result<()> {
+      Ok(())
+    }
+    fn what_type(&self) -> String {
+      "ReadOnly".into()
+    }
+  }
+
+  #[tokio::test]
+  async fn it_reports_each_step() {
+    let results = run_checks(&ReadOnly, false).await;
+    let outcomes: Vec<(Step, bool)> = results
+      .iter()
+      .map(|(step, result)| (*step, result.is_ok()))
+      .collect();
+    assert_eq!(outcomes, vec![(Step::Read, true), (Step::Write, false)]);
+
+    let results = run_checks(&ReadOnly, true).await;
+    assert_eq!(results.len(), 1);
+  }
+}
@@ -0,0 +1,153 @@
+use crate::{
+  cli::DiffArgs,
+  database::{
+    config::{Config, Timezone},
+    find_entry_at, Database, Entry,
+  },
+  errors::*,
+  score::{print_delta, TableOptions},
+};
+
+/// Finds the entries saved at `from` and `to`, which can be anything find_entry_at understands
+pub fn entries_between(
+  entries: &[Entry],
+  from: &str,
+  to: &str,
+  timezone: &Timezone,
+) -> Result<(Entry, Entry)> {
+  let find = |at: &str| {
+    find_entry_at(entries, at, timezone)?
+      .ok_or_else(|| eyre!("Unable to find an entry saved by {}", at))
+  };
+  Ok((find(from)?, find(to)?))
+}
+
+/// Reads the entries for the board passed by id, or an alias for one, or by name
+async fn board_entries(
+  client: &dyn Database,
+  config: &Config,
+  args: &DiffArgs,
+) -> Result<Vec<Entry>> {
+  let entries = match (args.board_id.as_deref(), args.board_name.as_deref()) {
+    (Some(id), _) => client.query_entries(config.board_id(id), None).await?,
+    (None, Some(name)) => client.query_entries_by_name(name.to_string(), None).await?,
+    (None, None) => return Err(eyre!("Either --board-id or --board-name is needed")),
+  };
+  Ok(entries.unwrap_or_default())
+}
+
+/// Prints how a board changed between two saved entries, without asking the board for anything
+pub async fn diff(
+  client: Box<dyn Database>,
+  config: &Config,
+  options: &TableOptions,
+  args: &DiffArgs,
+) -> Result<()> {
+  let entries = board_entries(client.as_ref(), config, args).await?;
+
+  let (before, after) =
+    entries_between(&entries, &args.from, &args.to, &config.date_format.timezone)?;
+  let board_name = match after.board_name.is_empty() {
+    true => after.board_id.clone(),
+    false => after.board_name.clone(),
+  };
+  print_delta(&after.decks, &before.decks, &board_name, options);
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::{DateRange, Entries};
+  use async_trait::async_trait;
+
+  /// A database holding one entry for a single board
+  struct OneBoard;
+
+  #[async_trait]
+  impl Database for OneBoard {
+    async fn add_entry(&self, _entry: Entry) -> Result<()> {
+      Ok(())
+    }
+    async fn all_entries(&self) -> Result<Option<Entries>> {
+      Ok(None)
+    }
+    async fn get_entry(&self, _board_name: String, _time_stamp: i64) -> Result<Option<Entry>> {
+      Ok(None)
+    }
+    async fn query_entries(
+      &self,
+      board_name: String,
+      _date_range: Option<DateRange>,
+    ) -> Result<Option<Entries>> {
+      Ok(match board_name.as_str() {
+        "3em95wSl" => Some(vec![Entry {
+          board_id: board_name,
+          board_name: String::new(),
+          time_stamp: 1,
+          decks: vec![],
+          tags: vec![],
+          cards: vec![],
+        }]),
+        _ => None,
+      })
+    }
+    async fn delete_entries(&self, _board_name: String, _time_stamps: Vec<i64>) -> Result<()> {
+      Ok(())
+    }
+    fn what_type(&self) -> String {
+      "OneBoard".into()
+    }
+  }
+
+  #[tokio::test]
+  async fn it_finds_entries_by_board_alias() {
+    let mut config = Config::default();
+    config
+      .boards
+      .insert("web".to_string(), "3em95wSl".to_string());
+    let args = |board: &str| DiffArgs {
+      board_id: Some(board.to_string()),
+      board_name: None,
+      from: "earliest".to_string(),
+      to: "latest".to_string(),
+      filter: Default::default(),
+    };
+
+    let entries = board_entries(&OneBoard, &config, &args("web"))
+      .await
+      .unwrap();
+    assert_eq!(entries.len(), 1);
+    let entries = board_entries(&OneBoard, &config, &args("3em95wSl"))
+      .await
+      .unwrap();
+    assert_eq!(entries.len(), 1);
+    let entries = board_entries(&OneBoard, &config, &args("mobile"))
+      .await
+      .unwrap();
+    assert!(entries.is_empty());
+  }
+
+  #[test]
+  fn it_finds_the_entries_to_diff() {
+    let entry = |time_stamp: i64, tags: &[&str]| Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp,
+      decks: vec![],
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      cards: vec![],
+    };
+    let entries = vec![
+      entry(100, &["sprint-1-start"]),
+      entry(86500, &[]),
+      entry(2 * 86400 + 100, &[]),
+    ];
+
+    let (before, after) =
+      entries_between(&entries, "tag:sprint-1-start", "1970-01-02", &Timezone::Utc).unwrap();
+    assert_eq!((before.time_stamp, after.time_stamp), (100, 86500));
+    assert!(entries_between(&entries, "tag:sprint-2-start", "latest", &Timezone::Utc).is_err());
+  }
+}
@@ -0,0 +1,177 @@
+use crate::{
+  cli::BoardArgs,
+  errors::*,
+  kanban::{Board, Card, Kanban, List},
+  score::Scorer,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use prettytable::Table;
+use std::collections::HashMap;
+
+/// How a list's points break down by when their cards are due
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DuePoints {
+  pub name: String,
+  pub overdue: i32,
+  // Due within the next seven days
+  pub this_week: i32,
+  pub later: i32,
+  pub no_due_date: i32,
+}
+
+impl DuePoints {
+  fn new(name: &str) -> Self {
+    DuePoints {
+      name: name.to_string(),
+      ..DuePoints::default()
+    }
+  }
+
+  fn add(&mut self, points: i32, due: Option<DateTime<Utc>>, now: DateTime<Utc>) {
+    match due {
+      None => self.no_due_date += points,
+      Some(due) if due < now => self.overdue += points,
+      Some(due) if due < now + Duration::days(7) => self.this_week += points,
+      Some(_) => self.later += points,
+    }
+  }
+}
+
+/// Adds up the points on each list by when they are due, in the order lists appear on the board,
+/// and for the whole board. Corrected points are used when a card has them.
+pub fn due_points(
+  lists: &[List],
+  cards: &[Card],
+  scorer: &Scorer,
+  now: DateTime<Utc>,
+) -> (Vec<DuePoints>, DuePoints) {
+  let mut by_list: HashMap<&str, DuePoints> = HashMap::new();
+  let mut board = DuePoints::new("TOTAL");
+
+  for card in cards {
+    let points = match scorer.score(&card.name) {
+      Some(score) => score.correction.or(score.estimated).unwrap_or(0),
+      None => 0,
+    };
+    if points == 0 {
+      continue;
+    }
+    by_list
+      .entry(card.parent_list.as_str())
+      .or_default()
+      .add(points, card.due, now);
+    board.add(points, card.due, now);
+  }
+
+  let lists = lists
+    .iter()
+    .filter_map(|list| {
+      by_list.remove(list.id.as_str()).map(|points| DuePoints {
+        name: list.name.clone(),
+        ..points
+      })
+    })
+    .collect();
+
+  (lists, board)
+}
+
+fn due_row(points: &DuePoints) -> prettytable::Row {
+  row![
+    points.name,
+    points.overdue,
+    points.this_week,
+    points.later,
+    points.no_due_date
+  ]
+}
+
+pub fn print_due(board_name: &str, lists: &[DuePoints], board: &DuePoints) {
+  println!("{}", board_name);
+  let mut table = Table::new();
+  table.set_titles(row![
+    "List",
+    "Overdue",
+    "Due this week",
+    "Later",
+    "No due date"
+  ]);
+  for points in lists {
+    table.add_row(due_row(points));
+  }
+  table.add_row(due_row(board));
+  table.printstd();
+}
+
+/// Breaks down the points on each of a board's lists into overdue, due this week, due later and
+/// without a due date
+pub async fn due(kanban: Box<dyn Kanban>, scorer: &Scorer, args: &BoardArgs) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let lists = kanban.get_lists(&board.id).await?;
+  let cards = kanban.get_cards(&board.id).await?;
+
+  let (list_points, board_points) = due_points(&lists, &cards, scorer, Utc::now());
+  print_due(&board.name, &list_points, &board_points);
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  #[test]
+  fn it_buckets_points_by_due_date() {
+    let now = Utc.with_ymd_and_hms(2021, 3, 5, 12, 0, 0).unwrap();
+    let list = |id: &str, name: &str| List {
+      name: name.to_string(),
+      id: id.to_string(),
+      board_id: "board".to_string(),
+    };
+    let card = |name: &str, list: &str, due_in_days: Option<i64>| Card {
+      name: name.to_string(),
+      parent_list: list.to_string(),
+      members: vec![],
+      labels: vec![],
+      due: due_in_days.map(|days| now + Duration::days(days)),
+    };
+    let lists = vec![list("1", "To Do"), list("2", "Doing"), list("3", "Done")];
+    let cards = vec![
+      card("(3) Login page", "1", Some(-1)),
+      card("(5)[2] Signup page", "1", Some(2)),
+      card("(8) Billing", "1", Some(30)),
+      card("(1) Typo", "2", None),
+      card("Unscored", "2", Some(-3)),
+    ];
+
+    let (lists, board) = due_points(&lists, &cards, &Scorer::default(), now);
+    assert_eq!(
+      lists
+        .iter()
+        .map(|points| (
+          points.name.as_str(),
+          points.overdue,
+          points.this_week,
+          points.later,
+          points.no_due_date
+        ))
+        .collect::<Vec<_>>(),
+      vec![("To Do", 3, 2, 8, 0), ("Doing", 0, 0, 0, 1)]
+    );
+    assert_eq!(
+      (
+        board.overdue,
+        board.this_week,
+        board.later,
+        board.no_due_date
+      ),
+      (3, 2, 8, 1)
+    );
+  }
+}
@@ -0,0 +1,316 @@
+use crate::{
+  cli::ExportArgs,
+  commands::ndjson::write_entries,
+  database::{config::Timezone, Database, DateRange, Entry},
+  errors::*,
+  kanban::{Board, Kanban},
+};
+
+use std::{
+  fs::File,
+  io::{self, BufWriter},
+  path::Path,
+  sync::Arc,
+};
+
+use arrow_array::{
+  ArrayRef, Int32Array, RecordBatch, StringArray, TimestampSecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use rust_xlsxwriter::{Chart, ChartType, ExcelDateTime, Format, Workbook};
+
+/// The columns written out for every deck in every entry
+pub fn entries_schema() -> Schema {
+  Schema::new(vec![
+    Field::new("board", DataType::Utf8, false),
+    Field::new(
+      "timestamp",
+      DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+      false,
+    ),
+    Field::new("list", DataType::Utf8, false),
+    Field::new("score", DataType::Int32, false),
+    Field::new("estimated", DataType::Int32, false),
+    Field::new("unscored", DataType::Int32, false),
+    Field::new("size", DataType::UInt64, false),
+  ])
+}
+
+/// Flattens entries so that each deck becomes its own row, tagged with the board and the time
+/// the entry was saved.
+pub fn entries_to_record_batch(entries: &[Entry]) -> Result<RecordBatch> {
+  let rows: Vec<(&Entry, &crate::score::Deck)> = entries
+    .iter()
+    .flat_map(|entry| entry.decks.iter().map(move |deck| (entry, deck)))
+    .collect();
+
+  let columns: Vec<ArrayRef> = vec![
+    Arc::new(StringArray::from_iter_values(
+      rows.iter().map(|(entry, _)| entry.board_id.as_str()),
+    )),
+    Arc::new(
+      TimestampSecondArray::from_iter_values(rows.iter().map(|(entry, _)| entry.time_stamp))
+        .with_timezone("UTC"),
+    ),
+    Arc::new(StringArray::from_iter_values(
+      rows.iter().map(|(_, deck)| deck.list_name.as_str()),
+    )),
+    Arc::new(Int32Array::from_iter_values(
+      rows.iter().map(|(_, deck)| deck.score),
+    )),
+    Arc::new(Int32Array::from_iter_values(
+      rows.iter().map(|(_, deck)| deck.estimated),
+    )),
+    Arc::new(Int32Array::from_iter_values(
+      rows.iter().map(|(_, deck)| deck.unscored),
+    )),
+    Arc::new(UInt64Array::from_iter_values(
+      rows.iter().map(|(_, deck)| deck.size as u64),
+    )),
+  ];
+
+  RecordBatch::try_new(Arc::new(entries_schema()), columns)
+    .wrap_err_with(|| "Unable to build columns from entries")
+}
+
+/// Writes entries out as a snappy compressed Parquet file
+pub fn write_parquet(entries: &[Entry], path: &Path) -> Result<()> {
+  let batch = entries_to_record_batch(entries)?;
+  let file =
+    File::create(path).wrap_err_with(|| format!("Unable to create file {}", path.display()))?;
+  let properties = WriterProperties::builder()
+    .set_compression(Compression::SNAPPY)
+    .build();
+
+  let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))
+    .wrap_err_with(|| "Unable to start writing parquet file")?;
+  writer
+    .write(&batch)
+    .wrap_err_with(|| "Unable to write entries to parquet file")?;
+  writer
+    .close()
+    .wrap_err_with(|| "Unable to finish writing parquet file")?;
+
+  Ok(())
+}
+
+/// The time stamp of an entry and the score of each list in it
+pub type ListScores = (i64, Vec<Option<i32>>);
+
+/// The score of each list at each entry, oldest first. Lists are in the order they first show up
+/// in, and a list that's missing from an entry has no score for it.
+pub fn list_history(entries: &[Entry]) -> (Vec<String>, Vec<ListScores>) {
+  let mut entries: Vec<&Entry> = entries.iter().collect();
+  entries.sort();
+
+  let mut lists: Vec<String> = vec![];
+  for deck in entries.iter().flat_map(|entry| entry.decks.iter()) {
+    if !lists.contains(&deck.list_name) {
+      lists.push(deck.list_name.clone());
+    }
+  }
+
+  let rows = entries
+    .iter()
+    .map(|entry| {
+      let scores = lists
+        .iter()
+        .map(|list| {
+          entry
+            .decks
+            .iter()
+            .find(|deck| &deck.list_name == list)
+            .map(|deck| deck.score)
+        })
+        .collect();
+      (entry.time_stamp, scores)
+    })
+    .collect();
+
+  (lists, rows)
+}
+
+/// Writes entries out as an Excel workbook with a sheet of every deck in every entry, the same rows
+/// as the parquet export, and a sheet of each list's score over time with a line chart of it
+pub fn write_xlsx(entries: &[Entry], path: &Path) -> Result<()> {
+  let date_format = Format::new().set_num_format("yyyy-mm-dd hh:mm");
+  let header_format = Format::new().set_bold();
+  let excel_date = |time_stamp: i64| {
+    ExcelDateTime::from_timestamp(time_stamp)
+      .wrap_err_with(|| format!("Unable to convert {} to an Excel date", time_stamp))
+  };
+
+  let mut workbook = Workbook::new();
+
+  let sheet = workbook.add_worksheet().set_name("Entries")?;
+  let headers = [
+    "board",
+    "timestamp",
+    "list",
+    "score",
+    "estimated",
+    "unscored",
+    "size",
+  ];
+  for (col, header) in headers.iter().enumerate() {
+    sheet.write_string_with_format(0, col as u16, *header, &header_format)?;
+  }
+  let rows = entries
+    .iter()
+    .flat_map(|entry| entry.decks.iter().map(move |deck| (entry, deck)));
+  for (row, (entry, deck)) in rows.enumerate() {
+    let row = row as u32 + 1;
+    sheet.write_string(row, 0, &entry.board_id)?;
+    sheet.write_datetime_with_format(row, 1, excel_date(entry.time_stamp)?, &date_format)?;
+    sheet.write_string(row, 2, &deck.list_name)?;
+    sheet.write_number(row, 3, deck.score)?;
+    sheet.write_number(row, 4, deck.estimated)?;
+    sheet.write_number(row, 5, deck.unscored)?;
+    sheet.write_number(row, 6, deck.size as u32)?;
+  }
+  sheet.set_column_width(1, 18)?;
+
+  let (lists, history) = list_history(entries);
+  let sheet = workbook.add_worksheet().set_name("History")?;
+  sheet.write_string_with_format(0, 0, "timestamp", &header_format)?;
+  for (col, list) in lists.iter().enumerate() {
+    sheet.write_string_with_format(0, col as u16 + 1, list, &header_format)?;
+  }
+  for (row, (time_stamp, scores)) in history.iter().enumerate() {
+    let row = row as u32 + 1;
+    sheet.write_datetime_with_format(row, 0, excel_date(*time_stamp)?, &date_format)?;
+    for (col, score) in scores.iter().enumerate() {
+      if let Some(score) = score {
+        sheet.write_number(row, col as u16 + 1, *score)?;
+      }
+    }
+  }
+  sheet.set_column_width(0, 18)?;
+
+  if !history.is_empty() {
+    let last_row = history.len() as u32;
+    let mut chart = Chart::new(ChartType::Line);
+    chart.title().set_name("Points per list");
+    for col in 1..=lists.len() as u16 {
+      chart
+        .add_series()
+        .set_name(("History", 0, col))
+        .set_categories(("History", 1, 0, last_row, 0))
+        .set_values(("History", 1, col, last_row, col));
+    }
+    sheet.insert_chart(1, lists.len() as u16 + 2, &chart)?;
+  }
+
+  workbook
+    .save(path)
+    .wrap_err_with(|| format!("Unable to write workbook {}", path.display()))
+}
+
+/// Pulls every entry for a board, optionally limited to a date range, and writes them to a file
+pub async fn export_entries(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  timezone: &Timezone,
+  args: &ExportArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let range = match (&args.start, &args.end) {
+    (Some(start), Some(end)) => {
+      Some(DateRange::resolve(client.as_ref(), &board.id, start, end, timezone).await?)
+    }
+    _ => None,
+  };
+
+  let entries = client
+    .query_entries(board.id.clone(), range)
+    .await?
+    .unwrap_or_default();
+
+  let path = args.output_file.as_deref().map(Path::new);
+
+  match (args.format.as_str(), path) {
+    ("parquet", Some(path)) => write_parquet(&entries, path)?,
+    ("parquet", None) => return Err(eyre!("An output file is required to export as parquet")),
+    ("xlsx", Some(path)) => write_xlsx(&entries, path)?,
+    ("xlsx", None) => return Err(eyre!("An output file is required to export as xlsx")),
+    ("ndjson", Some(path)) => {
+      let file =
+        File::create(path).wrap_err_with(|| format!("Unable to create {}", path.display()))?;
+      write_entries(&mut BufWriter::new(file), &entries)?
+    }
+    // Entries are streamed to standard out, so there's nothing more to say
+    ("ndjson", None) => return write_entries(&mut io::stdout().lock(), &entries),
+    (format, _) => return Err(eyre!("Export format {} not supported", format)),
+  }
+
+  if let Some(path) = path {
+    println!(
+      "Exported {} entries for {} to {}",
+      entries.len(),
+      board.name,
+      path.display()
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::score::Deck;
+  use arrow_array::Array;
+
+  #[test]
+  fn it_creates_a_row_per_deck() {
+    let deck = |list_name: &str, score| Deck {
+      list_name: list_name.to_string(),
+      size: 2,
+      score,
+      unscored: 1,
+      estimated: score,
+    };
+    let entries = vec![
+      Entry {
+        board_id: "board".to_string(),
+        board_name: String::new(),
+        time_stamp: 1,
+        decks: vec![deck("Todo", 5), deck("Done", 3)],
+        tags: vec![],
+        cards: vec![],
+      },
+      Entry {
+        board_id: "board".to_string(),
+        board_name: String::new(),
+        time_stamp: 86400,
+        decks: vec![deck("Done", 8)],
+        tags: vec![],
+        cards: vec![],
+      },
+    ];
+
+    let batch = entries_to_record_batch(&entries).unwrap();
+    assert_eq!(batch.num_rows(), 3);
+    assert_eq!(batch.num_columns(), 7);
+
+    let scores = batch
+      .column(3)
+      .as_any()
+      .downcast_ref::<Int32Array>()
+      .unwrap();
+    assert_eq!(scores.values(), &[5, 3, 8]);
+    assert_eq!(scores.null_count(), 0);
+
+    let (lists, history) = list_history(&entries);
+    assert_eq!(lists, vec!["Todo".to_string(), "Done".to_string()]);
+    assert_eq!(
+      history,
+      vec![(1, vec![Some(5), Some(3)]), (86400, vec![None, Some(8)])]
+    );
+  }
+}
@@ -0,0 +1,209 @@
+use crate::{
+  cli::ForecastArgs,
+  commands::burndown::{is_weekend, Burndown, BurndownOptions},
+  errors::*,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use rand::{seq::SliceRandom, Rng};
+
+// Simulated futures that haven't finished after this many days are counted as finishing on it
+const MAX_DAYS: usize = 3650;
+
+/// The points finished on each day of the burndown. Days between entries share the points finished
+/// between them, so gaps in the history don't look like days where nothing got done. With
+/// skip_weekends only weekdays share them, so quiet weekends don't drag the throughput down.
+pub fn daily_throughput(burndown: &Burndown, skip_weekends: bool) -> Vec<f64> {
+  burndown
+    .0
+    .windows(2)
+    .flat_map(|pair| {
+      let (before, after) = (&pair[0], &pair[1]);
+      let days = match skip_weekends {
+        true => working_days_between(before.0, after.0).max(1),
+        false => ((after.0 - before.0).num_hours() as f64 / 24.)
+          .round()
+          .max(1.) as usize,
+      };
+      let finished = (after.2 - before.2).max(0) as f64;
+      std::iter::repeat_n(finished / days as f64, days)
+    })
+    .collect()
+}
+
+/// The number of weekdays after from, up to and including the day of to
+fn working_days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> usize {
+  let mut day = from.date_naive().succ_opt().unwrap();
+  let mut days = 0;
+  while day <= to.date_naive() {
+    if !is_weekend(&day) {
+      days += 1;
+    }
+    day = day.succ_opt().unwrap();
+  }
+  days
+}
+
+/// How many days it takes to finish `remaining` points in each of `runs` futures, where every day's
+/// throughput is drawn at random from the history. Sorted from quickest to slowest.
+pub fn simulate<R: Rng>(
+  throughput: &[f64],
+  remaining: i32,
+  runs: usize,
+  rng: &mut R,
+) -> Vec<usize> {
+  let mut days: Vec<usize> = (0..runs)
+    .map(|_| {
+      let mut left = remaining as f64;
+      let mut days = 0;
+      while left > 0. && days < MAX_DAYS {
+        left -= throughput.choose(rng).copied().unwrap_or(0.);
+        days += 1;
+      }
+      days
+    })
+    .collect();
+  days.sort_unstable();
+  days
+}
+
+/// The number of days `percent` of the simulated futures finished within
+pub fn percentile(sorted_days: &[usize], percent: usize) -> usize {
+  let index = (sorted_days.len() * percent).div_ceil(100);
+  sorted_days[index.saturating_sub(1).min(sorted_days.len() - 1)]
+}
+
+/// The day that's `days` days after from, only counting weekdays when skip_weekends is set
+fn add_days(from: DateTime<Utc>, days: usize, skip_weekends: bool) -> DateTime<Utc> {
+  if !skip_weekends {
+    return from + Duration::days(days as i64);
+  }
+  let mut day = from;
+  let mut left = days;
+  while left > 0 {
+    day += Duration::days(1);
+    if !is_weekend(&day) {
+      left -= 1;
+    }
+  }
+  day
+}
+
+fn finish_date(from: DateTime<Utc>, days: usize, skip_weekends: bool) -> String {
+  match days {
+    MAX_DAYS => format!(
+      "after {}",
+      add_days(from, days, skip_weekends).format("%Y-%m-%d")
+    ),
+    days => add_days(from, days, skip_weekends)
+      .format("%Y-%m-%d")
+      .to_string(),
+  }
+}
+
+/// Forecasts when the points left on a board will be finished from how many points were finished
+/// each day in its saved entries. By default it assumes every day goes like the average one, with
+/// --monte-carlo it simulates many futures and reports how likely each finish date is.
+pub async fn forecast(options: BurndownOptions, args: &ForecastArgs) -> Result<()> {
+  let entries = options.entries().await?;
+  // Filled in days would look like days where nothing got done, daily_throughput spreads gaps out
+  let burndown =
+    Burndown::calculate_burndown(&entries, &options.filter, &options.states.done, None);
+  let (last_day, remaining) = match burndown.0.last() {
+    Some((day, incomplete, _)) => (*day, *incomplete),
+    None => return Err(eyre!("Unable to find any entries within the date range")),
+  };
+  if remaining <= 0 {
+    println!(
+      "Nothing left to finish as of {}",
+      last_day.format("%Y-%m-%d")
+    );
+    return Ok(());
+  }
+
+  let skip_weekends = args.skip_weekends;
+  let throughput = daily_throughput(&burndown, skip_weekends);
+  if !throughput.iter().any(|points| *points > 0.) {
+    return Err(eyre!(
+      "No points were finished within the date range, so there is nothing to forecast from"
+    ));
+  }
+
+  println!(
+    "{} points left as of {}, from {} days of history",
+    remaining,
+    last_day.format("%Y-%m-%d"),
+    throughput.len()
+  );
+
+  if !args.monte_carlo {
+    let average = throughput.iter().sum::<f64>() / throughput.len() as f64;
+    let days = (remaining as f64 / average).ceil() as usize;
+    println!(
+      "At {:.1} points a day, finishing on {}",
+      average,
+      finish_date(last_day, days.min(MAX_DAYS), skip_weekends)
+    );
+    return Ok(());
+  }
+
+  let runs = args.runs;
+  if runs == 0 {
+    return Err(eyre!("At least one simulation needs to be run"));
+  }
+
+  let days = simulate(&throughput, remaining, runs, &mut rand::thread_rng());
+  for percent in [50, 85, 95] {
+    println!(
+      "{}% chance of finishing by {}",
+      percent,
+      finish_date(last_day, percentile(&days, percent), skip_weekends)
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  #[test]
+  fn it_forecasts_from_daily_throughput() {
+    let day = |day: u32| Utc.with_ymd_and_hms(2021, 3, day, 12, 0, 0).unwrap();
+    let burndown = Burndown(vec![
+      (day(1), 40, 0),
+      (day(2), 36, 4),
+      // Nothing was saved on the 3rd
+      (day(4), 28, 12),
+      (day(5), 30, 10),
+    ]);
+    let throughput = daily_throughput(&burndown, false);
+    assert_eq!(throughput, vec![4., 4., 4., 0.]);
+
+    let days = simulate(&throughput, 12, 1000, &mut StdRng::seed_from_u64(7));
+    assert_eq!(days.len(), 1000);
+    assert_eq!(days[0], 3);
+    assert!(percentile(&days, 50) <= percentile(&days, 85));
+    assert!(percentile(&days, 85) <= percentile(&days, 95));
+    assert_eq!(percentile(&[1, 2, 3, 4], 50), 2);
+    assert_eq!(percentile(&[1, 2, 3, 4], 95), 4);
+  }
+
+  #[test]
+  fn it_leaves_weekends_out_of_the_forecast() {
+    // The 5th of March 2021 was a Friday
+    let burndown = Burndown(vec![
+      (Utc.with_ymd_and_hms(2021, 3, 5, 12, 0, 0).unwrap(), 20, 0),
+      (Utc.with_ymd_and_hms(2021, 3, 8, 12, 0, 0).unwrap(), 14, 6),
+    ]);
+    assert_eq!(daily_throughput(&burndown, false), vec![2., 2., 2.]);
+    assert_eq!(daily_throughput(&burndown, true), vec![6.]);
+
+    let friday = Utc.with_ymd_and_hms(2021, 3, 5, 12, 0, 0).unwrap();
+    assert_eq!(finish_date(friday, 2, false), "2021-03-07");
+    assert_eq!(finish_date(friday, 2, true), "2021-03-09");
+  }
+}
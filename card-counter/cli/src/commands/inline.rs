@@ -0,0 +1,323 @@
+use std::{
+  env,
+  io::{self, Write},
+};
+
+use crate::errors::*;
+
+/// The ways a terminal can be asked to display an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+  Kitty,
+  ITerm,
+  Sixel,
+}
+
+/// Works out which image protocol the terminal understands from the environment it sets up.
+/// CARD_COUNTER_GRAPHICS can be set to kitty, iterm, sixel, or none for terminals we can't
+/// recognize.
+pub fn detect_protocol() -> Option<Protocol> {
+  protocol_from(|name| env::var(name).ok())
+}
+
+fn protocol_from(var: impl Fn(&str) -> Option<String>) -> Option<Protocol> {
+  match var("CARD_COUNTER_GRAPHICS").as_deref() {
+    Some("kitty") => return Some(Protocol::Kitty),
+    Some("iterm") => return Some(Protocol::ITerm),
+    Some("sixel") => return Some(Protocol::Sixel),
+    Some("none") => return None,
+    _ => (),
+  }
+
+  let term = var("TERM").unwrap_or_default();
+  let program = var("TERM_PROGRAM").unwrap_or_default();
+  if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" {
+    Some(Protocol::Kitty)
+  } else if program == "iTerm.app" || program == "WezTerm" {
+    Some(Protocol::ITerm)
+  } else if term.contains("sixel")
+    || ["foot", "mlterm", "yaft"]
+      .iter()
+      .any(|sixel| term.starts_with(sixel))
+  {
+    Some(Protocol::Sixel)
+  } else {
+    None
+  }
+}
+
+/// An image where every pixel is an index into a small palette. Charts only need a handful of
+/// colours, which keeps both the PNG and sixel encodings simple.
+pub struct Canvas {
+  pub width: usize,
+  pub height: usize,
+  pub palette: Vec<(u8, u8, u8)>,
+  pixels: Vec<u8>,
+}
+
+impl Canvas {
+  /// Creates a canvas filled with the first colour in the palette
+  pub fn new(width: usize, height: usize, palette: Vec<(u8, u8, u8)>) -> Self {
+    Canvas {
+      width,
+      height,
+      palette,
+      pixels: vec![0; width * height],
+    }
+  }
+
+  pub fn get(&self, x: usize, y: usize) -> u8 {
+    self.pixels[y * self.width + x]
+  }
+
+  /// Colours a square of thickness pixels centred on x, y, ignoring anything off the canvas
+  fn plot(&mut self, x: i64, y: i64, colour: u8, thickness: i64) {
+    for dy in 0..thickness {
+      for dx in 0..thickness {
+        let (x, y) = (x + dx - thickness / 2, y + dy - thickness / 2);
+        if 0 <= x && x < self.width as i64 && 0 <= y && y < self.height as i64 {
+          self.pixels[y as usize * self.width + x as usize] = colour;
+        }
+      }
+    }
+  }
+
+  /// Draws a line between two points. When dash is given as (on, off) the line is broken up
+  /// into segments, ex: (6, 4) is 6 pixels drawn followed by 4 skipped.
+  pub fn line(
+    &mut self,
+    from: (f64, f64),
+    to: (f64, f64),
+    colour: u8,
+    thickness: i64,
+    dash: Option<(usize, usize)>,
+  ) {
+    let (x0, y0) = (from.0.round() as i64, from.1.round() as i64);
+    let (x1, y1) = (to.0.round() as i64, to.1.round() as i64);
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+
+    for step in 0..=steps {
+      if let Some((on, off)) = dash {
+        if step as usize % (on + off) >= on {
+          continue;
+        }
+      }
+      let x = x0 + (x1 - x0) * step / steps;
+      let y = y0 + (y1 - y0) * step / steps;
+      self.plot(x, y, colour, thickness);
+    }
+  }
+
+  /// Encodes the canvas as an indexed colour PNG. The image data is stored without compression,
+  /// which is plenty for something that only travels as far as the terminal.
+  pub fn to_png(&self) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = vec![];
+    header.extend_from_slice(&(self.width as u32).to_be_bytes());
+    header.extend_from_slice(&(self.height as u32).to_be_bytes());
+    // 8 bits per pixel, indexed colour, default compression, filtering, and no interlacing
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    png_chunk(&mut png, b"IHDR", &header);
+
+    let palette: Vec<u8> = self
+      .palette
+      .iter()
+      .flat_map(|(r, g, b)| vec![*r, *g, *b])
+      .collect();
+    png_chunk(&mut png, b"PLTE", &palette);
+
+    // Every row starts with the filter type, 0 for none
+    let rows: Vec<u8> = self
+      .pixels
+      .chunks(self.width)
+      .flat_map(|row| std::iter::once(0).chain(row.iter().cloned()))
+      .collect();
+    png_chunk(&mut png, b"IDAT", &zlib_stored(&rows));
+    png_chunk(&mut png, b"IEND", &[]);
+
+    png
+  }
+
+  /// Encodes the canvas as sixels, bands of 6 pixel tall columns drawn one colour at a time
+  pub fn to_sixel(&self) -> String {
+    let mut sixel = format!("\x1bPq\"1;1;{};{}", self.width, self.height);
+    for (index, (r, g, b)) in self.palette.iter().enumerate() {
+      let percent = |channel: &u8| *channel as u32 * 100 / 255;
+      sixel.push_str(&format!(
+        "#{};2;{};{};{}",
+        index,
+        percent(r),
+        percent(g),
+        percent(b)
+      ));
+    }
+
+    for band in (0..self.height).step_by(6) {
+      for colour in 0..self.palette.len() as u8 {
+        let columns: Vec<u8> = (0..self.width)
+          .map(|x| {
+            (0..6)
+              .filter(|row| band + row < self.height && self.get(x, band + row) == colour)
+              .fold(0, |bits, row| bits | 1 << row)
+          })
+          .collect();
+        if columns.iter().all(|bits| *bits == 0) {
+          continue;
+        }
+
+        sixel.push_str(&format!("#{}", colour));
+        sixel.push_str(&run_length(&columns));
+        // Return to the start of the band for the next colour
+        sixel.push('$');
+      }
+      sixel.push('-');
+    }
+
+    sixel.push_str("\x1b\\");
+    sixel
+  }
+
+  /// Wraps the canvas in the escape codes protocol needs to display it
+  pub fn escape(&self, protocol: Protocol) -> String {
+    match protocol {
+      Protocol::Sixel => self.to_sixel(),
+      Protocol::ITerm => {
+        let png = self.to_png();
+        format!(
+          "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+          png.len(),
+          base64::encode(&png)
+        )
+      }
+      Protocol::Kitty => {
+        // Kitty only accepts the image 4096 bytes at a time, m=1 means more is on the way
+        let encoded = base64::encode(self.to_png());
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+        chunks
+          .iter()
+          .enumerate()
+          .map(|(index, chunk)| {
+            let more = if index + 1 < chunks.len() { 1 } else { 0 };
+            let control = if index == 0 {
+              format!("f=100,a=T,m={}", more)
+            } else {
+              format!("m={}", more)
+            };
+            format!("\x1b_G{};{}\x1b\\", control, String::from_utf8_lossy(chunk))
+          })
+          .collect()
+      }
+    }
+  }
+
+  /// Prints the image to standard out
+  pub fn display(&self, protocol: Protocol) -> Result<()> {
+    let stdout = io::stdout();
+    let mut writer = stdout.lock();
+    writeln!(writer, "{}", self.escape(protocol))?;
+    writer.flush()?;
+    Ok(())
+  }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+  png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+  png.extend_from_slice(kind);
+  png.extend_from_slice(data);
+
+  let mut crc = crc32fast::Hasher::new();
+  crc.update(kind);
+  crc.update(data);
+  png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// Wraps data in a zlib stream made up of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+  let mut stream = vec![0x78, 0x01];
+  let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+  for (index, block) in blocks.iter().enumerate() {
+    let last = if index + 1 == blocks.len() { 1 } else { 0 };
+    let length = block.len() as u16;
+    stream.push(last);
+    stream.extend_from_slice(&length.to_le_bytes());
+    stream.extend_from_slice(&(!length).to_le_bytes());
+    stream.extend_from_slice(block);
+  }
+  if blocks.is_empty() {
+    stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+  }
+
+  let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+    let a = (a + *byte as u32) % 65521;
+    (a, (b + a) % 65521)
+  });
+  stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+  stream
+}
+
+/// Turns the bits for each column into sixel characters, collapsing repeats into !<count><char>
+fn run_length(columns: &[u8]) -> String {
+  let mut output = String::new();
+  let mut index = 0;
+  while index < columns.len() {
+    let bits = columns[index];
+    let count = columns[index..]
+      .iter()
+      .take_while(|other| **other == bits)
+      .count();
+    let character = (bits + 63) as char;
+    if count > 3 {
+      output.push_str(&format!("!{}{}", count, character));
+    } else {
+      (0..count).for_each(|_| output.push(character));
+    }
+    index += count;
+  }
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  #[test]
+  fn it_detects_image_protocols_from_the_environment() {
+    let detect = |vars: &[(&str, &str)]| {
+      let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+      protocol_from(|name| vars.get(name).cloned())
+    };
+
+    assert_eq!(detect(&[("TERM", "xterm-kitty")]), Some(Protocol::Kitty));
+    assert_eq!(
+      detect(&[("TERM_PROGRAM", "iTerm.app")]),
+      Some(Protocol::ITerm)
+    );
+    assert_eq!(detect(&[("TERM", "foot")]), Some(Protocol::Sixel));
+    assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+    assert_eq!(
+      detect(&[("TERM", "xterm-kitty"), ("CARD_COUNTER_GRAPHICS", "none")]),
+      None
+    );
+  }
+
+  #[test]
+  fn it_encodes_canvases() {
+    let mut canvas = Canvas::new(4, 2, vec![(255, 255, 255), (0, 0, 0)]);
+    canvas.line((0., 1.), (3., 1.), 1, 1, None);
+
+    let png = canvas.to_png();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+    assert_eq!(
+      canvas.to_sixel(),
+      "\x1bPq\"1;1;4;2#0;2;100;100;100#1;2;0;0;0#0!4@$#1!4A$-\x1b\\"
+    );
+  }
+}
@@ -0,0 +1,154 @@
+use crate::{
+  errors::*,
+  kanban::Board,
+  score::{add_deck, calculate_delta, filter_decks, Deck, ListFilter, ListNames},
+};
+
+use serde::Serialize;
+
+/// How much each of a deck's numbers changed since an earlier entry
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Delta {
+  pub size: i32,
+  pub score: i32,
+  pub estimated: i32,
+  pub unscored: i32,
+}
+
+impl Delta {
+  pub fn between(old: &Deck, new: &Deck) -> Self {
+    let delta = calculate_delta(old, new);
+    Delta {
+      size: delta["cards"],
+      score: delta["score"],
+      estimated: delta["estimated"],
+      unscored: delta["unscored"],
+    }
+  }
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeckWithDelta<'a> {
+  #[serde(flatten)]
+  pub deck: &'a Deck,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub delta: Option<Delta>,
+}
+
+/// Everything the score table shows, as printed by `--output json`
+#[derive(Serialize, Debug)]
+pub struct Score<'a> {
+  pub board: &'a Board,
+  pub decks: Vec<DeckWithDelta<'a>>,
+  pub total: Deck,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub total_delta: Option<Delta>,
+}
+
+fn total(decks: &[Deck]) -> Deck {
+  decks.iter().fold(
+    Deck {
+      list_name: "TOTAL".to_string(),
+      size: 0,
+      score: 0,
+      unscored: 0,
+      estimated: 0,
+    },
+    |total, deck| add_deck(&total, deck),
+  )
+}
+
+/// Builds the score for decks after applying filter. When old_decks are given each deck, and the
+/// total, carries how much it changed since then.
+pub fn score<'a>(
+  board: &'a Board,
+  decks: &'a [Deck],
+  old_decks: Option<&[Deck]>,
+  filter: &ListFilter,
+  list_names: &ListNames,
+) -> Score<'a> {
+  let old_decks = old_decks.map(|old_decks| filter_decks(old_decks, filter));
+  let kept: Vec<&Deck> = decks
+    .iter()
+    .filter(|deck| filter.keeps(&deck.list_name))
+    .collect();
+  let current_total = total(&filter_decks(decks, filter));
+
+  Score {
+    board,
+    decks: kept
+      .into_iter()
+      .map(|deck| DeckWithDelta {
+        deck,
+        delta: old_decks.as_ref().and_then(|old_decks| {
+          list_names
+            .find_matching(deck, old_decks)
+            .map(|old_deck| Delta::between(old_deck, deck))
+        }),
+      })
+      .collect(),
+    total_delta: old_decks
+      .as_ref()
+      .map(|old_decks| Delta::between(&total(old_decks), &current_total)),
+    total: current_total,
+  }
+}
+
+/// Prints the score as a single pretty printed JSON document
+pub fn print_score(
+  board: &Board,
+  decks: &[Deck],
+  old_decks: Option<&[Deck]>,
+  filter: &ListFilter,
+  list_names: &ListNames,
+) -> Result<()> {
+  println!(
+    "{}",
+    serde_json::to_string_pretty(&score(board, decks, old_decks, filter, list_names))
+      .wrap_err_with(|| "Unable to serialize the score as JSON")?
+  );
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  #[test]
+  fn it_includes_deltas_when_comparing() {
+    let deck = |list_name: &str, size: usize, score: i32| Deck {
+      list_name: list_name.to_string(),
+      size,
+      score,
+      unscored: 0,
+      estimated: score,
+    };
+    let board = Board {
+      id: "1".to_string(),
+      name: "Sprint".to_string(),
+    };
+    let decks = vec![deck("To Do", 2, 5), deck("Done", 3, 8)];
+    let old_decks = vec![deck("To Do", 4, 10)];
+
+    let json = serde_json::to_value(score(
+      &board,
+      &decks,
+      Some(&old_decks),
+      &ListFilter::default(),
+      &ListNames::new(&HashMap::new()),
+    ))
+    .unwrap();
+    assert_eq!(
+      json["decks"][0],
+      serde_json::json!({
+        "list_name": "To Do", "size": 2, "score": 5, "unscored": 0, "estimated": 5,
+        "delta": {"size": -2, "score": -5, "estimated": -5, "unscored": 0}
+      })
+    );
+    assert!(json["decks"][1].get("delta").is_none());
+    assert_eq!(json["total"]["score"], 13);
+    assert_eq!(json["total_delta"]["score"], 3);
+    assert_eq!(json["board"]["name"], "Sprint");
+  }
+}
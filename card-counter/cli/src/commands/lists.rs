@@ -0,0 +1,127 @@
+use crate::{
+  cli::BoardArgs,
+  commands::burndown::{ListState, StateMatcher},
+  errors::*,
+  kanban::{self, Board, Kanban, List},
+  score::{Deck, Scorer},
+};
+
+use prettytable::Table;
+
+/// A list on a board, how much is in it right now, and which state card-counter counts it as
+#[derive(Debug, PartialEq)]
+pub struct ListRow {
+  pub name: String,
+  pub id: String,
+  pub cards: usize,
+  pub score: i32,
+  pub state: &'static str,
+}
+
+fn state_name(state: ListState) -> &'static str {
+  match state {
+    ListState::Backlog => "backlog",
+    ListState::Committed => "committed",
+    ListState::InProgress => "in progress",
+    ListState::Done => "done",
+  }
+}
+
+/// Pairs each list with its deck. build_decks makes a deck for every list in order, so they line
+/// up one to one.
+pub fn list_rows(lists: &[List], decks: &[Deck], states: &StateMatcher) -> Vec<ListRow> {
+  lists
+    .iter()
+    .zip(decks)
+    .map(|(list, deck)| ListRow {
+      name: list.name.clone(),
+      id: list.id.clone(),
+      cards: deck.size,
+      score: deck.score,
+      state: state_name(states.state_of(&list.name)),
+    })
+    .collect()
+}
+
+pub fn print_lists(board_name: &str, rows: &[ListRow]) {
+  println!("{}", board_name);
+  let mut table = Table::new();
+  table.set_titles(row!["List", "ID", "cards", "score", "state"]);
+  for row in rows {
+    table.add_row(row![row.name, row.id, row.cards, row.score, row.state]);
+  }
+  table.printstd();
+}
+
+/// Prints every list on a board with its id, what's in it, and the state it's counted as, to help
+/// with writing filters and done_lists
+pub async fn lists(
+  kanban: Box<dyn Kanban>,
+  scorer: &Scorer,
+  states: &StateMatcher,
+  args: &BoardArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let lists = kanban.get_lists(&board.id).await?;
+  let cards = kanban::collect_cards(kanban.get_cards(&board.id).await?, None);
+  let decks = kanban::build_decks(lists.clone(), cards, scorer);
+
+  print_lists(&board.name, &list_rows(&lists, &decks, states));
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::commands::burndown::ListMatcher;
+
+  #[test]
+  fn it_describes_each_list() {
+    let list = |name: &str, id: &str| List {
+      name: name.to_string(),
+      id: id.to_string(),
+      board_id: "board".to_string(),
+    };
+    let deck = |list_name: &str, size: usize, score: i32| Deck {
+      list_name: list_name.to_string(),
+      size,
+      score,
+      unscored: 0,
+      estimated: score,
+    };
+    let states = StateMatcher {
+      in_progress: ListMatcher::new(&["Doing".to_string()]).unwrap(),
+      ..StateMatcher::default()
+    };
+
+    let rows = list_rows(
+      &[list("Doing", "a1"), list("Done", "b2")],
+      &[deck("Doing", 2, 5), deck("Done", 1, 3)],
+      &states,
+    );
+    assert_eq!(
+      rows,
+      vec![
+        ListRow {
+          name: "Doing".to_string(),
+          id: "a1".to_string(),
+          cards: 2,
+          score: 5,
+          state: "in progress",
+        },
+        ListRow {
+          name: "Done".to_string(),
+          id: "b2".to_string(),
+          cards: 1,
+          score: 3,
+          state: "done",
+        },
+      ]
+    );
+  }
+}
@@ -1,24 +1,98 @@
 use crate::{
-  commands::burndown::BurndownOptions,
-  database::{config::Config, get_decks_by_date, Database, DatabaseType},
-  errors::Result,
-  kanban::{self, init_kanban_board, Board, Card, Kanban},
-  score::{print_decks, print_delta, Deck},
+  cli::{
+    AccuracyArgs, AgingArgs, AnnotateArgs, AnomaliesArgs, BackfillArgs, BackupArgs, BoardArgs,
+    BoardsArgs, BurndownArgs, CheckArgs, CompareBoardsArgs, DiffArgs, ExportArgs, FilterArgs,
+    ForecastArgs, PruneArgs, RangeArgs, ReportArgs, RescoreArgs, RestoreArgs, RetroArgs,
+    ReviewArgs, ScoreArgs, SnapshotArgs, SummaryArgs, TimeInStatusArgs, VelocityArgs, WatchArgs,
+    WipArgs,
+  },
+  commands::{
+    anomalies::detect_anomalies,
+    burndown::{Burndown, BurndownOptions, FILE_OUTPUTS, SVG_TEMPLATE},
+  },
+  database::{
+    config::{Annotation, ChartStyle, Config},
+    find_entry_at, get_decks_by_date, CardSnapshot, Database, DatabaseType, DateRange, Entry,
+    TAG_PREFIX,
+  },
+  errors::*,
+  kanban::{self, init_kanban_board, init_kanban_board_from_config, Board, Card, Kanban},
+  score::{
+    decks_as_csv, off_scale, print_card_details, print_decks, print_delta, Deck, ListFilter,
+    ListNames, Scorer, TableOptions,
+  },
 };
 
-use std::collections::HashMap;
+use chrono::{Duration, Utc};
+use std::{
+  collections::HashMap,
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::Path,
+};
 
+pub mod accuracy;
+pub mod aging;
+pub mod anomalies;
+pub mod auth;
+pub mod backfill;
+pub mod backup;
+pub mod boards;
 pub mod burndown;
+pub mod compare_boards;
+pub mod cycletime;
+pub mod db;
+pub mod diff;
+pub mod due;
+pub mod export;
+pub mod forecast;
+pub mod inline;
+pub mod json;
+pub mod lists;
+pub mod ndjson;
+pub mod prune;
+pub mod report;
+pub mod rescore;
+pub mod retro;
+pub mod review;
+pub mod scope;
+pub mod snapshot;
+pub mod summary;
+pub mod thresholds;
+pub mod time_in_status;
+pub mod velocity;
+pub mod watch;
+pub mod webhook;
+pub mod wip;
 
-pub struct Command;
+/// Holds what every command needs to know before it reads the config
+pub struct Command {
+  profile: Option<String>,
+}
 
 /// Acts on commands issued by the user, often parses clap arguments to get the job done.
 impl Command {
-  pub fn check_for_database(database: Option<&str>) -> Result<DatabaseType> {
-    match (database, Config::from_file()?) {
+  pub fn new(profile: Option<String>) -> Command {
+    Command { profile }
+  }
+
+  /// The profile chosen with --profile, if any
+  pub fn profile(&self) -> Option<&str> {
+    self.profile.as_deref()
+  }
+
+  fn config(&self) -> Result<Config> {
+    Config::from_file_or_default(self.profile())
+  }
+
+  pub fn check_for_database(&self, database: Option<&str>) -> Result<DatabaseType> {
+    match (database, Config::from_file(self.profile())?) {
       (Some("aws"), _) => Ok(DatabaseType::Aws),
       (Some("local"), _) => Ok(DatabaseType::Local),
       (Some("azure"), _) => Ok(DatabaseType::Azure),
+      (Some("azure-blob"), _) => Ok(DatabaseType::AzureBlob),
+      (Some("s3"), _) => Ok(DatabaseType::S3),
+      (Some("redis"), _) => Ok(DatabaseType::Redis),
       (Some(some), _) => {
         println!(
           "Unable to find database for {}. Using local database instead",
@@ -28,7 +102,7 @@ impl Command {
       }
       (None, Some(config)) => Ok(config.database),
       (None, None) => {
-        println!("No database chosen, defaulting to local.");
+        eprintln!("No database chosen, defaulting to local.");
         Ok(DatabaseType::Local)
       }
     }
@@ -36,70 +110,889 @@ impl Command {
 
   pub async fn show_score(
     config: &Config,
-    matches: &clap::ArgMatches<'_>,
-    client: &Box<dyn Database>,
-  ) -> Result<(Board, Vec<Deck>)> {
-    let filter: Option<&str> = matches.value_of("filter");
+    args: &ScoreArgs,
+    ascii: bool,
+    client: &dyn Database,
+  ) -> Result<(Board, Vec<Deck>, Vec<CardSnapshot>)> {
+    let options = TableOptions {
+      group_separator: args.group_separator.clone(),
+      wip_limit: args
+        .fail_if_wip_over
+        .map(|limit| (args.wip_list.clone(), limit)),
+      ..table_options(
+        config,
+        &args.filter,
+        use_color(config, args.no_color, ascii),
+      )?
+    };
     // Parse arguments, if board_id isn't found
-    let kanban = init_kanban_board(config, matches);
-    let (board, decks) = kanban_compile_decks(kanban, matches).await?;
+    let kanban = init_kanban_board(config, args)?;
+    let scorer = config.scorer()?;
+    let (board, decks, label_decks, cards) =
+      kanban_compile_decks(kanban, args, &options.filter, &scorer).await?;
 
-    if matches.is_present("compare") {
+    let output = args.output.as_str();
+    let compare = args.compare || args.compare_with.is_some();
+    if output == "ndjson" || output == "json" {
+      let old_decks = match compare {
+        true => {
+          let old_entries = client
+            .query_entries(board.id.to_string(), None)
+            .await?
+            .unwrap_or_default();
+          match compare_at(args) {
+            Some(at) => find_entry_at(&old_entries, &at, &config.date_format.timezone)?
+              .map(|entry| entry.decks),
+            None => get_decks_by_date(old_entries, &config.date_format),
+          }
+        }
+        false => None,
+      };
+      match output {
+        "json" => json::print_score(
+          &board,
+          &decks,
+          old_decks.as_deref(),
+          &options.filter,
+          &options.list_names,
+        )?,
+        _ => ndjson::print_decks(
+          &board,
+          &decks,
+          old_decks.as_deref(),
+          &options.filter,
+          &options.list_names,
+        )?,
+      }
+    } else if output == "csv" {
+      for line in decks_as_csv(&decks, &options.filter, &config.csv) {
+        println!("{}", line);
+      }
+    } else if compare {
       if let Some(old_entries) = client.query_entries(board.id.to_string(), None).await? {
-        let old_decks = get_decks_by_date(old_entries).unwrap_or(vec![]);
-        print_delta(&decks, &old_decks, &board.name, filter);
+        let old_decks = match compare_at(args) {
+          Some(at) => find_entry_at(&old_entries, &at, &config.date_format.timezone)?
+            .map(|entry| entry.decks)
+            .ok_or_else(|| eyre!("Unable to find an entry at {}", at))?,
+          None => get_decks_by_date(old_entries, &config.date_format).unwrap_or(vec![]),
+        };
+        print_delta(&decks, &old_decks, &board.name, &options);
       } else {
         println!("Unable to retrieve any decks from the database.");
-        print_decks(&decks, &board.name, filter);
+        print_decks(&decks, &board.name, &options);
       }
     } else {
-      print_decks(&decks, &board.name, filter);
+      print_decks(&decks, &board.name, &options);
+    }
+
+    if let (Some(days), "table") = (args.sparkline, output) {
+      print_sparkline(
+        config,
+        client,
+        &board,
+        &decks,
+        &options.filter,
+        days,
+        ascii_only(config, ascii),
+      )
+      .await?;
+    }
+
+    if args.group_by == "label" && output == "table" {
+      // Filters and groups are about list names, so they don't apply to labels
+      let label_options = TableOptions {
+        palette: options.palette.clone(),
+        ..TableOptions::default()
+      };
+      print_decks(
+        &label_decks,
+        &format!("{} by label", board.name),
+        &label_options,
+      );
+    }
+
+    if args.detailed && output == "table" {
+      print_card_details(&decks, &cards, &scorer, &options);
+    }
+
+    let off_scale = off_scale(&cards, &scorer, &config.allowed_points);
+    for (card, estimated) in off_scale.iter() {
+      eprintln!(
+        "Warning: \"{}\" in {} is estimated at {}, which isn't one of the allowed points",
+        card.name, card.list_name, estimated
+      );
+    }
+    if args.strict && !off_scale.is_empty() {
+      return Err(eyre!(
+        "{} cards are estimated at points that aren't allowed",
+        off_scale.len()
+      ));
     }
 
-    Ok((board, decks))
+    Ok((board, decks, cards))
   }
 
-  /// Parses configuration passed in through matches
+  /// Draws a burndown for the entries saved within the range passed in
   pub async fn output_burndown(
-    matches: &clap::ArgMatches<'_>,
+    &self,
+    args: &BurndownArgs,
+    ascii: bool,
     client: Box<dyn Database>,
   ) -> Result<()> {
-    let config = match Config::from_file()? {
+    let config = match Config::from_file(self.profile())? {
       Some(config) => config,
       None => panic!("clean this up"),
     };
 
-    let kanban = init_kanban_board(&config, matches);
+    let output = args.output.as_str();
+    if args.output_file.is_some() && !FILE_OUTPUTS.contains(&output) {
+      return Err(eyre!(
+        "--output-file only works with {} output",
+        FILE_OUTPUTS.join(", ")
+      ));
+    }
+    // Only svg output is drawn with the template, the rest shouldn't fail on a missing one
+    let template = match args.template.as_deref().or(config.svg_template.as_deref()) {
+      Some(path) if output == "svg" => Some(
+        fs::read_to_string(path)
+          .wrap_err_with(|| format!("Unable to read SVG template {}", path))?,
+      ),
+      _ => None,
+    };
+    let style = chart_style(&config, args, output)?;
 
-    let options = BurndownOptions::init_with_matches(kanban, client, matches).await?;
+    let kanban = init_kanban_board_from_config(&config)?;
 
-    let burndown = options.into_burndown().await?;
+    let mut options = BurndownOptions::init_with_args(
+      kanban,
+      client,
+      config.state_matcher()?,
+      config.date_format.timezone,
+      &args.range,
+    )
+    .await?;
+    options.fill_missing_days = !args.no_fill;
+    let annotations = config.annotations_for(&options.board_id);
 
-    match matches.value_of("output") {
-      Some("ascii") => burndown.as_ascii().unwrap(),
-      Some("csv") => println!("{}", burndown.as_csv().join("\n")),
-      Some("svg") => println!("{}", burndown.as_svg().unwrap()),
-      Some(option) => println!("Output option {} not supported", option),
-      None => println!("{}", burndown.as_csv().join("\n")),
+    let entries = options.entries().await?;
+    if entries.is_empty() {
+      return Err(eyre!("Unable to find any entries within the date range"));
     }
+    let burndown = Burndown::calculate_burndown(
+      &entries,
+      &options.filter,
+      &options.states.done,
+      options.fill_missing_days.then_some(options.timezone),
+    );
+
+    let average = match args.average_of {
+      Some(count) => {
+        let average = options.trailing_average(count, args.sprint_length).await?;
+        if average.is_none() {
+          eprintln!(
+            "Unable to find any entries for the previous {} sprints",
+            count
+          );
+        }
+        average
+      }
+      None => None,
+    };
+
+    // Charts that can be written to --output-file, the rest are only printed
+    let chart: Option<Vec<u8>> = match output {
+      "ascii" | "inline" if ascii_only(&config, ascii) => {
+        println!(
+          "{}",
+          burndown
+            .as_text(&config.date_format, &style, &annotations, average.as_ref())
+            .join("\n")
+        );
+        None
+      }
+      "ascii" => {
+        burndown.as_ascii(
+          &config.date_format.timezone,
+          &style,
+          &annotations,
+          average.as_ref(),
+        )?;
+        None
+      }
+      "csv" => Some(
+        burndown
+          .as_csv(&config.date_format, &config.csv, average.as_ref())
+          .join("\n")
+          .into_bytes(),
+      ),
+      "svg" => Some(
+        burndown
+          .as_svg_with_template(
+            template.as_deref().unwrap_or(SVG_TEMPLATE),
+            &config.date_format,
+            &style,
+            &annotations,
+            average.as_ref(),
+            args.scope,
+            args.skip_weekends,
+          )?
+          .into_bytes(),
+      ),
+      "plotly" => Some(
+        burndown
+          .as_plotly(
+            &config.date_format,
+            &style,
+            &annotations,
+            average.as_ref(),
+            args.scope,
+            args.skip_weekends,
+          )?
+          .into_bytes(),
+      ),
+      "png" => Some(burndown.as_png(&config.date_format.timezone, &annotations, average.as_ref())),
+      "inline" => {
+        burndown.as_inline(&config.date_format, &style, &annotations, average.as_ref())?;
+        None
+      }
+      "ndjson" => {
+        burndown
+          .as_ndjson(&config.date_format.timezone, average.as_ref())
+          .iter()
+          .try_for_each(ndjson::print_line)?;
+        None
+      }
+      option => {
+        println!("Output option {} not supported", option);
+        None
+      }
+    };
+
+    if let Some(chart) = chart {
+      match &args.output_file {
+        Some(path) => {
+          write_output_file(Path::new(path), &chart, args.force)?;
+          eprintln!("Wrote burndown to {}", path);
+        }
+        None => {
+          let mut stdout = std::io::stdout();
+          stdout
+            .write_all(&chart)
+            .and_then(|_| match output {
+              "png" => Ok(()),
+              _ => stdout.write_all(b"\n"),
+            })
+            .wrap_err_with(|| "Unable to print burndown")?;
+        }
+      }
+    }
+
+    // Warnings go to stderr so they don't end up in piped csv or svg
+    detect_anomalies(
+      &entries,
+      &config.anomalies,
+      &options.filter,
+      &options.states.done,
+    )
+    .iter()
+    .for_each(|anomaly| eprintln!("Warning: {}", anomaly.describe(&config.date_format)));
 
     Ok(())
   }
+
+  /// Forecasts when a board will be finished from the entries saved for it
+  pub async fn forecast(&self, args: &ForecastArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    let options = BurndownOptions::init_with_args(
+      kanban,
+      client,
+      config.state_matcher()?,
+      config.date_format.timezone,
+      &args.range,
+    )
+    .await?;
+    forecast::forecast(options, args).await
+  }
+
+  /// Writes up a sprint review from the entries saved during the sprint
+  pub async fn report(&self, args: &ReportArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    let options = BurndownOptions::init_with_args(
+      kanban,
+      client,
+      config.state_matcher()?,
+      config.date_format.timezone,
+      &args.range,
+    )
+    .await?;
+    let annotations = config.annotations_for(&options.board_id);
+    report::report(
+      options,
+      &config.scorer()?,
+      &config.date_format,
+      &config.chart,
+      &ListNames::new(&config.list_aliases),
+      &annotations,
+      args,
+    )
+    .await
+  }
+
+  /// Lists when points were added to or removed from a board
+  pub async fn scope(&self, args: &RangeArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    let options = BurndownOptions::init_with_args(
+      kanban,
+      client,
+      config.state_matcher()?,
+      config.date_format.timezone,
+      args,
+    )
+    .await?;
+    scope::report(options, &config.date_format).await
+  }
+
+  /// Looks for unusual trends in a board's saved entries
+  pub async fn anomalies(&self, args: &AnomaliesArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    anomalies::report(&config, kanban, client, args).await
+  }
+
+  /// Compares two saved entries for a board, without fetching the board
+  pub async fn diff(&self, args: &DiffArgs, ascii: bool, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let options = table_options(&config, &args.filter, use_color(&config, false, ascii))?;
+
+    diff::diff(client, &config, &options, args).await
+  }
+
+  /// Writes saved entries for a board to a file for use in other tools
+  pub async fn export(&self, args: &ExportArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    export::export_entries(kanban, client, &config.date_format.timezone, args).await
+  }
+
+  /// Scores the cards saved with each entry again and updates the entries that changed
+  pub async fn rescore(&self, args: &RescoreArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    rescore::rescore(kanban, client, &config.date_format, &config.scorer()?, args).await
+  }
+
+  /// Rebuilds entries for days before card-counter was saving them from the board's history
+  pub async fn backfill(&self, args: &BackfillArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    backfill::backfill(kanban, client, &config, args).await
+  }
+
+  /// Saves entries for boards without printing tables or prompting, for scheduled jobs
+  pub async fn snapshot(&self, args: &SnapshotArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    snapshot::snapshot(kanban, client, &config, args).await
+  }
+
+  /// Checks a board's lists against the WIP limits in config
+  pub async fn wip(&self, args: &WipArgs) -> Result<Vec<wip::WipViolation>> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    wip::wip(kanban, &config, args).await
+  }
+
+  /// Prints a row for each board passed in, or each of summary_boards in config
+  pub async fn summary(
+    &self,
+    args: &SummaryArgs,
+    ascii: bool,
+    client: Box<dyn Database>,
+  ) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+    let board_ids = match args.board_id.is_empty() {
+      true => config.summary_boards.clone(),
+      false => args.board_id.clone(),
+    };
+
+    summary::summary(
+      kanban,
+      client,
+      &config.scorer()?,
+      &config.done_matcher()?,
+      &board_ids,
+      ascii_only(&config, ascii),
+    )
+    .await
+  }
+
+  /// Keeps scoring a board and saving entries on an interval, instead of relying on cron
+  pub async fn watch(
+    &self,
+    args: &WatchArgs,
+    ascii: bool,
+    client: Box<dyn Database>,
+  ) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+    let options = table_options(&config, &args.filter, use_color(&config, false, ascii))?;
+
+    watch::watch(kanban, client, &config, &options, args).await
+  }
+
+  /// Lists cards that have sat outside of done lists for several entries in a row
+  pub async fn aging(&self, args: &AgingArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    aging::aging(
+      kanban,
+      client,
+      &config.scorer()?,
+      &config.done_matcher()?,
+      &config.date_format,
+      args,
+    )
+    .await
+  }
+
+  /// Measures how close estimates came to corrections for the cards saved with each board's entries
+  pub async fn accuracy(&self, args: &AccuracyArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+
+    let board_ids: Vec<String> = args
+      .board_id
+      .iter()
+      .map(|board| config.board_id(board))
+      .collect();
+
+    accuracy::report(client, &config.scorer()?, &board_ids).await
+  }
+
+  /// Writes every entry in the database to a backup file
+  pub async fn backup(&self, args: &BackupArgs, client: Box<dyn Database>) -> Result<()> {
+    backup::backup(client, args).await
+  }
+
+  /// Saves every entry in a backup file to the database
+  pub async fn restore(&self, args: &RestoreArgs, client: Box<dyn Database>) -> Result<()> {
+    backup::restore(client, &self.config()?, args).await
+  }
+
+  /// Checks the database chosen with --database, or in your config, is ready to use
+  pub async fn check_database(&self, database: Option<&str>, args: &CheckArgs) -> Result<()> {
+    db::check(self.check_for_database(database)?, self.profile(), args).await
+  }
+
+  /// Removes a board's old entries from the database
+  pub async fn prune(&self, args: &PruneArgs, client: Box<dyn Database>) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    prune::prune(kanban, client, &config.date_format, args).await
+  }
+
+  /// Reports how long cards spend in each list and which ones are stuck
+  pub async fn time_in_status(&self, args: &TimeInStatusArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    time_in_status::report(kanban, args).await
+  }
+
+  pub async fn cycletime(&self, args: &BoardArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    cycletime::report(kanban, &config.state_matcher()?, args).await
+  }
+
+  pub async fn compare_boards(&self, args: &CompareBoardsArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    compare_boards::compare_boards(
+      kanban,
+      &config.scorer()?,
+      &ListNames::new(&config.list_aliases),
+      args,
+    )
+    .await
+  }
+
+  /// Reports the points each person finished per sprint
+  pub async fn velocity(&self, args: &VelocityArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    velocity::report(&config, kanban, args).await
+  }
+
+  /// Lists cards whose correction was far off from their estimate
+  pub async fn review(&self, args: &ReviewArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    review::review(kanban, &config.scorer()?, args).await
+  }
+
+  /// Compares estimates to corrections across a board
+  pub async fn retro(&self, args: &RetroArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    retro::retro(kanban, &config.scorer()?, args).await
+  }
+
+  /// Lists every board the configured credentials can see
+  pub async fn boards(&self, args: &BoardsArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    boards::list_boards(kanban.get_boards().await?, &config.kanban.to_string(), args)
+  }
+
+  /// Lists a board's lists with their ids, counts, and states
+  pub async fn lists(&self, args: &BoardArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    lists::lists(kanban, &config.scorer()?, &config.state_matcher()?, args).await
+  }
+
+  pub async fn due(&self, args: &BoardArgs) -> Result<()> {
+    let config = self.config()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    due::due(kanban, &config.scorer()?, args).await
+  }
+
+  /// Records a dated annotation for a board in the config file, so it can be drawn on burndown
+  /// charts.
+  pub async fn annotate(&self, args: &AnnotateArgs) -> Result<()> {
+    let mut config = Config::from_global_file_or_default()?;
+    let kanban = init_kanban_board_from_config(&config)?;
+
+    let board: Board = match args.board_id.as_deref() {
+      Some(id) => kanban.get_board(id).await?,
+      None => kanban.select_board().await?,
+    };
+
+    let date = match &args.date {
+      Some(date) => date.clone(),
+      None => config
+        .date_format
+        .timezone
+        .day(&Utc::now())
+        .format("%F")
+        .to_string(),
+    };
+
+    let annotation = Annotation {
+      board_id: board.id,
+      date,
+      label: args.label.clone(),
+    };
+    // Fail early rather than storing a date we can never draw
+    annotation.time_stamp(&config.date_format.timezone)?;
+
+    config.annotations.push(annotation);
+    config.persist()
+  }
+}
+
+/// Writes contents to path, creating any directories it needs. An existing file is only replaced
+/// when force is set.
+pub fn write_output_file(path: &Path, contents: &[u8], force: bool) -> Result<()> {
+  if let Some(parent) = path
+    .parent()
+    .filter(|parent| !parent.as_os_str().is_empty())
+  {
+    fs::create_dir_all(parent)
+      .wrap_err_with(|| format!("Unable to create directory {}", parent.display()))?;
+  }
+
+  let mut file = match force {
+    true => OpenOptions::new()
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(path),
+    false => OpenOptions::new().write(true).create_new(true).open(path),
+  }
+  .map_err(|err| match err.kind() {
+    io::ErrorKind::AlreadyExists => eyre!(
+      "{} already exists, pass --force to overwrite it",
+      path.display()
+    ),
+    _ => eyre!("Unable to create file {}: {}", path.display(), err),
+  })?;
+  file
+    .write_all(contents)
+    .wrap_err_with(|| format!("Unable to write to {}", path.display()))
+}
+
+/// The entry --compare should use instead of prompting, --against-tag TAG is shorthand for
+/// --at tag:TAG and --compare-with compares without needing --compare
+fn compare_at(args: &ScoreArgs) -> Option<String> {
+  match (&args.against_tag, &args.compare_with) {
+    (Some(tag), _) => Some(format!("{}{}", TAG_PREFIX, tag)),
+    (None, Some(at)) => Some(at.clone()),
+    (None, None) => args.at.clone(),
+  }
+}
+
+/// Prints a line of bars for the points completed on each of the last `days` days, from the
+/// entries saved for the board and the current score
+async fn print_sparkline(
+  config: &Config,
+  client: &dyn Database,
+  board: &Board,
+  decks: &[Deck],
+  filter: &ListFilter,
+  days: i64,
+  ascii: bool,
+) -> Result<()> {
+  let now = Utc::now();
+  let start = days
+    .checked_mul(24 * 60 * 60)
+    .and_then(Duration::try_seconds)
+    .and_then(|since| now.checked_sub_signed(since))
+    .ok_or_else(|| eyre!("--sparkline {} goes back too many days", days))?;
+  let range = DateRange {
+    start: start.timestamp(),
+    end: now.timestamp(),
+  };
+  let mut entries = client
+    .query_entries(board.id.to_string(), Some(range))
+    .await?
+    .unwrap_or_default();
+  entries.push(Entry {
+    board_id: board.id.clone(),
+    board_name: board.name.clone(),
+    time_stamp: now.timestamp(),
+    decks: decks.to_vec(),
+    tags: vec![],
+    cards: vec![],
+  });
+
+  let burndown = Burndown::calculate_burndown(
+    &entries,
+    filter,
+    &config.done_matcher()?,
+    Some(config.date_format.timezone),
+  );
+  let first = burndown.0.first().map(|(_, _, complete)| *complete);
+  let last = burndown.0.last().map(|(_, _, complete)| *complete);
+  println!(
+    "Last {} days: {} {} to {} points complete",
+    days,
+    burndown.as_sparkline(&config.date_format.timezone, ascii),
+    first.unwrap_or(0),
+    last.unwrap_or(0)
+  );
+  Ok(())
+}
+
+/// Colour is turned off by --no-color, a non-empty NO_COLOR environment variable, ASCII only
+/// output, or the monochrome theme. See https://no-color.org
+fn use_color(config: &Config, no_color: bool, ascii: bool) -> bool {
+  let no_color_env = std::env::var("NO_COLOR")
+    .map(|value| !value.is_empty())
+    .unwrap_or(false);
+
+  !(no_color || no_color_env || ascii_only(config, ascii))
+}
+
+/// Output is kept to plain ASCII, without box drawing characters, braille charts, or colour, with
+/// --ascii or ascii set in the config
+fn ascii_only(config: &Config, ascii: bool) -> bool {
+  ascii || config.ascii
+}
+
+/// The chart style in the config, with the title, labels, size, and colours passed in on the
+/// command line in its place. Sizes and colours are for the ASCII chart when output is ascii.
+fn chart_style(config: &Config, args: &BurndownArgs, output: &str) -> Result<ChartStyle> {
+  let mut style = config.chart.clone();
+
+  if let Some(title) = &args.title {
+    style.title = title.clone();
+  }
+  if let Some(label) = &args.x_label {
+    style.x_label = Some(label.clone());
+  }
+  if let Some(label) = &args.y_label {
+    style.y_label = Some(label.clone());
+  }
+
+  let ascii = output == "ascii";
+  if let Some(width) = args.width {
+    match ascii {
+      true => style.ascii_width = width,
+      false => style.width = width,
+    }
+  }
+  if let Some(height) = args.height {
+    match ascii {
+      true => style.ascii_height = height,
+      false => style.height = height,
+    }
+  }
+  if let Some(colour) = &args.incomplete_colour {
+    match ascii {
+      true => style.ascii_incomplete_colour = colour.clone(),
+      false => style.incomplete_colour = colour.clone(),
+    }
+  }
+  if let Some(colour) = &args.complete_colour {
+    match ascii {
+      true => style.ascii_complete_colour = colour.clone(),
+      false => style.complete_colour = colour.clone(),
+    }
+  }
+
+  // The SVG is drawn inside 50 pixels of padding on each side
+  if style.width <= 100 || style.height <= 100 {
+    return Err(eyre!(
+      "Charts need to be more than 100 pixels wide and tall, not {}x{}",
+      style.width,
+      style.height
+    ));
+  }
+
+  Ok(style)
+}
+
+/// The options every table is printed with, leaving out the lists filter matches
+fn table_options(config: &Config, filter: &FilterArgs, color: bool) -> Result<TableOptions> {
+  Ok(TableOptions {
+    filter: ListFilter::from_args(filter)?,
+    list_names: ListNames::new(&config.list_aliases),
+    group_separator: None,
+    palette: if color { config.theme.palette() } else { None },
+    wip_limit: None,
+    wip_limits: config.wip_limits()?,
+  })
 }
 
 async fn kanban_compile_decks(
   kanban: Box<dyn Kanban>,
-  matches: &clap::ArgMatches<'_>,
-) -> Result<(Board, Vec<Deck>)> {
-  let board: Board = match matches.value_of("board_id") {
+  args: &ScoreArgs,
+  filter: &ListFilter,
+  scorer: &Scorer,
+) -> Result<(Board, Vec<Deck>, Vec<Deck>, Vec<CardSnapshot>)> {
+  let board: Board = match args.board_id.as_deref() {
     Some(id) => kanban.get_board(id).await?,
     None => kanban.select_board().await?,
   };
 
-  let lists = kanban.get_lists(&board.id).await?;
-  let cards = kanban.get_cards(&board.id).await?;
-  let map_cards: HashMap<String, Vec<Card>> = kanban::collect_cards(cards);
-  let decks = kanban::build_decks(lists, map_cards);
+  let member = args.member.as_deref().or(args.assignee.as_deref());
+  let (decks, label_decks, cards) =
+    compile_decks_with_labels(kanban.as_ref(), &board.id, member, filter, scorer).await?;
+  // Entries are saved and compared under the entry key, which differs when counting one sprint
+  // or only some of the board's cards
+  let board = Board {
+    id: filtered_entry_key(
+      kanban.entry_key(&board.id).await?,
+      member,
+      args.jql.as_deref(),
+    ),
+    ..board
+  };
+
+  Ok((board, decks, label_decks, cards))
+}
+
+/// Counts limited to a member or a JQL query only hold some of the board's cards, so they're saved
+/// under their own key instead of being mixed into the board's history
+fn filtered_entry_key(key: String, member: Option<&str>, jql: Option<&str>) -> String {
+  let key = match member {
+    Some(member) => format!("{}:member:{}", key, member),
+    None => key,
+  };
+  match jql {
+    Some(jql) => format!("{}:jql:{}", key, jql),
+    None => key,
+  }
+}
+
+/// Fetches the lists and cards for a board and scores them, optionally only counting the cards
+/// assigned to member.
+pub async fn compile_decks(
+  kanban: &dyn Kanban,
+  board_id: &str,
+  member: Option<&str>,
+  scorer: &Scorer,
+) -> Result<(Vec<Deck>, Vec<CardSnapshot>)> {
+  let (decks, _, snapshots) =
+    compile_decks_with_labels(kanban, board_id, member, &ListFilter::default(), scorer).await?;
 
-  Ok((board, decks))
+  Ok((decks, snapshots))
+}
+
+/// Like compile_decks, but also scores the cards by label, leaving out lists that match filter
+async fn compile_decks_with_labels(
+  kanban: &dyn Kanban,
+  board_id: &str,
+  member: Option<&str>,
+  filter: &ListFilter,
+  scorer: &Scorer,
+) -> Result<(Vec<Deck>, Vec<Deck>, Vec<CardSnapshot>)> {
+  let lists = kanban.get_lists(board_id).await?;
+  let cards = kanban.get_cards(board_id).await?;
+  let map_cards: HashMap<String, Vec<Card>> = kanban::collect_cards(cards, member);
+  let snapshots = kanban::snapshot_cards(&lists, &map_cards);
+  let label_decks = kanban::build_label_decks(&lists, &map_cards, filter, scorer);
+
+  Ok((
+    kanban::build_decks(lists, map_cards, scorer),
+    label_decks,
+    snapshots,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_saves_filtered_counts_apart_from_the_board() {
+    assert_eq!(filtered_entry_key("1".to_string(), None, None), "1");
+    assert_eq!(
+      filtered_entry_key("1:42".to_string(), Some("justin"), None),
+      "1:42:member:justin"
+    );
+    assert_eq!(
+      filtered_entry_key("1".to_string(), Some("justin"), Some("labels = web")),
+      "1:member:justin:jql:labels = web"
+    );
+  }
+
+  #[test]
+  fn it_refuses_to_overwrite_output_files_without_force() {
+    let dir = std::env::temp_dir().join(format!("card-counter-output-{}", std::process::id()));
+    let path = dir.join("charts").join("burndown.svg");
+
+    write_output_file(&path, b"first", false).unwrap();
+    assert!(write_output_file(&path, b"second", false).is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"first");
+
+    write_output_file(&path, b"third", true).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"third");
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
 }
@@ -0,0 +1,130 @@
+use crate::{
+  database::Entry,
+  errors::*,
+  kanban::Board,
+  score::{filter_decks, Deck, ListFilter, ListNames},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// A single deck as printed by `--output ndjson`, along with the board it came from and, when
+/// comparing, the same list in the previous entry.
+#[derive(Serialize, Debug)]
+pub struct DeckLine<'a> {
+  pub board_id: &'a str,
+  pub board: &'a str,
+  #[serde(flatten)]
+  pub deck: &'a Deck,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub previous: Option<&'a Deck>,
+}
+
+/// One day of a burndown
+#[derive(Serialize, Debug)]
+pub struct BurndownLine {
+  pub date: DateTime<Utc>,
+  pub incomplete: i32,
+  pub complete: i32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub average_incomplete: Option<i32>,
+}
+
+/// Writes value as a single line of JSON and flushes it, so whoever is reading on the other end
+/// gets it right away instead of when the buffer fills up.
+pub fn write_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<()> {
+  serde_json::to_writer(&mut *writer, value).wrap_err_with(|| "Unable to serialize as JSON")?;
+  writer.write_all(b"\n")?;
+  writer.flush()?;
+  Ok(())
+}
+
+/// Prints value to standard out as a single line of JSON
+pub fn print_line<T: Serialize>(value: &T) -> Result<()> {
+  write_line(&mut io::stdout().lock(), value)
+}
+
+/// Prints one line per deck, after applying filter. When old_decks are given each line also
+/// carries the matching deck from the earlier entry.
+pub fn print_decks(
+  board: &Board,
+  decks: &[Deck],
+  old_decks: Option<&[Deck]>,
+  filter: &ListFilter,
+  list_names: &ListNames,
+) -> Result<()> {
+  let old_decks = old_decks.map(|old_decks| filter_decks(old_decks, filter));
+  let stdout = io::stdout();
+  let mut writer = stdout.lock();
+
+  for deck in filter_decks(decks, filter).iter() {
+    write_line(
+      &mut writer,
+      &DeckLine {
+        board_id: &board.id,
+        board: &board.name,
+        deck,
+        previous: old_decks
+          .as_ref()
+          .and_then(|old_decks| list_names.find_matching(deck, old_decks)),
+      },
+    )?;
+  }
+  Ok(())
+}
+
+/// Streams entries to writer, one entry per line
+pub fn write_entries<W: Write>(writer: &mut W, entries: &[Entry]) -> Result<()> {
+  entries
+    .iter()
+    .try_for_each(|entry| write_line(writer, entry))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_writes_one_object_per_line() {
+    let deck = Deck {
+      list_name: "Done".to_string(),
+      size: 2,
+      score: 5,
+      unscored: 0,
+      estimated: 5,
+    };
+    let mut output = vec![];
+    write_line(
+      &mut output,
+      &DeckLine {
+        board_id: "1",
+        board: "Sprint",
+        deck: &deck,
+        previous: None,
+      },
+    )
+    .unwrap();
+    write_entries(
+      &mut output,
+      &[Entry {
+        board_id: "1".to_string(),
+        board_name: String::new(),
+        time_stamp: 86400,
+        decks: vec![deck.clone()],
+        tags: vec![],
+        cards: vec![],
+      }],
+    )
+    .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+      lines[0],
+      r#"{"board_id":"1","board":"Sprint","list_name":"Done","size":2,"score":5,"unscored":0,"estimated":5}"#
+    );
+    assert!(lines[1].starts_with(r#"{"board_id":"1","time_stamp":86400,"decks":[{"#));
+  }
+}
@@ -0,0 +1,258 @@
+use crate::{
+  cli::PruneArgs,
+  database::{
+    config::{Config, DateFormat, Timezone},
+    Database, Entry,
+  },
+  errors::*,
+  kanban::{Board, Kanban},
+};
+
+use chrono::{DateTime, NaiveDate};
+use std::collections::{HashMap, HashSet};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Which entries history prune removes. Tagged entries are always kept.
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+  // Entries saved before this timestamp are removed
+  pub before: Option<i64>,
+  // Only the last entry saved each day is kept
+  pub daily: bool,
+  // Only this many of the newest untagged entries are kept
+  pub keep_latest: Option<usize>,
+  // The timezone daily splits entries into days by
+  pub timezone: Timezone,
+}
+
+impl PruneOptions {
+  pub fn from_args(args: &PruneArgs, now: i64, timezone: Timezone) -> Self {
+    PruneOptions {
+      before: args.older_than.map(|days| now - days * SECONDS_PER_DAY),
+      daily: args.daily,
+      keep_latest: None,
+      timezone,
+    }
+  }
+
+  /// The retention policy set in config, if there is one
+  pub fn from_config(config: &Config, now: i64) -> Option<Self> {
+    if config.retention_days.is_none() && config.max_entries_per_board.is_none() {
+      return None;
+    }
+
+    Some(PruneOptions {
+      before: config
+        .retention_days
+        .map(|days| now - days * SECONDS_PER_DAY),
+      daily: false,
+      keep_latest: config.max_entries_per_board,
+      timezone: config.date_format.timezone,
+    })
+  }
+}
+
+/// The timestamps of the entries that options says should be removed, oldest first
+pub fn entries_to_prune(entries: &[Entry], options: &PruneOptions) -> Vec<i64> {
+  let day_of = |time_stamp: i64| -> NaiveDate {
+    options
+      .timezone
+      .day(&DateTime::from_timestamp(time_stamp, 0).unwrap_or_default())
+  };
+  let mut last_of_day: HashMap<NaiveDate, i64> = HashMap::new();
+  for entry in entries {
+    let last = last_of_day
+      .entry(day_of(entry.time_stamp))
+      .or_insert(entry.time_stamp);
+    *last = (*last).max(entry.time_stamp);
+  }
+
+  let mut newest_first: Vec<i64> = entries
+    .iter()
+    .filter(|entry| entry.tags.is_empty())
+    .map(|entry| entry.time_stamp)
+    .collect();
+  newest_first.sort_unstable_by(|a, b| b.cmp(a));
+  let over_limit: HashSet<i64> = match options.keep_latest {
+    Some(keep) => newest_first.into_iter().skip(keep).collect(),
+    None => HashSet::new(),
+  };
+
+  let mut time_stamps: Vec<i64> = entries
+    .iter()
+    .filter(|entry| entry.tags.is_empty())
+    .filter(|entry| {
+      let too_old = options
+        .before
+        .map(|before| entry.time_stamp < before)
+        .unwrap_or(false);
+      let replaced = options.daily && last_of_day[&day_of(entry.time_stamp)] != entry.time_stamp;
+      too_old || replaced || over_limit.contains(&entry.time_stamp)
+    })
+    .map(|entry| entry.time_stamp)
+    .collect();
+  time_stamps.sort_unstable();
+  time_stamps
+}
+
+/// Applies the retention policy in config to board_id's entries, returning how many were removed.
+/// Does nothing when no policy is set.
+pub async fn enforce_retention(
+  client: &dyn Database,
+  config: &Config,
+  board_id: &str,
+) -> Result<usize> {
+  let options = match PruneOptions::from_config(config, Entry::get_current_timestamp()?) {
+    Some(options) => options,
+    None => return Ok(0),
+  };
+
+  let entries = client
+    .query_entries(board_id.to_string(), None)
+    .await?
+    .unwrap_or_default();
+  let time_stamps = entries_to_prune(&entries, &options);
+  let removed = time_stamps.len();
+  if removed > 0 {
+    client
+      .delete_entries(board_id.to_string(), time_stamps)
+      .await
+      .wrap_err_with(|| format!("Unable to remove old entries for {}", board_id))?;
+  }
+
+  Ok(removed)
+}
+
+/// Removes old entries for a board so the database doesn't grow forever. A dry run only lists
+/// the entries that would be removed.
+pub async fn prune(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  format: &DateFormat,
+  args: &PruneArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let options = PruneOptions::from_args(args, Entry::get_current_timestamp()?, format.timezone);
+
+  let entries = client
+    .query_entries(board.id.clone(), None)
+    .await?
+    .unwrap_or_default();
+  let time_stamps = entries_to_prune(&entries, &options);
+
+  if time_stamps.is_empty() {
+    println!("No entries for {} need to be removed", board.name);
+    return Ok(());
+  }
+
+  if args.dry_run {
+    for time_stamp in time_stamps.iter() {
+      let date = DateTime::from_timestamp(*time_stamp, 0).unwrap();
+      println!("{}", format.format(&date, &format.timestamp));
+    }
+    println!(
+      "{} of {} entries for {} would be removed",
+      time_stamps.len(),
+      entries.len(),
+      board.name
+    );
+    return Ok(());
+  }
+
+  let removed = time_stamps.len();
+  client.delete_entries(board.id.clone(), time_stamps).await?;
+  println!(
+    "Removed {} of {} entries for {}",
+    removed,
+    entries.len(),
+    board.name
+  );
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(time_stamp: i64, tags: &[&str]) -> Entry {
+    Entry {
+      board_id: "board".to_string(),
+      time_stamp,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      ..Entry::default()
+    }
+  }
+
+  #[test]
+  fn it_prunes_entries_older_than_a_cutoff() {
+    let entries = vec![
+      entry(SECONDS_PER_DAY * 3, &[]),
+      entry(10, &["sprint-1-start"]),
+      entry(20, &[]),
+    ];
+    let options = PruneOptions {
+      before: Some(SECONDS_PER_DAY),
+      ..PruneOptions::default()
+    };
+
+    assert_eq!(entries_to_prune(&entries, &options), vec![20]);
+  }
+
+  #[test]
+  fn it_keeps_the_last_entry_of_each_day() {
+    let entries = vec![
+      entry(30, &[]),
+      entry(10, &[]),
+      entry(20, &["standup"]),
+      entry(SECONDS_PER_DAY + 5, &[]),
+    ];
+    let options = PruneOptions {
+      daily: true,
+      timezone: Timezone::Utc,
+      ..PruneOptions::default()
+    };
+
+    assert_eq!(entries_to_prune(&entries, &options), vec![10]);
+  }
+
+  #[test]
+  fn it_splits_days_in_the_configured_timezone() {
+    // 16:00 and 18:00 on Jan 1st in Edmonton, but on different days in UTC
+    let entries = vec![entry(23 * 60 * 60, &[]), entry(25 * 60 * 60, &[])];
+    let options = |timezone: Timezone| PruneOptions {
+      daily: true,
+      timezone,
+      ..PruneOptions::default()
+    };
+
+    assert!(entries_to_prune(&entries, &options(Timezone::Utc)).is_empty());
+    assert_eq!(
+      entries_to_prune(&entries, &options("America/Edmonton".parse().unwrap())),
+      vec![23 * 60 * 60]
+    );
+  }
+
+  #[test]
+  fn it_keeps_the_newest_entries_from_config() {
+    let config = Config {
+      max_entries_per_board: Some(2),
+      ..Config::default()
+    };
+    let options = PruneOptions::from_config(&config, 100).unwrap();
+    let entries = vec![
+      entry(40, &[]),
+      entry(10, &[]),
+      entry(20, &["sprint-1-start"]),
+      entry(30, &[]),
+      entry(50, &[]),
+    ];
+
+    assert_eq!(entries_to_prune(&entries, &options), vec![10, 30]);
+    assert!(PruneOptions::from_config(&Config::default(), 100).is_none());
+  }
+}
@@ -0,0 +1,368 @@
+use crate::{
+  cli::ReportArgs,
+  commands::burndown::{Burndown, BurndownOptions, ListMatcher, ScopeChange},
+  database::{
+    config::{Annotation, ChartStyle, DateFormat, Timezone},
+    CardSnapshot, DateRange, Entry,
+  },
+  errors::*,
+  score::{filter_decks, Deck, ListFilter, ListNames, Scorer},
+};
+
+use chrono::{DateTime, Utc};
+
+/// Everything that goes into a sprint review, worked out from the entries saved during the sprint
+#[derive(Debug)]
+pub struct SprintReport {
+  pub board_name: String,
+  pub start: DateTime<Utc>,
+  pub end: DateTime<Utc>,
+  // The board as of the last entry in the sprint, and the first
+  pub decks: Vec<Deck>,
+  pub start_decks: Vec<Deck>,
+  // Points on the board when the sprint started
+  pub committed: i32,
+  // Points that made it into a done list during the sprint
+  pub completed: i32,
+  // What the cards in done lists were estimated at, and what they came to after corrections
+  pub done_estimated: i32,
+  pub done_corrected: i32,
+  pub scope_changes: Vec<ScopeChange>,
+  pub burndown: Burndown,
+}
+
+fn to_date(time_stamp: i64) -> DateTime<Utc> {
+  DateTime::from_timestamp(time_stamp, 0).unwrap()
+}
+
+/// What the cards in done lists were estimated at, and what they came to. Only cards with an
+/// estimate are counted, and a card without a correction came to its estimate.
+fn done_points(
+  cards: &[CardSnapshot],
+  filter: &ListFilter,
+  done_lists: &ListMatcher,
+  scorer: &Scorer,
+) -> (i32, i32) {
+  cards
+    .iter()
+    .filter(|card| filter.keeps(&card.list_name) && done_lists.matches(&card.list_name))
+    .filter_map(|card| scorer.score(&card.name))
+    .filter_map(|score| Some((score.estimated?, score.correction)))
+    .fold((0, 0), |(estimated, corrected), (estimate, correction)| {
+      (
+        estimated + estimate,
+        corrected + correction.unwrap_or(estimate),
+      )
+    })
+}
+
+impl SprintReport {
+  /// Builds a report from the entries saved within range. Returns None when there aren't any.
+  /// Days without an entry are filled in on the burndown as in Burndown::calculate_burndown.
+  pub fn new(
+    entries: &[Entry],
+    range: &DateRange,
+    filter: &ListFilter,
+    done_lists: &ListMatcher,
+    scorer: &Scorer,
+    fill_days_in: Option<Timezone>,
+  ) -> Option<Self> {
+    let first = entries.iter().min()?;
+    let last = entries.iter().max()?;
+    let burndown = Burndown::calculate_burndown(entries, filter, done_lists, fill_days_in);
+    let (_, start_complete) = first.calculate_score(filter, done_lists);
+    let (_, end_complete) = last.calculate_score(filter, done_lists);
+
+    let decks = filter_decks(&last.decks, filter);
+    let (done_estimated, done_corrected) = done_points(&last.cards, filter, done_lists, scorer);
+
+    Some(SprintReport {
+      board_name: match last.board_name.is_empty() {
+        true => last.board_id.clone(),
+        false => last.board_name.clone(),
+      },
+      start: to_date(range.start),
+      end: to_date(range.end),
+      start_decks: filter_decks(&first.decks, filter),
+      decks,
+      committed: burndown.0.first().map(|day| day.1 + day.2).unwrap_or(0),
+      completed: end_complete - start_complete,
+      done_estimated,
+      done_corrected,
+      scope_changes: burndown.scope_changes(),
+      burndown,
+    })
+  }
+
+  fn title(&self, format: &DateFormat) -> String {
+    format!(
+      "Sprint report for {}, {} to {}",
+      self.board_name,
+      format.format(&self.start, &format.date),
+      format.format(&self.end, &format.date)
+    )
+  }
+
+  /// Each list with its cards and points at the end of the sprint, and how its points changed
+  /// since the start
+  fn deck_rows(&self, list_names: &ListNames) -> Vec<[String; 6]> {
+    self
+      .decks
+      .iter()
+      .map(|deck| {
+        let change = deck.score
+          - list_names
+            .find_matching(deck, &self.start_decks)
+            .map(|start| start.score)
+            .unwrap_or(0);
+        [
+          deck.list_name.clone(),
+          deck.size.to_string(),
+          deck.score.to_string(),
+          deck.estimated.to_string(),
+          deck.unscored.to_string(),
+          format!("{:+}", change),
+        ]
+      })
+      .collect()
+  }
+
+  fn summary(&self) -> Vec<String> {
+    let percent = |part: i32, whole: i32| match whole {
+      0 => String::new(),
+      whole => format!(" ({:.0}%)", part as f64 / whole as f64 * 100.),
+    };
+    vec![
+      format!(
+        "Completed {} of the {} points committed at the start{}",
+        self.completed,
+        self.committed,
+        percent(self.completed, self.committed)
+      ),
+      format!(
+        "Done cards were estimated at {} points and came to {}",
+        self.done_estimated, self.done_corrected
+      ),
+    ]
+  }
+
+  fn scope_lines(&self, format: &DateFormat) -> Vec<String> {
+    self
+      .scope_changes
+      .iter()
+      .map(|change| {
+        format!(
+          "{}: {} {} points, from {} to {}",
+          format.format(&change.date, &format.date),
+          match change.change() > 0 {
+            true => "added",
+            false => "removed",
+          },
+          change.change().abs(),
+          change.before,
+          change.after
+        )
+      })
+      .collect()
+  }
+
+  pub fn as_markdown(&self, format: &DateFormat, list_names: &ListNames) -> String {
+    let mut lines = vec![format!("# {}", self.title(format)), String::new()];
+
+    lines.push("## Summary".to_string());
+    lines.extend(self.summary().into_iter().map(|line| format!("- {}", line)));
+
+    lines.extend([String::new(), "## Lists".to_string()]);
+    lines.push("| List | Cards | Score | Estimated | Unscored | Since start |".to_string());
+    lines.push("|---|---|---|---|---|---|".to_string());
+    for row in self.deck_rows(list_names) {
+      lines.push(format!("| {} |", row.join(" | ")));
+    }
+
+    lines.extend([String::new(), "## Scope changes".to_string()]);
+    match self.scope_lines(format) {
+      changes if changes.is_empty() => lines.push("The scope didn't change".to_string()),
+      changes => lines.extend(changes.into_iter().map(|line| format!("- {}", line))),
+    }
+
+    lines.extend([String::new(), "## Burndown".to_string()]);
+    lines.push("| Date | Incomplete | Complete |".to_string());
+    lines.push("|---|---|---|".to_string());
+    for (date, incomplete, complete) in &self.burndown.0 {
+      lines.push(format!(
+        "| {} | {} | {} |",
+        format.format(date, &format.date),
+        incomplete,
+        complete
+      ));
+    }
+
+    lines.join("\n")
+  }
+
+  pub fn as_html(
+    &self,
+    format: &DateFormat,
+    style: &ChartStyle,
+    list_names: &ListNames,
+    annotations: &[Annotation],
+  ) -> Result<String> {
+    let list = |lines: Vec<String>| {
+      lines
+        .iter()
+        .map(|line| format!("<li>{}</li>", escape(line)))
+        .collect::<String>()
+    };
+    let rows: String = self
+      .deck_rows(list_names)
+      .iter()
+      .map(|row| {
+        format!(
+          "<tr>{}</tr>",
+          row
+            .iter()
+            .map(|cell| format!("<td>{}</td>", escape(cell)))
+            .collect::<String>()
+        )
+      })
+      .collect();
+    let scope = match self.scope_lines(format) {
+      changes if changes.is_empty() => "<p>The scope didn't change</p>".to_string(),
+      changes => format!("<ul>{}</ul>", list(changes)),
+    };
+
+    Ok(format!(
+      "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head><body>\n<h1>{title}</h1>\n<h2>Summary</h2>\n<ul>{summary}</ul>\n<h2>Lists</h2>\n<table><tr><th>List</th><th>Cards</th><th>Score</th><th>Estimated</th><th>Unscored</th><th>Since start</th></tr>{rows}</table>\n<h2>Scope changes</h2>\n{scope}\n<h2>Burndown</h2>\n{svg}\n</body></html>",
+      title = escape(&self.title(format)),
+      summary = list(self.summary()),
+      rows = rows,
+      scope = scope,
+      svg = self.burndown.as_svg(format, style, annotations, None, true, false)?
+    ))
+  }
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+/// Puts together a sprint review document from the entries saved between --start and --end
+pub async fn report(
+  options: BurndownOptions,
+  scorer: &Scorer,
+  format: &DateFormat,
+  style: &ChartStyle,
+  list_names: &ListNames,
+  annotations: &[Annotation],
+  args: &ReportArgs,
+) -> Result<()> {
+  let entries = options.entries().await?;
+  let report = SprintReport::new(
+    &entries,
+    &options.range,
+    &options.filter,
+    &options.states.done,
+    scorer,
+    options.fill_missing_days.then_some(options.timezone),
+  )
+  .ok_or_else(|| eyre!("Unable to find any entries within the date range"))?;
+
+  match args.output.as_str() {
+    "html" => println!(
+      "{}",
+      report.as_html(format, style, list_names, annotations)?
+    ),
+    _ => println!("{}", report.as_markdown(format, list_names)),
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  #[test]
+  fn it_reports_on_a_sprint() {
+    let scorer = Scorer::default();
+    let entry = |day: i64, lists: &[(&str, &[&str])]| Entry {
+      board_id: "board".to_string(),
+      board_name: "Sprint".to_string(),
+      time_stamp: day * 86400,
+      decks: lists
+        .iter()
+        .map(|(list_name, names)| {
+          let (score, unscored, estimated) = scorer.tally(names.iter().copied());
+          Deck {
+            list_name: list_name.to_string(),
+            size: names.len(),
+            score,
+            unscored,
+            estimated,
+          }
+        })
+        .collect(),
+      tags: vec![],
+      cards: lists
+        .iter()
+        .flat_map(|(list_name, names)| {
+          names.iter().map(move |name| CardSnapshot {
+            name: name.to_string(),
+            list_name: list_name.to_string(),
+          })
+        })
+        .collect(),
+    };
+    let entries = vec![
+      entry(0, &[("To Do", &["(5) Login", "(5) Signup"]), ("Done", &[])]),
+      entry(
+        1,
+        &[
+          ("To Do", &["(5) Login", "(5) Signup", "(3) Search"]),
+          ("Done", &[]),
+        ],
+      ),
+      entry(
+        2,
+        &[
+          ("To Do", &["(5) Signup", "Write docs"]),
+          ("Done", &["(5)[4] Login", "(3)[5] Search"]),
+        ],
+      ),
+    ];
+    let range = DateRange {
+      start: 0,
+      end: 2 * 86400,
+    };
+
+    let report = SprintReport::new(
+      &entries,
+      &range,
+      &ListFilter::default(),
+      &ListMatcher::default(),
+      &scorer,
+      None,
+    )
+    .unwrap();
+    assert_eq!((report.committed, report.completed), (10, 9));
+    assert_eq!((report.done_estimated, report.done_corrected), (8, 9));
+    assert_eq!(
+      report
+        .scope_changes
+        .iter()
+        .map(ScopeChange::change)
+        .collect::<Vec<_>>(),
+      vec![3, 1]
+    );
+
+    let markdown = report.as_markdown(&DateFormat::default(), &ListNames::new(&HashMap::new()));
+    assert!(markdown.contains("- Completed 9 of the 10 points committed at the start (90%)"));
+    assert!(markdown.contains("- Done cards were estimated at 8 points and came to 9"));
+    assert!(markdown.contains("| Done | 2 | 9 | 0 | 0 | +9 |"));
+  }
+}
@@ -0,0 +1,234 @@
+use crate::{
+  cli::RescoreArgs,
+  database::{config::DateFormat, Database, DateRange, Entry},
+  errors::*,
+  kanban::{Board, Kanban},
+  score::{Deck, Scorer},
+};
+
+use chrono::DateTime;
+use prettytable::Table;
+
+/// A deck whose numbers changed after rescoring the cards saved with its entry
+#[derive(Debug)]
+pub struct Change {
+  pub time_stamp: i64,
+  pub before: Deck,
+  pub after: Deck,
+}
+
+/// Scores the cards saved with entry again, returning a deck for each of the entry's decks.
+/// Entries saved without cards can't be rescored.
+pub fn rescore_entry(entry: &Entry, scorer: &Scorer) -> Option<Vec<Deck>> {
+  if entry.cards.is_empty() {
+    return None;
+  }
+
+  Some(
+    entry
+      .decks
+      .iter()
+      .map(|deck| {
+        let names: Vec<&str> = entry
+          .cards
+          .iter()
+          .filter(|card| card.list_name == deck.list_name)
+          .map(|card| card.name.as_str())
+          .collect();
+        let (score, unscored, estimated) = scorer.tally(names.iter().cloned());
+
+        Deck {
+          list_name: deck.list_name.clone(),
+          size: names.len(),
+          score,
+          unscored,
+          estimated,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Pairs up the decks that differ between the saved entry and its rescored decks
+pub fn changes(entry: &Entry, rescored: &[Deck]) -> Vec<Change> {
+  entry
+    .decks
+    .iter()
+    .zip(rescored.iter())
+    .filter(|(before, after)| {
+      (before.score, before.unscored, before.estimated)
+        != (after.score, after.unscored, after.estimated)
+    })
+    .map(|(before, after)| Change {
+      time_stamp: entry.time_stamp,
+      before: before.clone(),
+      after: after.clone(),
+    })
+    .collect()
+}
+
+pub fn print_changes(changes: &[Change], format: &DateFormat) {
+  let mut table = Table::new();
+  table.set_titles(row!["Date", "List", "Score", "Estimated", "Unscored"]);
+
+  let describe = |before: i32, after: i32| {
+    if before == after {
+      before.to_string()
+    } else {
+      format!("{} -> {}", before, after)
+    }
+  };
+
+  for change in changes {
+    let date = DateTime::from_timestamp(change.time_stamp, 0).unwrap();
+    table.add_row(row![
+      format.format(&date, &format.date),
+      change.before.list_name,
+      describe(change.before.score, change.after.score),
+      describe(change.before.estimated, change.after.estimated),
+      describe(change.before.unscored, change.after.unscored)
+    ]);
+  }
+
+  table.printstd();
+}
+
+/// Recomputes the decks of saved entries from their cards, printing what changed. Unless this
+/// is a dry run the updated entries are saved over the originals.
+pub async fn rescore(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  format: &DateFormat,
+  scorer: &Scorer,
+  args: &RescoreArgs,
+) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let range = match (&args.start, &args.end) {
+    (Some(start), Some(end)) => {
+      Some(DateRange::resolve(client.as_ref(), &board.id, start, end, &format.timezone).await?)
+    }
+    _ => None,
+  };
+
+  let mut entries = client
+    .query_entries(board.id.clone(), range)
+    .await?
+    .unwrap_or_default();
+  entries.sort();
+
+  let dry_run = args.dry_run;
+  let mut all_changes = vec![];
+  let mut skipped = 0;
+  let mut updated = 0;
+
+  for entry in entries {
+    let rescored = match rescore_entry(&entry, scorer) {
+      Some(rescored) => rescored,
+      None => {
+        skipped += 1;
+        continue;
+      }
+    };
+
+    let entry_changes = changes(&entry, &rescored);
+    if entry_changes.is_empty() {
+      continue;
+    }
+    all_changes.extend(entry_changes);
+    updated += 1;
+
+    if !dry_run {
+      client
+        .add_entry(Entry {
+          decks: rescored,
+          ..entry
+        })
+        .await?;
+    }
+  }
+
+  if all_changes.is_empty() {
+    println!("No entries for {} changed after rescoring", board.name);
+  } else {
+    print_changes(&all_changes, format);
+    match dry_run {
+      true => println!("{} entries for {} would be updated", updated, board.name),
+      false => println!("Updated {} entries for {}", updated, board.name),
+    }
+  }
+  if skipped > 0 {
+    println!(
+      "Skipped {} entries that were saved without their cards",
+      skipped
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::CardSnapshot;
+
+  fn deck(list_name: &str, size: usize, score: i32, unscored: i32, estimated: i32) -> Deck {
+    Deck {
+      list_name: list_name.to_string(),
+      size,
+      score,
+      unscored,
+      estimated,
+    }
+  }
+
+  fn card(name: &str, list_name: &str) -> CardSnapshot {
+    CardSnapshot {
+      name: name.to_string(),
+      list_name: list_name.to_string(),
+    }
+  }
+
+  #[test]
+  fn it_rescores_entries_from_their_cards() {
+    let entry = Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp: 1,
+      // Saved when only estimates were counted
+      decks: vec![deck("To Do", 1, 0, 1, 0), deck("Done", 2, 5, 0, 5)],
+      tags: vec![],
+      cards: vec![
+        card("Login page", "To Do"),
+        card("(3) Signup page", "Done"),
+        card("(2)[5] Password reset", "Done"),
+      ],
+    };
+
+    let rescored = rescore_entry(&entry, &Scorer::default()).unwrap();
+    assert_eq!(rescored[0].score, 0);
+    assert_eq!(rescored[1].score, 8);
+    assert_eq!(rescored[1].estimated, 3);
+
+    let changes = changes(&entry, &rescored);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].before.list_name, "Done");
+  }
+
+  #[test]
+  fn it_skips_entries_without_cards() {
+    let entry = Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp: 1,
+      decks: vec![deck("Done", 2, 5, 0, 5)],
+      tags: vec![],
+      cards: vec![],
+    };
+
+    assert!(rescore_entry(&entry, &Scorer::default()).is_none());
+  }
+}
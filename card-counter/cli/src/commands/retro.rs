@@ -0,0 +1,242 @@
+use crate::{
+  cli::RetroArgs,
+  errors::*,
+  kanban::{Board, Card, Kanban, List},
+  score::Scorer,
+};
+
+use prettytable::Table;
+use std::collections::HashMap;
+
+/// How the estimates for a list's corrected cards, or the whole board's, held up
+#[derive(Debug, PartialEq, Clone)]
+pub struct Totals {
+  pub name: String,
+  // Only cards with both an estimate and a correction are counted
+  pub cards: usize,
+  pub estimated: i32,
+  pub corrected: i32,
+}
+
+impl Totals {
+  fn new(name: &str) -> Self {
+    Totals {
+      name: name.to_string(),
+      cards: 0,
+      estimated: 0,
+      corrected: 0,
+    }
+  }
+
+  /// How far the corrections drifted from the estimates, as a percentage of the estimates
+  pub fn drift(&self) -> Option<f64> {
+    match self.estimated {
+      0 => None,
+      estimated => Some((self.corrected - estimated) as f64 / estimated as f64 * 100.),
+    }
+  }
+}
+
+/// A corrected card and how far it ended up from its estimate
+#[derive(Debug, PartialEq)]
+pub struct Variance {
+  pub name: String,
+  pub list_name: String,
+  pub estimated: i32,
+  pub correction: i32,
+}
+
+impl Variance {
+  pub fn difference(&self) -> i32 {
+    self.correction - self.estimated
+  }
+}
+
+/// Every card with both an estimate and a correction, in the order they appear on the board
+pub fn variances(lists: &[List], cards: &[Card], scorer: &Scorer) -> Vec<Variance> {
+  let list_names: HashMap<&str, &str> = lists
+    .iter()
+    .map(|list| (list.id.as_str(), list.name.as_str()))
+    .collect();
+
+  cards
+    .iter()
+    .filter_map(|card| {
+      let score = scorer.score(&card.name)?;
+      Some(Variance {
+        name: card.name.clone(),
+        list_name: list_names
+          .get(card.parent_list.as_str())
+          .map(|name| name.to_string())
+          .unwrap_or_else(|| card.parent_list.clone()),
+        estimated: score.estimated?,
+        correction: score.correction?,
+      })
+    })
+    .collect()
+}
+
+/// Adds up the estimated and corrected points for each list, in the order lists first appear, and
+/// for the whole board
+pub fn totals(variances: &[Variance], board_name: &str) -> (Vec<Totals>, Totals) {
+  let mut lists: Vec<Totals> = Vec::new();
+  let mut board = Totals::new(board_name);
+
+  for variance in variances {
+    let index = match lists
+      .iter()
+      .position(|totals| totals.name == variance.list_name)
+    {
+      Some(index) => index,
+      None => {
+        lists.push(Totals::new(&variance.list_name));
+        lists.len() - 1
+      }
+    };
+    for totals in [&mut lists[index], &mut board] {
+      totals.cards += 1;
+      totals.estimated += variance.estimated;
+      totals.corrected += variance.correction;
+    }
+  }
+
+  (lists, board)
+}
+
+/// The `count` cards that strayed furthest from their estimates, in either direction
+pub fn largest_variances(mut variances: Vec<Variance>, count: usize) -> Vec<Variance> {
+  variances.retain(|variance| variance.difference() != 0);
+  variances.sort_by_key(|variance| std::cmp::Reverse(variance.difference().abs()));
+  variances.truncate(count);
+  variances
+}
+
+fn totals_row(totals: &Totals) -> prettytable::Row {
+  row![
+    totals.name,
+    totals.cards,
+    totals.estimated,
+    totals.corrected,
+    totals
+      .drift()
+      .map(|drift| format!("{:+.0}%", drift))
+      .unwrap_or_default()
+  ]
+}
+
+pub fn print_retro(lists: &[Totals], board: &Totals, largest: &[Variance]) {
+  if board.cards == 0 {
+    println!(
+      "None of the cards on {} have both an estimate and a correction yet",
+      board.name
+    );
+    return;
+  }
+
+  println!("{}", board.name);
+  let mut table = Table::new();
+  table.set_titles(row!["List", "Cards", "Estimated", "Corrected", "Drift"]);
+  for totals in lists {
+    table.add_row(totals_row(totals));
+  }
+  table.add_row(totals_row(&Totals {
+    name: "TOTAL".to_string(),
+    ..board.clone()
+  }));
+  table.printstd();
+
+  if !largest.is_empty() {
+    println!("Largest variances");
+    let mut table = Table::new();
+    table.set_titles(row![
+      "Card",
+      "List",
+      "Estimated",
+      "Correction",
+      "Difference"
+    ]);
+    for variance in largest {
+      table.add_row(row![
+        variance.name,
+        variance.list_name,
+        variance.estimated,
+        variance.correction,
+        format!("{:+}", variance.difference())
+      ]);
+    }
+    table.printstd();
+  }
+}
+
+/// Compares the estimates on a board's cards to their corrections, by list and for the whole
+/// board, and lists the cards that were furthest off
+pub async fn retro(kanban: Box<dyn Kanban>, scorer: &Scorer, args: &RetroArgs) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let lists = kanban.get_lists(&board.id).await?;
+  let cards = kanban.get_cards(&board.id).await?;
+
+  let variances = variances(&lists, &cards, scorer);
+  let (list_totals, board_totals) = totals(&variances, &board.name);
+  print_retro(
+    &list_totals,
+    &board_totals,
+    &largest_variances(variances, args.top),
+  );
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_totals_corrections_by_list_and_board() {
+    let list = |id: &str, name: &str| List {
+      name: name.to_string(),
+      id: id.to_string(),
+      board_id: "board".to_string(),
+    };
+    let card = |name: &str, list: &str| Card {
+      name: name.to_string(),
+      parent_list: list.to_string(),
+      members: vec![],
+      labels: vec![],
+      due: None,
+    };
+    let lists = vec![list("1", "Doing"), list("2", "Done")];
+    let cards = vec![
+      card("(3)[5] Login page", "2"),
+      card("(8)[2] Signup page", "2"),
+      card("(2) Not corrected", "2"),
+      card("(1)[1] Typo", "1"),
+    ];
+
+    let variances = variances(&lists, &cards, &Scorer::default());
+    let (lists, board) = totals(&variances, "Sprint");
+    assert_eq!(
+      lists
+        .iter()
+        .map(|totals| (
+          totals.name.as_str(),
+          totals.cards,
+          totals.estimated,
+          totals.corrected
+        ))
+        .collect::<Vec<_>>(),
+      vec![("Done", 2, 11, 7), ("Doing", 1, 1, 1)]
+    );
+    assert_eq!((board.cards, board.estimated, board.corrected), (3, 12, 8));
+    assert_eq!(board.drift().map(|drift| drift.round()), Some(-33.));
+
+    let largest: Vec<i32> = largest_variances(variances, 5)
+      .iter()
+      .map(Variance::difference)
+      .collect();
+    assert_eq!(largest, vec![-6, 2]);
+  }
+}
@@ -0,0 +1,206 @@
+use crate::{
+  cli::ReviewArgs,
+  errors::*,
+  kanban::{Board, Card, Kanban, List},
+  score::Scorer,
+};
+
+use prettytable::Table;
+use std::collections::HashMap;
+
+/// A finished card whose correction strayed too far from its estimate
+#[derive(Debug, PartialEq)]
+pub struct Outlier {
+  pub name: String,
+  pub estimated: i32,
+  pub correction: i32,
+  // How many times bigger the larger of the two numbers is
+  pub ratio: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GroupBy {
+  List,
+  Label,
+}
+
+/// Compares a card's correction to its estimate, only cards with both count as reviewed
+pub fn outlier(card: &Card, max_ratio: f64, scorer: &Scorer) -> Option<Outlier> {
+  let score = scorer.score(&card.name)?;
+  let (estimated, correction) = (score.estimated?, score.correction?);
+
+  let (smaller, larger) = if estimated < correction {
+    (estimated, correction)
+  } else {
+    (correction, estimated)
+  };
+  // Anything estimated, or corrected, to zero is as far off as it gets
+  let ratio = if smaller <= 0 {
+    f64::INFINITY
+  } else {
+    larger as f64 / smaller as f64
+  };
+
+  if larger == smaller || ratio < max_ratio {
+    return None;
+  }
+
+  Some(Outlier {
+    name: card.name.clone(),
+    estimated,
+    correction,
+    ratio,
+  })
+}
+
+/// Collects outliers under each list or label, in the order the groups appear on the board.
+/// Cards without a label are grouped under "No label".
+pub fn find_outliers(
+  lists: &[List],
+  cards: &[Card],
+  max_ratio: f64,
+  group_by: GroupBy,
+  scorer: &Scorer,
+) -> Vec<(String, Vec<Outlier>)> {
+  let list_names: HashMap<&str, &str> = lists
+    .iter()
+    .map(|list| (list.id.as_str(), list.name.as_str()))
+    .collect();
+
+  let mut groups: Vec<(String, Vec<Outlier>)> = Vec::new();
+  for card in cards {
+    let keys: Vec<String> = match group_by {
+      GroupBy::List => vec![list_names
+        .get(card.parent_list.as_str())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| card.parent_list.clone())],
+      GroupBy::Label if card.labels.is_empty() => vec!["No label".to_string()],
+      GroupBy::Label => card.labels.clone(),
+    };
+
+    for key in keys {
+      if let Some(outlier) = outlier(card, max_ratio, scorer) {
+        match groups.iter_mut().find(|(name, _)| *name == key) {
+          Some((_, outliers)) => outliers.push(outlier),
+          None => groups.push((key, vec![outlier])),
+        }
+      }
+    }
+  }
+
+  groups
+}
+
+pub fn print_outliers(groups: &[(String, Vec<Outlier>)], board_name: &str, max_ratio: f64) {
+  if groups.is_empty() {
+    println!(
+      "Every corrected card on {} was within {}x of its estimate",
+      board_name, max_ratio
+    );
+    return;
+  }
+
+  println!("{}", board_name);
+  for (group, outliers) in groups {
+    let mut table = Table::new();
+    println!("{}", group);
+    table.set_titles(row!["Card", "estimated", "correction", "ratio"]);
+    for outlier in outliers {
+      table.add_row(row![
+        outlier.name,
+        outlier.estimated,
+        outlier.correction,
+        format!("{:.1}x", outlier.ratio)
+      ]);
+    }
+    table.printstd();
+  }
+}
+
+/// Lists finished cards whose correction is more than ratio times off from their estimate
+pub async fn review(kanban: Box<dyn Kanban>, scorer: &Scorer, args: &ReviewArgs) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let max_ratio = args.ratio;
+  let group_by = match args.group_by.as_str() {
+    "label" => GroupBy::Label,
+    _ => GroupBy::List,
+  };
+
+  let lists = kanban.get_lists(&board.id).await?;
+  let cards = kanban.get_cards(&board.id).await?;
+
+  print_outliers(
+    &find_outliers(&lists, &cards, max_ratio, group_by, scorer),
+    &board.name,
+    max_ratio,
+  );
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn card(name: &str, list: &str, labels: &[&str]) -> Card {
+    Card {
+      name: name.to_string(),
+      parent_list: list.to_string(),
+      members: vec![],
+      labels: labels.iter().map(|label| label.to_string()).collect(),
+      due: None,
+    }
+  }
+
+  #[test]
+  fn it_only_flags_cards_past_the_ratio() {
+    let outlier = |name: &str| outlier(&card(name, "done", &[]), 1.5, &Scorer::default());
+    assert_eq!(outlier("(3)[3] same"), None);
+    assert_eq!(outlier("(3) not finished"), None);
+    assert_eq!(outlier("(4)[5] close"), None);
+    assert_eq!(
+      outlier("(2)[8] way off"),
+      Some(Outlier {
+        name: "(2)[8] way off".to_string(),
+        estimated: 2,
+        correction: 8,
+        ratio: 4.0,
+      })
+    );
+  }
+
+  #[test]
+  fn it_groups_outliers_by_label() {
+    let lists = vec![List {
+      name: "Done".to_string(),
+      id: "1".to_string(),
+      board_id: "board".to_string(),
+    }];
+    let cards = vec![
+      card("(1)[5] bug fix", "1", &["bug"]),
+      card("(8)[2] feature", "1", &["feature", "bug"]),
+      card("(2)[6] chore", "1", &[]),
+    ];
+
+    let groups: Vec<(String, usize)> =
+      find_outliers(&lists, &cards, 2.0, GroupBy::Label, &Scorer::default())
+        .into_iter()
+        .map(|(group, outliers)| (group, outliers.len()))
+        .collect();
+    assert_eq!(
+      groups,
+      vec![
+        ("bug".to_string(), 2),
+        ("feature".to_string(), 1),
+        ("No label".to_string(), 1)
+      ]
+    );
+
+    let groups = find_outliers(&lists, &cards, 2.0, GroupBy::List, &Scorer::default());
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].0, "Done");
+  }
+}
@@ -0,0 +1,83 @@
+use crate::{
+  commands::burndown::{Burndown, BurndownOptions, ScopeChange},
+  database::config::DateFormat,
+  errors::*,
+};
+
+use prettytable::Table;
+
+/// The points added to and removed from the board over all of the changes
+pub fn totals(changes: &[ScopeChange]) -> (i32, i32) {
+  changes
+    .iter()
+    .fold((0, 0), |(added, removed), change| match change.change() {
+      change if change > 0 => (added + change, removed),
+      change => (added, removed - change),
+    })
+}
+
+pub fn print_scope_changes(changes: &[ScopeChange], format: &DateFormat) {
+  if changes.is_empty() {
+    println!("The scope didn't change");
+    return;
+  }
+
+  let mut table = Table::new();
+  table.set_titles(row!["Date", "Change", "Before", "After"]);
+  for change in changes {
+    table.add_row(row![
+      format.format(&change.date, &format.date),
+      format!("{:+}", change.change()),
+      change.before,
+      change.after
+    ]);
+  }
+  table.printstd();
+
+  let (added, removed) = totals(changes);
+  println!("{} points added, {} points removed", added, removed);
+}
+
+/// Lists every time points were added to or removed from the board between the entries saved
+/// within the date range
+pub async fn report(options: BurndownOptions, format: &DateFormat) -> Result<()> {
+  let entries = options.entries().await?;
+  let burndown = Burndown::calculate_burndown(
+    &entries,
+    &options.filter,
+    &options.states.done,
+    options.fill_missing_days.then_some(options.timezone),
+  );
+
+  print_scope_changes(&burndown.scope_changes(), format);
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{TimeZone, Utc};
+
+  #[test]
+  fn it_finds_scope_added_and_removed() {
+    let day = |day: u32| Utc.with_ymd_and_hms(2021, 3, day, 12, 0, 0).unwrap();
+    let burndown = Burndown(vec![
+      (day(1), 20, 0),
+      // Finishing work doesn't change the scope
+      (day(2), 15, 5),
+      (day(3), 23, 5),
+      (day(4), 18, 7),
+    ]);
+
+    let changes = burndown.scope_changes();
+    assert_eq!(
+      changes
+        .iter()
+        .map(|change| (change.date, change.change()))
+        .collect::<Vec<_>>(),
+      vec![(day(3), 8), (day(4), -3)]
+    );
+    assert_eq!(totals(&changes), (8, 3));
+  }
+}
@@ -0,0 +1,136 @@
+use crate::{
+  cli::SnapshotArgs,
+  commands::{compile_decks, ndjson, prune::enforce_retention},
+  database::{config::Config, Database, Entry},
+  errors::*,
+  kanban::Kanban,
+  score::Scorer,
+};
+
+use serde::Serialize;
+
+/// What snapshot prints for each entry it saves
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SnapshotLine<'a> {
+  pub board_id: &'a str,
+  pub board: &'a str,
+  pub time_stamp: i64,
+  pub cards: usize,
+  pub score: i32,
+  pub estimated: i32,
+  pub unscored: i32,
+  // How many old entries the retention policy removed
+  pub pruned: usize,
+}
+
+impl<'a> SnapshotLine<'a> {
+  pub fn new(entry: &'a Entry, pruned: usize) -> Self {
+    entry.decks.iter().fold(
+      SnapshotLine {
+        board_id: &entry.board_id,
+        board: &entry.board_name,
+        time_stamp: entry.time_stamp,
+        cards: 0,
+        score: 0,
+        estimated: 0,
+        unscored: 0,
+        pruned,
+      },
+      |mut line, deck| {
+        line.cards += deck.size;
+        line.score += deck.score;
+        line.estimated += deck.estimated;
+        line.unscored += deck.unscored;
+        line
+      },
+    )
+  }
+}
+
+async fn snapshot_board(
+  kanban: &dyn Kanban,
+  client: &dyn Database,
+  config: &Config,
+  scorer: &Scorer,
+  board_id: &str,
+  tags: &[String],
+) -> Result<()> {
+  let board = kanban.get_board(board_id).await?;
+  let (decks, cards) = compile_decks(kanban, &board.id, None, scorer).await?;
+  let entry = Entry {
+    board_id: board.id,
+    board_name: board.name,
+    time_stamp: Entry::get_current_timestamp()?,
+    decks,
+    tags: tags.to_vec(),
+    cards,
+  };
+
+  client.add_entry(entry.clone()).await?;
+  let pruned = enforce_retention(client, config, &entry.board_id).await?;
+  ndjson::print_line(&SnapshotLine::new(&entry, pruned))
+}
+
+/// Saves an entry for each board and prints a line of JSON for each one, without ever prompting.
+/// Meant to be run by cron, systemd timers, or CI, so a board that can't be saved doesn't stop the
+/// others but still fails the command.
+pub async fn snapshot(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  config: &Config,
+  args: &SnapshotArgs,
+) -> Result<()> {
+  let scorer = config.scorer()?;
+
+  let mut failed = 0;
+  for board_id in args.board_id.iter() {
+    if let Err(err) = snapshot_board(
+      kanban.as_ref(),
+      client.as_ref(),
+      config,
+      &scorer,
+      board_id,
+      &args.tag,
+    )
+    .await
+    {
+      eprintln!("Unable to save an entry for board {}: {:?}", board_id, err);
+      failed += 1;
+    }
+  }
+
+  match failed {
+    0 => Ok(()),
+    failed => Err(eyre!("Unable to save entries for {} boards", failed)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::score::Deck;
+
+  #[test]
+  fn it_sums_up_the_entry() {
+    let deck = |list_name: &str, size: usize, score: i32, unscored: i32| Deck {
+      list_name: list_name.to_string(),
+      size,
+      score,
+      unscored,
+      estimated: score - 1,
+    };
+    let entry = Entry {
+      board_id: "1".to_string(),
+      board_name: "Sprint".to_string(),
+      time_stamp: 86400,
+      decks: vec![deck("To Do", 3, 8, 1), deck("Done", 2, 5, 0)],
+      tags: vec![],
+      cards: vec![],
+    };
+
+    assert_eq!(
+      serde_json::to_string(&SnapshotLine::new(&entry, 2)).unwrap(),
+      r#"{"board_id":"1","board":"Sprint","time_stamp":86400,"cards":5,"score":13,"estimated":11,"unscored":1,"pruned":2}"#
+    );
+  }
+}
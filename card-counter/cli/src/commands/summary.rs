@@ -0,0 +1,193 @@
+use crate::{
+  commands::{burndown::ListMatcher, compile_decks},
+  database::{Database, Entry},
+  errors::*,
+  kanban::Kanban,
+  score::{ListFilter, Scorer},
+};
+
+use futures::future::join_all;
+use prettytable::Table;
+
+/// How far along one board is, and which way it's heading
+#[derive(Debug, PartialEq)]
+pub struct BoardSummary {
+  pub name: String,
+  pub total: i32,
+  pub done: i32,
+  pub remaining: i32,
+  // The points left the last time an entry was saved for the board
+  pub previous_remaining: Option<i32>,
+}
+
+impl BoardSummary {
+  /// Sums up latest, comparing it against the entry saved before it if there is one
+  pub fn new(latest: &Entry, previous: Option<&Entry>, done_lists: &ListMatcher) -> Self {
+    let filter = ListFilter::default();
+    let (remaining, done) = latest.calculate_score(&filter, done_lists);
+    BoardSummary {
+      name: match latest.board_name.is_empty() {
+        true => latest.board_id.clone(),
+        false => latest.board_name.clone(),
+      },
+      total: remaining + done,
+      done,
+      remaining,
+      previous_remaining: previous.map(|entry| entry.calculate_score(&filter, done_lists).0),
+    }
+  }
+
+  pub fn percent_complete(&self) -> Option<f64> {
+    match self.total {
+      0 => None,
+      total => Some(self.done as f64 / total as f64 * 100.),
+    }
+  }
+
+  /// Down when there's less left than last time, up when there's more. Arrows are spelled out
+  /// with ascii.
+  pub fn trend(&self, ascii: bool) -> &'static str {
+    match (self.previous_remaining, ascii) {
+      (Some(previous), false) if self.remaining < previous => "↓",
+      (Some(previous), false) if self.remaining > previous => "↑",
+      (Some(_), false) => "→",
+      (Some(previous), true) if self.remaining < previous => "down",
+      (Some(previous), true) if self.remaining > previous => "up",
+      (Some(_), true) => "same",
+      (None, _) => "",
+    }
+  }
+}
+
+pub fn print_summaries(summaries: &[BoardSummary], ascii: bool) {
+  let mut table = Table::new();
+  table.set_titles(row![
+    "Board",
+    "total",
+    "done",
+    "remaining",
+    "complete",
+    "trend"
+  ]);
+  for summary in summaries {
+    table.add_row(row![
+      summary.name,
+      summary.total,
+      summary.done,
+      summary.remaining,
+      summary
+        .percent_complete()
+        .map(|percent| format!("{:.0}%", percent))
+        .unwrap_or_default(),
+      summary.trend(ascii)
+    ]);
+  }
+  table.printstd();
+}
+
+/// Scores a board as it is now and finds the last entry saved for it
+async fn summarize_board(
+  kanban: &dyn Kanban,
+  client: &dyn Database,
+  scorer: &Scorer,
+  done_lists: &ListMatcher,
+  board_id: &str,
+) -> Result<BoardSummary> {
+  let board = kanban.get_board(board_id).await?;
+  let (decks, _) = compile_decks(kanban, &board.id, None, scorer).await?;
+  let previous = client
+    .query_entries(board.id.clone(), None)
+    .await?
+    .unwrap_or_default()
+    .into_iter()
+    .max();
+
+  let latest = Entry {
+    board_id: board.id,
+    board_name: board.name,
+    time_stamp: Entry::get_current_timestamp()?,
+    decks,
+    tags: vec![],
+    cards: vec![],
+  };
+  Ok(BoardSummary::new(&latest, previous.as_ref(), done_lists))
+}
+
+/// Fetches every board at once and prints a row for each, with the trend measured against the last
+/// entry saved for it. Boards that can't be fetched are reported and left out.
+pub async fn summary(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  scorer: &Scorer,
+  done_lists: &ListMatcher,
+  board_ids: &[String],
+  ascii: bool,
+) -> Result<()> {
+  if board_ids.is_empty() {
+    return Err(eyre!(
+      "No boards to summarize. Pass in at least one --board-id or list them under summary_boards in your config"
+    ));
+  }
+
+  let results = join_all(board_ids.iter().map(|board_id| {
+    summarize_board(
+      kanban.as_ref(),
+      client.as_ref(),
+      scorer,
+      done_lists,
+      board_id,
+    )
+  }))
+  .await;
+
+  let mut summaries = vec![];
+  for (board_id, result) in board_ids.iter().zip(results) {
+    match result {
+      Ok(summary) => summaries.push(summary),
+      Err(err) => eprintln!("Unable to summarize board {}: {:?}", board_id, err),
+    }
+  }
+  print_summaries(&summaries, ascii);
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::score::Deck;
+
+  #[test]
+  fn it_summarizes_a_board_against_its_last_entry() {
+    let entry = |to_do: i32, done: i32| Entry {
+      board_id: "1".to_string(),
+      board_name: "Sprint".to_string(),
+      time_stamp: 0,
+      decks: vec![("To Do", to_do), ("Done", done)]
+        .into_iter()
+        .map(|(list_name, score)| Deck {
+          list_name: list_name.to_string(),
+          size: 1,
+          score,
+          unscored: 0,
+          estimated: score,
+        })
+        .collect(),
+      tags: vec![],
+      cards: vec![],
+    };
+
+    let summary = BoardSummary::new(&entry(15, 5), Some(&entry(18, 2)), &ListMatcher::default());
+    assert_eq!(
+      (summary.total, summary.done, summary.remaining),
+      (20, 5, 15)
+    );
+    assert_eq!(summary.percent_complete(), Some(25.));
+    assert_eq!(summary.trend(false), "↓");
+    assert_eq!(summary.trend(true), "down");
+
+    let summary = BoardSummary::new(&entry(0, 0), None, &ListMatcher::default());
+    assert_eq!(summary.percent_complete(), None);
+    assert_eq!(summary.trend(false), "");
+  }
+}
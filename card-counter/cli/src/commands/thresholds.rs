@@ -0,0 +1,182 @@
+use crate::{cli::ScoreArgs, commands::burndown::ListMatcher, score::Deck};
+
+/// The bit set in the exit status when there are too many unscored cards
+pub const UNSCORED_EXIT_BIT: i32 = 2;
+/// The bit set in the exit status when lists are over their work in progress limit, by
+/// --fail-if-wip-over or card-counter wip
+pub const WIP_EXIT_BIT: i32 = 4;
+/// The bit set in the exit status when there are too many incomplete points
+pub const INCOMPLETE_EXIT_BIT: i32 = 8;
+
+/// A limit the board is expected to stay under. When a board exceeds a threshold card-counter
+/// exits with a non-zero status so scheduled runs can alert on it. Each threshold has its own bit
+/// in the status, so scripts can tell which ones were exceeded.
+#[derive(Debug, PartialEq)]
+pub enum Threshold {
+  // The maximum number of unscored cards across the board
+  Unscored(i32),
+  // The maximum number of cards in lists whose name contains the given substring
+  Wip(i32, String),
+  // The maximum number of points in lists that aren't done
+  Incomplete(i32),
+}
+
+impl Threshold {
+  /// Builds the set of thresholds passed in by the user.
+  pub fn from_args(args: &ScoreArgs) -> Vec<Threshold> {
+    let mut thresholds = Vec::new();
+
+    if let Some(limit) = args.fail_if_unscored_over {
+      thresholds.push(Threshold::Unscored(limit));
+    }
+
+    if let Some(limit) = args.fail_if_wip_over {
+      thresholds.push(Threshold::Wip(limit, args.wip_list.clone()));
+    }
+
+    if let Some(limit) = args.fail_if_incomplete_over {
+      thresholds.push(Threshold::Incomplete(limit));
+    }
+
+    thresholds
+  }
+
+  /// Returns the value this threshold is measured against, lists matching done count as complete
+  pub fn measure(&self, decks: &[Deck], done: &ListMatcher) -> i32 {
+    match self {
+      Threshold::Unscored(_) => decks.iter().map(|deck| deck.unscored).sum(),
+      Threshold::Wip(_, list) => decks
+        .iter()
+        .filter(|deck| deck.list_name.contains(list.as_str()))
+        .map(|deck| deck.size as i32)
+        .sum(),
+      Threshold::Incomplete(_) => decks
+        .iter()
+        .filter(|deck| !done.matches(&deck.list_name))
+        .map(|deck| deck.score)
+        .sum(),
+    }
+  }
+
+  pub fn limit(&self) -> i32 {
+    match self {
+      Threshold::Unscored(limit) => *limit,
+      Threshold::Wip(limit, _) => *limit,
+      Threshold::Incomplete(limit) => *limit,
+    }
+  }
+
+  /// The bit set in the exit status when this threshold is exceeded
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      Threshold::Unscored(_) => UNSCORED_EXIT_BIT,
+      Threshold::Wip(_, _) => WIP_EXIT_BIT,
+      Threshold::Incomplete(_) => INCOMPLETE_EXIT_BIT,
+    }
+  }
+
+  /// Returns a message describing the violation if decks exceed the threshold
+  pub fn check(&self, decks: &[Deck], done: &ListMatcher) -> Option<String> {
+    let actual = self.measure(decks, done);
+    if actual <= self.limit() {
+      return None;
+    }
+
+    Some(match self {
+      Threshold::Unscored(limit) => format!(
+        "There are {} unscored cards, which is over the limit of {}.",
+        actual, limit
+      ),
+      Threshold::Wip(limit, list) => format!(
+        "There are {} cards in lists matching \"{}\", which is over the limit of {}.",
+        actual, list, limit
+      ),
+      Threshold::Incomplete(limit) => format!(
+        "There are {} points left to finish, which is over the limit of {}.",
+        actual, limit
+      ),
+    })
+  }
+}
+
+/// Checks decks against every threshold and returns the ones that were exceeded, along with a
+/// message describing each.
+pub fn check_thresholds<'a>(
+  thresholds: &'a [Threshold],
+  decks: &[Deck],
+  done: &ListMatcher,
+) -> Vec<(&'a Threshold, String)> {
+  thresholds
+    .iter()
+    .filter_map(|threshold| Some((threshold, threshold.check(decks, done)?)))
+    .collect()
+}
+
+/// The exit status for the exceeded thresholds, with the bit for each one set
+pub fn exit_code<'a>(exceeded: impl Iterator<Item = &'a Threshold>) -> i32 {
+  exceeded.fold(0, |code, threshold| code | threshold.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn decks() -> Vec<Deck> {
+    vec![
+      Deck {
+        list_name: "In Progress".to_string(),
+        size: 4,
+        score: 10,
+        unscored: 1,
+        estimated: 10,
+      },
+      Deck {
+        list_name: "Done".to_string(),
+        size: 3,
+        score: 8,
+        unscored: 2,
+        estimated: 8,
+      },
+    ]
+  }
+
+  #[test]
+  fn it_passes_when_under_the_limit() {
+    let thresholds = vec![
+      Threshold::Unscored(3),
+      Threshold::Wip(4, "In Progress".to_string()),
+    ];
+    assert!(check_thresholds(&thresholds, &decks(), &ListMatcher::default()).is_empty());
+  }
+
+  #[test]
+  fn it_fails_when_over_the_limit() {
+    let thresholds = vec![
+      Threshold::Unscored(2),
+      Threshold::Wip(3, "In Progress".to_string()),
+    ];
+    assert_eq!(
+      check_thresholds(&thresholds, &decks(), &ListMatcher::default()).len(),
+      2
+    );
+  }
+
+  #[test]
+  fn it_sets_a_bit_for_each_exceeded_threshold() {
+    let thresholds = vec![
+      Threshold::Unscored(3),
+      Threshold::Wip(3, "In Progress".to_string()),
+      Threshold::Incomplete(9),
+    ];
+
+    let exceeded = check_thresholds(&thresholds, &decks(), &ListMatcher::default());
+    assert_eq!(
+      exceeded[1].1,
+      "There are 10 points left to finish, which is over the limit of 9."
+    );
+    assert_eq!(
+      exit_code(exceeded.iter().map(|(threshold, _)| *threshold)),
+      12
+    );
+  }
+}
@@ -0,0 +1,205 @@
+use crate::{
+  cli::TimeInStatusArgs,
+  errors::*,
+  kanban::{Board, CardHistory, Kanban},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use prettytable::Table;
+
+/// Sums up how long a card has spent in each list, in the order the lists were first visited.
+/// Time in the current list is counted up until `now`.
+pub fn time_in_status(history: &CardHistory, now: DateTime<Utc>) -> Vec<(String, Duration)> {
+  let mut totals: Vec<(String, Duration)> = Vec::new();
+  let mut add =
+    |status: &str, duration: Duration| match totals.iter_mut().find(|(name, _)| name == status) {
+      Some((_, total)) => *total += duration,
+      None => totals.push((status.to_string(), duration)),
+    };
+
+  // Cards start in whatever list they were first moved out of
+  let mut status = history
+    .transitions
+    .first()
+    .and_then(|transition| transition.from.clone())
+    .unwrap_or_else(|| history.current_list.clone());
+  let mut since = history.created;
+
+  for transition in history.transitions.iter() {
+    add(&status, transition.at - since);
+    status = transition.to.clone();
+    since = transition.at;
+  }
+  add(&status, now - since);
+
+  totals
+}
+
+/// How long ago a card entered the list it's currently in
+pub fn time_in_current_status(history: &CardHistory, now: DateTime<Utc>) -> Duration {
+  let since = history
+    .transitions
+    .last()
+    .map(|transition| transition.at)
+    .unwrap_or(history.created);
+
+  now - since
+}
+
+/// Averages the time cards spent in each list, returning the list name, the average time, and
+/// how many cards have been through that list.
+pub fn average_time_in_status(
+  histories: &[CardHistory],
+  now: DateTime<Utc>,
+) -> Vec<(String, Duration, usize)> {
+  let mut totals: Vec<(String, Duration, usize)> = Vec::new();
+  for history in histories {
+    for (status, duration) in time_in_status(history, now) {
+      match totals.iter_mut().find(|(name, _, _)| *name == status) {
+        Some((_, total, count)) => {
+          *total += duration;
+          *count += 1;
+        }
+        None => totals.push((status, duration, 1)),
+      }
+    }
+  }
+
+  totals
+    .into_iter()
+    .map(|(status, total, count)| (status, total / count as i32, count))
+    .collect()
+}
+
+/// Finds cards that have been sitting in `status` for longer than `threshold`, longest first
+pub fn stuck_cards<'a>(
+  histories: &'a [CardHistory],
+  status: &str,
+  threshold: Duration,
+  now: DateTime<Utc>,
+) -> Vec<(&'a CardHistory, Duration)> {
+  let mut stuck: Vec<(&CardHistory, Duration)> = histories
+    .iter()
+    .filter(|history| history.current_list == status)
+    .map(|history| (history, time_in_current_status(history, now)))
+    .filter(|(_, duration)| *duration > threshold)
+    .collect();
+  stuck.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+  stuck
+}
+
+pub fn days(duration: &Duration) -> String {
+  format!("{:.1}", duration.num_minutes() as f64 / (60.0 * 24.0))
+}
+
+pub fn print_time_in_status(averages: &[(String, Duration, usize)], board_name: &str) {
+  let mut table = Table::new();
+  println!("{}", board_name);
+  table.set_titles(row!["Status", "average days", "cards"]);
+  for (status, average, count) in averages {
+    table.add_row(row![status, days(average), count]);
+  }
+  table.printstd();
+}
+
+pub fn print_stuck_cards(stuck: &[(&CardHistory, Duration)], status: &str) {
+  if stuck.is_empty() {
+    println!("Nothing is stuck in {}", status);
+    return;
+  }
+
+  let mut table = Table::new();
+  println!("Stuck in {}", status);
+  table.set_titles(row!["Card", "days"]);
+  for (history, duration) in stuck {
+    table.add_row(row![history.name, days(duration)]);
+  }
+  table.printstd();
+}
+
+/// Prints the average time cards spend in each list and which cards have been stuck in one
+/// list for too long.
+pub async fn report(kanban: Box<dyn Kanban>, args: &TimeInStatusArgs) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let stuck_in = args.stuck_in.as_str();
+  let stuck_after = args.stuck_after;
+
+  let histories = kanban.get_card_histories(&board.id).await?;
+  let now = Utc::now();
+
+  print_time_in_status(&average_time_in_status(&histories, now), &board.name);
+  print_stuck_cards(
+    &stuck_cards(&histories, stuck_in, Duration::days(stuck_after), now),
+    stuck_in,
+  );
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::kanban::Transition;
+  use chrono::TimeZone;
+
+  fn history(name: &str, current_list: &str, moves: &[(&str, &str, u32)]) -> CardHistory {
+    CardHistory {
+      id: name.to_string(),
+      name: name.to_string(),
+      created: Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+      current_list: current_list.to_string(),
+      members: vec![],
+      transitions: moves
+        .iter()
+        .map(|(from, to, day)| Transition {
+          from: Some(from.to_string()),
+          to: to.to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, *day, 0, 0, 0).unwrap(),
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn it_sums_time_spent_in_each_status() {
+    let now = Utc.with_ymd_and_hms(2021, 3, 10, 0, 0, 0).unwrap();
+    let card = history(
+      "card",
+      "Done",
+      &[
+        ("To Do", "In Review", 2),
+        ("In Review", "To Do", 3),
+        ("To Do", "In Review", 5),
+        ("In Review", "Done", 8),
+      ],
+    );
+
+    assert_eq!(
+      time_in_status(&card, now),
+      vec![
+        ("To Do".to_string(), Duration::days(3)),
+        ("In Review".to_string(), Duration::days(4)),
+        ("Done".to_string(), Duration::days(2)),
+      ]
+    );
+  }
+
+  #[test]
+  fn it_finds_cards_stuck_in_review() {
+    let now = Utc.with_ymd_and_hms(2021, 3, 10, 0, 0, 0).unwrap();
+    let histories = vec![
+      history("old", "In Review", &[("To Do", "In Review", 2)]),
+      history("new", "In Review", &[("To Do", "In Review", 9)]),
+      history("done", "Done", &[("In Review", "Done", 2)]),
+    ];
+
+    let stuck = stuck_cards(&histories, "In Review", Duration::days(3), now);
+    assert_eq!(stuck.len(), 1);
+    assert_eq!(stuck[0].0.name, "old");
+    assert_eq!(stuck[0].1, Duration::days(8));
+  }
+}
@@ -0,0 +1,300 @@
+use crate::{
+  cli::VelocityArgs,
+  database::config::{Config, CsvDialect, DateFormat},
+  errors::*,
+  kanban::{Board, CardHistory, Kanban},
+  score::Scorer,
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use prettytable::Table;
+
+// Cards without anyone assigned still count towards the team's velocity
+const UNASSIGNED: &str = "Unassigned";
+
+pub struct VelocityOptions {
+  // The list cards are moved into when they're finished
+  pub done_list: String,
+  // When the first sprint started
+  pub start: DateTime<Utc>,
+  pub sprint_length: Duration,
+  // How many sprints to average over
+  pub rolling: usize,
+}
+
+impl VelocityOptions {
+  pub fn from_args(args: &VelocityArgs) -> Result<Self> {
+    let start = NaiveDate::parse_from_str(&args.since, "%F")
+      .wrap_err_with(|| format!("Unable to parse {} as a yyyy-mm-dd date", args.since))?
+      .and_hms_opt(0, 0, 0)
+      .unwrap();
+
+    if args.sprint_length < 1 {
+      return Err(eyre!("Sprints need to be at least one day long"));
+    }
+
+    Ok(VelocityOptions {
+      done_list: args.done_list.clone(),
+      start: DateTime::from_naive_utc_and_offset(start, Utc),
+      sprint_length: Duration::days(args.sprint_length),
+      rolling: args.rolling.max(1),
+    })
+  }
+}
+
+/// Completed points for each person, bucketed by sprint
+#[derive(Debug, PartialEq)]
+pub struct Velocity {
+  // The day each sprint started on
+  pub sprints: Vec<DateTime<Utc>>,
+  // Each person and the points they completed in each sprint
+  pub people: Vec<(String, Vec<i32>)>,
+}
+
+/// When a card was finished, if it's still in the done list
+fn completed_at(history: &CardHistory, done_list: &str) -> Option<DateTime<Utc>> {
+  if history.current_list != done_list {
+    return None;
+  }
+
+  history
+    .transitions
+    .iter()
+    .rev()
+    .find(|transition| transition.to == done_list)
+    .map(|transition| transition.at)
+    .or(Some(history.created))
+}
+
+/// Corrections are what the card ended up costing, so they win over estimates
+fn card_points(name: &str, scorer: &Scorer) -> i32 {
+  match scorer.score(name) {
+    Some(score) => score.correction.or(score.estimated).unwrap_or(0),
+    None => 0,
+  }
+}
+
+/// Averages each sprint with the sprints before it, up to window sprints
+pub fn rolling_average(points: &[i32], window: usize) -> Vec<f64> {
+  (0..points.len())
+    .map(|index| {
+      let start = (index + 1).saturating_sub(window);
+      let sprints = &points[start..=index];
+      sprints.iter().sum::<i32>() as f64 / sprints.len() as f64
+    })
+    .collect()
+}
+
+/// Buckets the points of every finished card into the sprint it was finished in, for everyone
+/// assigned to it. Cards finished before the first sprint, or after now, are ignored.
+pub fn velocity_by_person(
+  histories: &[CardHistory],
+  options: &VelocityOptions,
+  scorer: &Scorer,
+  now: DateTime<Utc>,
+) -> Velocity {
+  let sprint_count =
+    ((now - options.start).num_seconds() / options.sprint_length.num_seconds() + 1).max(0) as usize;
+  let sprints: Vec<DateTime<Utc>> = (0..sprint_count)
+    .map(|sprint| options.start + options.sprint_length * sprint as i32)
+    .collect();
+
+  let mut people: Vec<(String, Vec<i32>)> = Vec::new();
+  for history in histories {
+    let finished = match completed_at(history, &options.done_list) {
+      Some(finished) if finished >= options.start && finished <= now => finished,
+      _ => continue,
+    };
+    let sprint =
+      ((finished - options.start).num_seconds() / options.sprint_length.num_seconds()) as usize;
+    let points = card_points(&history.name, scorer);
+
+    let members: Vec<String> = if history.members.is_empty() {
+      vec![UNASSIGNED.to_string()]
+    } else {
+      history.members.clone()
+    };
+    for member in members {
+      let index = match people.iter().position(|(name, _)| *name == member) {
+        Some(index) => index,
+        None => {
+          people.push((member, vec![0; sprint_count]));
+          people.len() - 1
+        }
+      };
+      people[index].1[sprint] += points;
+    }
+  }
+  people.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+  Velocity { sprints, people }
+}
+
+impl Velocity {
+  pub fn print_table(&self, format: &DateFormat, rolling: usize) {
+    let mut table = Table::new();
+    table.set_titles(row!["Person", "sprint", "points", "rolling average"]);
+    for (person, points) in self.people.iter() {
+      for ((sprint, points), average) in self
+        .sprints
+        .iter()
+        .zip(points.iter())
+        .zip(rolling_average(points, rolling))
+      {
+        table.add_row(row![
+          person,
+          format.format(sprint, &format.date),
+          points,
+          format!("{:.1}", average)
+        ]);
+      }
+    }
+    table.printstd();
+  }
+
+  pub fn as_csv(&self, format: &DateFormat, dialect: &CsvDialect, rolling: usize) -> Vec<String> {
+    let mut output = vec![dialect.row(&["Person", "Sprint", "Points", "Rolling Average"])];
+    for (person, points) in self.people.iter() {
+      for ((sprint, points), average) in self
+        .sprints
+        .iter()
+        .zip(points.iter())
+        .zip(rolling_average(points, rolling))
+      {
+        output.push(dialect.row(&[
+          person.clone(),
+          dialect.date(format, sprint),
+          dialect.number(points),
+          dialect.number(format!("{:.1}", average)),
+        ]));
+      }
+    }
+
+    output
+  }
+
+  /// Draws a bar for each person's points in each sprint
+  pub fn as_ascii(&self, format: &DateFormat, rolling: usize) -> String {
+    let max = self
+      .people
+      .iter()
+      .flat_map(|(_, points)| points.iter())
+      .cloned()
+      .max()
+      .unwrap_or(0)
+      .max(1);
+    let width = 50;
+
+    let mut output = vec![];
+    for (person, points) in self.people.iter() {
+      output.push(person.clone());
+      for ((sprint, points), average) in self
+        .sprints
+        .iter()
+        .zip(points.iter())
+        .zip(rolling_average(points, rolling))
+      {
+        output.push(format!(
+          "  {} |{} {} (avg {:.1})",
+          format.format(sprint, &format.date),
+          "#".repeat((*points.max(&0) * width / max) as usize),
+          points,
+          average
+        ));
+      }
+    }
+
+    output.join("\n")
+  }
+}
+
+/// Prints how many points each person finished per sprint
+pub async fn report(config: &Config, kanban: Box<dyn Kanban>, args: &VelocityArgs) -> Result<()> {
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let options = VelocityOptions::from_args(args)?;
+
+  let histories = kanban.get_card_histories(&board.id).await?;
+  let velocity = velocity_by_person(&histories, &options, &config.scorer()?, Utc::now());
+
+  match args.output.as_str() {
+    "csv" => println!(
+      "{}",
+      velocity
+        .as_csv(&config.date_format, &config.csv, options.rolling)
+        .join("\n")
+    ),
+    "ascii" => println!(
+      "{}",
+      velocity.as_ascii(&config.date_format, options.rolling)
+    ),
+    _ => {
+      println!("{}", board.name);
+      velocity.print_table(&config.date_format, options.rolling)
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::kanban::Transition;
+  use chrono::TimeZone;
+
+  fn finished(name: &str, members: &[&str], day: u32) -> CardHistory {
+    CardHistory {
+      id: name.to_string(),
+      name: name.to_string(),
+      created: Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+      current_list: "Done".to_string(),
+      members: members.iter().map(|member| member.to_string()).collect(),
+      transitions: vec![Transition {
+        from: Some("Doing".to_string()),
+        to: "Done".to_string(),
+        at: Utc.with_ymd_and_hms(2021, 3, day, 12, 0, 0).unwrap(),
+      }],
+    }
+  }
+
+  #[test]
+  fn it_buckets_points_by_person_and_sprint() {
+    let options = VelocityOptions {
+      done_list: "Done".to_string(),
+      start: Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+      sprint_length: Duration::days(7),
+      rolling: 2,
+    };
+    let histories = vec![
+      finished("(3) first", &["alice"], 2),
+      finished("(5)[8] second", &["alice", "bob"], 9),
+      finished("(2) third", &[], 10),
+      finished("unscored", &["bob"], 3),
+    ];
+
+    let velocity = velocity_by_person(
+      &histories,
+      &options,
+      &Scorer::default(),
+      Utc.with_ymd_and_hms(2021, 3, 12, 0, 0, 0).unwrap(),
+    );
+
+    assert_eq!(velocity.sprints.len(), 2);
+    assert_eq!(
+      velocity.people,
+      vec![
+        ("Unassigned".to_string(), vec![0, 2]),
+        ("alice".to_string(), vec![3, 8]),
+        ("bob".to_string(), vec![0, 8]),
+      ]
+    );
+  }
+
+  #[test]
+  fn it_averages_over_a_window() {
+    assert_eq!(rolling_average(&[2, 4, 6, 8], 2), vec![2.0, 3.0, 5.0, 7.0]);
+  }
+}
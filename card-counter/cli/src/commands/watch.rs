@@ -0,0 +1,124 @@
+use crate::{
+  cli::WatchArgs,
+  commands::{compile_decks, prune::enforce_retention},
+  database::{config::Config, Database, Entry},
+  errors::*,
+  kanban::{Board, Kanban},
+  score::{print_decks, print_delta, Deck, Scorer, TableOptions},
+};
+
+use std::time::Duration;
+
+/// Parses an interval like 90s, 15m, 1h, or 1d. A plain number is taken as minutes.
+pub fn parse_interval(interval: &str) -> Result<Duration> {
+  let interval = interval.trim();
+  let (amount, seconds_per_unit) = match interval.chars().last() {
+    Some('s') => (&interval[..interval.len() - 1], 1),
+    Some('m') => (&interval[..interval.len() - 1], 60),
+    Some('h') => (&interval[..interval.len() - 1], 60 * 60),
+    Some('d') => (&interval[..interval.len() - 1], 24 * 60 * 60),
+    _ => (interval, 60),
+  };
+  let amount = amount.parse::<u64>().wrap_err_with(|| {
+    format!(
+      "Unable to parse interval {}, expected something like 30m or 1h",
+      interval
+    )
+  })?;
+  if amount == 0 {
+    return Err(eyre!("The interval has to be longer than 0"));
+  }
+
+  let seconds = amount
+    .checked_mul(seconds_per_unit)
+    .ok_or_else(|| eyre!("The interval {} is too long", interval))?;
+
+  Ok(Duration::from_secs(seconds))
+}
+
+/// Scores the board, prints how it changed since previous, and saves an entry for it
+async fn poll(
+  kanban: &dyn Kanban,
+  client: &dyn Database,
+  config: &Config,
+  scorer: &Scorer,
+  board: &Board,
+  previous: Option<&[Deck]>,
+  options: &TableOptions,
+) -> Result<Vec<Deck>> {
+  let (decks, cards) = compile_decks(kanban, &board.id, None, scorer).await?;
+  match previous {
+    Some(previous) => print_delta(&decks, previous, &board.name, options),
+    None => print_decks(&decks, &board.name, options),
+  }
+
+  client
+    .add_entry(Entry {
+      board_id: board.id.clone(),
+      board_name: board.name.clone(),
+      time_stamp: Entry::get_current_timestamp()?,
+      decks: decks.clone(),
+      tags: vec![],
+      cards,
+    })
+    .await?;
+  enforce_retention(client, config, &board.id).await?;
+
+  Ok(decks)
+}
+
+/// Keeps running, scoring the board and saving an entry every --interval. Errors are reported but
+/// don't stop the watch, the next poll tries again.
+pub async fn watch(
+  kanban: Box<dyn Kanban>,
+  client: Box<dyn Database>,
+  config: &Config,
+  options: &TableOptions,
+  args: &WatchArgs,
+) -> Result<()> {
+  let interval = parse_interval(&args.interval)?;
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+  let scorer = config.scorer()?;
+
+  let mut previous: Option<Vec<Deck>> = None;
+  loop {
+    match poll(
+      kanban.as_ref(),
+      client.as_ref(),
+      config,
+      &scorer,
+      &board,
+      previous.as_deref(),
+      options,
+    )
+    .await
+    {
+      Ok(decks) => previous = Some(decks),
+      Err(err) => eprintln!("Unable to update {}: {:?}", board.name, err),
+    }
+    tokio::time::sleep(interval).await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_parses_intervals() {
+    assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(15 * 60));
+    assert_eq!(parse_interval("1h").unwrap(), Duration::from_secs(60 * 60));
+    assert_eq!(
+      parse_interval("2d").unwrap(),
+      Duration::from_secs(2 * 24 * 60 * 60)
+    );
+    assert_eq!(parse_interval("30").unwrap(), Duration::from_secs(30 * 60));
+    assert!(parse_interval("0h").is_err());
+    assert!(parse_interval("soon").is_err());
+    assert!(parse_interval(&format!("{}d", u64::MAX)).is_err());
+  }
+}
@@ -0,0 +1,385 @@
+use crate::{
+  cli::WebhookArgs,
+  commands::{compile_decks, prune::enforce_retention},
+  database::{config::Config, Database, Entry},
+  errors::*,
+  kanban::Kanban,
+  score::Scorer,
+};
+
+use std::{
+  collections::HashSet,
+  convert::Infallible,
+  net::SocketAddr,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use hyper::{
+  body::HttpBody,
+  header::CONTENT_LENGTH,
+  service::{make_service_fn, service_fn},
+  Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+// Jira payloads carry the issue and its changelog, anything much bigger than this isn't from Jira
+static MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// The part of a Jira webhook payload we care about
+#[derive(Deserialize, Debug)]
+struct WebhookEvent {
+  #[serde(rename = "webhookEvent")]
+  webhook_event: String,
+  issue: Option<WebhookIssue>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WebhookIssue {
+  #[serde(default)]
+  fields: IssueFields,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct IssueFields {
+  project: Option<Project>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Project {
+  key: String,
+}
+
+/// Settings for the webhook listener
+pub struct WebhookOptions {
+  pub port: u16,
+  // Boards to snapshot whenever an issue changes
+  pub board_ids: Vec<String>,
+  // How long to wait for things to settle down before saving entries
+  pub debounce: Duration,
+  // The longest to put off saving entries after the first event, for boards that never settle
+  pub max_wait: Duration,
+  // When set, requests must include ?token=<token> in the webhook URL
+  pub token: Option<String>,
+}
+
+impl WebhookOptions {
+  pub fn from_args(args: &WebhookArgs) -> Self {
+    WebhookOptions {
+      port: args.port,
+      board_ids: args.board_id.clone(),
+      debounce: Duration::from_secs(args.debounce),
+      max_wait: Duration::from_secs(args.max_wait),
+      token: args.token.clone(),
+    }
+  }
+}
+
+/// Issue events are the only ones that can change the score of a board
+fn is_issue_event(event: &WebhookEvent) -> bool {
+  event.webhook_event.starts_with("jira:issue_")
+}
+
+/// Whether the event's issue is in one of projects. None means a board isn't tied to a project,
+/// so any issue could be on it.
+fn is_for_projects(event: &WebhookEvent, projects: &Option<HashSet<String>>) -> bool {
+  let project = event
+    .issue
+    .as_ref()
+    .and_then(|issue| issue.fields.project.as_ref());
+  match (projects, project) {
+    (None, _) => true,
+    (Some(projects), Some(project)) => projects.contains(&project.key),
+    (Some(_), None) => false,
+  }
+}
+
+fn has_token(request: &Request<Body>, token: &Option<String>) -> bool {
+  match token {
+    Some(token) => form_urlencoded::parse(request.uri().query().unwrap_or("").as_bytes())
+      .any(|(key, value)| key == "token" && value == token.as_str()),
+    None => true,
+  }
+}
+
+/// Reads the request body, giving up once it's past MAX_BODY_BYTES
+async fn read_body(request: Request<Body>) -> Option<Vec<u8>> {
+  let declared = request
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|length| length.to_str().ok())
+    .and_then(|length| length.parse::<usize>().ok());
+  if declared.unwrap_or(0) > MAX_BODY_BYTES {
+    return None;
+  }
+
+  let mut body = request.into_body();
+  let mut bytes = vec![];
+  while let Some(chunk) = body.data().await {
+    bytes.extend_from_slice(&chunk.ok()?);
+    if bytes.len() > MAX_BODY_BYTES {
+      return None;
+    }
+  }
+  Some(bytes)
+}
+
+fn respond(status: StatusCode) -> Response<Body> {
+  let mut response = Response::new(Body::empty());
+  *response.status_mut() = status;
+  response
+}
+
+async fn handle(
+  request: Request<Body>,
+  sender: UnboundedSender<()>,
+  token: Option<String>,
+  projects: Arc<Option<HashSet<String>>>,
+) -> Result<Response<Body>, Infallible> {
+  if request.method() != Method::POST {
+    return Ok(respond(StatusCode::METHOD_NOT_ALLOWED));
+  }
+
+  if !has_token(&request, &token) {
+    return Ok(respond(StatusCode::UNAUTHORIZED));
+  }
+
+  let body = match read_body(request).await {
+    Some(body) => body,
+    None => return Ok(respond(StatusCode::PAYLOAD_TOO_LARGE)),
+  };
+
+  match serde_json::from_slice::<WebhookEvent>(&body) {
+    Ok(event) => {
+      if is_issue_event(&event) && is_for_projects(&event, &projects) {
+        // The receiver only goes away when we're shutting down
+        sender.send(()).ok();
+      }
+      Ok(respond(StatusCode::ACCEPTED))
+    }
+    Err(_) => Ok(respond(StatusCode::BAD_REQUEST)),
+  }
+}
+
+/// Saves a fresh entry for every board in one batch and applies the retention policy in config,
+/// errors are reported but don't stop the listener.
+async fn snapshot(
+  kanban: &dyn Kanban,
+  database: &dyn Database,
+  config: &Config,
+  scorer: &Scorer,
+  board_ids: &[String],
+) {
+  let mut entries = vec![];
+  for board_id in board_ids {
+    let entry = async {
+      let board = kanban.get_board(board_id).await?;
+      let (decks, cards) = compile_decks(kanban, board_id, None, scorer).await?;
+      Ok::<_, eyre::Report>(Entry {
+        board_id: board.id,
+        board_name: board.name,
+        time_stamp: Entry::get_current_timestamp()?,
+        decks,
+        tags: vec![],
+        cards,
+      })
+    }
+    .await;
+
+    match entry {
+      Ok(entry) => entries.push(entry),
+      Err(err) => eprintln!("Unable to count cards for board {}: {:?}", board_id, err),
+    }
+  }
+
+  if entries.is_empty() {
+    return;
+  }
+  if let Err(err) = database.add_entries(&entries).await {
+    eprintln!("Unable to save entries: {:?}", err);
+    return;
+  }
+  for entry in entries.iter() {
+    println!("Saved entry for board {}", entry.board_id);
+    if let Err(err) = enforce_retention(database, config, &entry.board_id).await {
+      eprintln!("{:?}", err);
+    }
+  }
+}
+
+/// Called after an event arrives, returns once no new events have arrived for debounce or
+/// max_wait has passed since the first one
+async fn wait_until_settled(
+  receiver: &mut UnboundedReceiver<()>,
+  debounce: Duration,
+  max_wait: Duration,
+) {
+  let deadline = Instant::now() + max_wait;
+  loop {
+    let wait = debounce.min(deadline.saturating_duration_since(Instant::now()));
+    if wait.is_zero() {
+      return;
+    }
+    match tokio::time::timeout(wait, receiver.recv()).await {
+      Ok(Some(_)) => continue,
+      _ => return,
+    }
+  }
+}
+
+/// Waits for webhook events and saves an entry for each board once no new events have arrived
+/// for the debounce period, or at most max_wait after the first. Moving a handful of issues at
+/// once only creates one entry.
+async fn debounce_events(
+  mut receiver: UnboundedReceiver<()>,
+  kanban: &dyn Kanban,
+  database: &dyn Database,
+  config: &Config,
+  scorer: &Scorer,
+  options: &WebhookOptions,
+) {
+  while receiver.recv().await.is_some() {
+    wait_until_settled(&mut receiver, options.debounce, options.max_wait).await;
+    snapshot(kanban, database, config, scorer, &options.board_ids).await;
+  }
+}
+
+/// Starts an HTTP server that listens for Jira webhooks and saves entries for the configured
+/// boards whenever an issue is created or updated.
+pub async fn listen(
+  kanban: Box<dyn Kanban>,
+  database: Box<dyn Database>,
+  config: &Config,
+  options: WebhookOptions,
+) -> Result<()> {
+  if options.board_ids.is_empty() {
+    return Err(eyre!(
+      "No boards to snapshot. Please pass in at least one --board-id"
+    ));
+  }
+  let scorer = config.scorer()?;
+
+  // Only issues from the boards' projects can change them, unless a board isn't tied to one
+  let mut projects = Some(HashSet::new());
+  for board_id in options.board_ids.iter() {
+    let board_projects = kanban.projects(board_id).await?;
+    match (&mut projects, board_projects.is_empty()) {
+      (Some(_), true) => projects = None,
+      (Some(projects), false) => projects.extend(board_projects),
+      (None, _) => (),
+    }
+  }
+  let projects = Arc::new(projects);
+
+  let (sender, receiver) = unbounded_channel::<()>();
+  let token = options.token.clone();
+  let make_service = make_service_fn(move |_connection| {
+    let sender = sender.clone();
+    let token = token.clone();
+    let projects = projects.clone();
+    async move {
+      Ok::<_, Infallible>(service_fn(move |request| {
+        handle(request, sender.clone(), token.clone(), projects.clone())
+      }))
+    }
+  });
+
+  // Without a token anyone who can reach the port could trigger snapshots, so stay local
+  let host = match options.token {
+    Some(_) => [0, 0, 0, 0],
+    None => {
+      eprintln!(
+        "Only accepting webhooks from this machine, pass --token to accept them from others"
+      );
+      [127, 0, 0, 1]
+    }
+  };
+  let address = SocketAddr::from((host, options.port));
+  let server = Server::try_bind(&address)
+    .wrap_err_with(|| format!("Unable to listen on port {}", options.port))?
+    .serve(make_service);
+  println!("Listening for Jira webhooks on {}", address);
+
+  tokio::select! {
+    result = server => result.wrap_err_with(|| "The webhook server stopped unexpectedly"),
+    _ = debounce_events(receiver, kanban.as_ref(), database.as_ref(), config, &scorer, &options) => Ok(()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn it_stops_waiting_at_the_max_wait() {
+    let (sender, mut receiver) = unbounded_channel::<()>();
+    // Events that never settle down
+    tokio::spawn(async move {
+      while sender.send(()).is_ok() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+    });
+
+    let started = Instant::now();
+    wait_until_settled(
+      &mut receiver,
+      Duration::from_millis(100),
+      Duration::from_millis(300),
+    )
+    .await;
+    assert!(started.elapsed() < Duration::from_secs(2));
+  }
+
+  #[test]
+  fn it_only_reacts_to_issue_events() {
+    let event: WebhookEvent =
+      serde_json::from_str(r#"{"webhookEvent": "jira:issue_updated", "issue": {}}"#).unwrap();
+    assert!(is_issue_event(&event));
+
+    let event: WebhookEvent =
+      serde_json::from_str(r#"{"webhookEvent": "sprint_started"}"#).unwrap();
+    assert!(!is_issue_event(&event));
+  }
+
+  #[test]
+  fn it_only_reacts_to_issues_from_the_boards_projects() {
+    let event: WebhookEvent = serde_json::from_str(
+      r#"{"webhookEvent": "jira:issue_updated", "issue": {"fields": {"project": {"key": "CC"}}}}"#,
+    )
+    .unwrap();
+    let projects = |keys: &[&str]| Some(keys.iter().map(|key| key.to_string()).collect());
+
+    assert!(is_for_projects(&event, &projects(&["CC", "WEB"])));
+    assert!(!is_for_projects(&event, &projects(&["WEB"])));
+    assert!(is_for_projects(&event, &None));
+  }
+
+  #[test]
+  fn it_checks_the_token() {
+    let request = Request::builder()
+      .uri("/?token=secret")
+      .body(Body::empty())
+      .unwrap();
+    assert!(has_token(&request, &Some("secret".to_string())));
+    assert!(!has_token(&request, &Some("other".to_string())));
+    assert!(has_token(&request, &None));
+
+    let request = Request::builder()
+      .uri("/?board=1&token=a%2Bb%3D")
+      .body(Body::empty())
+      .unwrap();
+    assert!(has_token(&request, &Some("a+b=".to_string())));
+  }
+
+  #[tokio::test]
+  async fn it_refuses_large_bodies() {
+    let request = Request::builder()
+      .body(Body::from(vec![b' '; MAX_BODY_BYTES + 1]))
+      .unwrap();
+    assert!(read_body(request).await.is_none());
+
+    let request = Request::builder().body(Body::from("{}")).unwrap();
+    assert_eq!(read_body(request).await, Some(b"{}".to_vec()));
+  }
+}
@@ -0,0 +1,170 @@
+use crate::{
+  cli::WipArgs,
+  commands::{burndown::ListMatcher, compile_decks},
+  database::config::{Config, WipLimit},
+  errors::*,
+  kanban::{Board, Kanban},
+  score::{filter_decks, Deck, ListFilter},
+};
+
+use prettytable::Table;
+use std::fmt;
+
+/// A list holding more cards or points than its WIP limit allows
+#[derive(Debug, PartialEq)]
+pub struct WipViolation {
+  pub list_name: String,
+  // What was over the limit, cards or points
+  pub measure: &'static str,
+  pub actual: i32,
+  pub limit: i32,
+}
+
+impl fmt::Display for WipViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} has {} {}, which is over its WIP limit of {}",
+      self.list_name, self.actual, self.measure, self.limit
+    )
+  }
+}
+
+/// The limit for a list, from the first of limits whose pattern matches its name
+pub fn limit_for<'a>(
+  list_name: &str,
+  limits: &'a [(ListMatcher, WipLimit)],
+) -> Option<&'a WipLimit> {
+  limits
+    .iter()
+    .find(|(matcher, _)| matcher.matches(list_name))
+    .map(|(_, limit)| limit)
+}
+
+/// Every list in decks over its card or point limit
+pub fn wip_violations(decks: &[Deck], limits: &[(ListMatcher, WipLimit)]) -> Vec<WipViolation> {
+  decks
+    .iter()
+    .filter_map(|deck| Some((deck, limit_for(&deck.list_name, limits)?)))
+    .flat_map(|(deck, limit)| {
+      let cards = limit
+        .cards
+        .filter(|cards| deck.size > *cards)
+        .map(|cards| WipViolation {
+          list_name: deck.list_name.clone(),
+          measure: "cards",
+          actual: deck.size as i32,
+          limit: cards as i32,
+        });
+      let points = limit
+        .points
+        .filter(|points| deck.score > *points)
+        .map(|points| WipViolation {
+          list_name: deck.list_name.clone(),
+          measure: "points",
+          actual: deck.score,
+          limit: points,
+        });
+      cards.into_iter().chain(points)
+    })
+    .collect()
+}
+
+pub fn print_wip(board_name: &str, decks: &[Deck], limits: &[(ListMatcher, WipLimit)]) {
+  let limit_cell = |limit: Option<i32>| limit.map(|limit| limit.to_string()).unwrap_or_default();
+
+  println!("{}", board_name);
+  let mut table = Table::new();
+  table.set_titles(row!["List", "cards", "card limit", "score", "point limit"]);
+  for deck in decks {
+    if let Some(limit) = limit_for(&deck.list_name, limits) {
+      table.add_row(row![
+        deck.list_name,
+        deck.size,
+        limit_cell(limit.cards.map(|cards| cards as i32)),
+        deck.score,
+        limit_cell(limit.points)
+      ]);
+    }
+  }
+  table.printstd();
+}
+
+/// Checks the board's lists against the WIP limits in config, printing the lists that have a limit
+/// and returning the ones over it
+pub async fn wip(
+  kanban: Box<dyn Kanban>,
+  config: &Config,
+  args: &WipArgs,
+) -> Result<Vec<WipViolation>> {
+  let limits = config.wip_limits()?;
+  if limits.is_empty() {
+    return Err(eyre!(
+      "No WIP limits have been set. Add some under wip_limits in your config"
+    ));
+  }
+  let board: Board = match args.board_id.as_deref() {
+    Some(id) => kanban.get_board(id).await?,
+    None => kanban.select_board().await?,
+  };
+
+  let (decks, _) = compile_decks(kanban.as_ref(), &board.id, None, &config.scorer()?).await?;
+  let decks = filter_decks(&decks, &ListFilter::from_args(&args.filter)?);
+
+  print_wip(&board.name, &decks, &limits);
+  let violations = wip_violations(&decks, &limits);
+  for violation in violations.iter() {
+    eprintln!("{}", violation);
+  }
+
+  Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_flags_lists_over_their_limits() {
+    let deck = |list_name: &str, size: usize, score: i32| Deck {
+      list_name: list_name.to_string(),
+      size,
+      score,
+      unscored: 0,
+      estimated: score,
+    };
+    let decks = vec![
+      deck("In Progress", 4, 10),
+      deck("Review", 2, 13),
+      deck("Done", 9, 40),
+    ];
+    let limits = vec![
+      (
+        ListMatcher::new(&["In Progress".to_string()]).unwrap(),
+        WipLimit {
+          cards: Some(3),
+          points: Some(10),
+        },
+      ),
+      (
+        ListMatcher::new(&["^Rev".to_string()]).unwrap(),
+        WipLimit {
+          cards: None,
+          points: Some(8),
+        },
+      ),
+    ];
+
+    let violations = wip_violations(&decks, &limits);
+    assert_eq!(
+      violations
+        .iter()
+        .map(|violation| violation.to_string())
+        .collect::<Vec<_>>(),
+      vec![
+        "In Progress has 4 cards, which is over its WIP limit of 3",
+        "Review has 13 points, which is over its WIP limit of 8",
+      ]
+    );
+  }
+}
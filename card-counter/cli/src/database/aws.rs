@@ -7,8 +7,10 @@ use rusoto_core::Region;
 use rusoto_dynamodb::{
   AttributeDefinition,
   AttributeValue,
+  BatchWriteItemInput,
   // Structs important for create_table
   CreateTableInput,
+  DeleteItemInput,
   DescribeTableError,
   DescribeTableInput,
   DynamoDb,
@@ -17,14 +19,24 @@ use rusoto_dynamodb::{
   KeySchemaElement,
   ProvisionedThroughput,
   PutItemInput,
+  PutRequest,
   QueryInput,
+  WriteRequest,
 };
 
-use super::{config::Config, DateRange};
+use super::{
+  config::{Config, DynamoDbBilling},
+  DateRange,
+};
 
 use dialoguer::Confirm;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+// DynamoDB accepts at most 25 items in a single BatchWriteItem request
+const BATCH_WRITE_LIMIT: usize = 25;
+// How many times items DynamoDB didn't get to are sent again, waiting twice as long each time
+const BATCH_WRITE_RETRIES: u32 = 5;
 
 /////////////////////////
 // Helper Functions
@@ -32,7 +44,17 @@ use std::collections::HashMap;
 // Functions for interacting with and dealing with
 // DynamoDB
 
-async fn create_table(client: &DynamoDbClient) -> Result<()> {
+async fn create_table(client: &DynamoDbClient, billing: &DynamoDbBilling) -> Result<()> {
+  let (billing_mode, provisioned_throughput) = match billing {
+    DynamoDbBilling::OnDemand => (Some("PAY_PER_REQUEST".to_string()), None),
+    DynamoDbBilling::Provisioned { read, write } => (
+      Some("PROVISIONED".to_string()),
+      Some(ProvisionedThroughput {
+        read_capacity_units: *read,
+        write_capacity_units: *write,
+      }),
+    ),
+  };
   let table_params = CreateTableInput {
     table_name: "card-counter".to_string(),
     attribute_definitions: [
@@ -46,7 +68,7 @@ async fn create_table(client: &DynamoDbClient) -> Result<()> {
       },
     ]
     .to_vec(),
-    billing_mode: None,
+    billing_mode,
     global_secondary_indexes: None,
     local_secondary_indexes: None,
     key_schema: [
@@ -60,10 +82,7 @@ async fn create_table(client: &DynamoDbClient) -> Result<()> {
       },
     ]
     .to_vec(),
-    provisioned_throughput: Some(ProvisionedThroughput {
-      read_capacity_units: 1,
-      write_capacity_units: 1,
-    }),
+    provisioned_throughput,
     sse_specification: None,
     stream_specification: None,
     tags: None,
@@ -117,26 +136,85 @@ impl Database for Aws {
     Ok(())
   }
 
+  /// Adds entries into DynamoDB 25 at a time using BatchWriteItem. Items DynamoDB couldn't process, usually because the table is being throttled, are sent again after a short wait.
+  async fn add_entries(&self, entries: &[Entry]) -> Result<()> {
+    for chunk in entries.chunks(BATCH_WRITE_LIMIT) {
+      let mut requests = chunk
+        .iter()
+        .map(|entry| -> Result<WriteRequest> {
+          Ok(WriteRequest {
+            put_request: Some(PutRequest {
+              item: serde_dynamodb::to_hashmap(entry)
+                .wrap_err_with(|| "Unable to parse database entry")?,
+            }),
+            ..Default::default()
+          })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+      let mut attempt = 0;
+      while !requests.is_empty() {
+        if attempt > BATCH_WRITE_RETRIES {
+          return Err(eyre!(
+            "DynamoDB was unable to save {} entries, the table may be throttled.",
+            requests.len()
+          ));
+        }
+        if attempt > 0 {
+          tokio::time::sleep(Duration::from_millis(100 * 2u64.pow(attempt))).await;
+        }
+
+        let mut request_items = HashMap::new();
+        request_items.insert("card-counter".to_string(), requests);
+        let output = self
+          .client
+          .batch_write_item(BatchWriteItemInput {
+            request_items,
+            ..Default::default()
+          })
+          .await
+          .wrap_err_with(|| "Unable to add entries to DynamoDB.")?;
+
+        requests = output
+          .unprocessed_items
+          .and_then(|mut items| items.remove("card-counter"))
+          .unwrap_or_default();
+        attempt += 1;
+      }
+    }
+
+    Ok(())
+  }
+
   /// Retrieves all entries for the `card-counter` table. It will return an error if there was a problem talking to DynamoDB.
   async fn all_entries(&self) -> Result<Option<Entries>> {
-    let scan = self
-      .client
-      .scan(rusoto_dynamodb::ScanInput {
-        table_name: "card-counter".to_string(),
-        ..Default::default()
-      })
-      .await
-      .wrap_err_with(|| "Error getting all decks from DynamoDb")?;
+    let mut entries: Entries = vec![];
+    let mut exclusive_start_key = None;
+
+    // Each scan returns at most 1MB of items, LastEvaluatedKey marks where the next page starts
+    loop {
+      let scan = self
+        .client
+        .scan(rusoto_dynamodb::ScanInput {
+          table_name: "card-counter".to_string(),
+          exclusive_start_key,
+          ..Default::default()
+        })
+        .await
+        .wrap_err_with(|| "Error getting all decks from DynamoDb")?;
 
-    match scan.items {
-      Some(entries) => Ok(Some(
-        entries
+      entries.extend(
+        scan
+          .items
+          .unwrap_or_default()
           .iter()
           .map(to_entry)
-          .filter_map(Result::ok)
-          .collect(),
-      )),
-      None => Ok(None),
+          .filter_map(Result::ok),
+      );
+      exclusive_start_key = match scan.last_evaluated_key {
+        Some(key) if !key.is_empty() => Some(key),
+        _ => return Ok(Some(entries)),
+      };
     }
   }
 
@@ -215,23 +293,70 @@ impl Database for Aws {
       );
     }
 
-    let query = self
-      .client
-      .query(QueryInput {
-        consistent_read: Some(true),
-        key_condition_expression: Some(query_string),
-        expression_attribute_values: Some(query_values),
-        table_name: "card-counter".to_string(),
-        ..Default::default()
-      })
-      .await
-      .wrap_err_with(|| "Error while talking to dynamodb.")?;
+    let mut entries: Entries = vec![];
+    let mut exclusive_start_key = None;
 
-    let entries: Entries = match query.items {
-      Some(items) => items.iter().map(to_entry).filter_map(Result::ok).collect(),
-      None => return Ok(None),
-    };
-    Ok(Some(entries))
+    // Each query returns at most 1MB of items, LastEvaluatedKey marks where the next page starts
+    loop {
+      let query = self
+        .client
+        .query(QueryInput {
+          consistent_read: Some(true),
+          key_condition_expression: Some(query_string.clone()),
+          expression_attribute_values: Some(query_values.clone()),
+          table_name: "card-counter".to_string(),
+          exclusive_start_key,
+          ..Default::default()
+        })
+        .await
+        .wrap_err_with(|| "Error while talking to dynamodb.")?;
+
+      entries.extend(
+        query
+          .items
+          .unwrap_or_default()
+          .iter()
+          .map(to_entry)
+          .filter_map(Result::ok),
+      );
+      exclusive_start_key = match query.last_evaluated_key {
+        Some(key) if !key.is_empty() => Some(key),
+        _ => return Ok(Some(entries)),
+      };
+    }
+  }
+
+  /// Deletes each entry one at a time, DynamoDB has no way to delete a range of items
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    for time_stamp in time_stamps {
+      let mut key: HashMap<String, AttributeValue> = HashMap::new();
+      key.insert(
+        "board_id".to_string(),
+        AttributeValue {
+          s: Some(board_name.clone()),
+          ..Default::default()
+        },
+      );
+      key.insert(
+        "time_stamp".to_string(),
+        AttributeValue {
+          n: Some(time_stamp.to_string()),
+          ..Default::default()
+        },
+      );
+
+      self
+        .client
+        .delete_item(DeleteItemInput {
+          table_name: "card-counter".to_string(),
+          key,
+          ..Default::default()
+        })
+        .await
+        .wrap_err_with(|| "Unable to delete entry from DynamoDB.")?;
+    }
+
+    Ok(())
   }
 
   fn what_type(&self) -> String {
@@ -243,26 +368,30 @@ impl Aws {
   /// Init tries to initiate a connection to DynamoDB.
   /// It will look to see the `card-counter` table exists and if it doesn't find one, it will prompt the user if it wants to create a new table in DynamoDB.
   /// It will error if it can't talk to DynamoDB or if it can't find the `card-counter` table and the user declines to create one.
-  pub async fn init(_config: &Config) -> Result<Self> {
+  pub async fn init(config: &Config) -> Result<Self> {
     // Boiler plate create pertinent AWS info
-
-    let region = Region::default();
+    let billing = config
+      .database_configuration
+      .clone()
+      .unwrap_or_default()
+      .dynamodb_billing()?;
 
     let aws = Aws {
-      client: DynamoDbClient::new(region),
+      client: DynamoDbClient::new(Region::default()),
     };
     // Maybe create table
     let table_exists = does_table_exist(&aws.client, "card-counter".to_string()).await?;
 
     if !table_exists {
-      match Confirm::new()
+      // Answer before awaiting, the prompt can't be held across an await and stay Send
+      let create = Confirm::new()
         .with_prompt(
           "Unable to find \"card-counter\" table in DynamoDB. Would you like to create a table?",
         )
         .interact()
-        .wrap_err_with(|| "There was a problem registering your response.")?
-      {
-        true => create_table(&aws.client).await?,
+        .wrap_err_with(|| "There was a problem registering your response.")?;
+      match create {
+        true => create_table(&aws.client, &billing).await?,
         false => {
           eprintln! {"Unable to update or query table."}
           ::std::process::exit(1);
@@ -272,4 +401,93 @@ impl Aws {
 
     Ok(aws)
   }
+
+  /// Connects to DynamoDB without creating anything, erroring if the `card-counter` table is
+  /// missing.
+  pub async fn connect() -> Result<Self> {
+    Aws::connect_with(DynamoDbClient::new(Region::default())).await
+  }
+
+  async fn connect_with(client: DynamoDbClient) -> Result<Self> {
+    let aws = Aws { client };
+    match does_table_exist(&aws.client, "card-counter".to_string()).await? {
+      true => Ok(aws),
+      false => Err(eyre!("Unable to find \"card-counter\" table in DynamoDB")),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rusoto_mock::{
+    MockCredentialsProvider, MockRequestDispatcher, MultipleMockRequestDispatcher,
+  };
+
+  fn page(time_stamp: i64, last_page: bool) -> String {
+    let item = format!(
+      r#"{{"board_id": {{"S": "board"}}, "time_stamp": {{"N": "{}"}}, "decks": {{"L": []}}}}"#,
+      time_stamp
+    );
+    match last_page {
+      true => format!(r#"{{"Count": 1, "Items": [{}]}}"#, item),
+      false => format!(
+        r#"{{"Count": 1, "Items": [{}], "LastEvaluatedKey": {{"board_id": {{"S": "board"}}, "time_stamp": {{"N": "{}"}}}}}}"#,
+        item, time_stamp
+      ),
+    }
+  }
+
+  #[tokio::test]
+  async fn it_errors_connecting_without_a_table() {
+    let dispatcher = MockRequestDispatcher::with_status(400).with_body(
+      r#"{"__type": "com.amazonaws.dynamodb.v20120810#ResourceNotFoundException", "message": "Requested resource not found"}"#,
+    );
+    let client = DynamoDbClient::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+
+    assert!(Aws::connect_with(client).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn it_reads_every_page_of_a_query() {
+    let dispatcher = MultipleMockRequestDispatcher::new(vec![
+      MockRequestDispatcher::with_status(200).with_body(&page(1, false)),
+      MockRequestDispatcher::with_status(200).with_body(&page(2, false)),
+      MockRequestDispatcher::with_status(200).with_body(&page(3, true)),
+    ]);
+    let aws = Aws {
+      client: DynamoDbClient::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1),
+    };
+
+    let entries = aws
+      .query_entries("board".to_string(), None)
+      .await
+      .unwrap()
+      .unwrap();
+    let time_stamps: Vec<i64> = entries.iter().map(|entry| entry.time_stamp).collect();
+    assert_eq!(time_stamps, vec![1, 2, 3]);
+  }
+
+  #[tokio::test]
+  async fn it_resends_unprocessed_items() {
+    let unprocessed = r#"{"UnprocessedItems": {"card-counter": [{"PutRequest": {"Item": {"board_id": {"S": "board"}, "time_stamp": {"N": "1"}, "decks": {"L": []}}}}]}}"#;
+    // 26 entries take two batches, and the first is sent twice
+    let dispatcher = MultipleMockRequestDispatcher::new(vec![
+      MockRequestDispatcher::with_status(200).with_body(unprocessed),
+      MockRequestDispatcher::with_status(200).with_body("{}"),
+      MockRequestDispatcher::with_status(200).with_body("{}"),
+    ]);
+    let aws = Aws {
+      client: DynamoDbClient::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1),
+    };
+
+    let entries: Entries = (0..26)
+      .map(|time_stamp| Entry {
+        board_id: "board".to_string(),
+        time_stamp,
+        ..Entry::default()
+      })
+      .collect();
+    assert!(aws.add_entries(&entries).await.is_ok());
+  }
 }
@@ -1,9 +1,10 @@
 use crate::{
-  database::{config::Config, Database, Entries, Entry},
+  database::{config::Config, CardSnapshot, Database, DateRange, Entries, Entry},
   errors::*,
   score::Deck,
 };
 use azure_cosmos::prelude::{collection::*, *};
+use futures::{stream, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, env};
 
@@ -17,14 +18,27 @@ pub struct Azure {
   client: CosmosClient,
   database_name: String,
   collection_name: String,
+  partition_key: String,
+  throughput: Option<u64>,
 }
 
+static DEFAULT_PARTITION_KEY: &str = "/board_id";
+// How many documents are written to CosmosDB at the same time when saving a batch of entries, kept
+// low so a batch doesn't use up the collection's throughput
+const BULK_CONCURRENCY: usize = 10;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct CosmosEntry {
   id: String,
   board_id: String,
+  #[serde(default)]
+  board_name: String,
   timestamp: i64,
   decks: Vec<Deck>,
+  #[serde(default)]
+  tags: Vec<String>,
+  #[serde(default)]
+  cards: Vec<CardSnapshot>,
 }
 
 impl PartialEq for CosmosEntry {
@@ -40,8 +54,11 @@ impl From<Entry> for CosmosEntry {
     CosmosEntry {
       id: format!("{}-{}", entry.board_id, entry.time_stamp),
       board_id: entry.board_id,
+      board_name: entry.board_name,
       timestamp: entry.time_stamp,
       decks: entry.decks,
+      tags: entry.tags,
+      cards: entry.cards,
     }
   }
 }
@@ -51,7 +68,24 @@ impl From<CosmosEntry> for Entry {
     Entry {
       time_stamp: entry.timestamp,
       board_id: entry.board_id,
+      board_name: entry.board_name,
       decks: entry.decks,
+      tags: entry.tags,
+      cards: entry.cards,
+    }
+  }
+}
+
+impl CosmosEntry {
+  /// Finds the value a document is partitioned on. Only string fields can be used as a key.
+  fn partition_key_value(&self, partition_key: &str) -> Result<&str> {
+    match partition_key {
+      "/board_id" => Ok(&self.board_id),
+      "/id" => Ok(&self.id),
+      _ => Err(eyre!(
+        "Unable to partition entries on {}. Expected /board_id or /id",
+        partition_key
+      )),
     }
   }
 }
@@ -61,27 +95,50 @@ impl From<&CosmosEntry> for Entry {
     Entry {
       time_stamp: entry.timestamp,
       board_id: entry.board_id.clone(),
+      board_name: entry.board_name.clone(),
       decks: entry.decks.clone(),
+      tags: entry.tags.clone(),
+      cards: entry.cards.clone(),
     }
   }
 }
 
+/// Builds the query for a board's entries. Values are passed as parameters, rather than formatted
+/// into the SQL, so a board id with quotes in it can't change what the query does.
+fn entries_query(board_name: &str, date_range: Option<&DateRange>) -> Query<'static> {
+  let mut params = vec![Param::new("@board_id", board_name)];
+  let query = match date_range {
+    Some(range) => {
+      params.push(Param::new("@start", range.start));
+      params.push(Param::new("@end", range.end));
+      "SELECT * FROM c WHERE c.board_id = @board_id AND (c.timestamp BETWEEN @start AND @end) ORDER BY c.timestamp DESC"
+    }
+    None => "SELECT * FROM c WHERE c.board_id = @board_id ORDER BY c.timestamp DESC",
+  };
+  Query::with_params(query, params)
+}
+
+fn entry_query(board_name: &str, time_stamp: i64) -> Query<'static> {
+  Query::with_params(
+    "SELECT * FROM c WHERE c.board_id = @board_id AND c.timestamp = @timestamp ORDER BY c._ts DESC OFFSET 0 LIMIT 1",
+    vec![
+      Param::new("@board_id", board_name),
+      Param::new("@timestamp", time_stamp),
+    ],
+  )
+}
+
 #[async_trait]
 impl Database for Azure {
   async fn add_entry(&self, entry: Entry) -> Result<()> {
-    let document = Document::new(CosmosEntry::from(entry));
+    self.upsert_entry(entry).await
+  }
 
-    self
-      .client
-      .clone()
-      .into_database_client(self.database_name.clone())
-      .into_collection_client(self.collection_name.clone())
-      .create_document()
-      .execute_with_partition_key(&document, &document.document.board_id)
+  /// CosmosDB has no bulk insert over its REST API, so entries are upserted several at a time
+  async fn add_entries(&self, entries: &[Entry]) -> Result<()> {
+    stream::iter(entries.iter().cloned().map(Ok))
+      .try_for_each_concurrent(BULK_CONCURRENCY, |entry| self.upsert_entry(entry))
       .await
-      .wrap_err_with(|| "Unable to add entry")?;
-
-    Ok(())
   }
 
   async fn all_entries(&self) -> Result<Option<Entries>> {
@@ -111,13 +168,13 @@ impl Database for Azure {
       .into_database_client(self.database_name.clone())
       .into_collection_client(self.collection_name.clone())
       .query_documents()
-      // .consistency_level(ConsistencyLevel::Bounded)
-      .execute::<CosmosEntry, _>(&format!(
-        "SELECT * FROM c WHERE c.board_id = \"{}\" AND c.timestamp = {} ORDER BY c._ts DESC OFFSET 0 LIMIT 1",
-        board_name, time_stamp
-      ))
+      // The partition key is configurable, so the entry could be in any partition
+      .query_cross_partition(true)
+      .execute::<CosmosEntry, _>(entry_query(&board_name, time_stamp))
       .await
-      .wrap_err_with(||"Unable to get documents from CosmoDB")?.into_raw().results;
+      .wrap_err_with(|| "Unable to get documents from CosmoDB")?
+      .into_raw()
+      .results;
 
     if let Some(cosmo_entry) = results.first() {
       Ok(Some(Entry::from(cosmo_entry.to_owned())))
@@ -129,16 +186,8 @@ impl Database for Azure {
   async fn query_entries(
     &self,
     board_name: String,
-    date_range: Option<super::DateRange>,
+    date_range: Option<DateRange>,
   ) -> Result<Option<Entries>> {
-    let query = match date_range {
-      Some(range) => format!(
-        "SELECT * FROM c WHERE c.board_id = \"{}\" AND (c.timestamp BETWEEN {} AND {}) ORDER BY c.timestamp DESC",
-        board_name, range.start, range.end),
-      None => format!(
-        "SELECT * FROM c WHERE c.board_id = \"{}\" ORDER BY c.timestamp DESC", board_name)
-    };
-
     let results = self
       .client
       .clone()
@@ -147,7 +196,7 @@ impl Database for Azure {
       .query_documents()
       .query_cross_partition(true)
       .parallelize_cross_partition_query(true)
-      .execute::<CosmosEntry, _>(&query)
+      .execute::<CosmosEntry, _>(entries_query(&board_name, date_range.as_ref()))
       .await
       .wrap_err_with(|| "Unable to get documents from CosmoDB")?
       .into_raw()
@@ -156,14 +205,42 @@ impl Database for Azure {
     Ok(Some(results.iter().map(Entry::from).collect()))
   }
 
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    let collection = self
+      .client
+      .clone()
+      .into_database_client(self.database_name.clone())
+      .into_collection_client(self.collection_name.clone());
+
+    for time_stamp in time_stamps {
+      let entry = CosmosEntry::from(Entry {
+        board_id: board_name.clone(),
+        time_stamp,
+        ..Entry::default()
+      });
+      let partition_key = entry.partition_key_value(&self.partition_key)?.to_string();
+
+      collection
+        .clone()
+        .into_document_client(entry.id.clone(), &partition_key)
+        .wrap_err_with(|| "Unable to build partition key")?
+        .delete_document()
+        .execute()
+        .await
+        .wrap_err_with(|| format!("Unable to delete document {}", entry.id))?;
+    }
+
+    Ok(())
+  }
+
   fn what_type(&self) -> String {
     "Azure".into()
   }
 }
 impl Azure {
-  // I _hate_ this method. But ErrorChain is not working so it's hard
-  // to have things flow nicely right now.
-  pub async fn init(config: &Config) -> Result<Self> {
+  /// Builds a client for the database and container named in config, without talking to
+  /// CosmosDB yet
+  fn from_config(config: &Config) -> Result<Self> {
     let auth = match auth_from_env() {
       Some(auth) => auth,
       None => return Err(eyre!("Unable to find Azure Master Key")),
@@ -188,14 +265,48 @@ impl Azure {
       collection_name: database_details.container_name.clone().ok_or_else(|| {
         eyre!("No container name set. Please run 'card-counter config' to set the container name")
       })?,
+      partition_key: database_details
+        .partition_key
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PARTITION_KEY.to_string()),
+      throughput: database_details.throughput,
     };
+    // Catch a bad partition key before we create anything with it
+    CosmosEntry::from(Entry::default()).partition_key_value(&azure.partition_key)?;
+    Ok(azure)
+  }
+
+  /// Connects to CosmosDB without creating anything, erroring if the database or container is
+  /// missing.
+  pub async fn connect(config: &Config) -> Result<Self> {
+    let azure = Azure::from_config(config)?;
+    if !does_database_exist(&azure).await? {
+      return Err(eyre!(
+        "Unable to find \"{}\" database in CosmosDB",
+        azure.database_name
+      ));
+    }
+    if !does_collection_exist(&azure).await? {
+      return Err(eyre!(
+        "Unable to find \"{}\" collection in CosmosDB",
+        azure.collection_name
+      ));
+    }
+    Ok(azure)
+  }
+
+  // I _hate_ this method. But ErrorChain is not working so it's hard
+  // to have things flow nicely right now.
+  pub async fn init(config: &Config) -> Result<Self> {
+    let azure = Azure::from_config(config)?;
 
     let db_exist = does_database_exist(&azure).await?;
     if !db_exist {
       match dialoguer::Confirm::new()
-        .with_prompt(
-          "Unable to find \"card-counter\" database in CosmosDB. Would you like to create a database?",
-        )
+        .with_prompt(format!(
+          "Unable to find \"{}\" database in CosmosDB. Would you like to create a database?",
+          azure.database_name
+        ))
         .interact()
         .wrap_err_with(|| "There was a problem registering your response.")?
       {
@@ -207,12 +318,13 @@ impl Azure {
       }
     }
 
-    let collection_exist = does_collection_exist(&azure, "card-counter").await?;
+    let collection_exist = does_collection_exist(&azure).await?;
     if !collection_exist {
       match dialoguer::Confirm::new()
-        .with_prompt(
-          "Unable to find \"card-counter\" collection in CosmosDB. Would you like to create collection?",
-        )
+        .with_prompt(format!(
+          "Unable to find \"{}\" collection in CosmosDB. Would you like to create collection?",
+          azure.collection_name
+        ))
         .interact()
         .wrap_err_with(|| "There was a problem registering your response.")?
       {
@@ -226,6 +338,28 @@ impl Azure {
     Ok(azure)
   }
 
+  async fn upsert_entry(&self, entry: Entry) -> Result<()> {
+    let document = Document::new(CosmosEntry::from(entry));
+    let partition_key = document
+      .document
+      .partition_key_value(&self.partition_key)?
+      .to_string();
+
+    self
+      .client
+      .clone()
+      .into_database_client(self.database_name.clone())
+      .into_collection_client(self.collection_name.clone())
+      .create_document()
+      // Rescoring saves over existing entries
+      .is_upsert(true)
+      .execute_with_partition_key(&document, &partition_key)
+      .await
+      .wrap_err_with(|| "Unable to add entry")?;
+
+    Ok(())
+  }
+
   async fn create_collection(&self) -> Result<()> {
     let indexes = IncludedPathIndex {
       kind: KeyKind::Hash,
@@ -245,14 +379,20 @@ impl Azure {
       excluded_paths: vec![],
     };
 
+    let mut options = CreateCollectionOptions::new(self.partition_key.as_str()).indexing_policy(ip);
+    // Serverless accounts reject any attempt to provision throughput
+    if let Some(throughput) = self.throughput {
+      options = options.offer(Offer::Throughput(throughput));
+    }
+
     self
       .client
       .clone()
-      .into_database_client("card-counter")
+      .into_database_client(self.database_name.clone())
       .create_collection(
         azure_core::Context::new(),
-        "card-counter",
-        CreateCollectionOptions::new("/board_id").indexing_policy(ip),
+        self.collection_name.as_str(),
+        options,
       )
       .await
       .wrap_err_with(|| "Unable to create CosmosDB collection.")?;
@@ -261,12 +401,17 @@ impl Azure {
   }
 
   async fn create_database(&self) -> Result<()> {
+    let mut options = CreateDatabaseOptions::new();
+    if let Some(throughput) = self.throughput {
+      options = options.offer(Offer::Throughput(throughput));
+    }
+
     self
       .client
       .create_database(
         azure_core::Context::new(),
-        "card-counter",
-        CreateDatabaseOptions::new(),
+        self.database_name.as_str(),
+        options,
       )
       .await
       .wrap_err_with(|| "Unable to create Cosmos DB")?;
@@ -283,39 +428,29 @@ async fn does_database_exist(azure: &Azure) -> Result<bool> {
     .wrap_err_with(|| "Unable to connect to Azure CosmosDB")?
     .databases;
 
-  match databases.iter().find_map(|database| {
-    if database.id == azure.database_name {
-      Some(database)
-    } else {
-      None
-    }
-  }) {
-    Some(_db) => Ok(true),
-    None => Ok(false),
-  }
+  Ok(
+    databases
+      .iter()
+      .any(|database| database.id == azure.database_name),
+  )
 }
 
-async fn does_collection_exist(azure: &Azure, name: &str) -> Result<bool> {
+async fn does_collection_exist(azure: &Azure) -> Result<bool> {
   let collections = azure
     .client
     .clone()
-    .into_database_client("card-counter")
+    .into_database_client(azure.database_name.clone())
     .list_collections()
     .execute()
     .await
     .wrap_err_with(|| "There was an error talking to CosmosDB")?
     .collections;
 
-  match collections.iter().find_map(|collecation| {
-    if collecation.id == name {
-      Some(collecation)
-    } else {
-      None
-    }
-  }) {
-    Some(_collection) => Ok(true),
-    None => Ok(false),
-  }
+  Ok(
+    collections
+      .iter()
+      .any(|collection| collection.id == azure.collection_name),
+  )
 }
 
 fn auth_from_env() -> Option<HashMap<String, String>> {
@@ -351,24 +486,66 @@ fn auth_from_env() -> Option<HashMap<String, String>> {
 pub mod test {
 
   #[allow(unused_imports)]
-  use super::{CosmosEntry, Entry};
+  use super::{entries_query, entry_query, CosmosEntry, DateRange, Entry, DEFAULT_PARTITION_KEY};
 
   #[test]
   fn entry_and_cosmos_entry_can_be_equal() {
     let entry = Entry {
       board_id: "1".to_string(),
+      board_name: String::new(),
       time_stamp: 1,
       decks: vec![],
+      tags: vec![],
+      cards: vec![],
     };
 
     let cosmos = CosmosEntry {
       id: "1".to_string(),
       board_id: "1".to_string(),
+      board_name: String::new(),
       timestamp: 1,
       decks: vec![],
+      tags: vec![],
+      cards: vec![],
     };
 
     assert_eq!(&entry, &cosmos.clone().into());
     assert_eq!(&cosmos, &entry.into());
   }
+  #[test]
+  fn cosmos_entries_are_partitioned_on_string_fields() {
+    let cosmos: CosmosEntry = Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp: 1,
+      decks: vec![],
+      tags: vec![],
+      cards: vec![],
+    }
+    .into();
+
+    assert_eq!(
+      cosmos.partition_key_value(DEFAULT_PARTITION_KEY).unwrap(),
+      "board"
+    );
+    assert_eq!(cosmos.partition_key_value("/id").unwrap(), "board-1");
+    assert!(cosmos.partition_key_value("/timestamp").is_err());
+  }
+
+  #[test]
+  fn board_ids_are_passed_as_query_parameters() {
+    let board_id = r#"board" OR c.board_id != "board"#;
+    let range = DateRange { start: 1, end: 2 };
+
+    for query in [
+      entries_query(board_id, None),
+      entries_query(board_id, Some(&range)),
+      entry_query(board_id, 1),
+    ] {
+      let query = serde_json::to_value(query).unwrap();
+      assert!(!query["query"].as_str().unwrap().contains(board_id));
+      assert_eq!(query["parameters"][0]["name"], "@board_id");
+      assert_eq!(query["parameters"][0]["value"], board_id);
+    }
+  }
 }
@@ -0,0 +1,504 @@
+use crate::{
+  database::{
+    config::{AzureBlobConfig, Config},
+    Database, DateRange, Entries, Entry,
+  },
+  errors::*,
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{collections::HashMap, env};
+
+// The Blob service REST API version all requests are signed against
+const API_VERSION: &str = "2020-04-08";
+
+enum Credentials {
+  SharedKey(Vec<u8>),
+  SharedAccessSignature(String),
+  Bearer(String),
+}
+
+/// Stores each entry as a JSON blob named <board_id>/<timestamp>.json
+pub struct AzureBlob {
+  client: reqwest::Client,
+  account: String,
+  endpoint: String,
+  container: String,
+  credentials: Credentials,
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+  access_token: String,
+}
+
+/// Splits a connection string, "AccountName=name;AccountKey=key;...", into its parts
+fn parse_connection_string(connection_string: &str) -> HashMap<String, String> {
+  connection_string
+    .split(';')
+    .filter_map(|part| {
+      let mut pair = part.splitn(2, '=');
+      Some((pair.next()?.trim().to_string(), pair.next()?.to_string()))
+    })
+    .collect()
+}
+
+/// Pulls the value of every <tag> out of a blob listing, which is simple enough that we don't
+/// need a full XML parser.
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+  let open = format!("<{}>", tag);
+  let close = format!("</{}>", tag);
+  xml
+    .split(&open)
+    .skip(1)
+    .filter_map(|rest| rest.split(&close).next())
+    .map(String::from)
+    .collect()
+}
+
+/// Blob names are <board_id>/<timestamp>.json
+fn time_stamp_from_name(name: &str) -> Option<i64> {
+  name
+    .rsplit('/')
+    .next()?
+    .strip_suffix(".json")?
+    .parse::<i64>()
+    .ok()
+}
+
+fn blob_name(board_id: &str, time_stamp: i64) -> String {
+  format!("{}/{}.json", board_id, time_stamp)
+}
+
+/// Builds the string that is signed for Shared Key authorization, see
+/// https://docs.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key
+fn string_to_sign(
+  method: &Method,
+  account: &str,
+  path: &str,
+  query: &[(&str, String)],
+  headers: &[(&str, String)],
+  content_length: usize,
+  content_type: &str,
+) -> String {
+  let mut canonical_headers: Vec<(String, &String)> = headers
+    .iter()
+    .filter(|(name, _)| name.starts_with("x-ms-"))
+    .map(|(name, value)| (name.to_lowercase(), value))
+    .collect();
+  canonical_headers.sort();
+
+  let mut canonical_query: Vec<(String, &String)> = query
+    .iter()
+    .map(|(name, value)| (name.to_lowercase(), value))
+    .collect();
+  canonical_query.sort();
+
+  let content_length = if content_length == 0 {
+    String::new()
+  } else {
+    content_length.to_string()
+  };
+
+  format!(
+    "{}\n\n\n{}\n\n{}\n\n\n\n\n\n\n{}/{}{}{}",
+    method,
+    content_length,
+    content_type,
+    canonical_headers
+      .iter()
+      .map(|(name, value)| format!("{}:{}\n", name, value))
+      .collect::<String>(),
+    account,
+    path,
+    canonical_query
+      .iter()
+      .map(|(name, value)| format!("\n{}:{}", name, value))
+      .collect::<String>()
+  )
+}
+
+impl AzureBlob {
+  pub async fn init(config: &Config) -> Result<Self> {
+    let settings = config.azure_blob.clone().unwrap_or_default();
+    let client = reqwest::Client::new();
+
+    let connection_string = settings
+      .connection_string
+      .clone()
+      .or_else(|| env::var("AZURE_STORAGE_CONNECTION_STRING").ok());
+
+    let (account, endpoint, credentials) = match (connection_string, &settings) {
+      (
+        _,
+        AzureBlobConfig {
+          account: Some(account),
+          tenant_id: Some(tenant_id),
+          client_id: Some(client_id),
+          ..
+        },
+      ) => {
+        let token = active_directory_token(&client, tenant_id, client_id).await?;
+        (
+          account.clone(),
+          format!("https://{}.blob.core.windows.net", account),
+          Credentials::Bearer(token),
+        )
+      }
+      (Some(connection_string), _) => {
+        let parts = parse_connection_string(&connection_string);
+        let account = parts.get("AccountName").cloned().unwrap_or_default();
+        let endpoint = match parts.get("BlobEndpoint") {
+          Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+          None => format!(
+            "{}://{}.blob.{}",
+            parts
+              .get("DefaultEndpointsProtocol")
+              .map(String::as_str)
+              .unwrap_or("https"),
+            account,
+            parts
+              .get("EndpointSuffix")
+              .map(String::as_str)
+              .unwrap_or("core.windows.net")
+          ),
+        };
+        let credentials = match (parts.get("AccountKey"), parts.get("SharedAccessSignature")) {
+          (Some(key), _) => Credentials::SharedKey(
+            base64::decode(key).wrap_err_with(|| "Unable to decode the storage account key")?,
+          ),
+          (None, Some(signature)) => {
+            Credentials::SharedAccessSignature(signature.trim_start_matches('?').to_string())
+          }
+          (None, None) => {
+            return Err(eyre!(
+              "The storage connection string needs either an AccountKey or a SharedAccessSignature"
+            ))
+          }
+        };
+        (account, endpoint, credentials)
+      }
+      (None, _) => {
+        return Err(eyre!("Unable to find credentials for Azure Blob Storage. Please set AZURE_STORAGE_CONNECTION_STRING, or an account, tenant_id, and client_id under azure_blob in your config."))
+      }
+    };
+
+    Ok(AzureBlob {
+      client,
+      account,
+      endpoint,
+      container: settings.container,
+      credentials,
+    })
+  }
+
+  /// Creates a signed request for a path inside the container
+  fn request(
+    &self,
+    method: Method,
+    blob: Option<&str>,
+    query: Vec<(&str, String)>,
+    body: Option<String>,
+  ) -> Result<RequestBuilder> {
+    let path = match blob {
+      Some(blob) => format!("/{}/{}", self.container, blob),
+      None => format!("/{}", self.container),
+    };
+    let content_length = body.as_ref().map(String::len).unwrap_or(0);
+    let content_type = if body.is_some() {
+      "application/json"
+    } else {
+      ""
+    };
+
+    let mut headers = vec![
+      (
+        "x-ms-date",
+        Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+      ),
+      ("x-ms-version", API_VERSION.to_string()),
+    ];
+    if body.is_some() {
+      headers.push(("x-ms-blob-type", "BlockBlob".to_string()));
+    }
+
+    let mut url = Url::parse_with_params(&format!("{}{}", self.endpoint, path), &query)
+      .wrap_err_with(|| format!("Invalid blob endpoint {}", self.endpoint))?;
+
+    let authorization = match &self.credentials {
+      Credentials::SharedKey(key) => {
+        let mut mac =
+          Hmac::<Sha256>::new_varkey(key).map_err(|_| eyre!("Invalid storage account key"))?;
+        mac.update(
+          string_to_sign(
+            &method,
+            &self.account,
+            &path,
+            &query,
+            &headers,
+            content_length,
+            content_type,
+          )
+          .as_bytes(),
+        );
+        Some(format!(
+          "SharedKey {}:{}",
+          self.account,
+          base64::encode(mac.finalize().into_bytes())
+        ))
+      }
+      Credentials::SharedAccessSignature(signature) => {
+        let query = match url.query() {
+          Some(query) => format!("{}&{}", query, signature),
+          None => signature.clone(),
+        };
+        url.set_query(Some(&query));
+        None
+      }
+      Credentials::Bearer(token) => Some(format!("Bearer {}", token)),
+    };
+
+    let mut request = self.client.request(method, url);
+    for (name, value) in headers {
+      request = request.header(name, value);
+    }
+    if let Some(authorization) = authorization {
+      request = request.header("Authorization", authorization);
+    }
+    if let Some(body) = body {
+      request = request
+        .header("Content-Type", content_type)
+        .header("Content-Length", content_length)
+        .body(body);
+    }
+
+    Ok(request)
+  }
+
+  /// Lists the names of every blob starting with prefix, following continuation markers
+  async fn list_blobs(&self, prefix: Option<&str>) -> Result<Vec<String>> {
+    let mut names = vec![];
+    let mut marker: Option<String> = None;
+
+    loop {
+      let mut query = vec![
+        ("comp", "list".to_string()),
+        ("restype", "container".to_string()),
+      ];
+      if let Some(prefix) = prefix {
+        query.push(("prefix", prefix.to_string()));
+      }
+      if let Some(marker) = &marker {
+        query.push(("marker", marker.clone()));
+      }
+
+      let listing = self
+        .request(Method::GET, None, query, None)?
+        .send()
+        .await?
+        .error_for_status()
+        .wrap_err_with(|| format!("Unable to list blobs in container {}", self.container))?
+        .text()
+        .await?;
+
+      names.extend(xml_values(&listing, "Name"));
+      marker = xml_values(&listing, "NextMarker")
+        .into_iter()
+        .find(|marker| !marker.is_empty());
+      if marker.is_none() {
+        break;
+      }
+    }
+
+    Ok(names)
+  }
+
+  async fn get_blob(&self, name: &str) -> Result<Option<Entry>> {
+    let response = self
+      .request(Method::GET, Some(name), vec![], None)?
+      .send()
+      .await?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+      return Ok(None);
+    }
+
+    let body = response
+      .error_for_status()
+      .wrap_err_with(|| format!("Unable to download blob {}", name))?
+      .text()
+      .await?;
+    Ok(Some(serde_json::from_str(&body).wrap_err_with(|| {
+      format!("Unable to parse blob {} as an entry", name)
+    })?))
+  }
+
+  async fn get_blobs(&self, names: &[String]) -> Result<Entries> {
+    let mut entries = vec![];
+    for name in names {
+      if let Some(entry) = self.get_blob(name).await? {
+        entries.push(entry);
+      }
+    }
+    entries.sort();
+
+    Ok(entries)
+  }
+}
+
+/// Exchanges a service principal's client secret for a token that can access storage
+async fn active_directory_token(
+  client: &reqwest::Client,
+  tenant_id: &str,
+  client_id: &str,
+) -> Result<String> {
+  let secret = env::var("AZURE_CLIENT_SECRET").wrap_err_with(|| {
+    "AZURE_CLIENT_SECRET must be set to authenticate with Azure Active Directory"
+  })?;
+
+  let token: AccessToken = client
+    .post(format!(
+      "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+      tenant_id
+    ))
+    .form(&[
+      ("grant_type", "client_credentials"),
+      ("client_id", client_id),
+      ("client_secret", &secret),
+      ("scope", "https://storage.azure.com/.default"),
+    ])
+    .send()
+    .await?
+    .error_for_status()
+    .wrap_err_with(|| "Unable to authenticate with Azure Active Directory")?
+    .json()
+    .await?;
+
+  Ok(token.access_token)
+}
+
+#[async_trait]
+impl Database for AzureBlob {
+  async fn add_entry(&self, entry: Entry) -> Result<()> {
+    let body = serde_json::to_string(&entry)?;
+
+    self
+      .request(
+        Method::PUT,
+        Some(&blob_name(&entry.board_id, entry.time_stamp)),
+        vec![],
+        Some(body),
+      )?
+      .send()
+      .await?
+      .error_for_status()
+      .wrap_err_with(|| "Unable to add entry")?;
+
+    Ok(())
+  }
+
+  async fn all_entries(&self) -> Result<Option<Entries>> {
+    let names = self.list_blobs(None).await?;
+    Ok(Some(self.get_blobs(&names).await?))
+  }
+
+  async fn get_entry(&self, board_name: String, time_stamp: i64) -> Result<Option<Entry>> {
+    self.get_blob(&blob_name(&board_name, time_stamp)).await
+  }
+
+  async fn query_entries(
+    &self,
+    board_name: String,
+    date_range: Option<DateRange>,
+  ) -> Result<Option<Entries>> {
+    // Timestamps are part of the name, so only the entries in range need to be downloaded
+    let names: Vec<String> = self
+      .list_blobs(Some(&format!("{}/", board_name)))
+      .await?
+      .into_iter()
+      .filter(|name| match (&date_range, time_stamp_from_name(name)) {
+        (Some(range), Some(time_stamp)) => range.start <= time_stamp && time_stamp <= range.end,
+        (None, Some(_)) => true,
+        (_, None) => false,
+      })
+      .collect();
+
+    Ok(Some(self.get_blobs(&names).await?))
+  }
+
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    for time_stamp in time_stamps {
+      let name = blob_name(&board_name, time_stamp);
+      let response = self
+        .request(Method::DELETE, Some(&name), vec![], None)?
+        .send()
+        .await?;
+
+      // Someone else may have already removed it
+      if response.status() != StatusCode::NOT_FOUND {
+        response
+          .error_for_status()
+          .wrap_err_with(|| format!("Unable to delete blob {}", name))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn what_type(&self) -> String {
+    "Azure Blob Storage".into()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_reads_blob_listings() {
+    let listing = "<?xml version=\"1.0\" encoding=\"utf-8\"?><EnumerationResults><Blobs><Blob><Name>board/1.json</Name></Blob><Blob><Name>board/86400.json</Name></Blob></Blobs><NextMarker /></EnumerationResults>";
+
+    let names = xml_values(listing, "Name");
+    assert_eq!(names, vec!["board/1.json", "board/86400.json"]);
+    assert_eq!(time_stamp_from_name(&names[1]), Some(86400));
+    assert!(xml_values(listing, "NextMarker").is_empty());
+  }
+
+  #[test]
+  fn it_builds_the_string_to_sign() {
+    let signed = string_to_sign(
+      &Method::GET,
+      "myaccount",
+      "/card-counter",
+      &[
+        ("restype", "container".to_string()),
+        ("comp", "list".to_string()),
+      ],
+      &[
+        ("x-ms-version", API_VERSION.to_string()),
+        ("x-ms-date", "Fri, 26 Jun 2015 23:39:12 GMT".to_string()),
+      ],
+      0,
+      "",
+    );
+
+    assert_eq!(
+      signed,
+      "GET\n\n\n\n\n\n\n\n\n\n\n\nx-ms-date:Fri, 26 Jun 2015 23:39:12 GMT\nx-ms-version:2020-04-08\n/myaccount/card-counter\ncomp:list\nrestype:container"
+    );
+  }
+
+  #[test]
+  fn it_parses_connection_strings() {
+    let parts = parse_connection_string(
+      "DefaultEndpointsProtocol=https;AccountName=myaccount;AccountKey=a2V5==;EndpointSuffix=core.windows.net",
+    );
+    assert_eq!(parts.get("AccountName").unwrap(), "myaccount");
+    assert_eq!(parts.get("AccountKey").unwrap(), "a2V5==");
+  }
+}
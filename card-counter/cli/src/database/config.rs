@@ -1,26 +1,92 @@
-use dialoguer::{Input, Select};
+use chrono::{
+  format::{Item, StrftimeItems},
+  DateTime, Local, Locale, NaiveDate, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use dialoguer::{Confirm, Input, Select};
 use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::env;
 use std::fmt;
 
+use std::fs::File;
 use std::io::prelude::*;
 use std::io::{BufReader, BufWriter, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use super::DatabaseType;
 use crate::database::json::config_file;
 
-use crate::{errors::*, kanban::trello::TrelloAuth};
+use crate::{
+  commands::burndown::{ListMatcher, StateMatcher},
+  errors::*,
+  kanban::{
+    asana::AsanaAuth, gitlab::GitLabAuth, localfile::LocalFileAuth, monday::MondayAuth,
+    notion::NotionAuth, trello::TrelloAuth,
+  },
+  score::{Palette, Scorer},
+};
+
+// A config file that applies to a single project, found in the current directory or any above it
+pub static PROJECT_CONFIG: &str = ".card-counter.yaml";
+
+// Names the profile in the config to use, --profile sets it for the rest of the run
+pub static PROFILE_ENV: &str = "CARD_COUNTER_PROFILE";
 
 // The possible values that trello accepts for token expiration times
 pub static TRELLO_TOKEN_EXPIRATION: &[&str] = &["1hour", "1day", "30days", "never"];
 
+/// How requests to Jira are authenticated. Jira Cloud takes a username and API token, while Jira
+/// Server and Data Center can take a personal access token as a bearer token.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JiraAuthMethod {
+  #[default]
+  Basic,
+  Bearer,
+}
+
+impl fmt::Display for JiraAuthMethod {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      JiraAuthMethod::Basic => write!(f, "Username and API token (Jira Cloud)"),
+      JiraAuthMethod::Bearer => write!(f, "Personal access token (Jira Server / Data Center)"),
+    }
+  }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct JiraAuth {
   pub username: String,
   pub api_token: String,
   pub url: String,
+  #[serde(default)]
+  pub method: JiraAuthMethod,
+  // Path to a PEM certificate to trust, for servers using a self-signed certificate
+  #[serde(default)]
+  pub ca_certificate: Option<String>,
+  // How many pages of issues to request at once, pages are fetched one at a time when unset
+  #[serde(default)]
+  pub max_concurrent_requests: Option<usize>,
+  // Only issues matching this JQL are counted, ex: sprint in openSprints()
+  #[serde(default)]
+  pub jql: Option<String>,
+  // Id of the custom field holding story points, ex: customfield_10016
+  #[serde(default)]
+  pub story_points_field: Option<String>,
+  // Set from --sprint for a single run: a sprint id, "active", or empty to pick one
+  #[serde(skip)]
+  pub sprint: Option<String>,
+  // Issues resolved as one of these, ex: Won't Do, aren't counted
+  #[serde(default)]
+  pub excluded_resolutions: Vec<String>,
+  // Set from --include-archived for a single run, counts issues with an excluded resolution
+  #[serde(skip)]
+  pub include_archived: bool,
 }
 
 // impl JiraAuth {
@@ -33,6 +99,11 @@ pub struct JiraAuth {
 pub enum KanbanBoard {
   Trello(TrelloAuth),
   Jira(JiraAuth),
+  GitLab(GitLabAuth),
+  Asana(AsanaAuth),
+  Notion(NotionAuth),
+  Monday(MondayAuth),
+  LocalFile(LocalFileAuth),
 }
 
 impl fmt::Display for KanbanBoard {
@@ -40,6 +111,11 @@ impl fmt::Display for KanbanBoard {
     let kanban = match self {
       KanbanBoard::Jira(_) => "Jira",
       KanbanBoard::Trello(_) => "Trello",
+      KanbanBoard::GitLab(_) => "GitLab",
+      KanbanBoard::Asana(_) => "Asana",
+      KanbanBoard::Notion(_) => "Notion",
+      KanbanBoard::Monday(_) => "Monday",
+      KanbanBoard::LocalFile(_) => "LocalFile",
     };
     write!(f, "{}", kanban)
   }
@@ -51,6 +127,11 @@ impl Default for TrelloAuth {
       token: "".to_string(),
       key: "".to_string(),
       expiration: "1day".to_string(),
+      issued_at: None,
+      points_field: None,
+      workspace: None,
+      checklist_points: false,
+      include_archived: false,
     }
   }
 }
@@ -60,6 +141,14 @@ impl Default for JiraAuth {
       username: "".to_string(),
       api_token: "".to_string(),
       url: "".to_string(),
+      method: JiraAuthMethod::default(),
+      ca_certificate: None,
+      max_concurrent_requests: None,
+      jql: None,
+      story_points_field: None,
+      sprint: None,
+      excluded_resolutions: Vec::new(),
+      include_archived: false,
     }
   }
 }
@@ -77,6 +166,11 @@ impl FromStr for KanbanBoard {
     match s.to_lowercase().as_str() {
       "trello" => Ok(KanbanBoard::Trello(TrelloAuth::default())),
       "jira" => Ok(KanbanBoard::Jira(JiraAuth::default())),
+      "gitlab" => Ok(KanbanBoard::GitLab(GitLabAuth::default())),
+      "asana" => Ok(KanbanBoard::Asana(AsanaAuth::default())),
+      "notion" => Ok(KanbanBoard::Notion(NotionAuth::default())),
+      "monday" => Ok(KanbanBoard::Monday(MondayAuth::default())),
+      "localfile" => Ok(KanbanBoard::LocalFile(LocalFileAuth::default())),
       no_match => Err(KanbanParseError(no_match.to_string())),
     }
   }
@@ -87,6 +181,11 @@ impl KanbanBoard {
     match KanbanBoard::from_str(kanban) {
       Ok(KanbanBoard::Trello(_)) => trello_auth_from_env().ok().map(KanbanBoard::Trello),
       Ok(KanbanBoard::Jira(_)) => jira_auth_from_env().ok().map(KanbanBoard::Jira),
+      Ok(KanbanBoard::GitLab(_)) => gitlab_auth_from_env().ok().map(KanbanBoard::GitLab),
+      Ok(KanbanBoard::Asana(_)) => asana_auth_from_env().ok().map(KanbanBoard::Asana),
+      Ok(KanbanBoard::Notion(_)) => notion_auth_from_env().ok().map(KanbanBoard::Notion),
+      Ok(KanbanBoard::Monday(_)) => monday_auth_from_env().ok().map(KanbanBoard::Monday),
+      Ok(KanbanBoard::LocalFile(_)) => localfile_auth_from_env().ok().map(KanbanBoard::LocalFile),
       Err(_) => None,
     }
   }
@@ -105,10 +204,561 @@ pub struct Azure {
   cosmos_account: String,
 }
 
+/// Settings for storing entries in an Azure Storage container. Credentials can come from a
+/// connection string or, when a tenant and client id are given, from Azure Active Directory.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AzureBlobConfig {
+  pub container: String,
+  // Falls back to the AZURE_STORAGE_CONNECTION_STRING environment variable
+  pub connection_string: Option<String>,
+  // Needed when authenticating through Azure Active Directory
+  pub account: Option<String>,
+  pub tenant_id: Option<String>,
+  // The client secret is read from the AZURE_CLIENT_SECRET environment variable
+  pub client_id: Option<String>,
+}
+
+impl Default for AzureBlobConfig {
+  fn default() -> Self {
+    AzureBlobConfig {
+      container: "card-counter".to_string(),
+      connection_string: None,
+      account: None,
+      tenant_id: None,
+      client_id: None,
+    }
+  }
+}
+
+/// Settings for storing entries as JSON in an S3 bucket, one object per board. Credentials come
+/// from the usual AWS sources, as they do for DynamoDB.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct S3Config {
+  // Falls back to the CARD_COUNTER_S3_BUCKET environment variable
+  pub bucket: String,
+  // Objects are stored as <prefix>/<board_id>.json
+  pub prefix: String,
+  // Defaults to the region in your environment or AWS profile
+  pub region: Option<String>,
+  // For S3 compatible storage, such as MinIO
+  pub endpoint: Option<String>,
+}
+
+impl Default for S3Config {
+  fn default() -> Self {
+    S3Config {
+      bucket: "".to_string(),
+      prefix: "card-counter".to_string(),
+      region: None,
+      endpoint: None,
+    }
+  }
+}
+
+/// Settings for storing entries in Redis, with a sorted set for each board
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct RedisConfig {
+  // Falls back to the REDIS_URL environment variable, and then to a server on localhost
+  pub url: Option<String>,
+  // Each board's entries are stored under <prefix>:<board_id>
+  pub prefix: String,
+}
+
+impl Default for RedisConfig {
+  fn default() -> Self {
+    RedisConfig {
+      url: None,
+      prefix: "card-counter".to_string(),
+    }
+  }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct DatabaseConfig {
   pub database_name: Option<String>,
   pub container_name: Option<String>,
+  // Cosmos only: the path documents are partitioned on when the container is created, either
+  // "/board_id" (default) or "/id"
+  pub partition_key: Option<String>,
+  // Cosmos only: RU/s provisioned when creating the database and container. Leave it unset for
+  // serverless accounts.
+  pub throughput: Option<u64>,
+  // DynamoDB only: how the table is billed when card-counter creates it, either "PROVISIONED"
+  // (default) or "PAY_PER_REQUEST" for on-demand billing
+  pub billing_mode: Option<String>,
+  // DynamoDB only: the provisioned capacity for the table, each defaults to 1
+  pub read_capacity_units: Option<i64>,
+  pub write_capacity_units: Option<i64>,
+}
+
+/// How a DynamoDB table created by card-counter is billed
+#[derive(Debug, PartialEq)]
+pub enum DynamoDbBilling {
+  OnDemand,
+  Provisioned { read: i64, write: i64 },
+}
+
+impl DatabaseConfig {
+  pub fn dynamodb_billing(&self) -> Result<DynamoDbBilling> {
+    let mode = self
+      .billing_mode
+      .as_deref()
+      .unwrap_or("PROVISIONED")
+      .to_uppercase();
+    let capacity = (self.read_capacity_units, self.write_capacity_units);
+
+    match (mode.as_str(), capacity) {
+      ("PAY_PER_REQUEST", (None, None)) => Ok(DynamoDbBilling::OnDemand),
+      ("PAY_PER_REQUEST", _) => Err(eyre!(
+        "read_capacity_units and write_capacity_units can't be set with PAY_PER_REQUEST billing"
+      )),
+      ("PROVISIONED", (read, write)) => match (read.unwrap_or(1), write.unwrap_or(1)) {
+        (read, write) if read >= 1 && write >= 1 => {
+          Ok(DynamoDbBilling::Provisioned { read, write })
+        }
+        _ => Err(eyre!(
+          "read_capacity_units and write_capacity_units must be at least 1"
+        )),
+      },
+      (mode, _) => Err(eyre!(
+        "Unknown DynamoDB billing mode {}. Expected PROVISIONED or PAY_PER_REQUEST",
+        mode
+      )),
+    }
+  }
+}
+
+/// Describes how dates are rendered to the user. Each format is a strftime style string, see
+/// https://docs.rs/chrono/latest/chrono/format/strftime/index.html, or "iso" for yyyy-mm-dd and
+/// "locale" for the way the locale writes dates
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct DateFormat {
+  // Used for dates in tables and CSV output
+  pub date: String,
+  // Used when prompting the user to pick a previous entry
+  pub timestamp: String,
+  // Used for labels along the x-axis of charts
+  pub chart: String,
+  // A POSIX locale, such as "fr_FR", used for month and day names
+  pub locale: Option<String>,
+  // The timezone dates are shown in and split into days by
+  pub timezone: Timezone,
+}
+
+impl Default for DateFormat {
+  fn default() -> Self {
+    DateFormat {
+      date: "%d-%m-%Y".to_string(),
+      timestamp: "%b %d, %R %Z".to_string(),
+      chart: "%Y-%m-%d".to_string(),
+      locale: None,
+      timezone: Timezone::Local,
+    }
+  }
+}
+
+impl DateFormat {
+  /// Checks each of the patterns can be formatted, chrono panics on bad patterns instead of
+  /// erroring
+  pub fn validate(&self) -> Result<()> {
+    check_date_pattern("date_format.date", &self.date)?;
+    check_date_pattern("date_format.timestamp", &self.timestamp)?;
+    check_date_pattern("date_format.chart", &self.chart)
+  }
+
+  /// Formats date with pattern in the configured timezone, using the configured locale if chrono
+  /// knows about it.
+  pub fn format(&self, date: &DateTime<Utc>, pattern: &str) -> String {
+    let locale = self
+      .locale
+      .as_ref()
+      .and_then(|locale| Locale::try_from(locale.as_str()).ok());
+    let pattern = match pattern {
+      "iso" => "%Y-%m-%d",
+      "locale" => "%x",
+      pattern => pattern,
+    };
+    match &self.timezone {
+      Timezone::Local => format_in(&date.with_timezone(&Local), pattern, locale),
+      Timezone::Utc => format_in(date, pattern, locale),
+      Timezone::Named(tz) => format_in(&date.with_timezone(tz), pattern, locale),
+    }
+  }
+}
+
+fn check_date_pattern(name: &str, pattern: &str) -> Result<()> {
+  match StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+    true => Err(eyre!("{} has an invalid date format: {}", name, pattern)),
+    false => Ok(()),
+  }
+}
+
+fn format_in<Z: TimeZone>(date: &DateTime<Z>, pattern: &str, locale: Option<Locale>) -> String
+where
+  Z::Offset: fmt::Display,
+{
+  match locale {
+    Some(locale) => date.format_localized(pattern, locale).to_string(),
+    None => date.format(pattern).to_string(),
+  }
+}
+
+/// The timezone to show dates in, either "local" for the machine's timezone, "UTC", or an IANA
+/// name like "America/Edmonton"
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Timezone {
+  #[default]
+  Local,
+  Utc,
+  Named(Tz),
+}
+
+impl Timezone {
+  /// The calendar day date falls on in the timezone
+  pub fn day(&self, date: &DateTime<Utc>) -> NaiveDate {
+    match self {
+      Timezone::Local => date.with_timezone(&Local).naive_local().date(),
+      Timezone::Utc => date.naive_utc().date(),
+      Timezone::Named(tz) => date.with_timezone(tz).naive_local().date(),
+    }
+  }
+
+  /// When day starts in the timezone. Days that skip midnight for daylight saving time start at
+  /// midnight UTC instead.
+  pub fn start_of_day(&self, day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+    let start = match self {
+      Timezone::Local => Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc)),
+      Timezone::Utc => None,
+      Timezone::Named(tz) => tz
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc)),
+    };
+    start.unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+  }
+}
+
+impl fmt::Display for Timezone {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Timezone::Local => write!(f, "local"),
+      Timezone::Utc => write!(f, "UTC"),
+      Timezone::Named(tz) => write!(f, "{}", tz.name()),
+    }
+  }
+}
+
+impl FromStr for Timezone {
+  type Err = String;
+
+  fn from_str(timezone: &str) -> std::result::Result<Self, Self::Err> {
+    match timezone {
+      "local" => Ok(Timezone::Local),
+      "UTC" | "utc" => Ok(Timezone::Utc),
+      name => name.parse::<Tz>().map(Timezone::Named).map_err(|_| {
+        format!(
+          "Unknown timezone {}. Expected local, UTC, or a name like America/Edmonton",
+          name
+        )
+      }),
+    }
+  }
+}
+
+impl TryFrom<String> for Timezone {
+  type Error = String;
+
+  fn try_from(timezone: String) -> std::result::Result<Self, Self::Error> {
+    timezone.parse()
+  }
+}
+
+impl From<Timezone> for String {
+  fn from(timezone: Timezone) -> Self {
+    timezone.to_string()
+  }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Delimiter {
+  Comma,
+  Semicolon,
+  Tab,
+}
+
+impl Delimiter {
+  pub fn as_char(&self) -> char {
+    match self {
+      Delimiter::Comma => ',',
+      Delimiter::Semicolon => ';',
+      Delimiter::Tab => '\t',
+    }
+  }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Quoting {
+  // Only quote fields containing the delimiter, quotes, or new lines
+  Necessary,
+  Always,
+  Never,
+}
+
+/// Regexes that find a card's points in its name. Each captures the number in a group named
+/// points, ex: `SP:(?P<points>\d+)`. Cards are estimated with (N) and corrected with [N] by default.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ScorePatterns {
+  pub estimate: String,
+  pub correction: String,
+}
+
+impl Default for ScorePatterns {
+  fn default() -> Self {
+    ScorePatterns {
+      estimate: r"\((?P<points>\d+)\)".to_string(),
+      correction: r"\[(?P<points>\d+)\]".to_string(),
+    }
+  }
+}
+
+/// The names of, or regexes for, the lists in each state before done. Lists that aren't in any are
+/// counted as committed.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ListStates {
+  pub backlog: Vec<String>,
+  pub committed: Vec<String>,
+  pub in_progress: Vec<String>,
+}
+
+/// Describes how CSV is written and read. Spreadsheet programs in some locales expect semicolons
+/// between fields and commas as the decimal separator.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CsvDialect {
+  pub delimiter: Delimiter,
+  pub quoting: Quoting,
+  pub decimal_separator: char,
+  // The format for dates in CSV output, in place of date_format's date
+  pub date: Option<String>,
+}
+
+impl Default for CsvDialect {
+  fn default() -> Self {
+    CsvDialect {
+      delimiter: Delimiter::Comma,
+      quoting: Quoting::Necessary,
+      decimal_separator: '.',
+      date: None,
+    }
+  }
+}
+
+impl CsvDialect {
+  /// Formats date for a CSV field, using the dialect's date format if it has one
+  pub fn date(&self, format: &DateFormat, date: &DateTime<Utc>) -> String {
+    format.format(date, self.date.as_deref().unwrap_or(&format.date))
+  }
+
+  /// Joins fields into a single CSV row, quoting fields as needed
+  pub fn row<T: AsRef<str>>(&self, fields: &[T]) -> String {
+    fields
+      .iter()
+      .map(|field| self.quote(field.as_ref()))
+      .collect::<Vec<String>>()
+      .join(&self.delimiter.as_char().to_string())
+  }
+
+  /// Formats a number using the configured decimal separator
+  pub fn number<T: ToString>(&self, number: T) -> String {
+    number
+      .to_string()
+      .replace('.', &self.decimal_separator.to_string())
+  }
+
+  fn quote(&self, field: &str) -> String {
+    let needs_quotes = field.contains(self.delimiter.as_char())
+      || field.contains('"')
+      || field.contains('\n')
+      || field.contains('\r');
+
+    match (self.quoting, needs_quotes) {
+      (Quoting::Always, _) | (Quoting::Necessary, true) => {
+        format!("\"{}\"", field.replace('"', "\"\""))
+      }
+      _ => field.to_string(),
+    }
+  }
+
+  /// Splits a CSV row back into its fields, the reverse of row
+  pub fn split(&self, line: &str) -> Vec<String> {
+    let delimiter = self.delimiter.as_char();
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      match (c, quoted) {
+        ('"', true) if chars.peek() == Some(&'"') => {
+          field.push('"');
+          chars.next();
+        }
+        ('"', _) => quoted = !quoted,
+        (c, false) if c == delimiter => fields.push(std::mem::take(&mut field)),
+        (c, _) => field.push(c),
+      }
+    }
+    fields.push(field);
+    fields
+  }
+}
+
+/// The set of colours used when printing tables to the terminal
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+  #[default]
+  Default,
+  // Swaps red and green for blue and magenta
+  Colorblind,
+  // Never prints colour
+  Monochrome,
+}
+
+impl Theme {
+  /// Returns the palette for the theme, or None if colour shouldn't be used.
+  pub fn palette(&self) -> Option<Palette> {
+    match self {
+      Theme::Default => Some(Palette {
+        positive: "Fg".to_string(),
+        negative: "Fr".to_string(),
+        warning: "Fy".to_string(),
+      }),
+      Theme::Colorblind => Some(Palette {
+        positive: "Fb".to_string(),
+        negative: "Fm".to_string(),
+        warning: "Fy".to_string(),
+      }),
+      Theme::Monochrome => None,
+    }
+  }
+}
+
+/// How burndown charts are drawn. Colours are CSS colours for SVG and plotly charts, ASCII charts
+/// use the ascii_ colours, which are one of black, red, green, yellow, blue, magenta, cyan, or white.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ChartStyle {
+  pub title: String,
+  pub x_label: Option<String>,
+  pub y_label: Option<String>,
+  // The size of SVG charts in pixels
+  pub width: u32,
+  pub height: u32,
+  // The size of ASCII charts in braille dots
+  pub ascii_width: u32,
+  pub ascii_height: u32,
+  pub text_colour: String,
+  pub incomplete_colour: String,
+  pub complete_colour: String,
+  pub average_colour: String,
+  pub scope_colour: String,
+  pub annotation_colour: String,
+  pub weekend_colour: String,
+  pub ascii_incomplete_colour: String,
+  pub ascii_complete_colour: String,
+  pub ascii_average_colour: String,
+  pub ascii_annotation_colour: String,
+}
+
+impl Default for ChartStyle {
+  fn default() -> Self {
+    ChartStyle {
+      title: "Burndown".to_string(),
+      x_label: None,
+      y_label: None,
+      width: 900,
+      height: 600,
+      ascii_width: 120,
+      ascii_height: 60,
+      text_colour: "#74838F".to_string(),
+      incomplete_colour: "#D2222D".to_string(),
+      complete_colour: "#238823".to_string(),
+      average_colour: "#3A6EA5".to_string(),
+      scope_colour: "#7B5EA7".to_string(),
+      annotation_colour: "#E1A100".to_string(),
+      weekend_colour: "#EEF0F2".to_string(),
+      ascii_incomplete_colour: "red".to_string(),
+      ascii_complete_colour: "blue".to_string(),
+      ascii_average_colour: "magenta".to_string(),
+      ascii_annotation_colour: "yellow".to_string(),
+    }
+  }
+}
+
+/// A dated note about something that happened to a board, ex: "scope cut", that is drawn on
+/// burndown charts.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Annotation {
+  pub board_id: String,
+  // Formatted as yyyy-mm-dd
+  pub date: String,
+  pub label: String,
+}
+
+impl Annotation {
+  /// Returns the unix timestamp for the start of the annotated day in timezone
+  pub fn time_stamp(&self, timezone: &Timezone) -> Result<i64> {
+    let day = NaiveDate::parse_from_str(&self.date, "%F")
+      .wrap_err_with(|| format!("Unable to parse annotation date {}", self.date))?;
+    Ok(timezone.start_of_day(day).timestamp())
+  }
+}
+
+/// Thresholds used when checking saved entries for trends that need a human to look at them
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnomalyThresholds {
+  // Flag a day where the total points on the board grows by more than this percentage
+  pub scope_jump_percent: f64,
+  // Flag this many days in a row with no points completed
+  pub stalled_days: i64,
+  // Flag the number of unscored cards going up this many entries in a row
+  pub unscored_climbing: usize,
+  // Incoming webhook to post anomalies to
+  pub slack_webhook: Option<String>,
+}
+
+impl Default for AnomalyThresholds {
+  fn default() -> Self {
+    AnomalyThresholds {
+      scope_jump_percent: 20.0,
+      stalled_days: 3,
+      unscored_climbing: 3,
+      slack_webhook: None,
+    }
+  }
+}
+
+/// The most cards or points a list should hold at once, either can be left out
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct WipLimit {
+  pub cards: Option<usize>,
+  pub points: Option<i32>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -119,6 +769,70 @@ pub struct Config {
   #[serde(default)]
   pub database: DatabaseType,
   pub database_configuration: Option<DatabaseConfig>,
+  #[serde(default)]
+  pub azure_blob: Option<AzureBlobConfig>,
+  #[serde(default)]
+  pub s3: Option<S3Config>,
+  #[serde(default)]
+  pub redis: Option<RedisConfig>,
+  #[serde(default)]
+  pub date_format: DateFormat,
+  #[serde(default)]
+  pub annotations: Vec<Annotation>,
+  #[serde(default)]
+  pub csv: CsvDialect,
+  #[serde(default)]
+  pub theme: Theme,
+  // Keeps tables and charts to plain ASCII without colour, for logs, CI, and screen readers
+  #[serde(default)]
+  pub ascii: bool,
+  #[serde(default)]
+  pub chart: ChartStyle,
+  #[serde(default)]
+  pub anomalies: AnomalyThresholds,
+  // Entries older than this many days are removed each time an entry is saved
+  #[serde(default)]
+  pub retention_days: Option<i64>,
+  // Only this many of a board's newest entries are kept each time an entry is saved
+  #[serde(default)]
+  pub max_entries_per_board: Option<usize>,
+  #[serde(default)]
+  pub score_patterns: ScorePatterns,
+  // The points a card can be estimated at, ex: [1, 2, 3, 5, 8, 13]. Any estimate is allowed when
+  // this is empty
+  #[serde(default)]
+  pub allowed_points: Vec<i32>,
+  // The names of, or regexes for, the lists that count as done in a burndown
+  #[serde(default = "default_done_lists")]
+  pub done_lists: Vec<String>,
+  #[serde(default)]
+  pub list_states: ListStates,
+  // Lists that were renamed, from their old name to their new one, so compare still lines them up
+  #[serde(default)]
+  pub list_aliases: HashMap<String, String>,
+  // The names of, or regexes for, lists and the most cards or points each should hold
+  #[serde(default)]
+  pub wip_limits: HashMap<String, WipLimit>,
+  // The ids of the boards summary reports on when none are passed in
+  #[serde(default)]
+  pub summary_boards: Vec<String>,
+  // Short names for boards, ex: backend: 5f1c..., that can be passed to --board-id instead of an id
+  #[serde(default)]
+  pub boards: HashMap<String, String>,
+  // The board, by alias or id, used when none is passed in instead of asking for one
+  #[serde(default)]
+  pub default_board: Option<String>,
+  // A Tera template to draw SVG burndowns with instead of the built in one
+  #[serde(default)]
+  pub svg_template: Option<String>,
+  // Named sets of settings, ex: work-trello or client-jira, each laid over the rest of the config
+  // when it's picked with --profile or CARD_COUNTER_PROFILE
+  #[serde(default)]
+  pub profiles: serde_yaml::Mapping,
+}
+
+fn default_done_lists() -> Vec<String> {
+  vec!["Done".to_string()]
 }
 
 impl Default for Config {
@@ -128,6 +842,29 @@ impl Default for Config {
       azure: None,
       database: DatabaseType::default(),
       database_configuration: None,
+      azure_blob: None,
+      s3: None,
+      redis: None,
+      date_format: DateFormat::default(),
+      annotations: Vec::new(),
+      csv: CsvDialect::default(),
+      theme: Theme::default(),
+      ascii: false,
+      chart: ChartStyle::default(),
+      anomalies: AnomalyThresholds::default(),
+      retention_days: None,
+      max_entries_per_board: None,
+      score_patterns: ScorePatterns::default(),
+      allowed_points: Vec::new(),
+      done_lists: default_done_lists(),
+      list_states: ListStates::default(),
+      list_aliases: HashMap::new(),
+      wip_limits: HashMap::new(),
+      summary_boards: Vec::new(),
+      boards: HashMap::new(),
+      default_board: None,
+      svg_template: None,
+      profiles: serde_yaml::Mapping::new(),
     }
   }
 }
@@ -154,16 +891,48 @@ fn database_details(current_config: Option<DatabaseConfig>) -> Option<DatabaseCo
     .interact()
     .ok();
 
+  // Only used when card-counter creates the container, so keep whatever was set before
+  let partition_keys = ["/board_id", "/id"];
+  let current_partition_key = _current_config.partition_key.clone();
+  let partition_key = Select::new()
+    .with_prompt("Partition key for new containers")
+    .items(&partition_keys)
+    .default(
+      partition_keys
+        .iter()
+        .position(|key| current_partition_key.as_deref() == Some(*key))
+        .unwrap_or(0),
+    )
+    .interact()
+    .ok()
+    .map(|index| partition_keys[index].to_string());
+
+  let throughput = Input::<String>::new()
+    .with_prompt("Throughput (RU/s) for new databases and containers (leave empty for serverless):")
+    .default(
+      _current_config
+        .throughput
+        .map(|throughput| throughput.to_string())
+        .unwrap_or_default(),
+    )
+    .allow_empty(true)
+    .interact()
+    .ok()
+    .and_then(|throughput| throughput.trim().parse::<u64>().ok());
+
   Some(DatabaseConfig {
     database_name,
     container_name,
+    partition_key,
+    throughput,
+    .._current_config
   })
 }
 
 fn trello_details(kanban: KanbanBoard) -> Result<TrelloAuth> {
   let trello = match kanban {
-    KanbanBoard::Jira(_) => TrelloAuth::default(),
     KanbanBoard::Trello(trello) => trello,
+    _ => TrelloAuth::default(),
   };
 
   let key = Input::<String>::new()
@@ -185,20 +954,44 @@ https://trello.com/1/authorize?expiration={}&name=card-counter&scope=read&respon
 
   let token = Input::<String>::new()
     .with_prompt("Trello API Token")
-    .default(trello.token)
+    .default(trello.token.clone())
+    .interact()?;
+
+  // Only restart the clock when the user gives us a new token
+  let issued_at = if token == trello.token && expiration == trello.expiration {
+    trello.issued_at
+  } else {
+    Some(Utc::now().timestamp())
+  };
+
+  let points_field = Input::<String>::new()
+    .with_prompt("Custom field with story points (leave empty to read points from card names):")
+    .default(trello.points_field.unwrap_or_default())
+    .allow_empty(true)
+    .interact()?;
+
+  let workspace = Input::<String>::new()
+    .with_prompt("Workspace to choose boards from (leave empty for your own boards):")
+    .default(trello.workspace.unwrap_or_default())
+    .allow_empty(true)
     .interact()?;
 
   Ok(TrelloAuth {
     key,
     token,
     expiration,
+    issued_at,
+    points_field: Some(points_field).filter(|field| !field.is_empty()),
+    workspace: Some(workspace).filter(|workspace| !workspace.is_empty()),
+    checklist_points: trello.checklist_points,
+    include_archived: false,
   })
 }
 
 fn jira_details(kanban: KanbanBoard) -> Result<JiraAuth> {
   let jira = match kanban {
     KanbanBoard::Jira(jira) => jira,
-    KanbanBoard::Trello(_) => JiraAuth::default(),
+    _ => JiraAuth::default(),
   };
 
   let url = Input::<String>::new()
@@ -206,32 +999,231 @@ fn jira_details(kanban: KanbanBoard) -> Result<JiraAuth> {
     .default(jira.url.clone())
     .interact()?;
 
-  let username = Input::<String>::new()
-    .with_prompt("Jira Username:")
-    .default(jira.username.clone())
-    .interact()?;
+  let methods = [JiraAuthMethod::Basic, JiraAuthMethod::Bearer];
+  let method = methods[Select::new()
+    .with_prompt("How do you sign in to Jira?")
+    .items(&methods)
+    .default(
+      methods
+        .iter()
+        .position(|method| *method == jira.method)
+        .unwrap_or(0),
+    )
+    .interact()
+    .wrap_err_with(|| "There was an error setting your Jira auth method.")?];
 
-  println!(
-    "To generate an API token for your Jira account please follow the instructions here at:
+  let (username, api_token) = match method {
+    JiraAuthMethod::Basic => {
+      let username = Input::<String>::new()
+        .with_prompt("Jira Username:")
+        .default(jira.username.clone())
+        .interact()?;
+
+      println!(
+        "To generate an API token for your Jira account please follow the instructions here at:
 https://support.atlassian.com/atlassian-account/docs/manage-api-tokens-for-your-atlassian-account"
-  );
+      );
+
+      let api_token = Input::<String>::new()
+        .with_prompt("Jira API Token")
+        .default(jira.api_token.clone())
+        .interact()?;
+      (username, api_token)
+    }
+    JiraAuthMethod::Bearer => {
+      println!(
+        "To generate a personal access token please follow the instructions here at:
+https://confluence.atlassian.com/enterprise/using-personal-access-tokens-1026032365.html"
+      );
+
+      let api_token = Input::<String>::new()
+        .with_prompt("Jira Personal Access Token")
+        .default(jira.api_token.clone())
+        .interact()?;
+      (String::new(), api_token)
+    }
+  };
+
+  // Not worth a prompt, but keep whatever was set in the config file
+  let max_concurrent_requests = jira.max_concurrent_requests;
+  let ca_certificate = Input::<String>::new()
+    .with_prompt("Path to a CA certificate to trust (leave empty for none):")
+    .default(jira.ca_certificate.unwrap_or_default())
+    .allow_empty(true)
+    .interact()?;
+
+  let jql = Input::<String>::new()
+    .with_prompt("JQL to filter issues by (leave empty to count the whole board):")
+    .default(jira.jql.unwrap_or_default())
+    .allow_empty(true)
+    .interact()?;
 
-  let api_token = Input::<String>::new()
-    .with_prompt("Jira API Token")
-    .default(jira.api_token)
+  let story_points_field = Input::<String>::new()
+    .with_prompt(
+      "Story Points field id, ex: customfield_10016 (leave empty to read points from summaries):",
+    )
+    .default(jira.story_points_field.unwrap_or_default())
+    .allow_empty(true)
     .interact()?;
 
   Ok(JiraAuth {
     username,
     api_token,
     url,
+    method,
+    ca_certificate: Some(ca_certificate).filter(|path| !path.is_empty()),
+    max_concurrent_requests,
+    jql: Some(jql).filter(|jql| !jql.is_empty()),
+    story_points_field: Some(story_points_field).filter(|field| !field.is_empty()),
+    sprint: None,
+    excluded_resolutions: jira.excluded_resolutions,
+    include_archived: false,
+  })
+}
+
+fn gitlab_details(kanban: KanbanBoard) -> Result<GitLabAuth> {
+  let gitlab = match kanban {
+    KanbanBoard::GitLab(gitlab) => gitlab,
+    _ => GitLabAuth::default(),
+  };
+
+  let url = Input::<String>::new()
+    .with_prompt("GitLab URL:")
+    .default(gitlab.url.clone())
+    .interact()?;
+
+  let project = Input::<String>::new()
+    .with_prompt("GitLab project id or path:")
+    .default(gitlab.project.clone())
+    .interact()?;
+
+  println!(
+    "To generate a personal access token with the read_api scope please follow the instructions here at:
+https://docs.gitlab.com/ee/user/profile/personal_access_tokens.html"
+  );
+
+  let token = Input::<String>::new()
+    .with_prompt("GitLab Access Token")
+    .default(gitlab.token)
+    .interact()?;
+
+  Ok(GitLabAuth {
+    token,
+    url,
+    project,
   })
 }
 
+fn asana_details(kanban: KanbanBoard) -> Result<AsanaAuth> {
+  let asana = match kanban {
+    KanbanBoard::Asana(asana) => asana,
+    _ => AsanaAuth::default(),
+  };
+
+  println!(
+    "To generate a personal access token for Asana please visit:
+https://app.asana.com/0/my-apps"
+  );
+
+  let token = Input::<String>::new()
+    .with_prompt("Asana Personal Access Token")
+    .default(asana.token.clone())
+    .interact()?;
+
+  let points_field = Input::<String>::new()
+    .with_prompt("Custom field for story points:")
+    .default(asana.points_field)
+    .interact()?;
+
+  Ok(AsanaAuth {
+    token,
+    points_field,
+  })
+}
+
+fn notion_details(kanban: KanbanBoard) -> Result<NotionAuth> {
+  let notion = match kanban {
+    KanbanBoard::Notion(notion) => notion,
+    _ => NotionAuth::default(),
+  };
+
+  println!(
+    "To create an integration token for Notion, and share your database with it, please follow the instructions here at:
+https://developers.notion.com/docs/create-a-notion-integration"
+  );
+
+  let token = Input::<String>::new()
+    .with_prompt("Notion Integration Token")
+    .default(notion.token.clone())
+    .interact()?;
+
+  let status_property = Input::<String>::new()
+    .with_prompt("Property to use as lists:")
+    .default(notion.status_property.clone())
+    .interact()?;
+
+  let points_property = Input::<String>::new()
+    .with_prompt("Number property for story points:")
+    .default(notion.points_property)
+    .interact()?;
+
+  Ok(NotionAuth {
+    token,
+    status_property,
+    points_property,
+  })
+}
+
+fn monday_details(kanban: KanbanBoard) -> Result<MondayAuth> {
+  let monday = match kanban {
+    KanbanBoard::Monday(monday) => monday,
+    _ => MondayAuth::default(),
+  };
+
+  println!(
+    "To find your API token for Monday please follow the instructions here at:
+https://developer.monday.com/api-reference/docs/authentication"
+  );
+
+  let token = Input::<String>::new()
+    .with_prompt("Monday API Token")
+    .default(monday.token.clone())
+    .interact()?;
+
+  let points_column = Input::<String>::new()
+    .with_prompt("Numbers column for story points:")
+    .default(monday.points_column)
+    .interact()?;
+
+  Ok(MondayAuth {
+    token,
+    points_column,
+  })
+}
+
+fn localfile_details(kanban: KanbanBoard) -> Result<LocalFileAuth> {
+  let localfile = match kanban {
+    KanbanBoard::LocalFile(localfile) => localfile,
+    _ => LocalFileAuth::default(),
+  };
+
+  let path = Input::<String>::new()
+    .with_prompt("Path to your board file (JSON or CSV):")
+    .default(localfile.path)
+    .interact()?;
+
+  Ok(LocalFileAuth { path })
+}
+
 fn kanban_details(kanban: KanbanBoard) -> Result<KanbanBoard> {
   let preferences = [
     KanbanBoard::Trello(TrelloAuth::default()),
     KanbanBoard::Jira(JiraAuth::default()),
+    KanbanBoard::GitLab(GitLabAuth::default()),
+    KanbanBoard::Asana(AsanaAuth::default()),
+    KanbanBoard::Notion(NotionAuth::default()),
+    KanbanBoard::Monday(MondayAuth::default()),
+    KanbanBoard::LocalFile(LocalFileAuth::default()),
   ];
   let choice = Select::new()
     .with_prompt("What kanban board is this for?")
@@ -243,6 +1235,11 @@ fn kanban_details(kanban: KanbanBoard) -> Result<KanbanBoard> {
   let new_auth = match preferences[choice] {
     KanbanBoard::Trello(_) => KanbanBoard::Trello(trello_details(kanban)?),
     KanbanBoard::Jira(_) => KanbanBoard::Jira(jira_details(kanban)?),
+    KanbanBoard::GitLab(_) => KanbanBoard::GitLab(gitlab_details(kanban)?),
+    KanbanBoard::Asana(_) => KanbanBoard::Asana(asana_details(kanban)?),
+    KanbanBoard::Notion(_) => KanbanBoard::Notion(notion_details(kanban)?),
+    KanbanBoard::Monday(_) => KanbanBoard::Monday(monday_details(kanban)?),
+    KanbanBoard::LocalFile(_) => KanbanBoard::LocalFile(localfile_details(kanban)?),
   };
 
   Ok(new_auth)
@@ -278,6 +1275,9 @@ fn database_preference() -> Result<DatabaseType> {
     DatabaseType::Local,
     DatabaseType::Aws,   /*, DatabaseType::Azure */
     DatabaseType::Azure, /*, DatabaseType::Azure */
+    DatabaseType::AzureBlob,
+    DatabaseType::S3,
+    DatabaseType::Redis,
   ];
   let index = Select::new()
     .with_prompt("What database would you prefer?")
@@ -289,34 +1289,167 @@ fn database_preference() -> Result<DatabaseType> {
   Ok(preferences[index].clone())
 }
 
+/// Looks for a project config file in dir and then each of its parents
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+  dir
+    .ancestors()
+    .map(|dir| dir.join(PROJECT_CONFIG))
+    .find(|path| path.is_file())
+}
+
+/// Reads a YAML file, treating an empty file as if it didn't exist
+fn read_yaml(file: &File) -> Result<Option<Value>> {
+  // We need to know the length of the file or we could erroneously toss a JSON error.
+  // We should error out if we can't read metadata.
+  if file
+    .metadata()
+    .wrap_err_with(|| "Unable to read metadata for config file")?
+    .len()
+    == 0
+  {
+    return Ok(None);
+  };
+
+  // No Sane default: If we can't parse as json, it might be recoverable and we don't
+  // want to overwrite user data
+  serde_yaml::from_reader(BufReader::new(file)).wrap_err_with(|| "Unable to parse file as YAML")
+}
+
+/// Lays the values from local over those in global. Maps are merged key by key, so a project only
+/// needs to list the settings it changes. Enums are written as a map with a single capitalised
+/// key, ex: `Trello: {...}`, and switching variants replaces the value instead of merging it.
+fn merge_yaml(global: Value, local: Value) -> Value {
+  match (global, local) {
+    (Value::Mapping(mut global), Value::Mapping(local)) if !is_variant(&local) => {
+      for (key, value) in local {
+        let merged = match global.remove(&key) {
+          Some(existing) => merge_yaml(existing, value),
+          None => value,
+        };
+        global.insert(key, merged);
+      }
+      Value::Mapping(global)
+    }
+    (global, Value::Null) => global,
+    (_, local) => local,
+  }
+}
+
+/// Lays the settings in the profile called name over the rest of the config
+fn with_profile(config: Value, name: &str) -> Result<Value> {
+  let profiles = config.get("profiles").and_then(Value::as_mapping);
+  let profile = profiles
+    .and_then(|profiles| profiles.get(&Value::String(name.to_string())))
+    .cloned()
+    .ok_or_else(|| {
+      let names: Vec<&str> = profiles
+        .map(|profiles| {
+          profiles
+            .iter()
+            .filter_map(|(key, _)| key.as_str())
+            .collect()
+        })
+        .unwrap_or_default();
+      match names.is_empty() {
+        true => eyre!(
+          "Unable to find the profile {}, there aren't any profiles in your config",
+          name
+        ),
+        false => eyre!(
+          "Unable to find the profile {}, the profiles in your config are {}",
+          name,
+          names.join(", ")
+        ),
+      }
+    })?;
+
+  Ok(merge_yaml(config, profile))
+}
+
+fn is_variant(map: &serde_yaml::Mapping) -> bool {
+  map.len() == 1
+    && map
+      .iter()
+      .filter_map(|(key, _)| key.as_str())
+      .any(|key| key.starts_with(char::is_uppercase))
+}
+
 impl Config {
-  pub fn from_file() -> Result<Option<Config>> {
-    let config = match config_file() {
-      Ok(file) => file,
-      Err(_) => return Ok(None),
-    };
+  /// Picks the profile passed in, falling back to the one named in CARD_COUNTER_PROFILE
+  pub fn profile_name(profile: Option<&str>) -> Option<String> {
+    profile
+      .map(str::to_string)
+      .or_else(|| env::var(PROFILE_ENV).ok())
+      .filter(|profile| !profile.is_empty())
+  }
 
-    let reader = BufReader::new(&config);
+  /// Reads the config in $HOME/.card-counter, overridden by the nearest project config file and
+  /// then by the profile passed in, or the one named in CARD_COUNTER_PROFILE
+  pub fn from_file(profile: Option<&str>) -> Result<Option<Config>> {
+    let global = match config_file() {
+      Ok(file) => read_yaml(&file)?,
+      Err(_) => None,
+    };
 
-    // We need to know the length of the file or we could erroneously toss a JSON error.
-    // We should error out if we can't read metadata.
-    if config
-      .metadata()
-      .expect("Unable to read metadata for $HOME/.card-counter/config.yaml")
-      .len()
-      == 0
+    let local = match env::current_dir()
+      .ok()
+      .and_then(|dir| find_project_config(&dir))
     {
-      return Ok(None);
+      Some(path) => read_yaml(
+        &File::open(&path).wrap_err_with(|| format!("Unable to open {}", path.display()))?,
+      )
+      .wrap_err_with(|| format!("Unable to read {}", path.display()))?,
+      None => None,
+    };
+
+    let profile = Config::profile_name(profile);
+
+    let merged = match (global, local, profile) {
+      (None, None, None) => return Ok(None),
+      (global, local, profile) => {
+        let merged = merge_yaml(global.unwrap_or(Value::Null), local.unwrap_or(Value::Null));
+        match profile {
+          Some(profile) => with_profile(merged, &profile)?,
+          None => merged,
+        }
+      }
     };
 
-    // No Sane default: If we can't parse as json, it might be recoverable and we don't
-    // want to overwrite user data
-    serde_yaml::from_reader(reader).wrap_err_with(|| "Unable to parse file as YAML")
+    let config: Config =
+      serde_yaml::from_value(merged).wrap_err_with(|| "Unable to parse config")?;
+    config.validate()?;
+    Ok(Some(config))
+  }
+
+  /// Checks the settings that can't be checked while parsing
+  fn validate(&self) -> Result<()> {
+    self.date_format.validate()?;
+    match &self.csv.date {
+      Some(pattern) => check_date_pattern("csv.date", pattern),
+      None => Ok(()),
+    }
+  }
+
+  /// Reads only the config in $HOME/.card-counter. Use this when the config is going to be
+  /// persisted, so project settings aren't copied into it.
+  pub fn from_global_file() -> Result<Option<Config>> {
+    match config_file() {
+      Ok(file) => match read_yaml(&file)? {
+        Some(value) => {
+          let config: Config =
+            serde_yaml::from_value(value).wrap_err_with(|| "Unable to parse config")?;
+          config.validate()?;
+          Ok(Some(config))
+        }
+        None => Ok(None),
+      },
+      Err(_) => Ok(None),
+    }
   }
 
   // Handles the setup for the app, mostly checking for key and token and giving the proper prompts to the user to get the right info.
   pub fn check_for_auth() -> Result<Option<TrelloAuth>> {
-    match (trello_auth_from_env(), Config::from_file()?) {
+    match (trello_auth_from_env(), Config::from_file(None)?) {
       (Ok(env), _) => Ok(Some(env)),
       (Err(_), Some(config)) => Ok(config.trello_auth()),
       (Err(e), None) => {
@@ -353,20 +1486,68 @@ impl Config {
     Ok(())
   }
 
+  /// Walks the user through generating a new Trello token after theirs expired and saves it to
+  /// the global config. Returns false if the user would rather do it themselves, or if the token
+  /// came from a profile, which only they know where to update.
+  pub fn renew_trello_token(profile: Option<&str>) -> Result<bool> {
+    if let Some(profile) = Config::profile_name(profile) {
+      eprintln!(
+        "Your Trello token comes from the {} profile, update it there in your config file",
+        profile
+      );
+      return Ok(false);
+    }
+    let mut config = Config::from_global_file_or_default()?;
+    let auth = match &config.kanban {
+      KanbanBoard::Trello(auth) => auth.clone(),
+      _ => return Ok(false),
+    };
+
+    // Confirm fails when we aren't attached to a terminal, in which case there's nobody to ask
+    let renew = Confirm::new()
+      .with_prompt("Would you like to generate a new Trello token now?")
+      .default(true)
+      .interact()
+      .unwrap_or(false);
+    if !renew {
+      return Ok(false);
+    }
+
+    println!(
+      "Visit the link below and paste the token into the prompt:\n{}",
+      auth.authorize_url()
+    );
+    let token = Input::<String>::new()
+      .with_prompt("Trello API Token")
+      .interact()?;
+
+    config.kanban = KanbanBoard::Trello(TrelloAuth {
+      token,
+      issued_at: Some(Utc::now().timestamp()),
+      ..auth
+    });
+    config.persist()?;
+    Ok(true)
+  }
+
   pub fn update_file(self) -> Result<()> {
     self.user_update_prompts()?.persist().unwrap();
     Ok(())
   }
 
-  pub fn from_file_or_default() -> Result<Config> {
-    match Config::from_file()? {
+  pub fn from_file_or_default(profile: Option<&str>) -> Result<Config> {
+    match Config::from_file(profile)? {
       Some(config) => Ok(config),
       None => Ok(Config::default()),
     }
   }
 
-  pub fn init(kanban: Option<&str>) -> Result<Config> {
-    let config = Config::from_file_or_default()?;
+  pub fn from_global_file_or_default() -> Result<Config> {
+    Ok(Config::from_global_file()?.unwrap_or_default())
+  }
+
+  pub fn init(kanban: Option<&str>, profile: Option<&str>) -> Result<Config> {
+    let config = Config::from_file_or_default(profile)?;
     if let Some(auth) = KanbanBoard::from_env(kanban.unwrap_or(&config.kanban.to_string())) {
       Ok(Config {
         kanban: auth,
@@ -377,16 +1558,65 @@ impl Config {
     }
   }
 
+  /// Compiles score_patterns, erroring if one isn't a valid regex
+  pub fn scorer(&self) -> Result<Scorer> {
+    Scorer::new(&self.score_patterns)
+  }
+
+  /// Compiles done_lists, erroring if one isn't a valid regex
+  pub fn done_matcher(&self) -> Result<ListMatcher> {
+    ListMatcher::new(&self.done_lists)
+  }
+
+  /// Compiles list_states and done_lists, erroring if one isn't a valid regex
+  pub fn state_matcher(&self) -> Result<StateMatcher> {
+    Ok(StateMatcher {
+      backlog: ListMatcher::new(&self.list_states.backlog)?,
+      committed: ListMatcher::new(&self.list_states.committed)?,
+      in_progress: ListMatcher::new(&self.list_states.in_progress)?,
+      done: self.done_matcher()?,
+    })
+  }
+
+  /// Compiles wip_limits, sorted by list name, erroring if one isn't a valid regex
+  pub fn wip_limits(&self) -> Result<Vec<(ListMatcher, WipLimit)>> {
+    let mut limits: Vec<(&String, &WipLimit)> = self.wip_limits.iter().collect();
+    limits.sort_by(|a, b| a.0.cmp(b.0));
+    limits
+      .into_iter()
+      .map(|(list, limit)| Ok((ListMatcher::new(std::slice::from_ref(list))?, limit.clone())))
+      .collect()
+  }
+
+  /// Looks board up in boards, returning the id it's an alias for or board itself when it isn't one
+  pub fn board_id(&self, board: &str) -> String {
+    self
+      .boards
+      .get(board)
+      .cloned()
+      .unwrap_or_else(|| board.to_string())
+  }
+
+  /// Returns all annotations recorded for board_id
+  pub fn annotations_for(&self, board_id: &str) -> Vec<Annotation> {
+    self
+      .annotations
+      .iter()
+      .filter(|annotation| annotation.board_id == board_id)
+      .cloned()
+      .collect()
+  }
+
   pub fn trello_auth(self) -> Option<TrelloAuth> {
     if let Ok(auth) = trello_auth_from_env() {
       return Some(auth);
     }
     match self.kanban {
-      KanbanBoard::Jira(_) => {
+      KanbanBoard::Trello(trello) => Some(trello),
+      _ => {
         eprintln!("Unable to get auth details for Trello");
         None
       }
-      KanbanBoard::Trello(trello) => Some(trello),
     }
   }
 
@@ -397,7 +1627,7 @@ impl Config {
 
     match self.kanban {
       KanbanBoard::Jira(jira) => Some(jira),
-      KanbanBoard::Trello(_) => {
+      _ => {
         eprintln!("Unable to get auth details for Jira");
         None
       }
@@ -415,53 +1645,58 @@ pub fn trello_auth_from_env() -> Result<TrelloAuth> {
   let token: String = if let Ok(value) = env::var("TRELLO_API_TOKEN") {
     value
   } else {
-    return Err(eyre!("Trello API token is missing. Please visit https://trello.com/1/authorize?expiration=1day&name=card-counter&scope=read&response_type=token&key={}\n and set the token as the environment variable TRELLO_API_TOKEN".to_string()));
+    return Err(eyre!("Trello API token is missing. Please visit https://trello.com/1/authorize?expiration=1day&name=card-counter&scope=read&response_type=token&key={}\n and set the token as the environment variable TRELLO_API_TOKEN", key));
   };
 
   if key.is_empty() {
     return Err(eyre!("Trello API key not found. Please visit https://trello.com/app-key and set it as the environment variable \"TRELLO_API_KEY\"".to_string()));
   };
   if token.is_empty() {
-    return Err(eyre!("Trello API token is missing. Please visit https://trello.com/1/authorize?expiration=1day&name=card-counter&scope=read&response_type=token&key={}\n and set the token as the environment variable TRELLO_API_TOKEN"));
+    return Err(eyre!("Trello API token is missing. Please visit https://trello.com/1/authorize?expiration=1day&name=card-counter&scope=read&response_type=token&key={}\n and set the token as the environment variable TRELLO_API_TOKEN", key));
   };
   Ok(TrelloAuth {
     key,
     token,
     expiration: "".to_string(),
+    issued_at: None,
+    points_field: env::var("TRELLO_POINTS_FIELD")
+      .ok()
+      .filter(|field| !field.is_empty()),
+    workspace: env::var("TRELLO_WORKSPACE")
+      .ok()
+      .filter(|workspace| !workspace.is_empty()),
+    checklist_points: env::var("TRELLO_CHECKLIST_POINTS")
+      .map(|value| value == "true")
+      .unwrap_or(false),
+    include_archived: false,
   })
 }
 
 fn jira_auth_from_env() -> Result<JiraAuth> {
-  let username: String = match env::var("JIRA_USERNAME") {
-    Ok(value) => value,
-    Err(_) => {
-      return Err(eyre!("Jira username not found. Please set the environment variable \"JIRA_USERNAME\"
-For more information visit https://support.atlassian.com/atlassian-account/docs/manage-api-tokens-for-your-atlassian-account/ ".to_string()));
+  let method = match env::var("JIRA_AUTH_METHOD").as_deref() {
+    Ok("bearer") => JiraAuthMethod::Bearer,
+    Ok("basic") | Err(_) => JiraAuthMethod::Basic,
+    Ok(other) => {
+      return Err(eyre!(
+        "Unknown Jira auth method {}, JIRA_AUTH_METHOD should be basic or bearer",
+        other
+      ))
     }
   };
 
-  let api_token: String = match env::var("JIRA_API_TOKEN") {
-    Ok(value) => value,
-    Err(_) => {
-      return Err(eyre!("Jira API token is missing. Generate a token at https://support.atlassian.com/atlassian-account/docs/manage-api-tokens-for-your-atlassian-account/ and\n and set the token as the environment variable JIRA_API_TOKEN"));
-    }
-  };
-
-  let url: String = match env::var("JIRA_URL") {
-    Ok(value) => value,
-    Err(_) => {
-      return Err(eyre!("Jira URL is missing. Set the base URL for your Jira account in the environment variable \"JIRA_URL\""));
-    }
-  };
-
-  if username.is_empty() {
+  // Personal access tokens already identify the user, so only basic auth needs a username
+  let username: String = env::var("JIRA_USERNAME").unwrap_or_default();
+  if username.is_empty() && method == JiraAuthMethod::Basic {
     return Err(eyre!("Jira username not found. Please set the environment variable \"JIRA_USERNAME\"
 For more information visit https://support.atlassian.com/atlassian-account/docs/manage-api-tokens-for-your-atlassian-account/ ".to_string()));
   }
+
+  let api_token: String = env::var("JIRA_API_TOKEN").unwrap_or_default();
   if api_token.is_empty() {
     return Err(eyre!("Jira API token is missing. Generate a token at https://support.atlassian.com/atlassian-account/docs/manage-api-tokens-for-your-atlassian-account/ and\n and set the token as the environment variable JIRA_API_TOKEN"));
   }
 
+  let url: String = env::var("JIRA_URL").unwrap_or_default();
   if url.is_empty() {
     return Err(eyre!("Jira URL is missing. Set the base URL for your Jira account in the environment variable \"JIRA_URL\""));
   }
@@ -470,5 +1705,299 @@ For more information visit https://support.atlassian.com/atlassian-account/docs/
     username,
     api_token,
     url,
+    method,
+    ca_certificate: env::var("JIRA_CA_CERTIFICATE")
+      .ok()
+      .filter(|path| !path.is_empty()),
+    max_concurrent_requests: env::var("JIRA_MAX_CONCURRENT_REQUESTS")
+      .ok()
+      .and_then(|limit| limit.parse::<usize>().ok()),
+    jql: env::var("JIRA_JQL").ok().filter(|jql| !jql.is_empty()),
+    story_points_field: env::var("JIRA_STORY_POINTS_FIELD")
+      .ok()
+      .filter(|field| !field.is_empty()),
+    sprint: None,
+    excluded_resolutions: env::var("JIRA_EXCLUDED_RESOLUTIONS")
+      .map(|resolutions| {
+        resolutions
+          .split(',')
+          .map(str::trim)
+          .filter(|resolution| !resolution.is_empty())
+          .map(String::from)
+          .collect()
+      })
+      .unwrap_or_default(),
+    include_archived: false,
   })
 }
+
+fn gitlab_auth_from_env() -> Result<GitLabAuth> {
+  let token = env::var("GITLAB_TOKEN").unwrap_or_default();
+  if token.is_empty() {
+    return Err(eyre!("GitLab token is missing. Generate a personal access token with the read_api scope at https://docs.gitlab.com/ee/user/profile/personal_access_tokens.html and set it as the environment variable GITLAB_TOKEN"));
+  }
+
+  let project = env::var("GITLAB_PROJECT").unwrap_or_default();
+  if project.is_empty() {
+    return Err(eyre!("GitLab project is missing. Set the id or path of your project in the environment variable \"GITLAB_PROJECT\""));
+  }
+
+  Ok(GitLabAuth {
+    token,
+    project,
+    url: env::var("GITLAB_URL").unwrap_or_else(|_| GitLabAuth::default().url),
+  })
+}
+
+fn asana_auth_from_env() -> Result<AsanaAuth> {
+  let token = env::var("ASANA_TOKEN").unwrap_or_default();
+  if token.is_empty() {
+    return Err(eyre!("Asana personal access token is missing. Generate one at https://app.asana.com/0/my-apps and set it as the environment variable ASANA_TOKEN"));
+  }
+
+  Ok(AsanaAuth {
+    token,
+    points_field: env::var("ASANA_POINTS_FIELD")
+      .unwrap_or_else(|_| AsanaAuth::default().points_field),
+  })
+}
+
+fn notion_auth_from_env() -> Result<NotionAuth> {
+  let token = env::var("NOTION_TOKEN").unwrap_or_default();
+  if token.is_empty() {
+    return Err(eyre!("Notion integration token is missing. Create one at https://www.notion.so/my-integrations and set it as the environment variable NOTION_TOKEN"));
+  }
+
+  let defaults = NotionAuth::default();
+  Ok(NotionAuth {
+    token,
+    status_property: env::var("NOTION_STATUS_PROPERTY").unwrap_or(defaults.status_property),
+    points_property: env::var("NOTION_POINTS_PROPERTY").unwrap_or(defaults.points_property),
+  })
+}
+
+fn monday_auth_from_env() -> Result<MondayAuth> {
+  let token = env::var("MONDAY_TOKEN").unwrap_or_default();
+  if token.is_empty() {
+    return Err(eyre!("Monday API token is missing. Follow the instructions at https://developer.monday.com/api-reference/docs/authentication and set it as the environment variable MONDAY_TOKEN"));
+  }
+
+  Ok(MondayAuth {
+    token,
+    points_column: env::var("MONDAY_POINTS_COLUMN")
+      .unwrap_or_else(|_| MondayAuth::default().points_column),
+  })
+}
+
+fn localfile_auth_from_env() -> Result<LocalFileAuth> {
+  match env::var("CARD_COUNTER_BOARD_FILE") {
+    Ok(path) if !path.is_empty() => Ok(LocalFileAuth { path }),
+    _ => Err(eyre!("Board file is missing. Set the path to a JSON or CSV board in the environment variable \"CARD_COUNTER_BOARD_FILE\"")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_merges_project_config_over_global_config() {
+    let global: Value = serde_yaml::from_str(
+      "kanban:
+  Trello:
+    key: key
+    token: token
+    expiration: 1day
+database: Local
+date_format:
+  date: \"%d-%m-%Y\"
+  chart: \"%Y-%m-%d\"",
+    )
+    .unwrap();
+    let local: Value = serde_yaml::from_str(
+      "kanban:
+  Jira:
+    username: me
+    api_token: token
+    url: https://example.atlassian.net
+date_format:
+  date: \"%Y/%m/%d\"",
+    )
+    .unwrap();
+
+    let config: Config = serde_yaml::from_value(merge_yaml(global, local)).unwrap();
+    assert_eq!(config.kanban.to_string(), "Jira");
+    assert_eq!(config.database, DatabaseType::Local);
+    assert_eq!(config.date_format.date, "%Y/%m/%d");
+    assert_eq!(config.date_format.chart, "%Y-%m-%d");
+    assert_eq!(config.date_format.timezone, Timezone::Local);
+  }
+
+  #[test]
+  fn it_lays_a_profile_over_the_config() {
+    let config: Value = serde_yaml::from_str(
+      "kanban:
+  Trello:
+    key: key
+    token: token
+    expiration: 1day
+database: Local
+done_lists: [Done]
+profiles:
+  client-jira:
+    kanban:
+      Jira:
+        username: me
+        api_token: token
+        url: https://example.atlassian.net
+    database: Aws",
+    )
+    .unwrap();
+
+    let profiled: Config =
+      serde_yaml::from_value(with_profile(config.clone(), "client-jira").unwrap()).unwrap();
+    assert_eq!(profiled.kanban.to_string(), "Jira");
+    assert_eq!(profiled.database, DatabaseType::Aws);
+    assert_eq!(profiled.done_lists, vec!["Done".to_string()]);
+    assert_eq!(profiled.profiles.len(), 1);
+
+    assert!(with_profile(config, "work-trello").is_err());
+  }
+
+  #[test]
+  fn it_prefers_the_profile_passed_in() {
+    assert_eq!(
+      Config::profile_name(Some("client-jira")),
+      Some("client-jira".to_string())
+    );
+  }
+
+  #[test]
+  fn it_finds_boards_by_alias() {
+    let config: Config = serde_yaml::from_str(
+      "kanban:
+  Trello:
+    key: key
+    token: token
+    expiration: 1day
+boards:
+  backend: 5f1c
+  web: 60ab
+default_board: web",
+    )
+    .unwrap();
+
+    assert_eq!(config.board_id("backend"), "5f1c");
+    assert_eq!(config.board_id("60ab"), "60ab");
+    assert_eq!(config.default_board.as_deref(), Some("web"));
+  }
+
+  #[test]
+  fn it_starts_annotations_in_the_configured_timezone() {
+    let annotation = Annotation {
+      board_id: "1".to_string(),
+      date: "2021-03-01".to_string(),
+      label: "Release".to_string(),
+    };
+    let edmonton: Timezone = "America/Edmonton".parse().unwrap();
+
+    assert_eq!(
+      annotation.time_stamp(&edmonton).unwrap(),
+      Utc
+        .with_ymd_and_hms(2021, 3, 1, 7, 0, 0)
+        .unwrap()
+        .timestamp()
+    );
+    assert_eq!(
+      annotation.time_stamp(&Timezone::Utc).unwrap(),
+      Utc
+        .with_ymd_and_hms(2021, 3, 1, 0, 0, 0)
+        .unwrap()
+        .timestamp()
+    );
+  }
+
+  #[test]
+  fn it_formats_dates_in_the_configured_timezone() {
+    let format: DateFormat = serde_yaml::from_str("timezone: America/Edmonton").unwrap();
+    // 2021-03-02 03:00 UTC was still the 1st in Edmonton
+    let date = Utc.with_ymd_and_hms(2021, 3, 2, 3, 0, 0).unwrap();
+
+    assert_eq!(format.format(&date, "%F %R %Z"), "2021-03-01 20:00 MST");
+    assert_eq!(
+      format.timezone.day(&date),
+      NaiveDate::from_ymd_opt(2021, 3, 1).unwrap()
+    );
+    assert_eq!(
+      format
+        .timezone
+        .start_of_day(NaiveDate::from_ymd_opt(2021, 3, 1).unwrap()),
+      Utc.with_ymd_and_hms(2021, 3, 1, 7, 0, 0).unwrap()
+    );
+    assert_eq!(
+      Timezone::Utc.day(&date),
+      NaiveDate::from_ymd_opt(2021, 3, 2).unwrap()
+    );
+    assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+  }
+
+  #[test]
+  fn it_errors_on_invalid_date_patterns() {
+    let mut config = Config::default();
+    config.date_format.date = "%Q".to_string();
+    assert!(config.validate().is_err());
+
+    let mut config = Config::default();
+    config.csv.date = Some("%Y-%".to_string());
+    assert!(config.validate().is_err());
+
+    let mut config = Config::default();
+    config.date_format.date = "iso".to_string();
+    assert!(config.validate().is_ok());
+  }
+
+  #[test]
+  fn it_formats_dates_with_presets() {
+    let format = DateFormat {
+      timezone: Timezone::Utc,
+      locale: Some("en_US".to_string()),
+      ..DateFormat::default()
+    };
+    let date = Utc.with_ymd_and_hms(2021, 3, 2, 3, 0, 0).unwrap();
+    assert_eq!(format.format(&date, "iso"), "2021-03-02");
+    assert_eq!(format.format(&date, "locale"), "03/02/2021");
+
+    let dialect: CsvDialect = serde_yaml::from_str("date: iso").unwrap();
+    assert_eq!(dialect.date(&format, &date), "2021-03-02");
+    assert_eq!(CsvDialect::default().date(&format, &date), "02-03-2021");
+  }
+
+  #[test]
+  fn it_reads_dynamodb_billing_options() {
+    let billing = |mode: Option<&str>, read: Option<i64>, write: Option<i64>| {
+      DatabaseConfig {
+        billing_mode: mode.map(String::from),
+        read_capacity_units: read,
+        write_capacity_units: write,
+        ..DatabaseConfig::default()
+      }
+      .dynamodb_billing()
+    };
+
+    assert_eq!(
+      billing(None, None, None).unwrap(),
+      DynamoDbBilling::Provisioned { read: 1, write: 1 }
+    );
+    assert_eq!(
+      billing(Some("provisioned"), Some(10), None).unwrap(),
+      DynamoDbBilling::Provisioned { read: 10, write: 1 }
+    );
+    assert_eq!(
+      billing(Some("PAY_PER_REQUEST"), None, None).unwrap(),
+      DynamoDbBilling::OnDemand
+    );
+    assert!(billing(Some("PAY_PER_REQUEST"), Some(5), None).is_err());
+    assert!(billing(None, Some(0), None).is_err());
+    assert!(billing(Some("FREE"), None, None).is_err());
+  }
+}
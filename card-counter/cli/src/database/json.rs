@@ -1,27 +1,86 @@
 use std::io::prelude::*;
-use std::io::{BufReader, BufWriter, SeekFrom};
-use std::path::PathBuf;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use std::{
   fs,
   fs::{File, OpenOptions},
 };
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Mutex};
 
-use super::{Database, DateRange, Entries, Entry};
+use super::{CardSnapshot, Database, DateRange, Entries, Entry};
 use crate::errors::*;
 use crate::score::Deck;
 use async_trait::async_trait;
 use dirs::home_dir;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
 static CONFIG: &str = "card-counter.yaml";
 static DATABASE: &str = "database.json";
+// Held while the database is saved, so two card-counters can't save over each other
+static DATABASE_LOCK: &str = "database.json.lock";
 
-#[derive(Default, Clone)]
+#[derive(Default)]
 pub struct JSON {
-  database: HashMap<String, LocalEntry>,
+  // Kept behind a lock so entries added during a run, such as a restore, build on each other
+  database: Mutex<HashMap<String, LocalEntry>>,
 }
 
-pub type LocalEntry = HashMap<i64, Vec<Deck>>;
+pub type LocalEntry = HashMap<i64, LocalRecord>;
+
+/// What gets stored for each timestamp in the local database.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum LocalRecord {
+  // Entries are stored as a plain list of decks when there's nothing else to save, this also
+  // keeps databases written by older versions of card-counter readable.
+  Decks(Vec<Deck>),
+  Record {
+    decks: Vec<Deck>,
+    #[serde(default)]
+    board_name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    cards: Vec<CardSnapshot>,
+  },
+}
+
+impl LocalRecord {
+  pub fn from_entry(entry: Entry) -> Self {
+    if entry.board_name.is_empty() && entry.tags.is_empty() && entry.cards.is_empty() {
+      LocalRecord::Decks(entry.decks)
+    } else {
+      LocalRecord::Record {
+        decks: entry.decks,
+        board_name: entry.board_name,
+        tags: entry.tags,
+        cards: entry.cards,
+      }
+    }
+  }
+
+  pub fn into_entry(self, board_id: String, time_stamp: i64) -> Entry {
+    let (decks, board_name, tags, cards) = match self {
+      LocalRecord::Decks(decks) => (decks, String::new(), Vec::new(), Vec::new()),
+      LocalRecord::Record {
+        decks,
+        board_name,
+        tags,
+        cards,
+      } => (decks, board_name, tags, cards),
+    };
+
+    Entry {
+      board_id,
+      board_name,
+      time_stamp,
+      decks,
+      tags,
+      cards,
+    }
+  }
+}
 
 // This code has a lot of panics in it, I've chosen to do this because where there are panics it's in the case of IO or data errors.
 // Such as being unable to open the file, unable to parse the file into json, or being unable to save the file. Unfortunately,
@@ -72,6 +131,7 @@ fn get_file(name: &str) -> Result<File> {
       .write(true)
       .read(true)
       .create(true)
+      .truncate(false)
       .open(path)?,
   )
 }
@@ -86,6 +146,43 @@ fn database_file() -> Result<File> {
   get_file(DATABASE)
 }
 
+/// Reads the database from file, an empty file is an empty database
+fn read_database(file: &File) -> Result<HashMap<String, LocalEntry>> {
+  let reader = BufReader::new(file);
+
+  // We need to know the length of the file or we could erroneously toss a JSON error.
+  // We should error out if we can't read metadata.
+  if file
+    .metadata()
+    .wrap_err_with(|| "Unable to read metadata for $HOME/.card-counter/database.json.")?
+    .len()
+    == 0
+  {
+    Ok(HashMap::new())
+  } else {
+    // No Sane default: If we can't parse as json, it might be recoverable and we don't
+    // want to overwrite user data
+    serde_json::from_reader(reader).wrap_err_with(|| "Unable to parse database file as json")
+  }
+}
+
+/// Writes database next to path and then moves it over path, so the database is never left half
+/// written if card-counter is stopped partway through saving
+fn write_database(path: &Path, database: &HashMap<String, LocalEntry>) -> Result<()> {
+  // There is no safe default behavior we can perform here.
+  let json = serde_json::to_string(database).wrap_err_with(|| "Unable to parse database")?;
+
+  let temp_path = path.with_extension("json.tmp");
+  let mut temp = File::create(&temp_path)
+    .wrap_err_with(|| format!("Unable to create {}", temp_path.display()))?;
+  temp
+    .write_all(json.as_bytes())
+    .and_then(|_| temp.sync_all())
+    .wrap_err_with(|| format!("Unable to write to {}", temp_path.display()))?;
+
+  fs::rename(&temp_path, path).wrap_err_with(|| format!("Unable to replace {}", path.display()))
+}
+
 #[async_trait]
 impl Database for JSON {
   /// Updates or creates a local database and inserts the current set of decks as an entry
@@ -106,37 +203,55 @@ impl Database for JSON {
   /// }
   /// ```
   async fn add_entry(&self, entry: Entry) -> Result<()> {
-    // Copies the database and adds_entry into the copy
-    let mut json = self.clone();
-    match json.database.get_mut(&entry.board_id) {
-      Some(timestamps) => {
-        timestamps.insert(entry.time_stamp, entry.decks);
-      }
-      None => {
-        let mut timestamps = HashMap::new();
-        timestamps
-          .insert(entry.time_stamp, entry.decks)
-          .ok_or_else(|| eyre!("Unable to add entry to JSON."))?;
-        json.database.insert(entry.board_id, timestamps);
-      }
-    };
+    self.update(|database| {
+      database
+        .entry(entry.board_id.clone())
+        .or_default()
+        .insert(entry.time_stamp, LocalRecord::from_entry(entry));
+    })
+  }
 
-    json.save()
+  /// Writes the database once for all of the entries
+  async fn add_entries(&self, entries: &[Entry]) -> Result<()> {
+    self.update(|database| {
+      for entry in entries {
+        database
+          .entry(entry.board_id.clone())
+          .or_default()
+          .insert(entry.time_stamp, LocalRecord::from_entry(entry.clone()));
+      }
+    })
   }
+  /// Flattens every board's entries into a single list, ordered by board and then by time
   async fn all_entries(&self) -> Result<Option<Entries>> {
-    Ok(None)
+    let mut entries: Entries = self
+      .database
+      .lock()
+      .unwrap()
+      .iter()
+      .flat_map(|(board_id, records)| {
+        records
+          .iter()
+          .map(move |(time_stamp, record)| record.clone().into_entry(board_id.clone(), *time_stamp))
+      })
+      .collect();
+    entries.sort_by(|a, b| {
+      a.board_id
+        .cmp(&b.board_id)
+        .then(a.time_stamp.cmp(&b.time_stamp))
+    });
+
+    Ok(Some(entries))
   }
   async fn get_entry(&self, board_name: String, time_stamp: i64) -> Result<Option<Entry>> {
     let result = self
       .database
+      .lock()
+      .unwrap()
       .get(&board_name)
       .unwrap_or(&HashMap::default())
       .get(&time_stamp)
-      .map(|item| Entry {
-        board_id: board_name,
-        decks: item.clone(),
-        time_stamp,
-      });
+      .map(|item| item.clone().into_entry(board_name, time_stamp));
 
     Ok(result)
   }
@@ -146,7 +261,8 @@ impl Database for JSON {
     board_id: String,
     date_range: Option<DateRange>,
   ) -> Result<Option<Entries>> {
-    let results = match self.database.get(&board_id) {
+    let database = self.database.lock().unwrap();
+    let results = match database.get(&board_id) {
       Some(results) => results,
       None => return Ok(None),
     };
@@ -155,12 +271,8 @@ impl Database for JSON {
       let entries: Entries = results
         .iter()
         .fold(Vec::new(), |mut collection, (key, value)| {
-          if range.start < *key && *key < range.end {
-            collection.push(Entry {
-              board_id: board_id.clone(),
-              time_stamp: *key,
-              decks: value.clone(),
-            })
+          if range.start <= *key && *key <= range.end {
+            collection.push(value.clone().into_entry(board_id.clone(), *key))
           }
           collection
         });
@@ -168,16 +280,25 @@ impl Database for JSON {
     } else {
       let entries: Entries = results
         .iter()
-        .map(|(key, value)| Entry {
-          board_id: board_id.clone(),
-          time_stamp: *key,
-          decks: value.clone(),
-        })
+        .map(|(key, value)| value.clone().into_entry(board_id.clone(), *key))
         .collect();
       Ok(Some(entries))
     }
   }
 
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    self.update(|database| {
+      if let Some(board) = database.get_mut(&board_name) {
+        for time_stamp in time_stamps {
+          board.remove(&time_stamp);
+        }
+        if board.is_empty() {
+          database.remove(&board_name);
+        }
+      }
+    })
+  }
+
   fn what_type(&self) -> String {
     "JSON".into()
   }
@@ -188,47 +309,109 @@ impl JSON {
     // No Sane default: if we can't get the database we need to error out to the use
     let file =
       database_file().wrap_err_with(|| "Unable to open database at $HOME/.card-counter")?;
-    let reader = BufReader::new(&file);
-
-    // We need to know the length of the file or we could erroneously toss a JSON error.
-    // We should error out if we can't read metadata.
-    if file
-      .metadata()
-      .wrap_err_with(|| "Unable to read metadata for $HOME/.card-counter/database.json.")?
-      .len()
-      == 0
-    {
-      Ok(JSON::default())
-    } else {
-      // No Sane default: If we can't parse as json, it might be recoverable and we don't
-      // want to overwrite user data
-      Ok(JSON {
-        database: serde_json::from_reader(reader)
-          .wrap_err_with(|| "Unable to parse database file as json")?,
-      })
-    }
+
+    Ok(JSON {
+      database: Mutex::new(read_database(&file)?),
+    })
   }
 
-  /// Attempts to save the database and panics if it can't parse the db into JSON or if it can't write to
-  /// the database file.
-  fn save(&self) -> Result<()> {
+  /// Applies change to the database as it is on disk and saves it. Another card-counter may
+  /// have saved since we read the database, so it's read again while holding the lock to keep
+  /// from losing its entries.
+  fn update<F>(&self, change: F) -> Result<()>
+  where
+    F: FnOnce(&mut HashMap<String, LocalEntry>),
+  {
+    let mut database = self.database.lock().unwrap();
+
+    // The lock is released when the file is closed
+    let lock = get_file(DATABASE_LOCK)?;
+    lock
+      .lock_exclusive()
+      .wrap_err_with(|| "Unable to lock $HOME/.card-counter/database.json")?;
+
     // No Sane default: We want to error if we can't open or access the File handle
-    let file = database_file().wrap_err_with(|| "Unable to open database")?;
-
-    // Clear out file before writing to it.
-    file.set_len(0)?;
-    let mut writer = BufWriter::new(file);
-    // There is no safe default behavior we can perform here.
-    let json =
-      serde_json::to_string(&self.database).wrap_err_with(|| "Unable to parse database")?;
-
-    // No Sane default: IO Errors if we can't move around the file
-    writer
-      .seek(SeekFrom::Start(0))
-      .wrap_err_with(|| "Unable to write to file $HOME/.card-counter/database.json")?;
-    writer
-      .write_all(json.as_bytes())
-      .wrap_err_with(|| "Unable to write to file $HOME/.card-counter/database.json")?;
+    let mut latest = read_database(&database_file().wrap_err_with(|| "Unable to open database")?)?;
+    change(&mut latest);
+
+    let mut path = main_dir();
+    path.push(DATABASE);
+    write_database(&path, &latest)?;
+    *database = latest;
+
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn board(records: Vec<(i64, LocalRecord)>) -> LocalEntry {
+    records.into_iter().collect()
+  }
+
+  #[tokio::test]
+  async fn it_returns_every_boards_entries() {
+    let mut database = HashMap::new();
+    database.insert(
+      "second".to_string(),
+      board(vec![(5, LocalRecord::Decks(vec![]))]),
+    );
+    database.insert(
+      "first".to_string(),
+      board(vec![
+        (20, LocalRecord::Decks(vec![])),
+        (
+          10,
+          LocalRecord::Record {
+            decks: vec![],
+            board_name: "First".to_string(),
+            tags: vec!["sprint-1-start".to_string()],
+            cards: vec![],
+          },
+        ),
+      ]),
+    );
+    let json = JSON {
+      database: Mutex::new(database),
+    };
+
+    let entries = json.all_entries().await.unwrap().unwrap();
+    let keys: Vec<(&str, i64)> = entries
+      .iter()
+      .map(|entry| (entry.board_id.as_str(), entry.time_stamp))
+      .collect();
+    assert_eq!(keys, vec![("first", 10), ("first", 20), ("second", 5)]);
+    assert_eq!(entries[0].tags, vec!["sprint-1-start".to_string()]);
+    assert_eq!(entries[0].board_name, "First");
+  }
+
+  #[tokio::test]
+  async fn it_returns_no_entries_for_an_empty_database() {
+    let entries = JSON::default().all_entries().await.unwrap().unwrap();
+    assert!(entries.is_empty());
+  }
+
+  #[test]
+  fn it_replaces_the_database_file() {
+    let dir = std::env::temp_dir().join(format!("card-counter-json-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(DATABASE);
+    fs::write(&path, "{\"old\": {}}").unwrap();
+
+    let mut database = HashMap::new();
+    database.insert(
+      "new".to_string(),
+      board(vec![(1, LocalRecord::Decks(vec![]))]),
+    );
+    write_database(&path, &database).unwrap();
+
+    let saved = read_database(&File::open(&path).unwrap()).unwrap();
+    assert!(saved.contains_key("new"));
+    assert!(!saved.contains_key("old"));
+    assert!(!path.with_extension("json.tmp").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
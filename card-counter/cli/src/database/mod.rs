@@ -1,20 +1,28 @@
 use crate::{errors::*, score::Deck};
 use async_trait::async_trait;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, Utc};
+use config::{DateFormat, Timezone};
 use dialoguer::Select;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, fmt, time::SystemTime};
 
 pub mod aws;
 pub mod azure;
+pub mod azure_blob;
 pub mod config;
 pub mod json;
+pub mod redis;
+pub mod s3;
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
 pub enum DatabaseType {
   Aws,
+  #[default]
   Local,
   Azure,
+  AzureBlob,
+  S3,
+  Redis,
 }
 
 impl fmt::Display for DatabaseType {
@@ -23,24 +31,57 @@ impl fmt::Display for DatabaseType {
       DatabaseType::Local => write!(f, "local"),
       DatabaseType::Aws => write!(f, "aws"),
       DatabaseType::Azure => write!(f, "azure"),
+      DatabaseType::AzureBlob => write!(f, "azure-blob"),
+      DatabaseType::S3 => write!(f, "s3"),
+      DatabaseType::Redis => write!(f, "redis"),
     }
   }
 }
 
-impl Default for DatabaseType {
-  fn default() -> Self {
-    DatabaseType::Local
-  }
+/// Connects to the database, using the config, with the profile passed in, for any settings it
+/// needs
+pub async fn init_database(
+  database: &DatabaseType,
+  profile: Option<&str>,
+) -> Result<Box<dyn Database>> {
+  Ok(match database {
+    DatabaseType::Aws => Box::new(aws::Aws::init(&config::Config::init(None, profile)?).await?),
+    DatabaseType::Azure => {
+      Box::new(azure::Azure::init(&config::Config::init(None, profile)?).await?)
+    }
+    DatabaseType::AzureBlob => {
+      Box::new(azure_blob::AzureBlob::init(&config::Config::init(None, profile)?).await?)
+    }
+    DatabaseType::S3 => Box::new(s3::S3::init(&config::Config::init(None, profile)?).await?),
+    DatabaseType::Redis => {
+      Box::new(redis::Redis::init(&config::Config::init(None, profile)?).await?)
+    }
+    DatabaseType::Local => Box::new(json::JSON::init()?),
+  })
 }
 
-fn select_date(keys: &[i64]) -> Option<i64> {
+/// Connects to a database that's already set up, erroring instead of offering to create a
+/// missing table, database, or container
+pub async fn connect_database(
+  database: &DatabaseType,
+  profile: Option<&str>,
+) -> Result<Box<dyn Database>> {
+  Ok(match database {
+    DatabaseType::Aws => Box::new(aws::Aws::connect().await?),
+    DatabaseType::Azure => {
+      Box::new(azure::Azure::connect(&config::Config::init(None, profile)?).await?)
+    }
+    _ => init_database(database, profile).await?,
+  })
+}
+
+fn select_date(keys: &[i64], format: &DateFormat) -> Option<i64> {
   let rev_keys: Vec<i64> = keys.iter().cloned().rev().collect();
   let items: Vec<String> = rev_keys
     .iter()
     .map(|item| {
-      NaiveDateTime::from_timestamp(*item, 0)
-        .format("%b %d, %R UTC")
-        .to_string()
+      let date = DateTime::from_timestamp(*item, 0).unwrap();
+      format.format(&date, &format.timestamp)
     })
     .collect();
 
@@ -56,11 +97,30 @@ fn select_date(keys: &[i64]) -> Option<i64> {
   }
 }
 
+/// A card as it was when an entry was saved, kept so entries can be rescored if the way cards are
+/// scored changes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CardSnapshot {
+  pub name: String,
+  // The name of the list, and so the deck, the card was in
+  pub list_name: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Entry {
   pub board_id: String,
   pub time_stamp: i64,
   pub decks: Vec<Deck>,
+  // User defined labels, such as "sprint-42-end", used to pick out important snapshots
+  #[serde(default)]
+  pub tags: Vec<String>,
+  // Entries saved by older versions of card-counter don't have any cards
+  #[serde(default)]
+  pub cards: Vec<CardSnapshot>,
+  // The name of the board when the entry was saved, entries saved by older versions of card-counter
+  // don't have one
+  #[serde(default)]
+  pub board_name: String,
 }
 
 impl Ord for Entry {
@@ -86,17 +146,14 @@ impl Eq for Entry {}
 pub type Entries = Vec<Entry>;
 
 // Given a board, the user will be prompted to select an entry based on their timestamps. This can error based on generating prompts to a user.
-pub fn get_decks_by_date(entries: Entries) -> Option<Vec<Deck>> {
+pub fn get_decks_by_date(entries: Entries, format: &DateFormat) -> Option<Vec<Deck>> {
   let mut keys: Vec<i64> = entries.iter().map(|entry| entry.time_stamp).collect();
 
   keys.sort_unstable();
-  let date;
-
-  if !keys.is_empty() {
-    date = select_date(&keys)?;
-  } else {
+  if keys.is_empty() {
     return None;
   }
+  let date = select_date(&keys, format)?;
 
   entries
     .iter()
@@ -120,27 +177,135 @@ impl Default for Entry {
   fn default() -> Self {
     Entry {
       board_id: "Default".to_string(),
+      board_name: String::new(),
       // This name is hack around timestamp is a reserved keyword in some databases
       time_stamp: 0,
       decks: Vec::new(),
+      tags: Vec::new(),
+      cards: Vec::new(),
     }
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DateRange {
   pub start: i64,
   pub end: i64,
 }
 
+/// Prefix used on the command line to refer to an entry by one of its tags, ex: tag:sprint-42-end
+pub static TAG_PREFIX: &str = "tag:";
+
+/// Returns the most recent entry labelled with tag
+pub fn find_tagged_entry(entries: &[Entry], tag: &str) -> Option<Entry> {
+  entries
+    .iter()
+    .filter(|entry| entry.tags.iter().any(|entry_tag| entry_tag == tag))
+    .max()
+    .cloned()
+}
+
+/// Finds the entry the user is referring to with `at`. This is either a tag, "tag:sprint-42-start",
+/// "latest" for the most recent entry, or a day given as a date, "2020-04-14", or as a number of
+/// days before today, "7-days-ago". For days the last entry saved on or before that day is
+/// returned.
+pub fn find_entry_at(entries: &[Entry], at: &str, timezone: &Timezone) -> Result<Option<Entry>> {
+  if let Some(tag) = at.strip_prefix(TAG_PREFIX) {
+    return Ok(find_tagged_entry(entries, tag));
+  }
+  if at == "latest" {
+    return Ok(entries.iter().max().cloned());
+  }
+
+  let day = match at.strip_suffix("-days-ago") {
+    Some(days) => {
+      let days = days
+        .parse::<u64>()
+        .wrap_err_with(|| format!("Unable to parse {}, expected N-days-ago", at))?;
+      timezone
+        .day(&Utc::now())
+        .checked_sub_days(Days::new(days))
+        .ok_or_else(|| eyre!("{} is too far back", at))?
+    }
+    None => parse_day(at)?,
+  };
+  let end_of_day = match day.succ_opt() {
+    Some(next) => timezone.start_of_day(next).timestamp(),
+    None => i64::MAX,
+  };
+  Ok(
+    entries
+      .iter()
+      .filter(|entry| entry.time_stamp < end_of_day)
+      .max()
+      .cloned(),
+  )
+}
+
+/// Parses a date formatted as yyyy-mm-dd
+fn parse_day(date: &str) -> Result<NaiveDate> {
+  NaiveDate::parse_from_str(date, "%F")
+    .wrap_err_with(|| format!("Unable to parse date {}, expected yyyy-mm-dd", date))
+}
+
+/// Parses a date formatted as yyyy-mm-dd into a timestamp for the start of that day in timezone
+fn parse_date(date: &str, timezone: &Timezone) -> Result<i64> {
+  Ok(timezone.start_of_day(parse_day(date)?).timestamp())
+}
+
 impl DateRange {
+  /// Creates a DateRange where start and end may be either a date (yyyy-mm-dd), which starts at
+  /// midnight in timezone, or a tag (tag:sprint-42-start). Tags are looked up in the entries
+  /// stored for board_id.
+  pub async fn resolve(
+    database: &dyn Database,
+    board_id: &str,
+    start: &str,
+    end: &str,
+    timezone: &Timezone,
+  ) -> Result<Self> {
+    if !start.starts_with(TAG_PREFIX) && !end.starts_with(TAG_PREFIX) {
+      return Ok(DateRange {
+        start: parse_date(start, timezone)?,
+        end: parse_date(end, timezone)?,
+      });
+    }
+
+    let entries = database
+      .query_entries(board_id.to_string(), None)
+      .await?
+      .unwrap_or_default();
+
+    let resolve = |value: &str| -> Result<i64> {
+      match value.strip_prefix(TAG_PREFIX) {
+        Some(tag) => find_tagged_entry(&entries, tag)
+          .map(|entry| entry.time_stamp)
+          .ok_or_else(|| {
+            eyre!(
+              "Unable to find an entry tagged {} for board {}",
+              tag,
+              board_id
+            )
+          }),
+        None => parse_date(value, timezone),
+      }
+    };
+
+    Ok(DateRange {
+      start: resolve(start)?,
+      end: resolve(end)?,
+    })
+  }
+
   pub fn from_strs(start: &str, end: &str) -> Self {
     let start = NaiveDateTime::parse_from_str(&format!("{} 0:0:0", start), "%F %H:%M:%S")
       .expect("Unable to parse date")
+      .and_utc()
       .timestamp();
 
     let end = NaiveDateTime::parse_from_str(&format!("{} 0:0:0", end), "%F %H:%M:%S")
       .expect("Unable to parse date")
+      .and_utc()
       .timestamp();
 
     DateRange { start, end }
@@ -160,10 +325,77 @@ impl Default for DateRange {
   }
 }
 
+/// How far name is from what the user typed, ignoring case. Names containing query are closer
+/// than ones that are only a few typos away, anything further than that returns None.
+pub fn name_distance(query: &str, name: &str) -> Option<usize> {
+  let query = query.trim().to_lowercase();
+  let name = name.trim().to_lowercase();
+  if query.is_empty() || name.is_empty() {
+    return None;
+  }
+  if query == name {
+    return Some(0);
+  }
+  if name.contains(&query) {
+    return Some(1);
+  }
+
+  // Levenshtein distance, allowing roughly one typo for every four characters
+  let query: Vec<char> = query.chars().collect();
+  let mut previous: Vec<usize> = (0..=query.len()).collect();
+  for (i, name_char) in name.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, query_char) in query.iter().enumerate() {
+      let substitution = previous[j] + (*query_char != name_char) as usize;
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+  let typos = previous[query.len()];
+
+  match typos <= (query.len() / 4).max(1) {
+    true => Some(typos + 1),
+    false => None,
+  }
+}
+
+/// The entries saved under the board name closest to name, across every board id it was saved
+/// with, so a board that was deleted and recreated keeps its history
+pub fn entries_named(entries: Entries, name: &str, date_range: Option<&DateRange>) -> Entries {
+  let closest = entries
+    .iter()
+    .filter_map(|entry| Some((name_distance(name, &entry.board_name)?, &entry.board_name)))
+    .min()
+    .map(|(_, board_name)| board_name.to_lowercase());
+
+  let closest = match closest {
+    Some(closest) => closest,
+    None => return vec![],
+  };
+  let mut entries: Entries = entries
+    .into_iter()
+    .filter(|entry| entry.board_name.to_lowercase() == closest)
+    .filter(|entry| match date_range {
+      Some(range) => range.start <= entry.time_stamp && entry.time_stamp <= range.end,
+      None => true,
+    })
+    .collect();
+  entries.sort();
+  entries
+}
+
 #[async_trait]
-pub trait Database {
+pub trait Database: Send + Sync {
   // May mutate self
   async fn add_entry(&self, entry: Entry) -> Result<()>;
+  /// Saves several entries at once. Databases that can write in batches override this to cut down
+  /// on round trips, the rest save one entry at a time.
+  async fn add_entries(&self, entries: &[Entry]) -> Result<()> {
+    for entry in entries {
+      self.add_entry(entry.clone()).await?;
+    }
+    Ok(())
+  }
   async fn all_entries(&self) -> Result<Option<Entries>>;
   async fn get_entry(&self, board_name: String, time_stamp: i64) -> Result<Option<Entry>>;
   async fn query_entries(
@@ -171,6 +403,127 @@ pub trait Database {
     board_name: String,
     date_range: Option<DateRange>,
   ) -> Result<Option<Entries>>;
+  /// Removes the entries saved for board_name at each of time_stamps
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()>;
+  /// Finds the entries for the board whose saved name best matches name. This reads every entry
+  /// in the database, so prefer query_entries when the board id is known.
+  async fn query_entries_by_name(
+    &self,
+    name: String,
+    date_range: Option<DateRange>,
+  ) -> Result<Option<Entries>> {
+    let entries = self.all_entries().await?.unwrap_or_default();
+    let entries = entries_named(entries, &name, date_range.as_ref());
+
+    match entries.is_empty() {
+      true => Ok(None),
+      false => Ok(Some(entries)),
+    }
+  }
 
   fn what_type(&self) -> String;
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(time_stamp: i64, tags: &[&str]) -> Entry {
+    Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp,
+      decks: vec![],
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      cards: vec![],
+    }
+  }
+
+  #[test]
+  fn it_finds_the_latest_tagged_entry() {
+    let entries = vec![
+      entry(1, &["sprint-1-start"]),
+      entry(2, &[]),
+      entry(3, &["sprint-1-end", "sprint-2-start"]),
+    ];
+
+    assert_eq!(
+      find_entry_at(&entries, "tag:sprint-2-start", &Timezone::Utc)
+        .unwrap()
+        .map(|entry| entry.time_stamp),
+      Some(3)
+    );
+    assert_eq!(
+      find_entry_at(&entries, "tag:sprint-3-start", &Timezone::Utc).unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn it_finds_the_last_entry_on_a_day() {
+    let entries = vec![entry(1, &[]), entry(86399, &[]), entry(86400, &[])];
+
+    assert_eq!(
+      find_entry_at(&entries, "1970-01-01", &Timezone::Utc)
+        .unwrap()
+        .map(|entry| entry.time_stamp),
+      Some(86399)
+    );
+    assert!(find_entry_at(&entries, "not-a-date", &Timezone::Utc).is_err());
+
+    // 86400 is still Jan 1st in Edmonton
+    let edmonton: Timezone = "America/Edmonton".parse().unwrap();
+    assert_eq!(
+      find_entry_at(&entries, "1970-01-01", &edmonton)
+        .unwrap()
+        .map(|entry| entry.time_stamp),
+      Some(86400)
+    );
+  }
+
+  #[test]
+  fn it_finds_the_latest_entry_or_one_from_days_ago() {
+    let entries = vec![entry(1, &[]), entry(86400, &[])];
+
+    let time_stamp = |at: &str| {
+      find_entry_at(&entries, at, &Timezone::Utc)
+        .unwrap()
+        .map(|entry| entry.time_stamp)
+    };
+    assert_eq!(time_stamp("latest"), Some(86400));
+    assert_eq!(time_stamp("0-days-ago"), Some(86400));
+    assert!(find_entry_at(&entries, "a-few-days-ago", &Timezone::Utc).is_err());
+  }
+
+  #[test]
+  fn it_finds_entries_by_a_close_board_name() {
+    let named = |board_id: &str, board_name: &str, time_stamp: i64| Entry {
+      board_id: board_id.to_string(),
+      board_name: board_name.to_string(),
+      ..entry(time_stamp, &[])
+    };
+    let entries = vec![
+      named("new", "Team Board", 3),
+      named("old", "team board", 1),
+      named("other", "Team Boardroom", 2),
+      named("legacy", "", 0),
+    ];
+
+    let found = |name: &str| -> Vec<(String, i64)> {
+      entries_named(entries.clone(), name, None)
+        .into_iter()
+        .map(|entry| (entry.board_id, entry.time_stamp))
+        .collect()
+    };
+    assert_eq!(
+      found("TEAM BOARD"),
+      vec![("old".to_string(), 1), ("new".to_string(), 3)]
+    );
+    assert_eq!(
+      found("Taem Board"),
+      vec![("old".to_string(), 1), ("new".to_string(), 3)]
+    );
+    assert_eq!(found("boardroom"), vec![("other".to_string(), 2)]);
+    assert!(found("Roadmap").is_empty());
+  }
+}
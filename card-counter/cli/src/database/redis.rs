@@ -0,0 +1,198 @@
+use crate::{
+  database::{config::Config, Database, DateRange, Entries, Entry},
+  errors::*,
+};
+
+use async_trait::async_trait;
+use redis::{aio::MultiplexedConnection, AsyncCommands, AsyncIter};
+use std::env;
+
+/// Stores each board's entries in a sorted set, <prefix>:<board_id>, scored by their timestamp
+/// so a date range can be read back with a single ZRANGEBYSCORE.
+pub struct Redis {
+  connection: MultiplexedConnection,
+  prefix: String,
+}
+
+fn board_key(prefix: &str, board_id: &str) -> String {
+  format!("{}:{}", prefix, board_id)
+}
+
+/// The board a sorted set holds the entries for
+fn board_id_from_key(prefix: &str, key: &str) -> Option<String> {
+  key
+    .strip_prefix(prefix)?
+    .strip_prefix(':')
+    .filter(|board_id| !board_id.is_empty())
+    .map(String::from)
+}
+
+/// Entries are stored as JSON, erroring on the first member that can't be read as one rather
+/// than leaving it out of burndowns without a word
+fn parse_entries(members: Vec<String>) -> Result<Entries> {
+  members
+    .iter()
+    .map(|member| {
+      serde_json::from_str(member)
+        .wrap_err_with(|| format!("Unable to read {} as an entry", member))
+    })
+    .collect()
+}
+
+impl Redis {
+  pub async fn init(config: &Config) -> Result<Self> {
+    let settings = config.redis.clone().unwrap_or_default();
+    let url = settings
+      .url
+      .or_else(|| env::var("REDIS_URL").ok())
+      .unwrap_or_else(|| "redis://127.0.0.1/".to_string());
+
+    let client =
+      redis::Client::open(url.as_str()).wrap_err_with(|| format!("Invalid Redis URL {}", url))?;
+    let connection = client
+      .get_multiplexed_tokio_connection()
+      .await
+      .wrap_err_with(|| format!("Unable to connect to Redis at {}", url))?;
+
+    Ok(Redis {
+      connection,
+      prefix: settings.prefix,
+    })
+  }
+
+  async fn range(&self, board_id: &str, start: i64, end: i64) -> Result<Entries> {
+    let members: Vec<String> = self
+      .connection
+      .clone()
+      .zrangebyscore(board_key(&self.prefix, board_id), start, end)
+      .await
+      .wrap_err_with(|| format!("Unable to read entries for {} from Redis", board_id))?;
+
+    parse_entries(members).wrap_err_with(|| format!("Redis has a bad entry for {}", board_id))
+  }
+
+  async fn board_ids(&self) -> Result<Vec<String>> {
+    let mut connection = self.connection.clone();
+    let mut keys: AsyncIter<String> = connection
+      .scan_match(format!("{}:*", self.prefix))
+      .await
+      .wrap_err_with(|| "Unable to list boards in Redis")?;
+
+    let mut board_ids = vec![];
+    while let Some(key) = keys.next_item().await {
+      if let Some(board_id) = board_id_from_key(&self.prefix, &key) {
+        board_ids.push(board_id);
+      }
+    }
+    Ok(board_ids)
+  }
+}
+
+#[async_trait]
+impl Database for Redis {
+  /// Replaces any entry already saved for the board at the same time
+  async fn add_entry(&self, entry: Entry) -> Result<()> {
+    let key = board_key(&self.prefix, &entry.board_id);
+    let member = serde_json::to_string(&entry).wrap_err_with(|| "Unable to parse entry")?;
+
+    redis::pipe()
+      .atomic()
+      .zrembyscore(&key, entry.time_stamp, entry.time_stamp)
+      .ignore()
+      .zadd(&key, member, entry.time_stamp)
+      .ignore()
+      .query_async::<_, ()>(&mut self.connection.clone())
+      .await
+      .wrap_err_with(|| format!("Unable to save entry for {} to Redis", entry.board_id))
+  }
+
+  async fn all_entries(&self) -> Result<Option<Entries>> {
+    let mut entries = vec![];
+    for board_id in self.board_ids().await? {
+      entries.extend(self.range(&board_id, i64::MIN, i64::MAX).await?);
+    }
+
+    Ok(Some(entries))
+  }
+
+  async fn get_entry(&self, board_name: String, time_stamp: i64) -> Result<Option<Entry>> {
+    Ok(
+      self
+        .range(&board_name, time_stamp, time_stamp)
+        .await?
+        .into_iter()
+        .next(),
+    )
+  }
+
+  async fn query_entries(
+    &self,
+    board_name: String,
+    date_range: Option<DateRange>,
+  ) -> Result<Option<Entries>> {
+    let (start, end) = match date_range {
+      Some(range) => (range.start, range.end),
+      None => (i64::MIN, i64::MAX),
+    };
+    let entries = self.range(&board_name, start, end).await?;
+
+    match entries.is_empty() {
+      true => Ok(None),
+      false => Ok(Some(entries)),
+    }
+  }
+
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    let key = board_key(&self.prefix, &board_name);
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for time_stamp in time_stamps {
+      pipe.zrembyscore(&key, time_stamp, time_stamp).ignore();
+    }
+
+    pipe
+      .query_async::<_, ()>(&mut self.connection.clone())
+      .await
+      .wrap_err_with(|| format!("Unable to remove entries for {} from Redis", board_name))
+  }
+
+  fn what_type(&self) -> String {
+    "Redis".into()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_keys_sorted_sets_by_board() {
+    assert_eq!(board_key("card-counter", "board"), "card-counter:board");
+    assert_eq!(
+      board_id_from_key("card-counter", "card-counter:board"),
+      Some("board".to_string())
+    );
+    assert_eq!(board_id_from_key("card-counter", "card-counter:"), None);
+    assert_eq!(board_id_from_key("card-counter", "other:board"), None);
+  }
+
+  #[test]
+  fn it_reads_entries_and_names_bad_members() {
+    let entry = Entry {
+      board_id: "board".to_string(),
+      time_stamp: 10,
+      ..Entry::default()
+    };
+    let entries = parse_entries(vec![serde_json::to_string(&entry).unwrap()]).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].time_stamp, 10);
+    assert_eq!(entries[0].board_id, "board");
+
+    let err = parse_entries(vec![
+      serde_json::to_string(&entry).unwrap(),
+      "not an entry".to_string(),
+    ])
+    .unwrap_err();
+    assert!(format!("{:#}", err).contains("not an entry"));
+  }
+}
@@ -0,0 +1,296 @@
+use crate::{
+  database::{
+    config::{Config, S3Config},
+    json::{LocalEntry, LocalRecord},
+    Database, DateRange, Entries, Entry,
+  },
+  errors::*,
+};
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{
+  GetObjectError, GetObjectRequest, ListObjectsV2Request, PutObjectRequest, S3Client, S3 as _,
+};
+use std::{env, str::FromStr};
+
+/// Stores each board's entries as a single JSON object, <prefix>/<board_id>.json, in the same shape
+/// the local database keeps them in. Saving reads the object, adds the entry, and writes it back,
+/// so two entries saved for the same board at the same moment can overwrite each other.
+pub struct S3 {
+  client: S3Client,
+  bucket: String,
+  prefix: String,
+}
+
+fn object_key(prefix: &str, board_id: &str) -> String {
+  match prefix.trim_matches('/') {
+    "" => format!("{}.json", board_id),
+    prefix => format!("{}/{}.json", prefix, board_id),
+  }
+}
+
+/// The board an object holds the entries for, if the key is one of ours
+fn board_id_from_key(prefix: &str, key: &str) -> Option<String> {
+  let name = match prefix.trim_matches('/') {
+    "" => key,
+    prefix => key.strip_prefix(prefix)?.strip_prefix('/')?,
+  };
+  name
+    .strip_suffix(".json")
+    .filter(|board_id| !board_id.is_empty() && !board_id.contains('/'))
+    .map(String::from)
+}
+
+fn region(settings: &S3Config) -> Result<Region> {
+  match (&settings.endpoint, &settings.region) {
+    (Some(endpoint), region) => Ok(Region::Custom {
+      name: region.clone().unwrap_or_else(|| "us-east-1".to_string()),
+      endpoint: endpoint.clone(),
+    }),
+    (None, Some(region)) => {
+      Region::from_str(region).wrap_err_with(|| format!("Unknown AWS region {}", region))
+    }
+    (None, None) => Ok(Region::default()),
+  }
+}
+
+/// Turns a board's stored records into entries, keeping the ones within date_range
+fn board_entries(board_id: &str, board: LocalEntry, date_range: Option<&DateRange>) -> Entries {
+  let mut entries: Entries = board
+    .into_iter()
+    .filter(|(time_stamp, _)| match date_range {
+      Some(range) => range.start <= *time_stamp && *time_stamp <= range.end,
+      None => true,
+    })
+    .map(|(time_stamp, record)| record.into_entry(board_id.to_string(), time_stamp))
+    .collect();
+  entries.sort();
+  entries
+}
+
+impl S3 {
+  /// Credentials and the region come from the same places as they do for DynamoDB, the
+  /// environment or your AWS profile, unless a region or endpoint is set under s3 in your config.
+  pub async fn init(config: &Config) -> Result<Self> {
+    let settings = config.s3.clone().unwrap_or_default();
+    let bucket = match settings.bucket.is_empty() {
+      true => env::var("CARD_COUNTER_S3_BUCKET").unwrap_or_default(),
+      false => settings.bucket.clone(),
+    };
+    if bucket.is_empty() {
+      return Err(eyre!("Unable to find a bucket to store entries in. Please set CARD_COUNTER_S3_BUCKET, or bucket under s3 in your config."));
+    }
+
+    Ok(S3 {
+      client: S3Client::new(region(&settings)?),
+      bucket,
+      prefix: settings.prefix,
+    })
+  }
+
+  /// Downloads every entry saved for board_id, a board without an object has no entries yet
+  async fn get_board(&self, board_id: &str) -> Result<LocalEntry> {
+    let key = object_key(&self.prefix, board_id);
+    let response = self
+      .client
+      .get_object(GetObjectRequest {
+        bucket: self.bucket.clone(),
+        key: key.clone(),
+        ..Default::default()
+      })
+      .await;
+
+    let object = match response {
+      Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => return Ok(LocalEntry::new()),
+      // Missing objects aren't always reported as NoSuchKey
+      Err(RusotoError::Unknown(ref response)) if response.status.as_u16() == 404 => {
+        return Ok(LocalEntry::new())
+      }
+      response => response.wrap_err_with(|| format!("Unable to download {} from S3", key))?,
+    };
+
+    let body: Vec<u8> = match object.body {
+      Some(body) => body
+        .map_ok(|bytes| bytes.to_vec())
+        .try_concat()
+        .await
+        .wrap_err_with(|| format!("Unable to download {} from S3", key))?,
+      None => vec![],
+    };
+    if body.is_empty() {
+      return Ok(LocalEntry::new());
+    }
+
+    serde_json::from_slice(&body)
+      .wrap_err_with(|| format!("Unable to parse {} as a card-counter database", key))
+  }
+
+  async fn put_board(&self, board_id: &str, board: &LocalEntry) -> Result<()> {
+    let key = object_key(&self.prefix, board_id);
+    let body = serde_json::to_vec(board).wrap_err_with(|| "Unable to parse database")?;
+
+    self
+      .client
+      .put_object(PutObjectRequest {
+        bucket: self.bucket.clone(),
+        key: key.clone(),
+        body: Some(body.into()),
+        content_type: Some("application/json".to_string()),
+        ..Default::default()
+      })
+      .await
+      .wrap_err_with(|| format!("Unable to upload {} to S3", key))?;
+
+    Ok(())
+  }
+
+  /// Lists every board with entries in the bucket, following continuation tokens
+  async fn board_ids(&self) -> Result<Vec<String>> {
+    let prefix = match self.prefix.trim_matches('/') {
+      "" => None,
+      prefix => Some(format!("{}/", prefix)),
+    };
+    let mut board_ids = vec![];
+    let mut continuation_token: Option<String> = None;
+
+    loop {
+      let listing = self
+        .client
+        .list_objects_v2(ListObjectsV2Request {
+          bucket: self.bucket.clone(),
+          prefix: prefix.clone(),
+          continuation_token: continuation_token.clone(),
+          ..Default::default()
+        })
+        .await
+        .wrap_err_with(|| format!("Unable to list objects in bucket {}", self.bucket))?;
+
+      board_ids.extend(
+        listing
+          .contents
+          .unwrap_or_default()
+          .into_iter()
+          .filter_map(|object| object.key)
+          .filter_map(|key| board_id_from_key(&self.prefix, &key)),
+      );
+
+      match (listing.is_truncated, listing.next_continuation_token) {
+        (Some(true), Some(token)) => continuation_token = Some(token),
+        _ => return Ok(board_ids),
+      }
+    }
+  }
+}
+
+#[async_trait]
+impl Database for S3 {
+  async fn add_entry(&self, entry: Entry) -> Result<()> {
+    let board_id = entry.board_id.clone();
+    let mut board = self.get_board(&board_id).await?;
+    board.insert(entry.time_stamp, LocalRecord::from_entry(entry));
+
+    self.put_board(&board_id, &board).await
+  }
+
+  async fn all_entries(&self) -> Result<Option<Entries>> {
+    let mut entries = vec![];
+    for board_id in self.board_ids().await? {
+      let board = self.get_board(&board_id).await?;
+      entries.extend(board_entries(&board_id, board, None));
+    }
+
+    Ok(Some(entries))
+  }
+
+  async fn get_entry(&self, board_name: String, time_stamp: i64) -> Result<Option<Entry>> {
+    Ok(
+      self
+        .get_board(&board_name)
+        .await?
+        .remove(&time_stamp)
+        .map(|record| record.into_entry(board_name, time_stamp)),
+    )
+  }
+
+  async fn query_entries(
+    &self,
+    board_name: String,
+    date_range: Option<DateRange>,
+  ) -> Result<Option<Entries>> {
+    let board = self.get_board(&board_name).await?;
+    if board.is_empty() {
+      return Ok(None);
+    }
+
+    Ok(Some(board_entries(&board_name, board, date_range.as_ref())))
+  }
+
+  async fn delete_entries(&self, board_name: String, time_stamps: Vec<i64>) -> Result<()> {
+    let mut board = self.get_board(&board_name).await?;
+    for time_stamp in time_stamps {
+      board.remove(&time_stamp);
+    }
+
+    self.put_board(&board_name, &board).await
+  }
+
+  fn what_type(&self) -> String {
+    "S3".into()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_names_objects_after_boards() {
+    assert_eq!(
+      object_key("card-counter", "board"),
+      "card-counter/board.json"
+    );
+    assert_eq!(object_key("", "board"), "board.json");
+
+    assert_eq!(
+      board_id_from_key("card-counter/", "card-counter/board.json"),
+      Some("board".to_string())
+    );
+    assert_eq!(
+      board_id_from_key("", "board.json"),
+      Some("board".to_string())
+    );
+    assert_eq!(board_id_from_key("card-counter", "other/board.json"), None);
+    assert_eq!(
+      board_id_from_key("card-counter", "card-counter/notes.txt"),
+      None
+    );
+  }
+
+  #[test]
+  fn it_reads_entries_within_a_date_range() {
+    let board: LocalEntry = vec![
+      (30, LocalRecord::Decks(vec![])),
+      (10, LocalRecord::Decks(vec![])),
+      (20, LocalRecord::Decks(vec![])),
+    ]
+    .into_iter()
+    .collect();
+
+    let time_stamps =
+      |entries: Entries| -> Vec<i64> { entries.iter().map(|entry| entry.time_stamp).collect() };
+    assert_eq!(
+      time_stamps(board_entries("board", board.clone(), None)),
+      vec![10, 20, 30]
+    );
+    assert_eq!(
+      time_stamps(board_entries(
+        "board",
+        board,
+        Some(&DateRange { start: 15, end: 30 })
+      )),
+      vec![20, 30]
+    );
+  }
+}
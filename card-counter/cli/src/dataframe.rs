@@ -0,0 +1,209 @@
+//! Columnar views of saved entries and burndowns for use in notebooks and other analytics
+//! tools, so history doesn't need to be round tripped through csv or json.
+//!
+//! Only available with the `dataframe` feature enabled.
+use crate::{commands::burndown::Burndown, database::Entry, errors::*};
+
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Int32Array, RecordBatch, TimestampSecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use polars::prelude::*;
+
+pub use crate::commands::export::{entries_schema, entries_to_record_batch};
+
+/// The columns for each day in a burndown
+pub fn burndown_schema() -> Schema {
+  Schema::new(vec![
+    Field::new(
+      "date",
+      DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+      false,
+    ),
+    Field::new("incomplete", DataType::Int32, false),
+    Field::new("complete", DataType::Int32, false),
+  ])
+}
+
+/// Converts a burndown into a RecordBatch with one row per day
+pub fn burndown_to_record_batch(burndown: &Burndown) -> Result<RecordBatch> {
+  let columns: Vec<ArrayRef> = vec![
+    Arc::new(
+      TimestampSecondArray::from_iter_values(
+        burndown.0.iter().map(|(date, _, _)| date.timestamp()),
+      )
+      .with_timezone("UTC"),
+    ),
+    Arc::new(Int32Array::from_iter_values(
+      burndown.0.iter().map(|(_, incomplete, _)| *incomplete),
+    )),
+    Arc::new(Int32Array::from_iter_values(
+      burndown.0.iter().map(|(_, _, complete)| *complete),
+    )),
+  ];
+
+  RecordBatch::try_new(Arc::new(burndown_schema()), columns)
+    .wrap_err_with(|| "Unable to build columns from burndown")
+}
+
+// Entries are saved to the second, but polars' smallest common datetime unit is milliseconds
+fn timestamps(name: &str, seconds: Vec<i64>) -> Result<Series> {
+  let milliseconds: Vec<i64> = seconds.iter().map(|second| second * 1000).collect();
+  Series::new(name, milliseconds)
+    .cast(&polars::prelude::DataType::Datetime(
+      polars::prelude::TimeUnit::Milliseconds,
+      Some("UTC".into()),
+    ))
+    .wrap_err_with(|| format!("Unable to convert {} into dates", name))
+}
+
+/// Converts entries into a DataFrame with one row per deck, using the same columns as
+/// `entries_schema`.
+pub fn entries_to_dataframe(entries: &[Entry]) -> Result<DataFrame> {
+  let rows: Vec<(&Entry, &crate::score::Deck)> = entries
+    .iter()
+    .flat_map(|entry| entry.decks.iter().map(move |deck| (entry, deck)))
+    .collect();
+
+  DataFrame::new(vec![
+    Series::new(
+      "board",
+      rows
+        .iter()
+        .map(|(entry, _)| entry.board_id.as_str())
+        .collect::<Vec<&str>>(),
+    ),
+    timestamps(
+      "timestamp",
+      rows.iter().map(|(entry, _)| entry.time_stamp).collect(),
+    )?,
+    Series::new(
+      "list",
+      rows
+        .iter()
+        .map(|(_, deck)| deck.list_name.as_str())
+        .collect::<Vec<&str>>(),
+    ),
+    Series::new(
+      "score",
+      rows
+        .iter()
+        .map(|(_, deck)| deck.score)
+        .collect::<Vec<i32>>(),
+    ),
+    Series::new(
+      "estimated",
+      rows
+        .iter()
+        .map(|(_, deck)| deck.estimated)
+        .collect::<Vec<i32>>(),
+    ),
+    Series::new(
+      "unscored",
+      rows
+        .iter()
+        .map(|(_, deck)| deck.unscored)
+        .collect::<Vec<i32>>(),
+    ),
+    Series::new(
+      "size",
+      rows
+        .iter()
+        .map(|(_, deck)| deck.size as u64)
+        .collect::<Vec<u64>>(),
+    ),
+  ])
+  .wrap_err_with(|| "Unable to build a DataFrame from entries")
+}
+
+/// Converts a burndown into a DataFrame with one row per day, using the same columns as
+/// `burndown_schema`.
+pub fn burndown_to_dataframe(burndown: &Burndown) -> Result<DataFrame> {
+  DataFrame::new(vec![
+    timestamps(
+      "date",
+      burndown
+        .0
+        .iter()
+        .map(|(date, _, _)| date.timestamp())
+        .collect(),
+    )?,
+    Series::new(
+      "incomplete",
+      burndown
+        .0
+        .iter()
+        .map(|(_, incomplete, _)| *incomplete)
+        .collect::<Vec<i32>>(),
+    ),
+    Series::new(
+      "complete",
+      burndown
+        .0
+        .iter()
+        .map(|(_, _, complete)| *complete)
+        .collect::<Vec<i32>>(),
+    ),
+  ])
+  .wrap_err_with(|| "Unable to build a DataFrame from burndown")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    commands::burndown::ListMatcher,
+    score::{Deck, ListFilter},
+  };
+
+  #[test]
+  fn it_converts_burndowns_and_entries() {
+    let entry = |time_stamp, done| Entry {
+      board_id: "board".to_string(),
+      board_name: String::new(),
+      time_stamp,
+      tags: vec![],
+      cards: vec![],
+      decks: vec![
+        Deck {
+          list_name: "Todo".to_string(),
+          size: 1,
+          score: 10 - done,
+          unscored: 0,
+          estimated: 10 - done,
+        },
+        Deck {
+          list_name: "Done".to_string(),
+          size: 1,
+          score: done,
+          unscored: 0,
+          estimated: done,
+        },
+      ],
+    };
+    let entries = vec![entry(1, 2), entry(86401, 5)];
+    let burndown = Burndown::calculate_burndown(
+      &entries,
+      &ListFilter::default(),
+      &ListMatcher::default(),
+      None,
+    );
+
+    let batch = burndown_to_record_batch(&burndown).unwrap();
+    assert_eq!(batch.num_rows(), 2);
+
+    let frame = burndown_to_dataframe(&burndown).unwrap();
+    assert_eq!(frame.shape(), (2, 3));
+    let complete: Vec<Option<i32>> = frame
+      .column("complete")
+      .unwrap()
+      .i32()
+      .unwrap()
+      .into_iter()
+      .collect();
+    assert_eq!(complete, vec![Some(2), Some(5)]);
+
+    let frame = entries_to_dataframe(&entries).unwrap();
+    assert_eq!(frame.shape(), (4, 7));
+  }
+}
@@ -4,6 +4,8 @@ use std::{error::Error, fmt, write};
 #[derive(Debug)]
 pub enum AuthError {
   Trello(String),
+  // The token was rejected after the expiration the user picked for it had passed
+  TrelloExpired { expiration: String, url: String },
   Jira(String),
 }
 impl Error for AuthError {}
@@ -16,6 +18,12 @@ impl fmt::Display for AuthError {
 Unauthorized request to Trello API
 Please regenerate your Trello API token
 https://trello.com/1/authorize?expiration=1day&name=card-counter&scope=read&response_type=token&key={}", token)
+    ,
+      AuthError::TrelloExpired { expiration, url } =>
+        write!(f, "401 Unauthorized
+Your Trello token was set to expire after {} and has lapsed
+Please generate a new token and run `card-counter config` to save it
+{}", expiration, url)
     ,
       AuthError::Jira(_info) => write!(f, "401 Unauthorized
 Unauthorized request to Jira API")
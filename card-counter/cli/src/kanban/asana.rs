@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+
+use crate::{
+  database::config::{self, Config},
+  errors::*,
+  kanban::{name_with_points, parse_due_date, Board, Card, CardHistory, Kanban, List, Transition},
+  score::Scorer,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dialoguer::Select;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+static ASANA_API: &str = "https://app.asana.com/api/1.0";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AsanaAuth {
+  pub token: String,
+  // The number custom field story points are read from, for tasks without points in their name
+  pub points_field: String,
+}
+
+impl Default for AsanaAuth {
+  fn default() -> AsanaAuth {
+    AsanaAuth {
+      token: "".to_string(),
+      points_field: "Story Points".to_string(),
+    }
+  }
+}
+
+/// Asana wraps every response in data, with an offset for the next page when there is one
+#[derive(Deserialize, Debug)]
+struct Page<T> {
+  data: Vec<T>,
+  next_page: Option<NextPage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NextPage {
+  offset: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Single<T> {
+  data: T,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Compact {
+  gid: String,
+  #[serde(default)]
+  name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Membership {
+  project: Option<Compact>,
+  section: Option<Compact>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CustomField {
+  name: String,
+  number_value: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Task {
+  gid: String,
+  name: String,
+  assignee: Option<Compact>,
+  #[serde(default)]
+  tags: Vec<Compact>,
+  #[serde(default)]
+  memberships: Vec<Membership>,
+  #[serde(default)]
+  custom_fields: Vec<CustomField>,
+  created_at: Option<DateTime<Utc>>,
+  // Tasks are due on a day, or at a time when one was set
+  #[serde(default)]
+  due_on: Option<String>,
+  #[serde(default)]
+  due_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Story {
+  created_at: DateTime<Utc>,
+  resource_subtype: String,
+  old_section: Option<Compact>,
+  new_section: Option<Compact>,
+}
+
+pub struct AsanaClient {
+  client: reqwest::Client,
+  auth: AsanaAuth,
+  scorer: Scorer,
+}
+
+impl From<Compact> for Board {
+  fn from(project: Compact) -> Self {
+    Board {
+      id: project.gid,
+      name: project.name,
+    }
+  }
+}
+
+impl Task {
+  /// The section the task is in for project_id, tasks can belong to more than one project
+  fn section(&self, project_id: &str) -> Option<&Compact> {
+    self
+      .memberships
+      .iter()
+      .find(|membership| {
+        membership
+          .project
+          .as_ref()
+          .map(|project| project.gid == project_id)
+          .unwrap_or(false)
+      })
+      .and_then(|membership| membership.section.as_ref())
+  }
+
+  fn points(&self, points_field: &str) -> Option<f64> {
+    self
+      .custom_fields
+      .iter()
+      .find(|field| field.name == points_field)
+      .and_then(|field| field.number_value)
+  }
+
+  fn into_card(self, project_id: &str, points_field: &str, scorer: &Scorer) -> Card {
+    let parent_list = self
+      .section(project_id)
+      .map(|section| section.gid.clone())
+      .unwrap_or_default();
+    let points = self.points(points_field);
+    let due = self
+      .due_at
+      .as_deref()
+      .or(self.due_on.as_deref())
+      .and_then(parse_due_date);
+
+    Card {
+      name: name_with_points(scorer, self.name, points),
+      parent_list,
+      members: self.assignee.into_iter().map(|user| user.name).collect(),
+      labels: self.tags.into_iter().map(|tag| tag.name).collect(),
+      due,
+    }
+  }
+
+  /// Rebuilds the sections a task has moved through from its stories
+  fn into_history(self, project_id: &str, stories: &[Story]) -> CardHistory {
+    let mut transitions: Vec<Transition> = stories
+      .iter()
+      .filter(|story| story.resource_subtype == "section_changed")
+      .filter_map(|story| {
+        story.new_section.as_ref().map(|section| Transition {
+          from: story.old_section.as_ref().map(|old| old.name.clone()),
+          to: section.name.clone(),
+          at: story.created_at,
+        })
+      })
+      .collect();
+    transitions.sort_by_key(|transition| transition.at);
+
+    let current_list = self
+      .section(project_id)
+      .map(|section| section.name.clone())
+      .unwrap_or_default();
+
+    CardHistory {
+      id: self.gid,
+      name: self.name,
+      created: self
+        .created_at
+        .or_else(|| transitions.first().map(|transition| transition.at))
+        .unwrap_or_else(Utc::now),
+      current_list,
+      members: self.assignee.into_iter().map(|user| user.name).collect(),
+      transitions,
+    }
+  }
+}
+
+impl AsanaClient {
+  pub fn init(config: &Config) -> Result<Self> {
+    match &config.kanban {
+      config::KanbanBoard::Asana(auth) => Ok(AsanaClient {
+        client: reqwest::Client::new(),
+        auth: auth.to_owned(),
+        scorer: config.scorer()?,
+      }),
+      _ => Err(eyre!(
+        "Unable to find information needed to authenticate with Asana API."
+      )),
+    }
+  }
+
+  async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T> {
+    let response = self
+      .client
+      .get(url)
+      .bearer_auth(&self.auth.token)
+      .send()
+      .await?;
+
+    if let Err(err) = response.error_for_status_ref() {
+      return match err.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) => Err(eyre!(
+          "Asana rejected your personal access token. Generate a new one at https://app.asana.com/0/my-apps"
+        )),
+        _ => Err(eyre!(err.to_string())),
+      };
+    }
+
+    Ok(
+      response
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Asana".to_string()))?,
+    )
+  }
+
+  /// Follows Asana's pagination until every item for route has been fetched
+  async fn get_all<T: DeserializeOwned>(
+    &self,
+    route: &str,
+    params: &[(&str, &str)],
+  ) -> Result<Vec<T>> {
+    let mut items = vec![];
+    let mut offset: Option<String> = None;
+    loop {
+      let mut query = params.to_vec();
+      query.push(("limit", "100"));
+      if let Some(offset) = &offset {
+        query.push(("offset", offset));
+      }
+      let url = Url::parse_with_params(&format!("{}/{}", ASANA_API, route), &query)
+        .wrap_err_with(|| format!("Unable to build Asana URL for {}", route))?;
+
+      let page: Page<T> = self.get_json(url).await?;
+      items.extend(page.data);
+      match page.next_page {
+        Some(next) => offset = Some(next.offset),
+        None => return Ok(items),
+      }
+    }
+  }
+
+  async fn get_tasks(&self, project_id: &str, fields: &str) -> Result<Vec<Task>> {
+    self
+      .get_all(
+        &format!("projects/{}/tasks", project_id),
+        &[("opt_fields", fields)],
+      )
+      .await
+  }
+
+  /// Every unarchived project in every workspace, labeled with the workspace it's in
+  async fn get_projects(&self) -> Result<Vec<(String, Board)>> {
+    let workspaces: Vec<Compact> = self.get_all("workspaces", &[]).await?;
+    let mut boards = vec![];
+    for workspace in workspaces {
+      let projects: Vec<Compact> = self
+        .get_all(
+          "projects",
+          &[("workspace", &workspace.gid), ("archived", "false")],
+        )
+        .await?;
+      for project in projects {
+        boards.push((
+          format!("{} / {}", workspace.name, project.name),
+          project.into(),
+        ));
+      }
+    }
+    Ok(boards)
+  }
+}
+
+#[async_trait]
+impl Kanban for AsanaClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    let url = Url::parse(&format!("{}/projects/{}", ASANA_API, board_id))
+      .wrap_err_with(|| format!("Unable to build Asana URL for {}", board_id))?;
+    let project: Single<Compact> = self.get_json(url).await?;
+    Ok(project.data.into())
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    Ok(
+      self
+        .get_projects()
+        .await?
+        .into_iter()
+        .map(|(_, board)| board)
+        .collect(),
+    )
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    let boards: HashMap<String, Board> = self.get_projects().await?.into_iter().collect();
+
+    let mut board_names: Vec<String> = boards.keys().cloned().collect();
+    board_names.sort();
+    let name_index: usize = Select::new()
+      .with_prompt("Select a project: ")
+      .items(&board_names)
+      .default(0)
+      .max_length(15)
+      .interact()
+      .wrap_err_with(|| "There was an error while trying to select a project.")?;
+
+    Ok(
+      boards
+        .get(&board_names[name_index])
+        .ok_or_else(|| eyre!("There was an error fetching selected project"))?
+        .to_owned(),
+    )
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    let sections: Vec<Compact> = self
+      .get_all(&format!("projects/{}/sections", board_id), &[])
+      .await?;
+
+    Ok(
+      sections
+        .into_iter()
+        .map(|section| List {
+          id: section.gid,
+          name: section.name,
+          board_id: board_id.to_string(),
+        })
+        .collect(),
+    )
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    let tasks = self
+      .get_tasks(
+        board_id,
+        "name,assignee.name,tags.name,memberships.project,memberships.section,custom_fields.name,custom_fields.number_value,due_on,due_at",
+      )
+      .await?;
+
+    Ok(
+      tasks
+        .into_iter()
+        .map(|task| task.into_card(board_id, &self.auth.points_field, &self.scorer))
+        .collect(),
+    )
+  }
+
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let tasks = self
+      .get_tasks(
+        board_id,
+        "name,assignee.name,memberships.project,memberships.section.name,created_at",
+      )
+      .await?;
+
+    let mut histories = vec![];
+    for task in tasks {
+      let stories: Vec<Story> = self
+        .get_all(
+          &format!("tasks/{}/stories", task.gid),
+          &[(
+            "opt_fields",
+            "created_at,resource_subtype,old_section.name,new_section.name",
+          )],
+        )
+        .await?;
+      histories.push(task.into_history(board_id, &stories));
+    }
+    Ok(histories)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn task() -> Task {
+    serde_json::from_str(
+      r#"{
+        "gid": "1",
+        "name": "Add login page",
+        "assignee": {"gid": "10", "name": "Justin"},
+        "tags": [{"gid": "20", "name": "frontend"}],
+        "memberships": [
+          {"project": {"gid": "other"}, "section": {"gid": "s9", "name": "Backlog"}},
+          {"project": {"gid": "project"}, "section": {"gid": "s2", "name": "Done"}}
+        ],
+        "custom_fields": [
+          {"name": "Priority", "number_value": 1},
+          {"name": "Story Points", "number_value": 3}
+        ],
+        "created_at": "2021-03-01T09:00:00.000Z"
+      }"#,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn it_turns_tasks_into_cards() {
+    let card = task().into_card("project", "Story Points", &Scorer::default());
+    assert_eq!(card.name, "(3) Add login page");
+    assert_eq!(card.parent_list, "s2");
+    assert_eq!(card.members, vec!["Justin".to_string()]);
+    assert_eq!(card.labels, vec!["frontend".to_string()]);
+
+    let card = task().into_card("project", "Estimate", &Scorer::default());
+    assert_eq!(card.name, "Add login page");
+  }
+
+  #[test]
+  fn it_rebuilds_section_changes_from_stories() {
+    let section = |name: &str| {
+      Some(Compact {
+        gid: name.to_lowercase(),
+        name: name.to_string(),
+      })
+    };
+    let stories = vec![
+      Story {
+        created_at: Utc.with_ymd_and_hms(2021, 3, 4, 9, 0, 0).unwrap(),
+        resource_subtype: "section_changed".to_string(),
+        old_section: section("Doing"),
+        new_section: section("Done"),
+      },
+      Story {
+        created_at: Utc.with_ymd_and_hms(2021, 3, 3, 9, 0, 0).unwrap(),
+        resource_subtype: "comment_added".to_string(),
+        old_section: None,
+        new_section: None,
+      },
+      Story {
+        created_at: Utc.with_ymd_and_hms(2021, 3, 2, 9, 0, 0).unwrap(),
+        resource_subtype: "section_changed".to_string(),
+        old_section: section("To Do"),
+        new_section: section("Doing"),
+      },
+    ];
+
+    let history = task().into_history("project", &stories);
+    assert_eq!(history.current_list, "Done");
+    assert_eq!(
+      history.created,
+      Utc.with_ymd_and_hms(2021, 3, 1, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+      history.transitions,
+      vec![
+        Transition {
+          from: Some("To Do".to_string()),
+          to: "Doing".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 2, 9, 0, 0).unwrap(),
+        },
+        Transition {
+          from: Some("Doing".to_string()),
+          to: "Done".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 4, 9, 0, 0).unwrap(),
+        },
+      ]
+    );
+  }
+}
@@ -0,0 +1,64 @@
+use crate::{
+  database::config::Config,
+  errors::Result,
+  kanban::{Board, Card, CardHistory, Kanban, List},
+};
+
+use async_trait::async_trait;
+
+/// Wraps a kanban client so boards can be passed by the aliases in config's boards, and so the
+/// default_board is used instead of asking for one
+pub struct BoardAliases {
+  kanban: Box<dyn Kanban>,
+  config: Config,
+}
+
+impl BoardAliases {
+  pub fn new(kanban: Box<dyn Kanban>, config: &Config) -> Self {
+    BoardAliases {
+      kanban,
+      config: config.clone(),
+    }
+  }
+}
+
+#[async_trait]
+impl Kanban for BoardAliases {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    self.kanban.get_board(&self.config.board_id(board_id)).await
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    self.kanban.get_lists(&self.config.board_id(board_id)).await
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    self.kanban.get_cards(&self.config.board_id(board_id)).await
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    match self.config.default_board.as_deref() {
+      Some(board) => self.get_board(board).await,
+      None => self.kanban.select_board().await,
+    }
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    self.kanban.get_boards().await
+  }
+
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    self
+      .kanban
+      .get_card_histories(&self.config.board_id(board_id))
+      .await
+  }
+
+  async fn entry_key(&self, board_id: &str) -> Result<String> {
+    self.kanban.entry_key(&self.config.board_id(board_id)).await
+  }
+
+  async fn projects(&self, board_id: &str) -> Result<Vec<String>> {
+    self.kanban.projects(&self.config.board_id(board_id)).await
+  }
+}
@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use crate::{
+  database::config::{self, Config},
+  errors::*,
+  kanban::{parse_due_date, Board, Card, CardHistory, Kanban, List, Transition},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dialoguer::Select;
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+// GitLab boards always have these two lists, even though they aren't returned with the board
+static OPEN: &str = "Open";
+static CLOSED: &str = "Closed";
+// How many issues' label events are requested at once when building histories
+static LABEL_EVENT_REQUESTS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GitLabAuth {
+  pub token: String,
+  // Base URL for gitlab.com or a self-hosted instance, ex: https://gitlab.example.com
+  pub url: String,
+  // The id or path of the project whose issue boards are counted, ex: gitlab-org/gitlab
+  pub project: String,
+}
+
+impl Default for GitLabAuth {
+  fn default() -> GitLabAuth {
+    GitLabAuth {
+      token: "".to_string(),
+      url: "https://gitlab.com".to_string(),
+      project: "".to_string(),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Label {
+  name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabList {
+  id: u64,
+  label: Option<Label>,
+  position: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Milestone {
+  title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabBoard {
+  id: u64,
+  name: String,
+  #[serde(default)]
+  lists: Vec<GitLabList>,
+  // A board's scope limits which issues show up on it
+  #[serde(default)]
+  milestone: Option<Milestone>,
+  #[serde(default)]
+  labels: Vec<Label>,
+  #[serde(default)]
+  assignee: Option<User>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct User {
+  username: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Issue {
+  id: u64,
+  iid: u64,
+  title: String,
+  state: String,
+  #[serde(default)]
+  labels: Vec<String>,
+  #[serde(default)]
+  assignees: Vec<User>,
+  created_at: DateTime<Utc>,
+  closed_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  due_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LabelEvent {
+  created_at: DateTime<Utc>,
+  label: Option<Label>,
+  action: String,
+}
+
+pub struct GitLabClient {
+  client: reqwest::Client,
+  auth: GitLabAuth,
+}
+
+impl From<&GitLabBoard> for Board {
+  fn from(board: &GitLabBoard) -> Self {
+    Board {
+      id: board.id.to_string(),
+      name: board.name.clone(),
+    }
+  }
+}
+
+/// The labels that make up a board's lists, in the order they appear on the board
+fn list_labels(board: &GitLabBoard) -> Vec<String> {
+  let mut lists: Vec<&GitLabList> = board.lists.iter().collect();
+  lists.sort_by_key(|list| list.position);
+  lists
+    .iter()
+    .filter_map(|list| list.label.as_ref().map(|label| label.name.clone()))
+    .collect()
+}
+
+/// The issue filters matching a board's scope, so only the issues on the board are counted
+fn issue_scope(board: &GitLabBoard) -> Vec<(&'static str, String)> {
+  let mut scope = vec![("scope", "all".to_string())];
+  if let Some(milestone) = &board.milestone {
+    scope.push(("milestone", milestone.title.clone()));
+  }
+  if !board.labels.is_empty() {
+    let labels: Vec<&str> = board
+      .labels
+      .iter()
+      .map(|label| label.name.as_str())
+      .collect();
+    scope.push(("labels", labels.join(",")));
+  }
+  if let Some(assignee) = &board.assignee {
+    scope.push(("assignee_username", assignee.username.clone()));
+  }
+  scope
+}
+
+/// Works out which list an issue shows up in. Closed issues are always in Closed, otherwise an
+/// issue is in the first list whose label it has, or Open when it has none of them.
+fn list_for_issue(issue: &Issue, labels: &[String]) -> String {
+  if issue.state == "closed" {
+    return CLOSED.to_string();
+  }
+  labels
+    .iter()
+    .find(|label| issue.labels.contains(label))
+    .cloned()
+    .unwrap_or_else(|| OPEN.to_string())
+}
+
+fn issue_to_card(issue: &Issue, labels: &[String]) -> Card {
+  Card {
+    name: issue.title.clone(),
+    parent_list: list_for_issue(issue, labels),
+    members: issue
+      .assignees
+      .iter()
+      .map(|user| user.username.clone())
+      .collect(),
+    labels: issue.labels.clone(),
+    due: issue.due_date.as_deref().and_then(parse_due_date),
+  }
+}
+
+/// Rebuilds an issue's path across the board from the list labels added to it, and when it was
+/// closed
+fn build_history(issue: &Issue, labels: &[String], events: &[LabelEvent]) -> CardHistory {
+  let mut events: Vec<&LabelEvent> = events
+    .iter()
+    .filter(|event| event.action == "add")
+    .filter(|event| {
+      event
+        .label
+        .as_ref()
+        .map(|label| labels.contains(&label.name))
+        .unwrap_or(false)
+    })
+    .collect();
+  events.sort_by_key(|event| event.created_at);
+
+  let mut transitions = vec![];
+  let mut current = OPEN.to_string();
+  for event in events {
+    let to = event.label.as_ref().unwrap().name.clone();
+    transitions.push(Transition {
+      from: Some(current.clone()),
+      to: to.clone(),
+      at: event.created_at,
+    });
+    current = to;
+  }
+  if let Some(closed_at) = issue.closed_at {
+    transitions.push(Transition {
+      from: Some(current),
+      to: CLOSED.to_string(),
+      at: closed_at,
+    });
+  }
+
+  CardHistory {
+    id: issue.id.to_string(),
+    name: issue.title.clone(),
+    created: issue.created_at,
+    current_list: list_for_issue(issue, labels),
+    members: issue
+      .assignees
+      .iter()
+      .map(|user| user.username.clone())
+      .collect(),
+    transitions,
+  }
+}
+
+impl GitLabClient {
+  pub fn init(config: &Config) -> Self {
+    match &config.kanban {
+      config::KanbanBoard::GitLab(auth) => GitLabClient {
+        client: reqwest::Client::new(),
+        auth: auth.to_owned(),
+      },
+      _ => panic!("Unable to find information needed to authenticate with GitLab API."),
+    }
+  }
+
+  /// Builds a URL for a route under the configured project, ex: "boards/1"
+  fn project_url(&self, route: &str, params: &[(&str, &str)]) -> Result<Url> {
+    // Project paths have to be url encoded as a single segment
+    let mut url = Url::parse(&self.auth.url).wrap_err_with(|| "Invalid GitLab URL")?;
+    url
+      .path_segments_mut()
+      .map_err(|_| eyre!("Invalid GitLab URL {}", self.auth.url))?
+      .pop_if_empty()
+      .extend(&["api", "v4", "projects", &self.auth.project])
+      .extend(route.split('/'));
+    url.query_pairs_mut().extend_pairs(params);
+    Ok(url)
+  }
+
+  async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T> {
+    let response = self
+      .client
+      .get(url)
+      .header("PRIVATE-TOKEN", &self.auth.token)
+      .send()
+      .await?;
+
+    if let Err(err) = response.error_for_status_ref() {
+      return match err.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) => Err(eyre!(
+          "GitLab rejected your token. Check that it has the read_api scope."
+        )),
+        _ => Err(eyre!(err.to_string())),
+      };
+    }
+
+    Ok(
+      response
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("GitLab".to_string()))?,
+    )
+  }
+
+  /// Fetches every page of a list endpoint, GitLab returns at most 100 items at a time
+  async fn get_all<T: DeserializeOwned>(
+    &self,
+    route: &str,
+    params: &[(&str, &str)],
+  ) -> Result<Vec<T>> {
+    let mut items = vec![];
+    let mut page = 1;
+    loop {
+      let page_number = page.to_string();
+      let mut query = params.to_vec();
+      query.push(("per_page", "100"));
+      query.push(("page", &page_number));
+
+      let batch: Vec<T> = self.get_json(self.project_url(route, &query)?).await?;
+      let done = batch.len() < 100;
+      items.extend(batch);
+      if done {
+        return Ok(items);
+      }
+      page += 1;
+    }
+  }
+
+  async fn get_gitlab_board(&self, board_id: &str) -> Result<GitLabBoard> {
+    self
+      .get_json(self.project_url(&format!("boards/{}", board_id), &[])?)
+      .await
+  }
+
+  /// Fetches the issues within the board's scope
+  async fn get_board_issues(&self, board: &GitLabBoard) -> Result<Vec<Issue>> {
+    let scope = issue_scope(board);
+    let params: Vec<(&str, &str)> = scope
+      .iter()
+      .map(|(key, value)| (*key, value.as_str()))
+      .collect();
+    self.get_all("issues", &params).await
+  }
+
+  async fn get_issue_history(&self, issue: Issue, labels: &[String]) -> Result<CardHistory> {
+    let events: Vec<LabelEvent> = self
+      .get_all(&format!("issues/{}/resource_label_events", issue.iid), &[])
+      .await?;
+    Ok(build_history(&issue, labels, &events))
+  }
+}
+
+#[async_trait]
+impl Kanban for GitLabClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    Ok((&self.get_gitlab_board(board_id).await?).into())
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    let boards: Vec<GitLabBoard> = self.get_all("boards", &[]).await?;
+    Ok(boards.iter().map(|board| board.into()).collect())
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    let boards: HashMap<String, Board> = self
+      .get_boards()
+      .await?
+      .into_iter()
+      .map(|board| (board.name.clone(), board))
+      .collect();
+
+    let mut board_names: Vec<String> = boards.keys().cloned().collect();
+    board_names.sort();
+    let name_index: usize = Select::new()
+      .with_prompt("Select a board: ")
+      .items(&board_names)
+      .default(0)
+      .max_length(15)
+      .interact()
+      .wrap_err_with(|| "There was an error while trying to select a board.")?;
+
+    Ok(
+      boards
+        .get(&board_names[name_index])
+        .ok_or_else(|| eyre!("There was an error fetching selected board"))?
+        .to_owned(),
+    )
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    let board = self.get_gitlab_board(board_id).await?;
+    let mut names = vec![OPEN.to_string()];
+    names.extend(list_labels(&board));
+    names.push(CLOSED.to_string());
+
+    Ok(
+      names
+        .into_iter()
+        .map(|name| List {
+          id: name.clone(),
+          name,
+          board_id: board_id.to_string(),
+        })
+        .collect(),
+    )
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    let board = self.get_gitlab_board(board_id).await?;
+    let labels = list_labels(&board);
+    let issues = self.get_board_issues(&board).await?;
+
+    Ok(
+      issues
+        .iter()
+        .map(|issue| issue_to_card(issue, &labels))
+        .collect(),
+    )
+  }
+
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let board = self.get_gitlab_board(board_id).await?;
+    let labels = list_labels(&board);
+    let issues = self.get_board_issues(&board).await?;
+
+    stream::iter(issues)
+      .map(|issue| self.get_issue_history(issue, &labels))
+      .buffer_unordered(LABEL_EVENT_REQUESTS)
+      .try_collect()
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn issue(state: &str, labels: &[&str], closed_at: Option<DateTime<Utc>>) -> Issue {
+    Issue {
+      id: 100,
+      iid: 1,
+      title: "(3) Add login page".to_string(),
+      state: state.to_string(),
+      labels: labels.iter().map(|label| label.to_string()).collect(),
+      assignees: vec![User {
+        username: "justin".to_string(),
+      }],
+      created_at: Utc.with_ymd_and_hms(2021, 3, 1, 9, 0, 0).unwrap(),
+      closed_at,
+      due_date: None,
+    }
+  }
+
+  #[test]
+  fn it_places_issues_in_lists() {
+    let labels = vec!["Doing".to_string(), "Review".to_string()];
+
+    assert_eq!(
+      list_for_issue(&issue("opened", &["bug"], None), &labels),
+      OPEN
+    );
+    assert_eq!(
+      list_for_issue(&issue("opened", &["bug", "Review"], None), &labels),
+      "Review"
+    );
+    assert_eq!(
+      list_for_issue(&issue("closed", &["Review"], None), &labels),
+      CLOSED
+    );
+  }
+
+  #[test]
+  fn it_filters_issues_by_the_board_scope() {
+    let board: GitLabBoard = serde_json::from_str(
+      r#"{
+        "id": 1,
+        "name": "Sprint",
+        "milestone": { "id": 12, "title": "v1.0" },
+        "labels": [{ "name": "frontend" }, { "name": "bug" }],
+        "assignee": { "username": "justin" }
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(
+      issue_scope(&board),
+      vec![
+        ("scope", "all".to_string()),
+        ("milestone", "v1.0".to_string()),
+        ("labels", "frontend,bug".to_string()),
+        ("assignee_username", "justin".to_string()),
+      ]
+    );
+
+    let unscoped: GitLabBoard = serde_json::from_str(r#"{ "id": 2, "name": "All" }"#).unwrap();
+    assert_eq!(issue_scope(&unscoped), vec![("scope", "all".to_string())]);
+  }
+
+  #[test]
+  fn it_rebuilds_issue_movement_from_label_events() {
+    let labels = vec!["Doing".to_string(), "Review".to_string()];
+    let event = |label: &str, action: &str, day: u32| LabelEvent {
+      created_at: Utc.with_ymd_and_hms(2021, 3, day, 9, 0, 0).unwrap(),
+      label: Some(Label {
+        name: label.to_string(),
+      }),
+      action: action.to_string(),
+    };
+    let closed_at = Utc.with_ymd_and_hms(2021, 3, 5, 9, 0, 0).unwrap();
+    let events = vec![
+      event("Review", "add", 3),
+      event("bug", "add", 1),
+      event("Doing", "add", 2),
+      event("Doing", "remove", 3),
+    ];
+
+    let history = build_history(&issue("closed", &[], Some(closed_at)), &labels, &events);
+    assert_eq!(history.current_list, CLOSED);
+    let path: Vec<(Option<String>, String)> = history
+      .transitions
+      .into_iter()
+      .map(|transition| (transition.from, transition.to))
+      .collect();
+    assert_eq!(
+      path,
+      vec![
+        (Some(OPEN.to_string()), "Doing".to_string()),
+        (Some("Doing".to_string()), "Review".to_string()),
+        (Some("Review".to_string()), CLOSED.to_string()),
+      ]
+    );
+  }
+}
@@ -1,22 +1,29 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Mutex};
 
 use crate::{
   database::config,
-  database::config::Config,
+  database::config::{Config, JiraAuth, JiraAuthMethod},
   errors::*,
-  kanban::{Board, Card, Kanban, List},
+  kanban::{
+    name_with_field_points, parse_due_date, Board, Card, CardHistory, Kanban, List, Transition,
+  },
+  score::Scorer,
 };
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 use dialoguer::Select;
+use futures::{stream, StreamExt, TryStreamExt};
 use reqwest;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 
 struct Auth {
   username: String,
   token: String,
   base_url: String,
+  method: JiraAuthMethod,
 }
 // Jesus, the amount of structures we have to define
 // to get some simple kanban stats from Jira is incredible
@@ -30,10 +37,29 @@ struct Pagination {
   total: u32,
 }
 
+impl Pagination {
+  /// Where each page after this one starts
+  fn remaining_pages(&self) -> Vec<u32> {
+    // Guard against a page size of zero, which would never get through the issues
+    let page_size = self.max_results.max(1);
+    (self.start_at + page_size..self.total)
+      .step_by(page_size as usize)
+      .collect()
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct JiraBoard {
   id: u32,
   name: String,
+  #[serde(default)]
+  location: Option<BoardLocation>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BoardLocation {
+  #[serde(rename = "projectKey")]
+  project_key: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -42,10 +68,34 @@ struct Status {
   name: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct Resolution {
+  name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct User {
+  #[serde(rename = "accountId")]
+  account_id: Option<String>,
+  #[serde(rename = "displayName")]
+  display_name: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct IssueFields {
   summary: String,
   status: Status,
+  assignee: Option<User>,
+  #[serde(default)]
+  resolution: Option<Resolution>,
+  #[serde(default)]
+  labels: Vec<String>,
+  // Due dates are plain dates, ex: 2021-03-05
+  #[serde(default)]
+  duedate: Option<String>,
+  // Custom fields have ids like customfield_10016, so they're kept by id
+  #[serde(flatten)]
+  custom: HashMap<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,6 +104,44 @@ struct Issue {
   fields: IssueFields,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct ChangeItem {
+  field: String,
+  #[serde(rename = "fromString")]
+  from_string: Option<String>,
+  #[serde(rename = "toString")]
+  to_string: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ChangeHistory {
+  created: String,
+  items: Vec<ChangeItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Changelog {
+  histories: Vec<ChangeHistory>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HistoryFields {
+  summary: String,
+  status: Status,
+  created: String,
+  assignee: Option<User>,
+  // Only holds the story points field, when one is configured
+  #[serde(flatten)]
+  custom: HashMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IssueWithChangelog {
+  id: String,
+  fields: HistoryFields,
+  changelog: Changelog,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct PagedBoards {
   #[serde(flatten)]
@@ -62,16 +150,79 @@ struct PagedBoards {
   boards: Vec<JiraBoard>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Sprint {
+  id: u32,
+  name: String,
+  state: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
-struct Issues {
+struct PagedSprints {
+  #[serde(rename = "isLast", default)]
+  is_last: bool,
+  values: Vec<Sprint>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Issues<T> {
   #[serde(flatten)]
   pagination: Pagination,
-  issues: Vec<Issue>,
+  issues: Vec<T>,
 }
 
 pub struct JiraClient {
   client: reqwest::Client,
   auth: Auth,
+  // How many pages of issues can be requested at once
+  concurrency: usize,
+  jql: Option<String>,
+  story_points_field: Option<String>,
+  // Takes estimates out of summaries when the story points field replaces them
+  scorer: Scorer,
+  sprint: Option<SprintChoice>,
+  // Issues resolved as one of these are left out
+  excluded_resolutions: Vec<String>,
+  // The sprint we settled on, so the user is only asked to pick one once
+  sprint_id: Mutex<Option<String>>,
+}
+
+/// Which sprint's issues to count
+#[derive(Debug, PartialEq)]
+enum SprintChoice {
+  Select,
+  Active,
+  Id(String),
+}
+
+impl From<&str> for SprintChoice {
+  fn from(sprint: &str) -> Self {
+    match sprint.trim() {
+      "" => SprintChoice::Select,
+      "active" => SprintChoice::Active,
+      id => SprintChoice::Id(id.to_string()),
+    }
+  }
+}
+
+impl std::fmt::Display for Sprint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} ({})", self.name, self.state)
+  }
+}
+
+fn select_sprint(mut sprints: Vec<Sprint>) -> Result<Sprint> {
+  // Jira lists sprints oldest first, the recent ones are more likely to be wanted
+  sprints.reverse();
+  let index: usize = Select::new()
+    .with_prompt("Select a sprint: ")
+    .items(&sprints)
+    .default(0)
+    .max_length(15)
+    .interact()
+    .wrap_err_with(|| "There was an error while trying to select a sprint.")?;
+
+  Ok(sprints.swap_remove(index))
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -92,21 +243,92 @@ pub struct Configuration {
   column_config: ColumnConfig,
 }
 
-impl From<Issue> for Card {
-  fn from(issue: Issue) -> Self {
+// Jira Cloud only exposes account ids, so we keep the display name around as well to allow
+// people to filter by something they can remember.
+fn assignee_names(assignee: &Option<User>) -> Vec<String> {
+  match assignee {
+    Some(user) => vec![user.account_id.clone(), user.display_name.clone()]
+      .into_iter()
+      .flatten()
+      .collect(),
+    None => vec![],
+  }
+}
+
+/// Points from the Story Points field, when one is configured and the issue has them
+fn story_points(custom: &HashMap<String, Value>, field: Option<&str>) -> Option<f64> {
+  field
+    .and_then(|field| custom.get(field))
+    .and_then(Value::as_f64)
+}
+
+impl Issue {
+  fn is_excluded(&self, excluded_resolutions: &[String]) -> bool {
+    self
+      .fields
+      .resolution
+      .as_ref()
+      .map(|resolution| excluded_resolutions.contains(&resolution.name))
+      .unwrap_or(false)
+  }
+
+  fn to_card(&self, story_points_field: Option<&str>, scorer: &Scorer) -> Card {
     Card {
-      name: issue.fields.summary,
-      parent_list: issue.fields.status.name,
+      name: name_with_field_points(
+        scorer,
+        self.fields.summary.clone(),
+        story_points(&self.fields.custom, story_points_field),
+      ),
+      parent_list: self.fields.status.name.clone(),
+      members: assignee_names(&self.fields.assignee),
+      labels: self.fields.labels.clone(),
+      due: self.fields.duedate.as_deref().and_then(parse_due_date),
     }
   }
 }
 
-impl From<&Issue> for Card {
-  fn from(issue: &Issue) -> Self {
-    Card {
-      name: issue.fields.summary.clone(),
-      parent_list: issue.fields.status.name.clone(),
+/// Jira timestamps look like 2021-03-05T10:00:00.000+0000
+fn parse_jira_date(date: &str) -> Result<DateTime<Utc>> {
+  DateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f%z")
+    .map(|date| date.with_timezone(&Utc))
+    .wrap_err_with(|| format!("Unable to parse Jira date {}", date))
+}
+
+impl IssueWithChangelog {
+  /// Pulls the status changes out of an issue's changelog, in the order they happened
+  fn into_history(self, story_points_field: Option<&str>, scorer: &Scorer) -> Result<CardHistory> {
+    let mut transitions = vec![];
+    for history in self.changelog.histories.iter() {
+      for item in history.items.iter().filter(|item| item.field == "status") {
+        if let Some(to) = &item.to_string {
+          transitions.push(Transition {
+            from: item.from_string.clone(),
+            to: to.clone(),
+            at: parse_jira_date(&history.created)?,
+          });
+        }
+      }
     }
+    transitions.sort_by_key(|transition| transition.at);
+
+    Ok(CardHistory {
+      id: self.id,
+      name: name_with_field_points(
+        scorer,
+        self.fields.summary,
+        story_points(&self.fields.custom, story_points_field),
+      ),
+      created: parse_jira_date(&self.fields.created)?,
+      current_list: self.fields.status.name,
+      // Account ids aren't much use in a report, so prefer the name people know each other by
+      members: self
+        .fields
+        .assignee
+        .and_then(|user| user.display_name.or(user.account_id))
+        .into_iter()
+        .collect(),
+      transitions,
+    })
   }
 }
 
@@ -152,59 +374,230 @@ pub fn config_to_lists(config: &Configuration) -> Vec<List> {
     .collect()
 }
 
+/// Builds a client that also trusts the CA certificate configured for the server, if any
+fn build_client(auth: &JiraAuth) -> Result<reqwest::Client> {
+  let mut builder = reqwest::Client::builder();
+  if let Some(path) = &auth.ca_certificate {
+    let pem = std::fs::read(path)
+      .wrap_err_with(|| format!("Unable to read Jira CA certificate {}", path))?;
+    let certificate = reqwest::Certificate::from_pem(&pem)
+      .wrap_err_with(|| format!("Unable to parse Jira CA certificate {}", path))?;
+    builder = builder.add_root_certificate(certificate);
+  }
+  builder
+    .build()
+    .wrap_err_with(|| "Unable to build a client for Jira")
+}
+
 impl JiraClient {
-  pub fn init(config: &Config) -> Self {
+  /// Errors when the config isn't for Jira or its CA certificate can't be loaded
+  pub fn init(config: &Config) -> Result<Self> {
     match &config.kanban {
-      config::KanbanBoard::Jira(auth) => JiraClient {
-        client: reqwest::Client::new(),
+      config::KanbanBoard::Jira(auth) => Ok(JiraClient {
+        client: build_client(auth)?,
         auth: Auth {
           username: auth.username.clone(),
           base_url: auth.url.clone(),
           token: auth.api_token.clone(),
+          method: auth.method,
         },
-      },
-      _ => panic!("Unable to find information needed to authenticate with Jira API."),
+        concurrency: auth.max_concurrent_requests.unwrap_or(1).max(1),
+        jql: auth.jql.clone(),
+        story_points_field: auth.story_points_field.clone(),
+        scorer: config.scorer()?,
+        sprint: auth.sprint.as_deref().map(SprintChoice::from),
+        excluded_resolutions: match auth.include_archived {
+          true => vec![],
+          false => auth.excluded_resolutions.clone(),
+        },
+        sprint_id: Mutex::new(None),
+      }),
+      _ => Err(eyre!(
+        "Unable to find information needed to authenticate with Jira API."
+      )),
     }
   }
-}
 
-#[async_trait]
-impl Kanban for JiraClient {
-  async fn get_board(&self, board_id: &str) -> Result<Board> {
+  /// Starts a GET request to route, authenticated the way the server expects
+  fn get(&self, route: &str) -> reqwest::RequestBuilder {
+    let request = self.client.get(route);
+    match self.auth.method {
+      JiraAuthMethod::Basic => request.basic_auth(&self.auth.username, Some(&self.auth.token)),
+      JiraAuthMethod::Bearer => request.bearer_auth(&self.auth.token),
+    }
+  }
+
+  async fn get_jira_board(&self, board_id: &str) -> Result<JiraBoard> {
     let route = format!("{}/rest/agile/1.0/board/{}", self.auth.base_url, board_id);
-    let board: JiraBoard = self
-      .client
+    self
       .get(&route)
-      .basic_auth(&self.auth.username, Some(&self.auth.token))
       .send()
       .await?
       .json()
       .await
-      .map_err(|_e| JsonParseError("Jira".to_string()))?;
+      .map_err(|_e| JsonParseError("Jira".to_string()).into())
+  }
 
-    Ok(board.into())
+  /// Every sprint for the board in one of states, a comma separated list of active, future and
+  /// closed
+  async fn get_sprints(&self, board_id: &str, states: &str) -> Result<Vec<Sprint>> {
+    let route = format!(
+      "{}/rest/agile/1.0/board/{}/sprint",
+      self.auth.base_url, board_id
+    );
+    let mut sprints: Vec<Sprint> = vec![];
+    loop {
+      let page: PagedSprints = self
+        .get(&route)
+        .query(&[("state", states)])
+        .query(&[("startAt", sprints.len())])
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Jira".to_string()))?;
+
+      let is_done = page.is_last || page.values.is_empty();
+      sprints.extend(page.values);
+      if is_done {
+        return Ok(sprints);
+      }
+    }
   }
 
-  async fn select_board(&self) -> Result<Board> {
+  /// The id of the sprint to count issues from, if counting was limited to one
+  async fn sprint_id(&self, board_id: &str) -> Result<Option<String>> {
+    let choice = match &self.sprint {
+      Some(choice) => choice,
+      None => return Ok(None),
+    };
+    let cached = self.sprint_id.lock().unwrap().clone();
+    if cached.is_some() {
+      return Ok(cached);
+    }
+
+    let id = match choice {
+      SprintChoice::Id(id) => id.clone(),
+      SprintChoice::Active => {
+        let mut active = self.get_sprints(board_id, "active").await?;
+        match active.len() {
+          0 => return Err(eyre!("Board {} doesn't have an active sprint", board_id)),
+          1 => active.remove(0).id.to_string(),
+          // Boards can run sprints in parallel
+          _ => select_sprint(active)?.id.to_string(),
+        }
+      }
+      SprintChoice::Select => {
+        let sprints = self.get_sprints(board_id, "active,future,closed").await?;
+        if sprints.is_empty() {
+          return Err(eyre!("Board {} doesn't have any sprints", board_id));
+        }
+        select_sprint(sprints)?.id.to_string()
+      }
+    };
+    *self.sprint_id.lock().unwrap() = Some(id.clone());
+    Ok(Some(id))
+  }
+
+  /// Issues come from the chosen sprint when there is one, otherwise the whole board
+  async fn issues_route(&self, board_id: &str) -> Result<String> {
+    Ok(match self.sprint_id(board_id).await? {
+      Some(sprint) => format!(
+        "{}/rest/agile/1.0/board/{}/sprint/{}/issue",
+        self.auth.base_url, board_id, sprint
+      ),
+      None => format!(
+        "{}/rest/agile/1.0/board/{}/issue",
+        self.auth.base_url, board_id
+      ),
+    })
+  }
+
+  fn issue_page(&self, route: &str, start_at: u32) -> reqwest::RequestBuilder {
+    let request = self.get(route).query(&[("startAt", start_at)]);
+    match &self.jql {
+      Some(jql) => request.query(&[("jql", jql)]),
+      None => request,
+    }
+  }
+
+  async fn get_issue_page<T: DeserializeOwned>(
+    &self,
+    route: &str,
+    start_at: u32,
+  ) -> Result<Issues<T>> {
+    Ok(
+      self
+        .issue_page(route, start_at)
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Jira".to_string()))?,
+    )
+  }
+
+  /// Fetches the page of boards visible to the user starting at start_at
+  async fn get_board_page(&self, start_at: u32) -> Result<PagedBoards> {
     let route = format!("{}/rest/agile/1.0/board", self.auth.base_url);
+    Ok(
+      self
+        .get(&route)
+        .query(&[("startAt", start_at)])
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Jira".to_string()))?,
+    )
+  }
 
-    let response = self
-      .client
-      .get(&route)
-      .basic_auth(&self.auth.username, Some(&self.auth.token))
-      .send()
+  /// Jira only returns a page of issues at a time, so this keeps requesting pages until total
+  /// issues have been fetched. Once the first page tells us how many there are, the rest are
+  /// requested up to concurrency at a time.
+  async fn get_issues<T: DeserializeOwned + Send>(&self, route: &str) -> Result<Vec<T>> {
+    let first: Issues<T> = self.get_issue_page(route, 0).await?;
+    let mut issues = first.issues;
+
+    let pages: Vec<Issues<T>> = stream::iter(first.pagination.remaining_pages())
+      .map(|start_at| self.get_issue_page(route, start_at))
+      .buffered(self.concurrency)
+      .try_collect()
+      .await?;
+    issues.extend(pages.into_iter().flat_map(|page| page.issues));
+
+    Ok(issues)
+  }
+}
+
+#[async_trait]
+impl Kanban for JiraClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    Ok(self.get_jira_board(board_id).await?.into())
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    let first = self.get_board_page(0).await?;
+    let mut boards = first.boards;
+
+    let pages: Vec<PagedBoards> = stream::iter(first.pagination.remaining_pages())
+      .map(|start_at| self.get_board_page(start_at))
+      .buffered(self.concurrency)
+      .try_collect()
       .await?;
+    boards.extend(pages.into_iter().flat_map(|page| page.boards));
 
-    let result: PagedBoards = response.json().await?;
+    Ok(boards.iter().map(|board| board.into()).collect())
+  }
 
+  async fn select_board(&self) -> Result<Board> {
     // Storing it as a hash-map, so we can easily retrieve and return the id
-    let boards: _ = result.boards.iter().fold(
-      HashMap::new(),
-      |mut collection: HashMap<String, Board>, board: &JiraBoard| {
-        collection.insert(board.name.clone(), board.into());
-        collection
-      },
-    );
+    let boards: HashMap<String, Board> = self
+      .get_boards()
+      .await?
+      .into_iter()
+      .map(|board| (board.name.clone(), board))
+      .collect();
 
     // Pull out names and get user to select a board name
     let mut board_names: Vec<String> = boards.keys().cloned().collect();
@@ -231,9 +624,7 @@ impl Kanban for JiraClient {
       self.auth.base_url, board_id
     );
     let config: Configuration = self
-      .client
       .get(&route)
-      .basic_auth(&self.auth.username, Some(&self.auth.token))
       .send()
       .await?
       .json()
@@ -244,20 +635,315 @@ impl Kanban for JiraClient {
   }
 
   async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    let route = self.issues_route(board_id).await?;
+    let issues: Vec<Issue> = self.get_issues(&route).await?;
+
+    Ok(
+      issues
+        .iter()
+        .filter(|issue| !issue.is_excluded(&self.excluded_resolutions))
+        .map(|issue| issue.to_card(self.story_points_field.as_deref(), &self.scorer))
+        .collect(),
+    )
+  }
+
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let mut fields = "summary,status,created,assignee".to_string();
+    if let Some(field) = &self.story_points_field {
+      fields = format!("{},{}", fields, field);
+    }
     let route = format!(
-      "{}/rest/agile/1.0/board/{}/issue",
-      self.auth.base_url, board_id
+      "{}?expand=changelog&fields={}",
+      self.issues_route(board_id).await?,
+      fields
     );
-    let response: Issues = self
-      .client
-      .get(&route)
-      .basic_auth(&self.auth.username, Some(&self.auth.token))
-      .send()
-      .await?
-      .json()
-      .await
-      .map_err(|_e| JsonParseError("Jira".to_string()))?;
+    let issues: Vec<IssueWithChangelog> = self.get_issues(&route).await?;
+
+    issues
+      .into_iter()
+      .map(|issue| issue.into_history(self.story_points_field.as_deref(), &self.scorer))
+      .collect()
+  }
 
-    Ok(response.issues.iter().map(|issue| issue.into()).collect())
+  async fn projects(&self, board_id: &str) -> Result<Vec<String>> {
+    Ok(
+      self
+        .get_jira_board(board_id)
+        .await?
+        .location
+        .and_then(|location| location.project_key)
+        .into_iter()
+        .collect(),
+    )
+  }
+
+  async fn entry_key(&self, board_id: &str) -> Result<String> {
+    Ok(match self.sprint_id(board_id).await? {
+      Some(sprint) => format!("{}:{}", board_id, sprint),
+      None => board_id.to_string(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::config::ScorePatterns;
+  use chrono::TimeZone;
+
+  #[test]
+  fn it_errors_on_a_missing_ca_certificate() {
+    let config: Config = serde_yaml::from_str(
+      "kanban:
+  Jira:
+    username: justin
+    api_token: secret
+    url: https://jira.example.com
+    ca_certificate: /does/not/exist.pem",
+    )
+    .unwrap();
+
+    let err = JiraClient::init(&config).err().unwrap();
+    assert!(format!("{:#}", err).contains("/does/not/exist.pem"));
+  }
+
+  #[test]
+  fn it_authenticates_with_the_configured_method() {
+    let client = |method| JiraClient {
+      client: reqwest::Client::new(),
+      auth: Auth {
+        username: "justin".to_string(),
+        token: "secret".to_string(),
+        base_url: "https://jira.example.com".to_string(),
+        method,
+      },
+      concurrency: 1,
+      jql: None,
+      story_points_field: None,
+      scorer: Scorer::default(),
+      sprint: None,
+      excluded_resolutions: vec![],
+      sprint_id: Mutex::new(None),
+    };
+    let authorization = |client: JiraClient| {
+      client
+        .get("https://jira.example.com/rest/agile/1.0/board")
+        .build()
+        .unwrap()
+        .headers()["authorization"]
+        .to_str()
+        .unwrap()
+        .to_string()
+    };
+
+    assert_eq!(
+      authorization(client(JiraAuthMethod::Basic)),
+      format!("Basic {}", base64::encode("justin:secret"))
+    );
+    assert_eq!(
+      authorization(client(JiraAuthMethod::Bearer)),
+      "Bearer secret"
+    );
+  }
+
+  #[tokio::test]
+  async fn it_saves_sprint_entries_apart_from_the_board() {
+    let client = |sprint: Option<&str>| JiraClient {
+      client: reqwest::Client::new(),
+      auth: Auth {
+        username: "justin".to_string(),
+        token: "secret".to_string(),
+        base_url: "https://jira.example.com".to_string(),
+        method: JiraAuthMethod::Basic,
+      },
+      concurrency: 1,
+      jql: None,
+      story_points_field: None,
+      scorer: Scorer::default(),
+      sprint: sprint.map(SprintChoice::from),
+      excluded_resolutions: vec![],
+      sprint_id: Mutex::new(None),
+    };
+
+    assert_eq!(client(None).entry_key("1").await.unwrap(), "1");
+    assert_eq!(client(Some("42")).entry_key("1").await.unwrap(), "1:42");
+  }
+
+  #[test]
+  fn it_filters_issues_with_jql() {
+    let client = JiraClient {
+      client: reqwest::Client::new(),
+      auth: Auth {
+        username: "justin".to_string(),
+        token: "secret".to_string(),
+        base_url: "https://jira.example.com".to_string(),
+        method: JiraAuthMethod::Basic,
+      },
+      concurrency: 1,
+      jql: Some("sprint in openSprints()".to_string()),
+      story_points_field: None,
+      scorer: Scorer::default(),
+      sprint: None,
+      excluded_resolutions: vec![],
+      sprint_id: Mutex::new(None),
+    };
+    let request = client
+      .issue_page(
+        "https://jira.example.com/rest/agile/1.0/board/1/issue?expand=changelog",
+        50,
+      )
+      .build()
+      .unwrap();
+
+    assert_eq!(
+      request.url().query(),
+      Some("expand=changelog&startAt=50&jql=sprint+in+openSprints%28%29")
+    );
+  }
+
+  #[test]
+  fn it_prefers_the_story_points_field() {
+    let issue = |summary: &str, points: Value| -> Issue {
+      serde_json::from_value(serde_json::json!({
+        "id": "10001",
+        "fields": {
+          "summary": summary,
+          "status": {"id": "1", "name": "To Do"},
+          "assignee": null,
+          "customfield_10016": points
+        }
+      }))
+      .unwrap()
+    };
+    let field = Some("customfield_10016");
+    let scorer = Scorer::default();
+
+    assert_eq!(
+      issue("(3) Add login page [5]", 8.into())
+        .to_card(field, &scorer)
+        .name,
+      "(8) Add login page [5]"
+    );
+    assert_eq!(
+      issue("(3) Add login page", Value::Null)
+        .to_card(field, &scorer)
+        .name,
+      "(3) Add login page"
+    );
+    assert_eq!(
+      issue("Add login page", 2.into())
+        .to_card(None, &scorer)
+        .name,
+      "Add login page"
+    );
+
+    // Estimates written with the configured pattern are replaced as well
+    let scorer = Scorer::new(&ScorePatterns {
+      estimate: r"\{(?P<points>\d+)\}".to_string(),
+      correction: r"\[(?P<points>\d+)\]".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+      issue("Add login page {3} [5]", 8.into())
+        .to_card(field, &scorer)
+        .name,
+      "(8) Add login page [5]"
+    );
+  }
+
+  #[test]
+  fn it_leaves_out_excluded_resolutions() {
+    let issue = |resolution: Value| -> Issue {
+      serde_json::from_value(serde_json::json!({
+        "id": "10001",
+        "fields": {
+          "summary": "(3) Add login page",
+          "status": {"id": "3", "name": "Done"},
+          "assignee": null,
+          "resolution": resolution
+        }
+      }))
+      .unwrap()
+    };
+    let excluded = vec!["Won't Do".to_string()];
+
+    assert!(issue(serde_json::json!({"name": "Won't Do"})).is_excluded(&excluded));
+    assert!(!issue(serde_json::json!({"name": "Done"})).is_excluded(&excluded));
+    assert!(!issue(Value::Null).is_excluded(&excluded));
+  }
+
+  #[test]
+  fn it_reads_sprint_choices() {
+    assert_eq!(SprintChoice::from(""), SprintChoice::Select);
+    assert_eq!(SprintChoice::from("active"), SprintChoice::Active);
+    assert_eq!(SprintChoice::from("42"), SprintChoice::Id("42".to_string()));
+  }
+
+  #[test]
+  fn it_finds_the_remaining_pages_of_issues() {
+    let pagination = |max_results, total| Pagination {
+      start_at: 0,
+      max_results,
+      total,
+    };
+
+    assert_eq!(pagination(50, 120).remaining_pages(), vec![50, 100]);
+    assert_eq!(pagination(50, 100).remaining_pages(), vec![50]);
+    assert_eq!(pagination(50, 30).remaining_pages(), Vec::<u32>::new());
+    assert_eq!(pagination(0, 3).remaining_pages(), vec![1, 2]);
+  }
+
+  #[test]
+  fn it_reads_status_changes_from_the_changelog() {
+    let issue: IssueWithChangelog = serde_json::from_str(
+      r#"{
+        "id": "10001",
+        "fields": {
+          "summary": "(3) Add login page",
+          "status": {"id": "3", "name": "Done"},
+          "created": "2021-03-01T09:00:00.000+0000",
+          "assignee": {"accountId": "5b10ac8d82e05b22cc7d4ef5", "displayName": "Justin"},
+          "customfield_10016": 5
+        },
+        "changelog": {
+          "histories": [
+            {
+              "created": "2021-03-04T12:00:00.000+0000",
+              "items": [{"field": "status", "fromString": "In Review", "toString": "Done"}]
+            },
+            {
+              "created": "2021-03-02T09:00:00.000-0100",
+              "items": [
+                {"field": "assignee", "fromString": null, "toString": "Justin"},
+                {"field": "status", "fromString": "To Do", "toString": "In Review"}
+              ]
+            }
+          ]
+        }
+      }"#,
+    )
+    .unwrap();
+
+    let history = issue
+      .into_history(Some("customfield_10016"), &Scorer::default())
+      .unwrap();
+    assert_eq!(history.name, "(5) Add login page");
+    assert_eq!(history.current_list, "Done");
+    assert_eq!(history.members, vec!["Justin".to_string()]);
+    assert_eq!(
+      history.transitions,
+      vec![
+        Transition {
+          from: Some("To Do".to_string()),
+          to: "In Review".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 2, 10, 0, 0).unwrap(),
+        },
+        Transition {
+          from: Some("In Review".to_string()),
+          to: "Done".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 4, 12, 0, 0).unwrap(),
+        },
+      ]
+    );
   }
 }
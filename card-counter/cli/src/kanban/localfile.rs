@@ -0,0 +1,338 @@
+use std::{
+  collections::HashMap,
+  fs,
+  io::{self, Read},
+  path::Path,
+  sync::Mutex,
+};
+
+use crate::{
+  database::config::{self, Config, CsvDialect},
+  errors::*,
+  kanban::{Board, Card, CardHistory, Kanban, List},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LocalFileAuth {
+  // The board file to use when --board-id isn't given, "-" reads the board from stdin
+  pub path: String,
+}
+
+/// A card in a board file, either just its name or its name along with who it's assigned to and
+/// its labels
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum FileCard {
+  Name(String),
+  Card {
+    name: String,
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    due: Option<DateTime<Utc>>,
+  },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FileList {
+  name: String,
+  #[serde(default)]
+  cards: Vec<FileCard>,
+}
+
+/// A board definition read from a JSON or CSV file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct BoardFile {
+  #[serde(default)]
+  name: String,
+  lists: Vec<FileList>,
+}
+
+impl FileCard {
+  fn into_card(self, list_name: &str) -> Card {
+    let (name, members, labels, due) = match self {
+      FileCard::Name(name) => (name, vec![], vec![], None),
+      FileCard::Card {
+        name,
+        members,
+        labels,
+        due,
+      } => (name, members, labels, due),
+    };
+    Card {
+      name,
+      parent_list: list_name.to_string(),
+      members,
+      labels,
+      due,
+    }
+  }
+}
+
+/// Reads a board from CSV with a header row. The list and name columns are required, members and
+/// labels are optional and separated by semicolons. Lists are kept in the order they first appear.
+fn parse_csv(contents: &str, dialect: &CsvDialect) -> Result<BoardFile> {
+  let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+  let header: Vec<String> = dialect
+    .split(lines.next().unwrap_or_default())
+    .into_iter()
+    .map(|column| column.trim().to_lowercase())
+    .collect();
+  let column = |name: &str| header.iter().position(|column| column == name);
+  let (list_column, name_column) = match (column("list"), column("name")) {
+    (Some(list), Some(name)) => (list, name),
+    _ => {
+      return Err(eyre!(
+        "Board CSV files need a header with list and name columns"
+      ))
+    }
+  };
+  let split = |field: Option<&String>| -> Vec<String> {
+    field
+      .map(|field| {
+        field
+          .split(';')
+          .map(|value| value.trim().to_string())
+          .filter(|value| !value.is_empty())
+          .collect()
+      })
+      .unwrap_or_default()
+  };
+
+  let mut lists: Vec<FileList> = vec![];
+  for line in lines {
+    let fields = dialect.split(line);
+    let list_name = fields.get(list_column).cloned().unwrap_or_default();
+    let card = FileCard::Card {
+      name: fields.get(name_column).cloned().unwrap_or_default(),
+      members: split(column("members").and_then(|index| fields.get(index))),
+      labels: split(column("labels").and_then(|index| fields.get(index))),
+      due: None,
+    };
+
+    match lists.iter_mut().find(|list| list.name == list_name) {
+      Some(list) => list.cards.push(card),
+      None => lists.push(FileList {
+        name: list_name,
+        cards: vec![card],
+      }),
+    }
+  }
+
+  Ok(BoardFile {
+    name: String::new(),
+    lists,
+  })
+}
+
+fn parse_board(path: &str, contents: &str, dialect: &CsvDialect) -> Result<BoardFile> {
+  let is_json = path.ends_with(".json") || (path == "-" && contents.trim_start().starts_with('{'));
+  let mut board = if is_json {
+    serde_json::from_str(contents)
+      .wrap_err_with(|| format!("Unable to parse board file {}", path))?
+  } else {
+    parse_csv(contents, dialect)?
+  };
+
+  if board.name.is_empty() {
+    board.name = Path::new(path)
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().to_string())
+      .filter(|stem| stem != "-")
+      .unwrap_or_else(|| "stdin".to_string());
+  }
+  Ok(board)
+}
+
+/// Reads boards from files on disk, so scoring and burndowns can be tried without any API
+/// credentials. The board id is the path to the file.
+pub struct LocalFileClient {
+  auth: LocalFileAuth,
+  dialect: CsvDialect,
+  // Stdin can only be read once, so every board we've read is kept around
+  boards: Mutex<HashMap<String, BoardFile>>,
+}
+
+impl LocalFileClient {
+  /// Board files don't need credentials, so when another kanban is configured the board file
+  /// has to come from --board-id instead
+  pub fn init(config: &Config) -> Self {
+    let auth = match &config.kanban {
+      config::KanbanBoard::LocalFile(auth) => auth.to_owned(),
+      _ => LocalFileAuth::default(),
+    };
+    LocalFileClient {
+      auth,
+      dialect: config.csv.clone(),
+      boards: Mutex::new(HashMap::new()),
+    }
+  }
+
+  fn read_board(&self, path: &str) -> Result<BoardFile> {
+    let path = if path.is_empty() {
+      &self.auth.path
+    } else {
+      path
+    };
+    if path.is_empty() {
+      return Err(eyre!(
+        "No board file given. Pass the path to one with --board-id"
+      ));
+    }
+
+    let mut boards = self.boards.lock().unwrap();
+    if let Some(board) = boards.get(path) {
+      return Ok(board.clone());
+    }
+
+    let contents = if path == "-" {
+      let mut contents = String::new();
+      io::stdin()
+        .read_to_string(&mut contents)
+        .wrap_err_with(|| "Unable to read board from stdin")?;
+      contents
+    } else {
+      fs::read_to_string(path).wrap_err_with(|| format!("Unable to read board file {}", path))?
+    };
+    let board = parse_board(path, &contents, &self.dialect)?;
+    boards.insert(path.to_string(), board.clone());
+    Ok(board)
+  }
+}
+
+#[async_trait]
+impl Kanban for LocalFileClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    let board = self.read_board(board_id)?;
+    Ok(Board {
+      id: board_id.to_string(),
+      name: board.name,
+    })
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    let path = self.auth.path.clone();
+    self.get_board(&path).await
+  }
+
+  /// There's only ever the one board, the file in config
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    Ok(vec![self.select_board().await?])
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    Ok(
+      self
+        .read_board(board_id)?
+        .lists
+        .into_iter()
+        .map(|list| List {
+          id: list.name.clone(),
+          name: list.name,
+          board_id: board_id.to_string(),
+        })
+        .collect(),
+    )
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    Ok(
+      self
+        .read_board(board_id)?
+        .lists
+        .into_iter()
+        .flat_map(|list| {
+          let name = list.name;
+          list
+            .cards
+            .into_iter()
+            .map(move |card| card.into_card(&name))
+        })
+        .collect(),
+    )
+  }
+
+  /// Board files are a snapshot, so every card has been where it is since the file was written
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let created: DateTime<Utc> = fs::metadata(board_id)
+      .and_then(|metadata| metadata.modified())
+      .map(DateTime::from)
+      .unwrap_or_else(|_| Utc::now());
+
+    Ok(
+      self
+        .get_cards(board_id)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(index, card)| CardHistory {
+          id: index.to_string(),
+          name: card.name,
+          created,
+          current_list: card.parent_list,
+          members: card.members,
+          transitions: vec![],
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_reads_json_boards() {
+    let board = parse_board(
+      "boards/sprint.json",
+      r#"{
+        "lists": [
+          {"name": "To Do", "cards": ["(3) Login page"]},
+          {"name": "Done", "cards": [{"name": "(2) Signup page", "members": ["sam"]}]}
+        ]
+      }"#,
+      &CsvDialect::default(),
+    )
+    .unwrap();
+
+    assert_eq!(board.name, "sprint");
+    let cards: Vec<Card> = board.lists[1]
+      .cards
+      .iter()
+      .cloned()
+      .map(|card| card.into_card("Done"))
+      .collect();
+    assert_eq!(cards[0].name, "(2) Signup page");
+    assert_eq!(cards[0].members, vec!["sam".to_string()]);
+  }
+
+  #[test]
+  fn it_reads_csv_boards() {
+    let board = parse_board(
+      "-",
+      "list,name,labels\nTo Do,\"(3) Login, and logout\",web;auth\nDone,(2) Signup page,\nTo Do,Password reset,\n",
+      &CsvDialect::default(),
+    )
+    .unwrap();
+
+    assert_eq!(board.name, "stdin");
+    let lists: Vec<(&str, usize)> = board
+      .lists
+      .iter()
+      .map(|list| (list.name.as_str(), list.cards.len()))
+      .collect();
+    assert_eq!(lists, vec![("To Do", 2), ("Done", 1)]);
+
+    let card = board.lists[0].cards[0].clone().into_card("To Do");
+    assert_eq!(card.name, "(3) Login, and logout");
+    assert_eq!(card.labels, vec!["web".to_string(), "auth".to_string()]);
+  }
+}
@@ -1,16 +1,33 @@
+pub mod asana;
+pub mod board_aliases;
+pub mod gitlab;
 pub mod jira;
+pub mod localfile;
+pub mod monday;
+pub mod notion;
 pub mod trello;
 use std::collections::HashMap;
 
 use crate::{
-  database::config::{self, Config},
+  cli::ScoreArgs,
+  database::{
+    config::{self, Config},
+    CardSnapshot,
+  },
   errors::Result,
-  score::{get_score, Deck},
+  score::{Deck, ListFilter, Scorer},
 };
+use asana::AsanaClient;
+use board_aliases::BoardAliases;
+use gitlab::GitLabClient;
 use jira::JiraClient;
+use localfile::LocalFileClient;
+use monday::MondayClient;
+use notion::NotionClient;
 use trello::TrelloClient;
 
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -28,6 +45,31 @@ pub struct List {
 pub struct Card {
   pub name: String,
   pub parent_list: String,
+  // Usernames, or Jira account ids and display names, of everyone assigned to the card
+  pub members: Vec<String>,
+  pub labels: Vec<String>,
+  // When the card is due, for boards that track due dates
+  pub due: Option<DateTime<Utc>>,
+}
+
+/// A card moving from one list, or status, to another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+  pub from: Option<String>,
+  pub to: String,
+  pub at: DateTime<Utc>,
+}
+
+/// Everywhere a card has been since it was created
+#[derive(Debug, Clone)]
+pub struct CardHistory {
+  pub id: String,
+  pub name: String,
+  pub created: DateTime<Utc>,
+  pub current_list: String,
+  // Who the card is assigned to, by username or display name
+  pub members: Vec<String>,
+  pub transitions: Vec<Transition>,
 }
 
 pub trait KanbanClient {
@@ -35,50 +77,56 @@ pub trait KanbanClient {
 }
 
 #[async_trait]
-pub trait Kanban {
+pub trait Kanban: Send + Sync {
   async fn get_board(&self, board_id: &str) -> Result<Board>;
   async fn get_lists(&self, board_id: &str) -> Result<Vec<List>>;
   async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>>;
   async fn select_board(&self) -> Result<Board>;
+  /// Fetches every board the credentials can see
+  async fn get_boards(&self) -> Result<Vec<Board>>;
+  /// Fetches every card on a board along with the lists it has moved through
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>>;
+  /// The id entries for the board are saved under. Boards counted one sprint at a time save
+  /// under board_id:sprint_id, so they aren't mixed up with entries for the whole board.
+  async fn entry_key(&self, board_id: &str) -> Result<String> {
+    Ok(board_id.to_string())
+  }
+  /// The keys of the projects a board's issues come from, empty when the board isn't tied to a
+  /// project
+  async fn projects(&self, _board_id: &str) -> Result<Vec<String>> {
+    Ok(vec![])
+  }
 }
 
-pub fn collect_cards(cards: Vec<Card>) -> HashMap<String, Vec<Card>> {
-  cards.into_iter().fold(
-    HashMap::new(),
-    |mut collection: HashMap<String, Vec<Card>>, card: Card| {
-      let list_id = card.parent_list.clone();
-      collection.entry(list_id).or_default().push(card);
-      collection
-    },
-  )
+/// Groups cards by the list they belong to. If a member is given, only cards assigned to that
+/// member are kept.
+pub fn collect_cards(cards: Vec<Card>, member: Option<&str>) -> HashMap<String, Vec<Card>> {
+  cards
+    .into_iter()
+    .filter(|card| match member {
+      Some(member) => card.members.iter().any(|name| name == member),
+      None => true,
+    })
+    .fold(
+      HashMap::new(),
+      |mut collection: HashMap<String, Vec<Card>>, card: Card| {
+        let list_id = card.parent_list.clone();
+        collection.entry(list_id).or_default().push(card);
+        collection
+      },
+    )
 }
 
 pub fn build_decks(
   lists: Vec<List>,
   mut associated_cards: HashMap<String, Vec<Card>>,
+  scorer: &Scorer,
 ) -> Vec<Deck> {
   let mut decks = Vec::new();
 
   for list in lists {
     let cards = associated_cards.entry(list.id.clone()).or_default();
-    let (score, unscored, estimated) =
-      cards
-        .iter()
-        .fold((0, 0, 0), |(total, unscored, estimate), card| {
-          if let Some(score) = get_score(&card.name) {
-            if let Some(correction) = score.correction {
-              (total + correction, unscored, estimate)
-            } else {
-              (
-                total + score.estimated.unwrap(),
-                unscored,
-                estimate + score.estimated.unwrap(),
-              )
-            }
-          } else {
-            (total, unscored + 1, estimate)
-          }
-        });
+    let (score, unscored, estimated) = scorer.tally(cards.iter().map(|card| card.name.as_str()));
 
     decks.push(Deck {
       list_name: list.name,
@@ -92,20 +140,226 @@ pub fn build_decks(
   decks
 }
 
-pub fn init_kanban_board(config: &Config, matches: &clap::ArgMatches<'_>) -> Box<dyn Kanban> {
-  match matches.value_of("kanban") {
-    Some("trello") => Box::new(TrelloClient::init(config)),
-    Some("jira") => Box::new(JiraClient::init(config)),
-    None => init_kanban_board_from_config(config),
+/// Scores cards by label rather than by list, in the order labels first appear on the board.
+/// Cards with several labels count towards each of them and cards without one are counted under
+/// "No label". Cards in lists the filter doesn't keep are left out, as they are by list.
+pub fn build_label_decks(
+  lists: &[List],
+  associated_cards: &HashMap<String, Vec<Card>>,
+  filter: &ListFilter,
+  scorer: &Scorer,
+) -> Vec<Deck> {
+  let mut labels: Vec<(String, Vec<&str>)> = Vec::new();
+  let counted_lists = lists.iter().filter(|list| filter.keeps(&list.name));
+  for list in counted_lists {
+    for card in associated_cards.get(&list.id).into_iter().flatten() {
+      let card_labels = match card.labels.is_empty() {
+        true => vec!["No label".to_string()],
+        false => card.labels.clone(),
+      };
+      for label in card_labels {
+        match labels.iter_mut().find(|(name, _)| *name == label) {
+          Some((_, names)) => names.push(&card.name),
+          None => labels.push((label, vec![&card.name])),
+        }
+      }
+    }
+  }
+
+  labels
+    .into_iter()
+    .map(|(label, names)| {
+      let (score, unscored, estimated) = scorer.tally(names.iter().copied());
+      Deck {
+        list_name: label,
+        size: names.len(),
+        score,
+        unscored,
+        estimated,
+      }
+    })
+    .collect()
+}
+
+/// Records the name and list of every card that would be counted by build_decks
+pub fn snapshot_cards(
+  lists: &[List],
+  associated_cards: &HashMap<String, Vec<Card>>,
+) -> Vec<CardSnapshot> {
+  lists
+    .iter()
+    .flat_map(|list| {
+      associated_cards
+        .get(&list.id)
+        .into_iter()
+        .flatten()
+        .map(move |card| CardSnapshot {
+          name: card.name.clone(),
+          list_name: list.name.clone(),
+        })
+    })
+    .collect()
+}
+
+/// Boards that keep points in a field rather than in the card's name have them added to the name
+/// as an estimate, ex: "(3) Login page", so they are scored like any other card. Points that are
+/// already in the name, read with the configured score patterns, win.
+pub fn name_with_points(scorer: &Scorer, name: String, points: Option<f64>) -> String {
+  match points {
+    Some(points) if scorer.score(&name).is_none() => prefix_points(points, &name),
+    _ => name,
+  }
+}
+
+/// Like name_with_points, but for boards where the field is the source of truth, so its points
+/// replace any estimate in the name. A correction in the name, ex: [5], is kept since the board
+/// has nowhere else to put it.
+pub fn name_with_field_points(scorer: &Scorer, name: String, points: Option<f64>) -> String {
+  match points {
+    Some(points) => prefix_points(points, &scorer.without_estimate(&name)),
+    None => name,
+  }
+}
+
+fn prefix_points(points: f64, name: &str) -> String {
+  format!("({}) {}", points.round() as i32, name)
+}
+
+/// Due dates come back from boards as either full timestamps or plain dates. Plain dates are due by
+/// the end of that day.
+pub fn parse_due_date(date: &str) -> Option<DateTime<Utc>> {
+  match DateTime::parse_from_rfc3339(date) {
+    Ok(due) => Some(due.with_timezone(&Utc)),
+    Err(_) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+      .ok()
+      .map(|day| DateTime::from_naive_utc_and_offset(day.and_hms_opt(23, 59, 59).unwrap(), Utc)),
+  }
+}
+
+/// Options given on the command line take precedence over the ones in the config file
+fn with_cli_options(config: &Config, args: &ScoreArgs) -> Config {
+  let mut config = config.clone();
+  if let config::KanbanBoard::Trello(auth) = &mut config.kanban {
+    if let Some(workspace) = &args.workspace {
+      auth.workspace = Some(workspace.clone());
+    }
+    auth.include_archived = args.include_archived;
+  }
+  if let config::KanbanBoard::Jira(auth) = &mut config.kanban {
+    auth.include_archived = args.include_archived;
+    if let Some(jql) = &args.jql {
+      auth.jql = Some(jql.clone());
+    }
+    if let Some(sprint) = &args.sprint {
+      auth.sprint = Some(sprint.clone().unwrap_or_default());
+    }
+  }
+  config
+}
+
+pub fn init_kanban_board(config: &Config, args: &ScoreArgs) -> Result<Box<dyn Kanban>> {
+  let config = &with_cli_options(config, args);
+  let kanban: Box<dyn Kanban> = match args.kanban.as_deref() {
+    Some("trello") => Box::new(TrelloClient::init(config)?),
+    Some("jira") => Box::new(JiraClient::init(config)?),
+    Some("gitlab") => Box::new(GitLabClient::init(config)),
+    Some("asana") => Box::new(AsanaClient::init(config)?),
+    Some("notion") => Box::new(NotionClient::init(config)?),
+    Some("monday") => Box::new(MondayClient::init(config)?),
+    Some("localfile") => Box::new(LocalFileClient::init(config)),
+    None => return init_kanban_board_from_config(config),
     Some(unknown) => {
       panic!("Unknown kanban board: {}", unknown)
     }
-  }
+  };
+  Ok(Box::new(BoardAliases::new(kanban, config)))
+}
+
+pub fn init_kanban_board_from_config(config: &Config) -> Result<Box<dyn Kanban>> {
+  let kanban: Box<dyn Kanban> = match config.kanban {
+    config::KanbanBoard::Trello(_) => Box::new(TrelloClient::init(config)?),
+    config::KanbanBoard::Jira(_) => Box::new(JiraClient::init(config)?),
+    config::KanbanBoard::GitLab(_) => Box::new(GitLabClient::init(config)),
+    config::KanbanBoard::Asana(_) => Box::new(AsanaClient::init(config)?),
+    config::KanbanBoard::Notion(_) => Box::new(NotionClient::init(config)?),
+    config::KanbanBoard::Monday(_) => Box::new(MondayClient::init(config)?),
+    config::KanbanBoard::LocalFile(_) => Box::new(LocalFileClient::init(config)),
+  };
+  Ok(Box::new(BoardAliases::new(kanban, config)))
 }
 
-pub fn init_kanban_board_from_config(config: &Config) -> Box<dyn Kanban> {
-  match config.kanban {
-    config::KanbanBoard::Trello(_) => Box::new(TrelloClient::init(config)),
-    config::KanbanBoard::Jira(_) => Box::new(JiraClient::init(config)),
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::config::ScorePatterns;
+
+  #[test]
+  fn it_keeps_points_already_in_the_name() {
+    let scorer = Scorer::new(&ScorePatterns {
+      estimate: r"SP:(?P<points>\d+)".to_string(),
+      correction: r"\[(?P<points>\d+)\]".to_string(),
+    })
+    .unwrap();
+
+    assert_eq!(
+      name_with_points(&scorer, "SP:5 Login page".to_string(), Some(3.0)),
+      "SP:5 Login page"
+    );
+    assert_eq!(
+      name_with_points(&scorer, "Login page".to_string(), Some(3.0)),
+      "(3) Login page"
+    );
+  }
+
+  #[test]
+  fn it_scores_cards_by_label() {
+    let list = |id: &str, name: &str| List {
+      id: id.to_string(),
+      name: name.to_string(),
+      board_id: "board".to_string(),
+    };
+    let card = |name: &str, list: &str, labels: &[&str]| Card {
+      name: name.to_string(),
+      parent_list: list.to_string(),
+      members: vec![],
+      labels: labels.iter().map(|label| label.to_string()).collect(),
+      due: None,
+    };
+    let lists = vec![list("1", "To Do"), list("2", "Done"), list("3", "Archive")];
+    let cards = collect_cards(
+      vec![
+        card("(3) Login page", "1", &["feature"]),
+        card("(2) Crash on start", "2", &["bug", "feature"]),
+        card("Typo", "2", &[]),
+        card("(8) Old bug", "3", &["bug"]),
+      ],
+      None,
+    );
+
+    let decks = build_label_decks(
+      &lists,
+      &cards,
+      &ListFilter::new(Some("Archive"), false).unwrap(),
+      &Scorer::default(),
+    );
+    let scores: Vec<(&str, usize, i32, i32)> = decks
+      .iter()
+      .map(|deck| {
+        (
+          deck.list_name.as_str(),
+          deck.size,
+          deck.score,
+          deck.unscored,
+        )
+      })
+      .collect();
+    assert_eq!(
+      scores,
+      vec![
+        ("feature", 2, 5, 0),
+        ("bug", 1, 2, 0),
+        ("No label", 1, 0, 1)
+      ]
+    );
   }
 }
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use crate::{
+  database::config::{self, Config},
+  errors::*,
+  kanban::{name_with_points, parse_due_date, Board, Card, CardHistory, Kanban, List, Transition},
+  score::Scorer,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dialoguer::Select;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+static MONDAY_API: &str = "https://api.monday.com/v2";
+static MONDAY_VERSION: &str = "2023-10";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MondayAuth {
+  pub token: String,
+  // The id or title of the numbers column items are scored from, for items without points in
+  // their name
+  pub points_column: String,
+}
+
+impl Default for MondayAuth {
+  fn default() -> MondayAuth {
+    MondayAuth {
+      token: "".to_string(),
+      points_column: "Points".to_string(),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug)]
+struct Response<T> {
+  data: Option<T>,
+  #[serde(default)]
+  errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQLError {
+  message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Boards<T> {
+  boards: Vec<T>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MondayBoard {
+  id: String,
+  name: String,
+  #[serde(default)]
+  groups: Vec<Group>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Group {
+  id: String,
+  title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ItemsPage {
+  cursor: Option<String>,
+  items: Vec<Item>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BoardItems {
+  items_page: ItemsPage,
+}
+
+#[derive(Deserialize, Debug)]
+struct NextItems {
+  next_items_page: ItemsPage,
+}
+
+#[derive(Deserialize, Debug)]
+struct ColumnTitle {
+  title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ColumnValue {
+  id: String,
+  #[serde(rename = "type")]
+  kind: String,
+  text: Option<String>,
+  column: Option<ColumnTitle>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Item {
+  id: String,
+  name: String,
+  created_at: Option<DateTime<Utc>>,
+  group: Group,
+  #[serde(default)]
+  column_values: Vec<ColumnValue>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ActivityLog {
+  event: String,
+  data: String,
+  created_at: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BoardLogs {
+  activity_logs: Vec<ActivityLog>,
+}
+
+pub struct MondayClient {
+  client: reqwest::Client,
+  auth: MondayAuth,
+  scorer: Scorer,
+}
+
+impl From<&MondayBoard> for Board {
+  fn from(board: &MondayBoard) -> Self {
+    Board {
+      id: board.id.clone(),
+      name: board.name.clone(),
+    }
+  }
+}
+
+impl Item {
+  fn column(&self, id_or_title: &str) -> Option<&ColumnValue> {
+    self.column_values.iter().find(|value| {
+      value.id == id_or_title
+        || value
+          .column
+          .as_ref()
+          .map(|column| column.title == id_or_title)
+          .unwrap_or(false)
+    })
+  }
+
+  /// Monday gives us people and tags as comma separated text
+  fn names(&self, kind: &str) -> Vec<String> {
+    self
+      .column_values
+      .iter()
+      .filter(|value| value.kind == kind)
+      .filter_map(|value| value.text.as_ref())
+      .flat_map(|text| text.split(", "))
+      .filter(|name| !name.is_empty())
+      .map(String::from)
+      .collect()
+  }
+
+  fn into_card(self, points_column: &str, scorer: &Scorer) -> Card {
+    let points = self
+      .column(points_column)
+      .and_then(|value| value.text.as_ref())
+      .and_then(|text| text.parse::<f64>().ok());
+    let members = self.names("people");
+    let labels = self.names("tags");
+    let due = self
+      .column_values
+      .iter()
+      .find(|value| value.kind == "date")
+      .and_then(|value| value.text.as_deref())
+      .and_then(parse_due_date);
+
+    Card {
+      name: name_with_points(scorer, self.name, points),
+      parent_list: self.group.id,
+      members,
+      labels,
+      due,
+    }
+  }
+}
+
+/// Activity log timestamps are in tenths of a microsecond since the epoch
+fn parse_log_time(created_at: &str) -> Option<DateTime<Utc>> {
+  let ticks = created_at.parse::<i64>().ok()?;
+  DateTime::from_timestamp(ticks / 10_000_000, 0)
+}
+
+/// Rebuilds the groups each item has moved through from a board's activity log
+fn build_histories(items: Vec<Item>, logs: &[ActivityLog]) -> Vec<CardHistory> {
+  let mut moves: HashMap<String, Vec<Transition>> = HashMap::new();
+  for log in logs
+    .iter()
+    .filter(|log| log.event == "move_pulse_into_group")
+  {
+    let data: Value = match serde_json::from_str(&log.data) {
+      Ok(data) => data,
+      Err(_) => continue,
+    };
+    let (item_id, to, at) = match (
+      data["pulse_id"].as_i64(),
+      data["dest_group"]["title"].as_str(),
+      parse_log_time(&log.created_at),
+    ) {
+      (Some(item_id), Some(to), Some(at)) => (item_id, to, at),
+      _ => continue,
+    };
+    moves
+      .entry(item_id.to_string())
+      .or_default()
+      .push(Transition {
+        from: data["source_group"]["title"].as_str().map(String::from),
+        to: to.to_string(),
+        at,
+      });
+  }
+
+  items
+    .into_iter()
+    .map(|item| {
+      let mut transitions = moves.remove(&item.id).unwrap_or_default();
+      transitions.sort_by_key(|transition| transition.at);
+      let members = item.names("people");
+
+      CardHistory {
+        created: item
+          .created_at
+          .or_else(|| transitions.first().map(|transition| transition.at))
+          .unwrap_or_else(Utc::now),
+        id: item.id,
+        name: item.name,
+        current_list: item.group.title,
+        members,
+        transitions,
+      }
+    })
+    .collect()
+}
+
+static ITEM_FIELDS: &str =
+  "id name created_at group { id title } column_values { id type text column { title } }";
+
+impl MondayClient {
+  pub fn init(config: &Config) -> Result<Self> {
+    match &config.kanban {
+      config::KanbanBoard::Monday(auth) => Ok(MondayClient {
+        client: reqwest::Client::new(),
+        auth: auth.to_owned(),
+        scorer: config.scorer()?,
+      }),
+      _ => Err(eyre!(
+        "Unable to find information needed to authenticate with Monday API."
+      )),
+    }
+  }
+
+  async fn query<T: DeserializeOwned>(&self, query: &str, variables: Value) -> Result<T> {
+    let response = self
+      .client
+      .post(MONDAY_API)
+      .header("Authorization", &self.auth.token)
+      .header("API-Version", MONDAY_VERSION)
+      .json(&json!({ "query": query, "variables": variables }))
+      .send()
+      .await?;
+
+    if let Err(err) = response.error_for_status_ref() {
+      return match err.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) => Err(eyre!(
+          "Monday rejected your API token. You can find your token under Developers > My access tokens"
+        )),
+        _ => Err(eyre!(err.to_string())),
+      };
+    }
+
+    let response: Response<T> = response
+      .json()
+      .await
+      .map_err(|_e| JsonParseError("Monday".to_string()))?;
+    if let Some(error) = response.errors.first() {
+      return Err(eyre!("Monday returned an error: {}", error.message));
+    }
+    response
+      .data
+      .ok_or_else(|| eyre!("Monday returned an empty response"))
+  }
+
+  async fn get_monday_board(&self, board_id: &str) -> Result<MondayBoard> {
+    let boards: Boards<MondayBoard> = self
+      .query(
+        "query ($board: [ID!]) { boards(ids: $board) { id name groups { id title } } }",
+        json!({ "board": [board_id] }),
+      )
+      .await?;
+    boards
+      .boards
+      .into_iter()
+      .next()
+      .ok_or_else(|| eyre!("Unable to find board {} on Monday", board_id))
+  }
+
+  /// Fetches every item on a board, Monday hands them out 500 at a time with a cursor for the rest
+  async fn get_items(&self, board_id: &str) -> Result<Vec<Item>> {
+    let boards: Boards<BoardItems> = self
+      .query(
+        &format!(
+          "query ($board: [ID!]) {{ boards(ids: $board) {{ items_page(limit: 500) {{ cursor items {{ {} }} }} }} }}",
+          ITEM_FIELDS
+        ),
+        json!({ "board": [board_id] }),
+      )
+      .await?;
+    let mut page = boards
+      .boards
+      .into_iter()
+      .next()
+      .ok_or_else(|| eyre!("Unable to find board {} on Monday", board_id))?
+      .items_page;
+
+    let mut items = vec![];
+    loop {
+      items.extend(page.items);
+      let cursor = match page.cursor {
+        Some(cursor) => cursor,
+        None => return Ok(items),
+      };
+      let next: NextItems = self
+        .query(
+          &format!(
+            "query ($cursor: String!) {{ next_items_page(limit: 500, cursor: $cursor) {{ cursor items {{ {} }} }} }}",
+            ITEM_FIELDS
+          ),
+          json!({ "cursor": cursor }),
+        )
+        .await?;
+      page = next.next_items_page;
+    }
+  }
+}
+
+#[async_trait]
+impl Kanban for MondayClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    Ok((&self.get_monday_board(board_id).await?).into())
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    let boards: Boards<MondayBoard> = self
+      .query("{ boards(limit: 500) { id name } }", json!({}))
+      .await?;
+    Ok(boards.boards.iter().map(|board| board.into()).collect())
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    let boards: HashMap<String, Board> = self
+      .get_boards()
+      .await?
+      .into_iter()
+      .map(|board| (board.name.clone(), board))
+      .collect();
+
+    let mut board_names: Vec<String> = boards.keys().cloned().collect();
+    board_names.sort();
+    let name_index: usize = Select::new()
+      .with_prompt("Select a board: ")
+      .items(&board_names)
+      .default(0)
+      .max_length(15)
+      .interact()
+      .wrap_err_with(|| "There was an error while trying to select a board.")?;
+
+    Ok(
+      boards
+        .get(&board_names[name_index])
+        .ok_or_else(|| eyre!("There was an error fetching selected board"))?
+        .to_owned(),
+    )
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    Ok(
+      self
+        .get_monday_board(board_id)
+        .await?
+        .groups
+        .into_iter()
+        .map(|group| List {
+          id: group.id,
+          name: group.title,
+          board_id: board_id.to_string(),
+        })
+        .collect(),
+    )
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    Ok(
+      self
+        .get_items(board_id)
+        .await?
+        .into_iter()
+        .map(|item| item.into_card(&self.auth.points_column, &self.scorer))
+        .collect(),
+    )
+  }
+
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let items = self.get_items(board_id).await?;
+    let logs: Boards<BoardLogs> = self
+      .query(
+        "query ($board: [ID!]) { boards(ids: $board) { activity_logs(limit: 10000) { event data created_at } } }",
+        json!({ "board": [board_id] }),
+      )
+      .await?;
+    let logs: Vec<ActivityLog> = logs
+      .boards
+      .into_iter()
+      .flat_map(|board| board.activity_logs)
+      .collect();
+
+    Ok(build_histories(items, &logs))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn item() -> Item {
+    serde_json::from_value(json!({
+      "id": "1",
+      "name": "Add login page",
+      "created_at": "2021-03-01T09:00:00Z",
+      "group": {"id": "topics", "title": "Done"},
+      "column_values": [
+        {"id": "numbers", "type": "numbers", "text": "8", "column": {"title": "Points"}},
+        {"id": "person", "type": "people", "text": "Justin, Sam", "column": {"title": "Owner"}},
+        {"id": "tags", "type": "tags", "text": "", "column": {"title": "Tags"}}
+      ]
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn it_turns_items_into_cards() {
+    let card = item().into_card("Points", &Scorer::default());
+    assert_eq!(card.name, "(8) Add login page");
+    assert_eq!(card.parent_list, "topics");
+    assert_eq!(card.members, vec!["Justin".to_string(), "Sam".to_string()]);
+    assert!(card.labels.is_empty());
+
+    assert_eq!(
+      item().into_card("numbers", &Scorer::default()).name,
+      "(8) Add login page"
+    );
+  }
+
+  #[test]
+  fn it_rebuilds_group_moves_from_the_activity_log() {
+    let log = |from: &str, to: &str, day: i64| ActivityLog {
+      event: "move_pulse_into_group".to_string(),
+      data: json!({
+        "pulse_id": 1,
+        "source_group": {"title": from},
+        "dest_group": {"title": to}
+      })
+      .to_string(),
+      created_at: ((Utc
+        .with_ymd_and_hms(2021, 3, 1, 9, 0, 0)
+        .unwrap()
+        .timestamp()
+        + day * 86400)
+        * 10_000_000)
+        .to_string(),
+    };
+    let logs = vec![log("Doing", "Done", 3), log("To Do", "Doing", 1)];
+
+    let histories = build_histories(vec![item()], &logs);
+    assert_eq!(histories[0].current_list, "Done");
+    assert_eq!(
+      histories[0].transitions,
+      vec![
+        Transition {
+          from: Some("To Do".to_string()),
+          to: "Doing".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 2, 9, 0, 0).unwrap(),
+        },
+        Transition {
+          from: Some("Doing".to_string()),
+          to: "Done".to_string(),
+          at: Utc.with_ymd_and_hms(2021, 3, 4, 9, 0, 0).unwrap(),
+        },
+      ]
+    );
+  }
+}
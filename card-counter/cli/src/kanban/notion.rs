@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use crate::{
+  database::config::{self, Config},
+  errors::*,
+  kanban::{name_with_points, parse_due_date, Board, Card, CardHistory, Kanban, List},
+  score::Scorer,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dialoguer::Select;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+static NOTION_API: &str = "https://api.notion.com/v1";
+static NOTION_VERSION: &str = "2022-06-28";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotionAuth {
+  pub token: String,
+  // The status or select property whose options are used as lists
+  pub status_property: String,
+  // The number property cards are scored from, for pages without points in their title
+  pub points_property: String,
+}
+
+impl Default for NotionAuth {
+  fn default() -> NotionAuth {
+    NotionAuth {
+      token: "".to_string(),
+      status_property: "Status".to_string(),
+      points_property: "Points".to_string(),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug)]
+struct Paged<T> {
+  results: Vec<T>,
+  has_more: bool,
+  next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Database {
+  id: String,
+  #[serde(default)]
+  title: Vec<RichText>,
+  #[serde(default)]
+  properties: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RichText {
+  plain_text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page {
+  id: String,
+  created_time: DateTime<Utc>,
+  properties: HashMap<String, Value>,
+}
+
+pub struct NotionClient {
+  client: reqwest::Client,
+  auth: NotionAuth,
+  scorer: Scorer,
+}
+
+fn plain_text(text: &[RichText]) -> String {
+  text.iter().map(|text| text.plain_text.as_str()).collect()
+}
+
+impl From<&Database> for Board {
+  fn from(database: &Database) -> Self {
+    Board {
+      id: database.id.clone(),
+      name: plain_text(&database.title),
+    }
+  }
+}
+
+/// The options for a status or select property, in the order Notion shows them
+fn property_options(property: &Value) -> Vec<String> {
+  let kind = property["type"].as_str().unwrap_or_default();
+  property[kind]["options"]
+    .as_array()
+    .map(|options| {
+      options
+        .iter()
+        .filter_map(|option| option["name"].as_str().map(String::from))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+impl Page {
+  fn properties_of_type<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Value> {
+    self
+      .properties
+      .values()
+      .filter(move |property| property["type"] == kind)
+  }
+
+  fn title(&self) -> String {
+    self
+      .properties_of_type("title")
+      .next()
+      .and_then(|property| property["title"].as_array())
+      .map(|text| {
+        text
+          .iter()
+          .filter_map(|text| text["plain_text"].as_str())
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  /// The selected option for a status or select property
+  fn status(&self, name: &str) -> Option<String> {
+    let property = self.properties.get(name)?;
+    let kind = property["type"].as_str()?;
+    property[kind]["name"].as_str().map(String::from)
+  }
+
+  fn names(&self, kind: &str) -> Vec<String> {
+    self
+      .properties_of_type(kind)
+      .filter_map(|property| property[kind].as_array())
+      .flatten()
+      .filter_map(|value| value["name"].as_str().map(String::from))
+      .collect()
+  }
+
+  /// The start of the page's first date property
+  fn due(&self) -> Option<DateTime<Utc>> {
+    self
+      .properties_of_type("date")
+      .find_map(|property| property["date"]["start"].as_str())
+      .and_then(parse_due_date)
+  }
+
+  fn into_card(self, auth: &NotionAuth, scorer: &Scorer) -> Card {
+    let points = self
+      .properties
+      .get(&auth.points_property)
+      .and_then(|property| property["number"].as_f64());
+
+    Card {
+      name: name_with_points(scorer, self.title(), points),
+      parent_list: self.status(&auth.status_property).unwrap_or_default(),
+      members: self.names("people"),
+      labels: self.names("multi_select"),
+      due: self.due(),
+    }
+  }
+}
+
+impl NotionClient {
+  pub fn init(config: &Config) -> Result<Self> {
+    match &config.kanban {
+      config::KanbanBoard::Notion(auth) => Ok(NotionClient {
+        client: reqwest::Client::new(),
+        auth: auth.to_owned(),
+        scorer: config.scorer()?,
+      }),
+      _ => Err(eyre!(
+        "Unable to find information needed to authenticate with Notion API."
+      )),
+    }
+  }
+
+  async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+    let response = request
+      .bearer_auth(&self.auth.token)
+      .header("Notion-Version", NOTION_VERSION)
+      .send()
+      .await?;
+
+    if let Err(err) = response.error_for_status_ref() {
+      return match err.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) => Err(eyre!(
+          "Notion rejected your integration token. Check the token at https://www.notion.so/my-integrations"
+        )),
+        Some(reqwest::StatusCode::NOT_FOUND) => Err(eyre!(
+          "Unable to find that database in Notion. Make sure it has been shared with your integration."
+        )),
+        _ => Err(eyre!(err.to_string())),
+      };
+    }
+
+    Ok(
+      response
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Notion".to_string()))?,
+    )
+  }
+
+  /// Posts body to route, following next_cursor until every result has been fetched
+  async fn post_all<T: DeserializeOwned>(&self, route: &str, body: Value) -> Result<Vec<T>> {
+    let mut results = vec![];
+    let mut cursor: Option<String> = None;
+    loop {
+      let mut body = body.clone();
+      body["page_size"] = json!(100);
+      if let Some(cursor) = &cursor {
+        body["start_cursor"] = json!(cursor);
+      }
+
+      let page: Paged<T> = self
+        .send(
+          self
+            .client
+            .post(format!("{}/{}", NOTION_API, route))
+            .json(&body),
+        )
+        .await?;
+      results.extend(page.results);
+      match (page.has_more, page.next_cursor) {
+        (true, Some(next)) => cursor = Some(next),
+        _ => return Ok(results),
+      }
+    }
+  }
+
+  async fn get_database(&self, database_id: &str) -> Result<Database> {
+    self
+      .send(
+        self
+          .client
+          .get(format!("{}/databases/{}", NOTION_API, database_id)),
+      )
+      .await
+  }
+
+  async fn get_pages(&self, database_id: &str) -> Result<Vec<Page>> {
+    self
+      .post_all(&format!("databases/{}/query", database_id), json!({}))
+      .await
+  }
+}
+
+#[async_trait]
+impl Kanban for NotionClient {
+  async fn get_board(&self, board_id: &str) -> Result<Board> {
+    Ok((&self.get_database(board_id).await?).into())
+  }
+
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    let databases: Vec<Database> = self
+      .post_all(
+        "search",
+        json!({"filter": {"property": "object", "value": "database"}}),
+      )
+      .await?;
+    Ok(databases.iter().map(|database| database.into()).collect())
+  }
+
+  async fn select_board(&self) -> Result<Board> {
+    let boards: HashMap<String, Board> = self
+      .get_boards()
+      .await?
+      .into_iter()
+      .map(|board| (board.name.clone(), board))
+      .collect();
+
+    let mut board_names: Vec<String> = boards.keys().cloned().collect();
+    board_names.sort();
+    let name_index: usize = Select::new()
+      .with_prompt("Select a database: ")
+      .items(&board_names)
+      .default(0)
+      .max_length(15)
+      .interact()
+      .wrap_err_with(|| "There was an error while trying to select a database.")?;
+
+    Ok(
+      boards
+        .get(&board_names[name_index])
+        .ok_or_else(|| eyre!("There was an error fetching selected database"))?
+        .to_owned(),
+    )
+  }
+
+  async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
+    let database = self.get_database(board_id).await?;
+    let property = database
+      .properties
+      .get(&self.auth.status_property)
+      .ok_or_else(|| {
+        eyre!(
+          "Unable to find a {} property in {}",
+          self.auth.status_property,
+          plain_text(&database.title)
+        )
+      })?;
+
+    Ok(
+      property_options(property)
+        .into_iter()
+        .map(|name| List {
+          id: name.clone(),
+          name,
+          board_id: board_id.to_string(),
+        })
+        .collect(),
+    )
+  }
+
+  async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    Ok(
+      self
+        .get_pages(board_id)
+        .await?
+        .into_iter()
+        .map(|page| page.into_card(&self.auth, &self.scorer))
+        .collect(),
+    )
+  }
+
+  /// Notion doesn't keep a history of property changes, so only where each page is now and when
+  /// it was created are known
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    Ok(
+      self
+        .get_pages(board_id)
+        .await?
+        .into_iter()
+        .map(|page| CardHistory {
+          name: page.title(),
+          current_list: page.status(&self.auth.status_property).unwrap_or_default(),
+          members: page.names("people"),
+          created: page.created_time,
+          id: page.id,
+          transitions: vec![],
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_reads_cards_from_page_properties() {
+    let page: Page = serde_json::from_value(json!({
+      "id": "page",
+      "created_time": "2021-03-01T09:00:00.000Z",
+      "properties": {
+        "Name": {"type": "title", "title": [{"plain_text": "Add "}, {"plain_text": "login page"}]},
+        "Status": {"type": "status", "status": {"name": "In Progress"}},
+        "Points": {"type": "number", "number": 5},
+        "Owner": {"type": "people", "people": [{"name": "Justin"}]},
+        "Tags": {"type": "multi_select", "multi_select": [{"name": "frontend"}]}
+      }
+    }))
+    .unwrap();
+
+    let card = page.into_card(&NotionAuth::default(), &Scorer::default());
+    assert_eq!(card.name, "(5) Add login page");
+    assert_eq!(card.parent_list, "In Progress");
+    assert_eq!(card.members, vec!["Justin".to_string()]);
+    assert_eq!(card.labels, vec!["frontend".to_string()]);
+  }
+
+  #[test]
+  fn it_reads_lists_from_status_and_select_options() {
+    let status = json!({
+      "type": "status",
+      "status": {"options": [{"name": "Not started"}, {"name": "Done"}]}
+    });
+    let select = json!({"type": "select", "select": {"options": [{"name": "Backlog"}]}});
+
+    assert_eq!(property_options(&status), vec!["Not started", "Done"]);
+    assert_eq!(property_options(&select), vec!["Backlog"]);
+  }
+}
@@ -4,20 +4,79 @@ use crate::{
   database::config,
   database::config::Config,
   errors::*,
-  kanban::{Board, Card, Kanban, List},
+  kanban::{name_with_points, Board, Card, CardHistory, Kanban, List, Transition},
+  score::Scorer,
 };
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 use dialoguer::Select;
 use reqwest;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// The most actions Trello returns for a single request
+static ACTION_PAGE_SIZE: usize = 1000;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TrelloAuth {
   pub key: String,
   pub token: String,
   pub expiration: String,
+  // Unix timestamp for when the token was entered, used to tell if it has expired
+  #[serde(default)]
+  pub issued_at: Option<i64>,
+  // Name of the Custom Fields number field that holds story points, for cards without points in
+  // their name
+  #[serde(default)]
+  pub points_field: Option<String>,
+  // Id or short name of a workspace to pick boards from, rather than the boards you're a member of
+  #[serde(default)]
+  pub workspace: Option<String>,
+  // Adds up the points on checklist items, ex: "(2) Write migration", for cards without points in
+  // their name
+  #[serde(default)]
+  pub checklist_points: bool,
+  // Set from --include-archived for a single run, counts archived lists and cards
+  #[serde(skip)]
+  pub include_archived: bool,
+}
+
+/// How many seconds a token lasts for each of Trello's expiration options
+fn expiration_seconds(expiration: &str) -> Option<i64> {
+  match expiration {
+    "1hour" => Some(60 * 60),
+    "1day" => Some(24 * 60 * 60),
+    "30days" => Some(30 * 24 * 60 * 60),
+    _ => None,
+  }
+}
+
+impl TrelloAuth {
+  /// Whether the token has most likely lapsed by now. Tokens that never expire, or come from the
+  /// environment, are never considered expired. Temporary tokens that were saved before we started
+  /// recording when they were issued are assumed to have expired.
+  pub fn has_expired(&self, now: i64) -> bool {
+    match (expiration_seconds(&self.expiration), self.issued_at) {
+      (Some(lifetime), Some(issued_at)) => issued_at + lifetime <= now,
+      (Some(_), None) => true,
+      (None, _) => false,
+    }
+  }
+
+  /// The page a user visits to generate a new token, keeping the expiration they chose before
+  pub fn authorize_url(&self) -> String {
+    let expiration = if self.expiration.is_empty() {
+      "1day"
+    } else {
+      &self.expiration
+    };
+    format!(
+      "https://trello.com/1/authorize?expiration={}&name=card-counter&scope=read&response_type=token&key={}",
+      expiration, self.key
+    )
+  }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -32,8 +91,35 @@ pub struct TrelloList {
   pub color: Option<String>,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloMember {
+  pub id: String,
+  pub username: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloLabel {
+  pub id: String,
+  // Labels can be left unnamed, in which case they're only a colour
+  #[serde(default)]
+  pub name: String,
+  pub color: Option<String>,
+}
+
+impl TrelloLabel {
+  pub fn display_name(&self) -> String {
+    match (&self.name, &self.color) {
+      (name, Some(color)) if name.is_empty() => color.clone(),
+      (name, _) => name.clone(),
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TrelloCard {
+  #[serde(default)]
+  pub id: String,
+
   pub name: String,
 
   #[serde(rename = "idList")]
@@ -41,11 +127,116 @@ pub struct TrelloCard {
 
   #[serde(rename = "idBoard")]
   pub board_id: String,
+
+  // Only populated when the request includes members=true
+  #[serde(default)]
+  pub members: Vec<TrelloMember>,
+
+  #[serde(default)]
+  pub labels: Vec<TrelloLabel>,
+
+  // Only populated when the request includes customFieldItems=true
+  #[serde(default, rename = "customFieldItems")]
+  pub custom_field_items: Vec<TrelloCustomFieldItem>,
+
+  // Only populated when the request includes checklists=all
+  #[serde(default)]
+  pub checklists: Vec<TrelloChecklist>,
+
+  #[serde(default)]
+  pub due: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloChecklist {
+  #[serde(default, rename = "checkItems")]
+  pub check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloCheckItem {
+  pub name: String,
+}
+
+/// A field added to a board by the Custom Fields power-up
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloCustomField {
+  pub id: String,
+  pub name: String,
+  #[serde(rename = "type")]
+  pub field_type: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloCustomFieldItem {
+  #[serde(rename = "idCustomField")]
+  pub id_custom_field: String,
+  // Trello sends every value as a string keyed by the field's type, ex: {"number": "3"}
+  #[serde(default)]
+  pub value: Value,
+}
+
+impl TrelloCard {
+  /// The value of the number custom field with id field_id
+  pub fn custom_number(&self, field_id: &str) -> Option<f64> {
+    self
+      .custom_field_items
+      .iter()
+      .find(|item| item.id_custom_field == field_id)
+      .and_then(|item| item.value["number"].as_str())
+      .and_then(|number| number.parse::<f64>().ok())
+  }
+
+  /// The total of the points on the card's checklist items, if any of them have some. A
+  /// correction counts in place of the item's estimate.
+  pub fn checklist_points(&self, scorer: &Scorer) -> Option<f64> {
+    self
+      .checklists
+      .iter()
+      .flat_map(|checklist| checklist.check_items.iter())
+      .filter_map(|item| {
+        let score = scorer.score(&item.name)?;
+        score.correction.or(score.estimated)
+      })
+      .fold(None, |total: Option<f64>, points| {
+        Some(total.unwrap_or(0.) + points as f64)
+      })
+  }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloActionList {
+  pub id: String,
+  pub name: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloActionCard {
+  pub id: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloActionData {
+  pub card: Option<TrelloActionCard>,
+  #[serde(rename = "listBefore")]
+  pub list_before: Option<TrelloActionList>,
+  #[serde(rename = "listAfter")]
+  pub list_after: Option<TrelloActionList>,
+}
+
+/// A card being moved between lists, from the updateCard:idList action
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrelloAction {
+  pub id: String,
+  pub date: DateTime<Utc>,
+  pub data: TrelloActionData,
 }
 
 pub struct TrelloClient {
   pub client: reqwest::Client,
   pub auth: TrelloAuth,
+  // Reads the points on checklist items
+  pub scorer: Scorer,
 }
 
 impl From<TrelloList> for List {
@@ -73,6 +264,17 @@ impl From<TrelloCard> for Card {
     Card {
       name: card.name,
       parent_list: card.id_list,
+      members: card
+        .members
+        .into_iter()
+        .map(|member| member.username)
+        .collect(),
+      labels: card
+        .labels
+        .iter()
+        .map(|label| label.display_name())
+        .collect(),
+      due: card.due,
     }
   }
 }
@@ -82,17 +284,158 @@ impl From<&TrelloCard> for Card {
     Card {
       name: card.name.clone(),
       parent_list: card.id_list.clone(),
+      members: card
+        .members
+        .iter()
+        .map(|member| member.username.clone())
+        .collect(),
+      labels: card
+        .labels
+        .iter()
+        .map(|label| label.display_name())
+        .collect(),
+      due: card.due,
     }
   }
 }
 
+/// Trello ids start with the unix timestamp, in hex, of when the object was created
+fn created_from_id(id: &str) -> Option<DateTime<Utc>> {
+  let seconds = i64::from_str_radix(id.get(0..8)?, 16).ok()?;
+  DateTime::from_timestamp(seconds, 0)
+}
+
+/// Rebuilds the path each card took across the board from the list movement actions
+pub fn build_card_histories(
+  cards: &[TrelloCard],
+  lists: &[TrelloList],
+  actions: &[TrelloAction],
+) -> Vec<CardHistory> {
+  let list_names: HashMap<&str, &str> = lists
+    .iter()
+    .map(|list| (list.id.as_str(), list.name.as_str()))
+    .collect();
+
+  let mut moves: HashMap<&str, Vec<Transition>> = HashMap::new();
+  for action in actions {
+    if let (Some(card), Some(after)) = (&action.data.card, &action.data.list_after) {
+      moves.entry(card.id.as_str()).or_default().push(Transition {
+        from: action
+          .data
+          .list_before
+          .as_ref()
+          .map(|list| list.name.clone()),
+        to: after.name.clone(),
+        at: action.date,
+      });
+    }
+  }
+
+  cards
+    .iter()
+    .map(|card| {
+      let mut transitions = moves.remove(card.id.as_str()).unwrap_or_default();
+      transitions.sort_by_key(|transition| transition.at);
+      let created = created_from_id(&card.id)
+        .or_else(|| transitions.first().map(|transition| transition.at))
+        .unwrap_or_else(Utc::now);
+
+      CardHistory {
+        id: card.id.clone(),
+        name: card.name.clone(),
+        created,
+        current_list: list_names
+          .get(card.id_list.as_str())
+          .map(|name| name.to_string())
+          .unwrap_or_else(|| card.id_list.clone()),
+        members: card
+          .members
+          .iter()
+          .map(|member| member.username.clone())
+          .collect(),
+        transitions,
+      }
+    })
+    .collect()
+}
+
 impl TrelloClient {
-  pub fn init(config: &Config) -> Self {
+  /// Sends a GET request to a route and parses the response, checking that we're authenticated
+  async fn get_json<T: DeserializeOwned>(&self, route: &str) -> Result<T> {
+    let response = self.client.get(route).send().await?;
+
+    no_authentication(&self.auth, &response)?;
+
+    if let Err(err) = response.error_for_status_ref() {
+      match err.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) => {
+          return Err(AuthError::Trello(self.auth.key.clone()).into())
+        }
+        _ => return Err(eyre!(err.to_string())),
+      }
+    };
+
+    Ok(
+      response
+        .json()
+        .await
+        .map_err(|_e| JsonParseError("Trello".to_string()))?,
+    )
+  }
+
+  /// Finds the id of the number custom field named points_field on a board
+  async fn points_field_id(&self, board_id: &str, points_field: &str) -> Result<String> {
+    let fields: Vec<TrelloCustomField> = self
+      .get_json(&format!(
+        "https://api.trello.com/1/boards/{}/customFields?key={}&token={}",
+        board_id, self.auth.key, self.auth.token
+      ))
+      .await?;
+
+    fields
+      .into_iter()
+      .find(|field| field.name == points_field && field.field_type == "number")
+      .map(|field| field.id)
+      .ok_or_else(|| {
+        eyre!(
+          "Unable to find a number custom field named {} on board {}",
+          points_field,
+          board_id
+        )
+      })
+  }
+
+  /// Returns every time a card on the board was moved between lists, newest first. Trello caps
+  /// actions at ACTION_PAGE_SIZE per request, so we keep asking for the ones before the oldest
+  /// we've seen until a page comes back short.
+  async fn get_list_moves(&self, board_id: &str) -> Result<Vec<TrelloAction>> {
+    let mut actions: Vec<TrelloAction> = vec![];
+    loop {
+      let mut route = format!(
+        "https://api.trello.com/1/boards/{}/actions?filter=updateCard:idList&limit={}&key={}&token={}",
+        board_id, ACTION_PAGE_SIZE, self.auth.key, self.auth.token
+      );
+      if let Some(oldest) = actions.last() {
+        route = format!("{}&before={}", route, oldest.id);
+      }
+
+      let page: Vec<TrelloAction> = self.get_json(&route).await?;
+      let is_last = page.len() < ACTION_PAGE_SIZE;
+      actions.extend(page);
+      if is_last {
+        return Ok(actions);
+      }
+    }
+  }
+
+  /// Errors when the config's score patterns aren't valid
+  pub fn init(config: &Config) -> Result<Self> {
     match &config.kanban {
-      config::KanbanBoard::Trello(auth) => TrelloClient {
+      config::KanbanBoard::Trello(auth) => Ok(TrelloClient {
         client: reqwest::Client::new(),
         auth: auth.to_owned(),
-      },
+        scorer: config.scorer()?,
+      }),
       _ => panic!("Unable to find information needed to authenticate with Jira API."),
     }
   }
@@ -102,6 +445,15 @@ impl TrelloClient {
 pub fn no_authentication(auth: &TrelloAuth, response: &reqwest::Response) -> Result<()> {
   if let Err(err) = response.error_for_status_ref() {
     match err.status() {
+      Some(reqwest::StatusCode::UNAUTHORIZED) if auth.has_expired(Utc::now().timestamp()) => {
+        return Err(
+          AuthError::TrelloExpired {
+            expiration: auth.expiration.clone(),
+            url: auth.authorize_url(),
+          }
+          .into(),
+        )
+      }
       Some(reqwest::StatusCode::UNAUTHORIZED) => {
         return Err(AuthError::Trello(auth.key.clone()).into())
       }
@@ -112,6 +464,23 @@ pub fn no_authentication(auth: &TrelloAuth, response: &reqwest::Response) -> Res
   Ok(())
 }
 
+/// Trello leaves out archived lists by default, asking for them explicitly keeps counts matching
+/// the board
+fn lists_filter(include_archived: bool) -> &'static str {
+  match include_archived {
+    true => "all",
+    false => "open",
+  }
+}
+
+/// Visible cards are the open ones in open lists
+fn cards_filter(include_archived: bool) -> &'static str {
+  match include_archived {
+    true => "all",
+    false => "visible",
+  }
+}
+
 pub fn trello_to_lists(lists: Vec<TrelloList>) -> Vec<List> {
   lists.iter().map(|list| list.into()).collect()
 }
@@ -143,23 +512,25 @@ impl Kanban for TrelloClient {
     Ok(response.json().await?)
   }
 
-  /// Allows the user to select a board from a list
-  async fn select_board(&self) -> Result<Board> {
-    let route = format!(
-      "https://api.trello.com/1/members/me/boards?key={}&token={}",
-      self.auth.key, self.auth.token
-    );
-
-    // Getting all the boards
-    let response = self.client.get(&route).send().await?;
+  /// Allows the user to select a board from a list, either their own boards or every open board in
+  /// the configured workspace
+  async fn get_boards(&self) -> Result<Vec<Board>> {
+    let route = match &self.auth.workspace {
+      Some(workspace) => format!(
+        "https://api.trello.com/1/organizations/{}/boards?filter=open&fields=id,name&key={}&token={}",
+        workspace, self.auth.key, self.auth.token
+      ),
+      None => format!(
+        "https://api.trello.com/1/members/me/boards?key={}&token={}",
+        self.auth.key, self.auth.token
+      ),
+    };
 
-    // TODO: Handle this better
-    // maybe create a custom error types for status codes?
+    self.get_json(&route).await
+  }
 
-    let result: Vec<Board> = response
-      .json()
-      .await
-      .map_err(|_e| JsonParseError("Trello".to_string()))?;
+  async fn select_board(&self) -> Result<Board> {
+    let result: Vec<Board> = self.get_boards().await?;
 
     // Storing it as a hash-map, so we can easily retrieve and return the id
     let boards: HashMap<String, Board> =
@@ -185,8 +556,11 @@ impl Kanban for TrelloClient {
   /// Counts the number of cards for all lists, ignoring lists whose name include the string filter, on a given board.
   async fn get_lists(&self, board_id: &str) -> Result<Vec<List>> {
     let route = format!(
-      "https://api.trello.com/1/boards/{}/lists?key={}&token={}",
-      board_id, &self.auth.key, &self.auth.token
+      "https://api.trello.com/1/boards/{}/lists?filter={}&key={}&token={}",
+      board_id,
+      lists_filter(self.auth.include_archived),
+      &self.auth.key,
+      &self.auth.token
     );
 
     let response = self.client.get(&route).send().await?;
@@ -203,9 +577,21 @@ impl Kanban for TrelloClient {
 
   /// Returns all cards associated with a board
   async fn get_cards(&self, board_id: &str) -> Result<Vec<Card>> {
+    let points_field_id = match &self.auth.points_field {
+      Some(points_field) => Some(self.points_field_id(board_id, points_field).await?),
+      None => None,
+    };
     let route = format!(
-      "https://api.trello.com/1/boards/{}/cards?card_fields=name&key={}&token={}",
-      board_id, self.auth.key, self.auth.token
+      "https://api.trello.com/1/boards/{}/cards/{}?card_fields=name,idList,idBoard,labels,due&members=true&member_fields=username&customFieldItems={}&checklists={}&key={}&token={}",
+      board_id,
+      cards_filter(self.auth.include_archived),
+      points_field_id.is_some(),
+      match self.auth.checklist_points {
+        true => "all",
+        false => "none",
+      },
+      self.auth.key,
+      self.auth.token
     );
 
     let response = self.client.get(&route).send().await?;
@@ -227,6 +613,182 @@ impl Kanban for TrelloClient {
       .await
       .map_err(|_e| JsonParseError("Trello".to_string()))?;
 
-    Ok(trello_cards.iter().map(|card| card.into()).collect())
+    Ok(
+      trello_cards
+        .iter()
+        .map(|trello_card| {
+          let card: Card = trello_card.into();
+          let points = points_field_id
+            .as_ref()
+            .and_then(|field_id| trello_card.custom_number(field_id))
+            .or_else(|| match self.auth.checklist_points {
+              true => trello_card.checklist_points(&self.scorer),
+              false => None,
+            });
+          Card {
+            name: name_with_points(&self.scorer, card.name, points),
+            ..card
+          }
+        })
+        .collect(),
+    )
+  }
+
+  /// Returns every open card on a board along with the lists it has been moved between
+  async fn get_card_histories(&self, board_id: &str) -> Result<Vec<CardHistory>> {
+    let cards: Vec<TrelloCard> = self
+      .get_json(&format!(
+        "https://api.trello.com/1/boards/{}/cards?card_fields=name,idList,idBoard&members=true&member_fields=username&key={}&token={}",
+        board_id, self.auth.key, self.auth.token
+      ))
+      .await?;
+    let lists: Vec<TrelloList> = self
+      .get_json(&format!(
+        "https://api.trello.com/1/boards/{}/lists?filter=all&key={}&token={}",
+        board_id, self.auth.key, self.auth.token
+      ))
+      .await?;
+    let actions = self.get_list_moves(board_id).await?;
+
+    Ok(build_card_histories(&cards, &lists, &actions))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  #[test]
+  fn it_rebuilds_card_movement_from_actions() {
+    let list = |id: &str, name: &str| TrelloList {
+      id: id.to_string(),
+      board_id: "board".to_string(),
+      name: name.to_string(),
+      color: None,
+    };
+    let lists = vec![list("1", "To Do"), list("2", "Doing"), list("3", "Done")];
+    let cards = vec![TrelloCard {
+      id: "603c2e80aaaaaaaaaaaaaaaa".to_string(),
+      name: "(2) Write docs".to_string(),
+      id_list: "3".to_string(),
+      board_id: "board".to_string(),
+      members: vec![],
+      labels: vec![],
+      custom_field_items: vec![],
+      checklists: vec![],
+      due: None,
+    }];
+    let actions: Vec<TrelloAction> = serde_json::from_str(
+      r#"[
+        {
+          "id": "6042000000000000000000b2",
+          "date": "2021-03-05T10:00:00.000Z",
+          "data": {
+            "card": {"id": "603c2e80aaaaaaaaaaaaaaaa"},
+            "listBefore": {"id": "2", "name": "Doing"},
+            "listAfter": {"id": "3", "name": "Done"}
+          }
+        },
+        {
+          "id": "603e000000000000000000b1",
+          "date": "2021-03-02T10:00:00.000Z",
+          "data": {
+            "card": {"id": "603c2e80aaaaaaaaaaaaaaaa"},
+            "listBefore": {"id": "1", "name": "To Do"},
+            "listAfter": {"id": "2", "name": "Doing"}
+          }
+        }
+      ]"#,
+    )
+    .unwrap();
+
+    let histories = build_card_histories(&cards, &lists, &actions);
+    assert_eq!(histories.len(), 1);
+    assert_eq!(histories[0].current_list, "Done");
+    assert_eq!(
+      histories[0].created,
+      Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+      histories[0]
+        .transitions
+        .iter()
+        .map(|transition| transition.to.as_str())
+        .collect::<Vec<&str>>(),
+      vec!["Doing", "Done"]
+    );
+  }
+
+  #[test]
+  fn it_reads_points_from_custom_fields() {
+    let card: TrelloCard = serde_json::from_str(
+      r#"{
+        "name": "Write docs",
+        "idList": "1",
+        "idBoard": "board",
+        "customFieldItems": [
+          {"idCustomField": "priority", "value": {"text": "high"}},
+          {"idCustomField": "points", "value": {"number": "5"}}
+        ]
+      }"#,
+    )
+    .unwrap();
+
+    assert_eq!(card.custom_number("points"), Some(5.0));
+    assert_eq!(card.custom_number("priority"), None);
+    assert_eq!(card.custom_number("missing"), None);
+  }
+
+  #[test]
+  fn it_adds_up_points_on_checklist_items() {
+    let card = |checklists: &str| -> TrelloCard {
+      serde_json::from_str(&format!(
+        r#"{{"name": "Signup", "idList": "1", "idBoard": "board", "checklists": {}}}"#,
+        checklists
+      ))
+      .unwrap()
+    };
+
+    let scored = card(
+      r#"[
+        {"checkItems": [{"name": "(2) Form"}, {"name": "(3)[5] Validation"}]},
+        {"checkItems": [{"name": "Copy review"}, {"name": "(1) Emails"}]}
+      ]"#,
+    );
+    let scorer = Scorer::default();
+    assert_eq!(scored.checklist_points(&scorer), Some(8.0));
+    assert_eq!(
+      card(r#"[{"checkItems": [{"name": "Copy review"}]}]"#).checklist_points(&scorer),
+      None
+    );
+    assert_eq!(card("[]").checklist_points(&scorer), None);
+  }
+
+  #[test]
+  fn it_knows_when_a_token_has_expired() {
+    let auth = TrelloAuth {
+      key: "key".to_string(),
+      token: "token".to_string(),
+      expiration: "1day".to_string(),
+      issued_at: Some(0),
+      points_field: None,
+      workspace: None,
+      checklist_points: false,
+      include_archived: false,
+    };
+
+    assert!(!auth.has_expired(60 * 60));
+    assert!(auth.has_expired(24 * 60 * 60));
+    assert!(TrelloAuth {
+      issued_at: None,
+      ..auth.clone()
+    }
+    .has_expired(0));
+    assert!(!TrelloAuth {
+      expiration: "never".to_string(),
+      ..auth
+    }
+    .has_expired(24 * 60 * 60));
   }
 }
@@ -3,8 +3,12 @@ extern crate prettytable;
 
 pub mod score;
 
+pub mod cli;
 pub mod commands;
 pub mod database;
 pub mod kanban;
 
+#[cfg(feature = "dataframe")]
+pub mod dataframe;
+
 pub mod errors;
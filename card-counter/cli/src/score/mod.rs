@@ -1,10 +1,18 @@
 // File for retrieving cards from trello and scoring them
-use crate::kanban::{Card, List};
-use prettytable::Table;
-use regex::Captures;
+use crate::{
+  cli::FilterArgs,
+  commands::{burndown::ListMatcher, wip::wip_violations},
+  database::{
+    config::{CsvDialect, ScorePatterns, WipLimit},
+    CardSnapshot,
+  },
+  errors::*,
+  kanban::{Card, List},
+};
+use prettytable::{Cell, Row, Table};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap, sync::OnceLock};
 
 /// A deck represents some summary data about a list of Trello cards
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -66,33 +74,109 @@ pub fn build_decks(
   decks
 }
 
-/// Converts a trello effort score either [\d] or (\d) into a number.
-/// If the item inside the brackets can not be converted into a number,
-/// return None instead.
-fn score_to_num(capture: Option<Captures>) -> Option<i32> {
-  // If at any point this fails we should return None
-  capture.map(|cap| cap.get(0).unwrap()).map(|parsed_string| {
-    let maybe_score = String::from(parsed_string.as_str());
-    let maybe_number = &maybe_score[1..maybe_score.len() - 1];
-    maybe_number.parse::<i32>().unwrap()
-  })
+/// Finds the estimate and correction in a card's name using the score patterns from config
+#[derive(Clone, Debug)]
+pub struct Scorer {
+  estimate: Regex,
+  correction: Regex,
+  // Points kept in a field are added to the name as "(N) " by kanban::name_with_points, and are
+  // read no matter what the estimate pattern is
+  field_points: Regex,
+  // The estimate pattern and the space after it, for taking an estimate out of a name
+  estimate_and_space: Regex,
 }
 
-/// Extracts a score from a trello card, based on using [] or (). If no score is found a 0 is returned
-pub fn get_score(maybe_points: &str) -> Option<Score> {
-  // this will capture on "(0)" or "[0]" where 0 is an arbitrary sized digit
-  let correction = score_to_num(Regex::new(r"\[(\d+)\]").unwrap().captures(maybe_points));
+/// Compiles pattern, making sure it has a group named points to read the number from
+fn points_pattern(kind: &str, pattern: &str) -> Result<Regex> {
+  let regex = Regex::new(pattern)
+    .wrap_err_with(|| format!("Unable to parse {} pattern {}", kind, pattern))?;
+  match regex.capture_names().flatten().any(|name| name == "points") {
+    true => Ok(regex),
+    false => Err(eyre!(
+      "The {} pattern {} needs a group named points, ex: (?P<points>\\d+)",
+      kind,
+      pattern
+    )),
+  }
+}
 
-  let estimated = score_to_num(Regex::new(r"\((\d+)\)").unwrap().captures(maybe_points));
+/// The number captured as points, if there is one and it fits in an i32
+fn points(regex: &Regex, name: &str) -> Option<i32> {
+  regex.captures(name)?.name("points")?.as_str().parse().ok()
+}
+
+impl Scorer {
+  pub fn new(patterns: &ScorePatterns) -> Result<Self> {
+    Ok(Scorer {
+      estimate: points_pattern("estimate", &patterns.estimate)?,
+      correction: points_pattern("correction", &patterns.correction)?,
+      field_points: Regex::new(r"^\((?P<points>\d+)\) ").unwrap(),
+      estimate_and_space: Regex::new(&format!(r"(?:{})\s*", patterns.estimate))
+        .wrap_err_with(|| format!("Unable to parse estimate pattern {}", patterns.estimate))?,
+    })
+  }
 
-  if let (None, None) = (estimated, correction) {
-    return None;
+  /// Takes the estimate out of a card's name, leaving any correction behind
+  pub fn without_estimate(&self, name: &str) -> String {
+    self
+      .estimate_and_space
+      .replace(name, "")
+      .trim_end()
+      .to_string()
   }
 
-  Some(Score {
-    estimated,
-    correction,
-  })
+  /// Extracts a score from a card's name, returning None if it has neither an estimate nor a
+  /// correction
+  pub fn score(&self, name: &str) -> Option<Score> {
+    let estimated = points(&self.estimate, name).or_else(|| points(&self.field_points, name));
+    let correction = points(&self.correction, name);
+
+    if let (None, None) = (estimated, correction) {
+      return None;
+    }
+
+    Some(Score {
+      estimated,
+      correction,
+    })
+  }
+
+  /// Adds up the score, number of unscored cards, and estimated score for a set of card names.
+  /// Corrections count towards the score in place of the estimate.
+  pub fn tally<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> (i32, i32, i32) {
+    names
+      .into_iter()
+      .fold((0, 0, 0), |(total, unscored, estimate), name| {
+        match self.score(name) {
+          Some(Score {
+            correction: Some(correction),
+            ..
+          }) => (total + correction, unscored, estimate),
+          Some(Score {
+            estimated: Some(estimated),
+            ..
+          }) => (total + estimated, unscored, estimate + estimated),
+          _ => (total, unscored + 1, estimate),
+        }
+      })
+  }
+}
+
+impl Default for Scorer {
+  fn default() -> Self {
+    Scorer::new(&ScorePatterns::default()).expect("The default score patterns are valid")
+  }
+}
+
+/// The scorer for the default patterns, compiled the first time it's needed
+fn default_scorer() -> &'static Scorer {
+  static SCORER: OnceLock<Scorer> = OnceLock::new();
+  SCORER.get_or_init(Scorer::default)
+}
+
+/// Extracts a score from a card's name using the default patterns, (N) and [N]
+pub fn get_score(maybe_points: &str) -> Option<Score> {
+  default_scorer().score(maybe_points)
 }
 
 // Testable
@@ -103,25 +187,116 @@ pub fn calculate_delta(old_deck: &Deck, new_deck: &Deck) -> HashMap<String, i32>
     "cards".to_string(),
     new_deck.size as i32 - old_deck.size as i32,
   );
-  collection.insert(
-    "score".to_string(),
-    new_deck.score as i32 - old_deck.score as i32,
-  );
+  collection.insert("score".to_string(), new_deck.score - old_deck.score);
   collection.insert(
     "unscored".to_string(),
-    new_deck.unscored as i32 - old_deck.unscored as i32,
+    new_deck.unscored - old_deck.unscored,
   );
   collection.insert(
     "estimated".to_string(),
-    new_deck.estimated as i32 - old_deck.estimated as i32,
+    new_deck.estimated - old_deck.estimated,
   );
 
   collection
 }
 
-pub fn print_decks(decks: &[Deck], board_name: &str, filter: Option<&str>) {
+/// Colours used to highlight table cells, written as prettytable style specifiers. Ex: "Fg" for
+/// green text or "Br" for a red background.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+  // Used for changes that move the board forward
+  pub positive: String,
+  // Used for changes that move the board backwards
+  pub negative: String,
+  // Used for rows that need attention, such as lists over their WIP limit
+  pub warning: String,
+}
+
+/// Options that change how score tables are printed
+#[derive(Clone, Debug, Default)]
+pub struct TableOptions {
+  // Lists the filter doesn't keep are left out of the table
+  pub filter: ListFilter,
+  // Lines up lists with the ones in an earlier entry when comparing
+  pub list_names: ListNames,
+  // Groups lists by the text before the separator and prints a subtotal for each group
+  pub group_separator: Option<String>,
+  // Colours used to highlight cells, None prints the table without colour
+  pub palette: Option<Palette>,
+  // Lists with a name containing the first value are highlighted when the cards in them
+  // exceed the limit
+  pub wip_limit: Option<(String, i32)>,
+  // Lists and the most cards or points each should hold, lists over their limit are highlighted
+  // and warned about
+  pub wip_limits: Vec<(ListMatcher, WipLimit)>,
+}
+
+impl TableOptions {
+  /// Returns the style to use for a deck's row, if the row needs to be highlighted
+  fn row_style(&self, deck: &Deck, decks: &[Deck]) -> Option<&str> {
+    let palette = self.palette.as_ref()?;
+    if !wip_violations(std::slice::from_ref(deck), &self.wip_limits).is_empty() {
+      return Some(&palette.warning);
+    }
+    let (list, limit) = self.wip_limit.as_ref()?;
+    if !deck.list_name.contains(list.as_str()) {
+      return None;
+    }
+
+    let wip: i32 = decks
+      .iter()
+      .filter(|deck| deck.list_name.contains(list.as_str()))
+      .map(|deck| deck.size as i32)
+      .sum();
+
+    if wip > *limit {
+      Some(&palette.warning)
+    } else {
+      None
+    }
+  }
+
+  /// Styles a cell showing a value and how much it changed. When `lower_is_better` is true a
+  /// drop is treated as progress.
+  fn delta_cell(&self, value: String, delta: i32, lower_is_better: bool) -> Cell {
+    let cell = Cell::new(&format!("{} ({})", value, delta));
+    let palette = match &self.palette {
+      Some(palette) => palette,
+      None => return cell,
+    };
+
+    match (delta.cmp(&0), lower_is_better) {
+      (Ordering::Greater, false) | (Ordering::Less, true) => cell.style_spec(&palette.positive),
+      (Ordering::Less, false) | (Ordering::Greater, true) => cell.style_spec(&palette.negative),
+      (Ordering::Equal, _) => cell,
+    }
+  }
+}
+
+/// Builds a table row for a deck, styling every cell with style if it's given
+fn deck_row(deck: &Deck, style: Option<&str>) -> Row {
+  let cells = [
+    deck.list_name.clone(),
+    deck.size.to_string(),
+    deck.score.to_string(),
+    deck.estimated.to_string(),
+    deck.unscored.to_string(),
+  ];
+
+  Row::new(
+    cells
+      .iter()
+      .map(|cell| match style {
+        Some(style) => Cell::new(cell).style_spec(style),
+        None => Cell::new(cell),
+      })
+      .collect(),
+  )
+}
+
+pub fn print_decks(decks: &[Deck], board_name: &str, options: &TableOptions) {
   let mut table = Table::new();
-  let current_decks = filter_decks(decks, filter);
+  let current_decks = filter_decks(decks, &options.filter);
   let mut total = Deck {
     list_name: "TOTAL".to_string(),
     size: 0,
@@ -132,22 +307,153 @@ pub fn print_decks(decks: &[Deck], board_name: &str, filter: Option<&str>) {
 
   println!("{}", board_name);
   table.set_titles(row!["List", "cards", "score", "estimated", "unscored"]);
-  for deck in current_decks {
-    table.add_row(row![
-      deck.list_name,
-      deck.size,
-      deck.score,
-      deck.estimated,
-      deck.unscored
-    ]);
-    total = add_deck(&total, &deck);
+  let groups = match &options.group_separator {
+    Some(separator) => group_decks(&current_decks, separator),
+    None => vec![(None, current_decks.clone())],
+  };
+  for (group, decks) in groups {
+    let mut subtotal = Deck {
+      list_name: format!("{} subtotal", group.clone().unwrap_or_default()),
+      size: 0,
+      score: 0,
+      estimated: 0,
+      unscored: 0,
+    };
+    for deck in decks {
+      table.add_row(deck_row(&deck, options.row_style(&deck, &current_decks)));
+      subtotal = add_deck(&subtotal, &deck);
+      total = add_deck(&total, &deck);
+    }
+    if group.is_some() {
+      table.add_row(
+        row![i => subtotal.list_name, subtotal.size, subtotal.score, subtotal.estimated, subtotal.unscored],
+      );
+    }
   }
   table
     .add_row(row![bc => total.list_name, total.size, total.score, total.estimated, total.unscored]);
   table.printstd();
+
+  for violation in wip_violations(&current_decks, &options.wip_limits) {
+    eprintln!("Warning: {}", violation);
+  }
 }
 
-fn add_deck(total: &Deck, deck: &Deck) -> Deck {
+/// A row for each deck the filter keeps, followed by their total
+pub fn decks_as_csv(decks: &[Deck], filter: &ListFilter, dialect: &CsvDialect) -> Vec<String> {
+  let row = |deck: &Deck| {
+    dialect.row(&[
+      deck.list_name.clone(),
+      deck.size.to_string(),
+      deck.score.to_string(),
+      deck.estimated.to_string(),
+      deck.unscored.to_string(),
+    ])
+  };
+  let decks = filter_decks(decks, filter);
+  let total = decks.iter().fold(
+    Deck {
+      list_name: "TOTAL".to_string(),
+      size: 0,
+      score: 0,
+      estimated: 0,
+      unscored: 0,
+    },
+    |total, deck| add_deck(&total, deck),
+  );
+
+  let mut output = vec![dialect.row(&["List", "Cards", "Score", "Estimated", "Unscored"])];
+  output.extend(decks.iter().map(row));
+  output.push(row(&total));
+  output
+}
+
+/// The estimate and correction read from each card in list_name, in the order they were saved
+pub fn card_scores<'a>(
+  list_name: &str,
+  cards: &'a [CardSnapshot],
+  scorer: &Scorer,
+) -> Vec<(&'a str, Option<i32>, Option<i32>)> {
+  cards
+    .iter()
+    .filter(|card| card.list_name == list_name)
+    .map(|card| match scorer.score(&card.name) {
+      Some(score) => (card.name.as_str(), score.estimated, score.correction),
+      None => (card.name.as_str(), None, None),
+    })
+    .collect()
+}
+
+/// The cards estimated at points that aren't in allowed, along with their estimate. Nothing is off
+/// the scale when allowed is empty.
+pub fn off_scale<'a>(
+  cards: &'a [CardSnapshot],
+  scorer: &Scorer,
+  allowed: &[i32],
+) -> Vec<(&'a CardSnapshot, i32)> {
+  if allowed.is_empty() {
+    return vec![];
+  }
+
+  cards
+    .iter()
+    .filter_map(|card| Some((card, scorer.score(&card.name)?.estimated?)))
+    .filter(|(_, estimated)| !allowed.contains(estimated))
+    .collect()
+}
+
+/// Prints every card under the list it's in, along with the estimate and correction read from its
+/// name, so a surprising total can be traced back to the card behind it
+pub fn print_card_details(
+  decks: &[Deck],
+  cards: &[CardSnapshot],
+  scorer: &Scorer,
+  options: &TableOptions,
+) {
+  let points = |points: Option<i32>| points.map(|points| points.to_string()).unwrap_or_default();
+
+  for deck in filter_decks(decks, &options.filter) {
+    let scores = card_scores(&deck.list_name, cards, scorer);
+    if scores.is_empty() {
+      continue;
+    }
+
+    let mut table = Table::new();
+    table.set_titles(row!["Card", "estimate", "correction"]);
+    for (name, estimated, correction) in scores {
+      match (estimated, correction) {
+        (None, None) => table.add_row(row![i => name, "unscored", ""]),
+        _ => table.add_row(row![name, points(estimated), points(correction)]),
+      };
+    }
+    println!("\n{}", deck.list_name);
+    table.printstd();
+  }
+}
+
+/// Splits decks into groups based on the text before the first `separator` in the list name,
+/// such that "Sprint 42 / In Progress" belongs to the group "Sprint 42". Groups are returned in
+/// the order they first appear and lists without a separator are returned in a group of `None`.
+pub fn group_decks(decks: &[Deck], separator: &str) -> Vec<(Option<String>, Vec<Deck>)> {
+  decks.iter().fold(
+    Vec::new(),
+    |mut groups: Vec<(Option<String>, Vec<Deck>)>, deck| {
+      let group = deck
+        .list_name
+        .split_once(separator)
+        .map(|(prefix, _)| prefix.trim().to_string());
+
+      match groups.iter_mut().find(|(name, _)| *name == group) {
+        Some((_, decks)) => decks.push(deck.clone()),
+        None => groups.push((group, vec![deck.clone()])),
+      }
+      groups
+    },
+  )
+}
+
+/// Adds deck's numbers onto total, keeping total's list name
+pub fn add_deck(total: &Deck, deck: &Deck) -> Deck {
   Deck {
     list_name: total.list_name.clone(),
     size: total.size + deck.size,
@@ -157,22 +463,128 @@ fn add_deck(total: &Deck, deck: &Deck) -> Deck {
   }
 }
 
-fn filter_decks(decks: &[Deck], filter: Option<&str>) -> Vec<Deck> {
-  decks.iter().fold(Vec::new(), |mut container, list| {
-    match filter {
-      Some(value) => {
-        if !list.list_name.contains(value) {
-          container.push(list.clone());
-        }
-      }
-      None => container.push(list.clone()),
-    };
+/// Decides which lists are left out of scores. Terms are separated by commas and each is either
+/// text a list's name contains, or a regex between slashes, ex: `Archive,/^Sprint \d+$/`. With
+/// include_only, only the lists matching a term are kept instead.
+#[derive(Clone, Debug, Default)]
+pub struct ListFilter {
+  terms: Vec<Regex>,
+  include_only: bool,
+}
+
+impl ListFilter {
+  pub fn new(filter: Option<&str>, include_only: bool) -> Result<Self> {
+    let terms = filter
+      .unwrap_or_default()
+      .split(',')
+      .map(str::trim)
+      .filter(|term| !term.is_empty())
+      .map(|term| {
+        let pattern = match term
+          .strip_prefix('/')
+          .and_then(|term| term.strip_suffix('/'))
+        {
+          Some(pattern) => pattern.to_string(),
+          None => regex::escape(term),
+        };
+        Regex::new(&pattern).wrap_err_with(|| format!("Unable to parse filter {}", term))
+      })
+      .collect::<Result<Vec<Regex>>>()?;
+
+    Ok(ListFilter {
+      terms,
+      include_only,
+    })
+  }
+
+  /// Reads --filter and --include-only
+  pub fn from_args(args: &FilterArgs) -> Result<Self> {
+    ListFilter::new(args.filter.as_deref(), args.include_only)
+  }
+
+  /// Whether a list's cards are counted. Every list is kept when there aren't any terms.
+  pub fn keeps(&self, list_name: &str) -> bool {
+    if self.terms.is_empty() {
+      return true;
+    }
+    let matched = self.terms.iter().any(|term| term.is_match(list_name));
+    matched == self.include_only
+  }
+}
+
+/// Decides whether a list in an earlier entry is the same as one on the board now. Names are
+/// compared ignoring case and whitespace, after swapping any old name for the one it's aliased to.
+#[derive(Clone, Debug, Default)]
+pub struct ListNames {
+  aliases: HashMap<String, String>,
+}
 
-    container
-  })
+impl ListNames {
+  pub fn new(aliases: &HashMap<String, String>) -> Self {
+    ListNames {
+      aliases: aliases
+        .iter()
+        .map(|(old, new)| (normalize_list_name(old), normalize_list_name(new)))
+        .collect(),
+    }
+  }
+
+  fn canonical(&self, list_name: &str) -> String {
+    let name = normalize_list_name(list_name);
+    self.aliases.get(&name).cloned().unwrap_or(name)
+  }
+
+  /// The deck in old_decks for the same list as deck, preferring one with exactly the same name
+  pub fn find_matching<'a>(&self, deck: &Deck, old_decks: &'a [Deck]) -> Option<&'a Deck> {
+    old_decks
+      .iter()
+      .find(|old_deck| old_deck.list_name == deck.list_name)
+      .or_else(|| {
+        let name = self.canonical(&deck.list_name);
+        old_decks
+          .iter()
+          .find(|old_deck| self.canonical(&old_deck.list_name) == name)
+      })
+  }
+}
+
+fn normalize_list_name(list_name: &str) -> String {
+  list_name
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+pub fn filter_decks(decks: &[Deck], filter: &ListFilter) -> Vec<Deck> {
+  decks
+    .iter()
+    .filter(|deck| filter.keeps(&deck.list_name))
+    .cloned()
+    .collect()
 }
 /// Prints a that compares two decks to standard out
-pub fn print_delta(decks: &[Deck], old_decks: &[Deck], board_name: &str, filter: Option<&str>) {
+pub fn print_delta(decks: &[Deck], old_decks: &[Deck], board_name: &str, options: &TableOptions) {
+  println!("{}", board_name);
+  delta_table(decks, old_decks, options).printstd();
+  println!("* Printing in detailed mode. Numbers in () mark the difference from the last time card-counter was run and saved data.");
+}
+
+/// A row for deck with how much each column changed since old_deck
+fn delta_row(name: Cell, deck: &Deck, old_deck: &Deck, options: &TableOptions) -> Row {
+  let delta = calculate_delta(old_deck, deck);
+  Row::new(vec![
+    name,
+    options.delta_cell(deck.size.to_string(), delta["cards"], false),
+    options.delta_cell(deck.score.to_string(), delta["score"], false),
+    options.delta_cell(deck.estimated.to_string(), delta["estimated"], false),
+    options.delta_cell(deck.unscored.to_string(), delta["unscored"], true),
+  ])
+}
+
+/// Compares decks to old_decks, with a subtotal for each group when there's a group_separator.
+/// A group's subtotal is compared against the old lists its lists were matched with.
+fn delta_table(decks: &[Deck], old_decks: &[Deck], options: &TableOptions) -> Table {
   let mut table = Table::new();
 
   table.set_titles(row!["List", "Cards", "Score", "Estimated", "Unscored"]);
@@ -184,53 +596,58 @@ pub fn print_delta(decks: &[Deck], old_decks: &[Deck], board_name: &str, filter:
     unscored: 0,
   };
 
-  let current_decks = filter_decks(decks, filter);
-  let other_decks = filter_decks(old_decks, filter);
-
-  println!("{}", board_name);
-  for deck in current_decks {
-    let matching_deck: Option<Deck> = other_decks.iter().fold(None, |match_deck, maybe_deck| {
-      if maybe_deck.list_name == deck.list_name {
-        Some(maybe_deck.clone())
-      } else if match_deck.is_some() {
-        match_deck
-      } else {
-        None
-      }
-    });
-
-    match matching_deck {
-      Some(old_deck) => {
-        let delta = calculate_delta(&old_deck, &deck);
-        let cards = format!("{} ({})", deck.size, delta.get("cards").unwrap());
-        let score = format!("{} ({})", deck.score, delta.get("score").unwrap());
-        let estimated = format!("{} ({})", deck.estimated, delta.get("estimated").unwrap());
-        let unscored = format!("{} ({})", deck.unscored, delta.get("unscored").unwrap());
+  let current_decks = filter_decks(decks, &options.filter);
+  let other_decks = filter_decks(old_decks, &options.filter);
 
-        table.add_row(row![deck.list_name, cards, score, estimated, unscored]);
-      }
+  let groups = match &options.group_separator {
+    Some(separator) => group_decks(&current_decks, separator),
+    None => vec![(None, current_decks.clone())],
+  };
+  for (group, decks) in groups {
+    let mut subtotal = Deck {
+      list_name: format!("{} subtotal", group.clone().unwrap_or_default()),
+      size: 0,
+      score: 0,
+      estimated: 0,
+      unscored: 0,
+    };
+    let mut old_subtotal = subtotal.clone();
+    for deck in decks.iter() {
+      match options.list_names.find_matching(deck, &other_decks) {
+        Some(old_deck) => {
+          let name = match options.row_style(deck, &current_decks) {
+            Some(style) => Cell::new(&deck.list_name).style_spec(style),
+            None => Cell::new(&deck.list_name),
+          };
+          table.add_row(delta_row(name, deck, old_deck, options));
+          old_subtotal = add_deck(&old_subtotal, old_deck);
+        }
 
-      None => {
-        table.add_row(row![
-          deck.list_name,
-          deck.size,
-          deck.score,
-          deck.estimated,
-          deck.unscored
-        ]);
+        None => {
+          table.add_row(deck_row(deck, options.row_style(deck, &current_decks)));
+        }
       }
+      subtotal = add_deck(&subtotal, deck);
+      total = add_deck(&total, deck);
+    }
+    if group.is_some() {
+      let name = Cell::new(&subtotal.list_name).style_spec("i");
+      table.add_row(delta_row(name, &subtotal, &old_subtotal, options));
     }
-    total = add_deck(&total, &deck);
   }
   table
     .add_row(row![bc => total.list_name, total.size, total.score, total.estimated, total.unscored]);
-  table.printstd();
-  println!("* Printing in detailed mode. Numbers in () mark the difference from the last time card-counter was run and saved data.");
+  table
 }
 
 pub mod test {
   #[allow(unused_imports)]
-  use super::{get_score, Score};
+  use super::{
+    card_scores, decks_as_csv, delta_table, filter_decks, get_score, group_decks, off_scale, Deck,
+    ListFilter, ListNames, Score, ScorePatterns, Scorer, TableOptions,
+  };
+  #[allow(unused_imports)]
+  use crate::database::{config::CsvDialect, CardSnapshot};
 
   #[test]
   fn get_score_handles_curlies() {
@@ -273,4 +690,209 @@ pub mod test {
     );
     assert_eq!(get_score("[100000000](9)").unwrap().estimated, Some(9));
   }
+
+  #[test]
+  fn card_scores_reads_each_card_in_a_list() {
+    let card = |name: &str, list_name: &str| CardSnapshot {
+      name: name.to_string(),
+      list_name: list_name.to_string(),
+    };
+    let cards = vec![
+      card("(3)[5] Login page", "Done"),
+      card("(2) Signup page", "Doing"),
+      card("Docs", "Done"),
+    ];
+
+    assert_eq!(
+      card_scores("Done", &cards, &Scorer::default()),
+      vec![
+        ("(3)[5] Login page", Some(3), Some(5)),
+        ("Docs", None, None)
+      ]
+    );
+  }
+
+  #[test]
+  fn list_filter_matches_terms_and_regexes() {
+    let names = |filter: &ListFilter| -> Vec<String> {
+      let decks: Vec<Deck> = ["To Do", "Archive", "Sprint 4", "Sprint 4 Retro"]
+        .iter()
+        .map(|name| Deck {
+          list_name: name.to_string(),
+          size: 1,
+          score: 1,
+          unscored: 0,
+          estimated: 1,
+        })
+        .collect();
+      filter_decks(&decks, filter)
+        .into_iter()
+        .map(|deck| deck.list_name)
+        .collect()
+    };
+
+    let filter = ListFilter::new(Some("Archive, /^Sprint \\d+$/"), false).unwrap();
+    assert_eq!(names(&filter), vec!["To Do", "Sprint 4 Retro"]);
+    let filter = ListFilter::new(Some("Sprint"), true).unwrap();
+    assert_eq!(names(&filter), vec!["Sprint 4", "Sprint 4 Retro"]);
+    assert_eq!(names(&ListFilter::default()).len(), 4);
+    assert!(ListFilter::new(Some("/(/"), false).is_err());
+  }
+
+  #[test]
+  fn list_names_line_up_renamed_lists() {
+    let deck = |name: &str| Deck {
+      list_name: name.to_string(),
+      size: 1,
+      score: 1,
+      unscored: 0,
+      estimated: 1,
+    };
+    let old_decks = vec![deck("ToDo"), deck("Backlog"), deck("In Review")];
+    let aliases = vec![("backlog".to_string(), "Icebox".to_string())]
+      .into_iter()
+      .collect();
+    let names = ListNames::new(&aliases);
+
+    let matching = |name: &str| {
+      names
+        .find_matching(&deck(name), &old_decks)
+        .map(|deck| deck.list_name.clone())
+    };
+    assert_eq!(matching("To Do "), Some("ToDo".to_string()));
+    assert_eq!(matching("Icebox"), Some("Backlog".to_string()));
+    assert_eq!(matching("In Review"), Some("In Review".to_string()));
+    assert_eq!(matching("Done"), None);
+  }
+
+  #[test]
+  fn off_scale_finds_estimates_missing_from_the_scale() {
+    let card = |name: &str| CardSnapshot {
+      name: name.to_string(),
+      list_name: "To Do".to_string(),
+    };
+    let cards = vec![card("(5) on scale"), card("(11)[8] typo"), card("unscored")];
+
+    let found: Vec<(&str, i32)> = off_scale(&cards, &Scorer::default(), &[1, 2, 3, 5, 8, 13])
+      .into_iter()
+      .map(|(card, estimated)| (card.name.as_str(), estimated))
+      .collect();
+    assert_eq!(found, vec![("(11)[8] typo", 11)]);
+    assert!(off_scale(&cards, &Scorer::default(), &[]).is_empty());
+  }
+
+  #[test]
+  fn scorer_uses_custom_patterns() {
+    let scorer = Scorer::new(&ScorePatterns {
+      estimate: r"SP:(?P<points>\d+)".to_string(),
+      correction: r"\{(?P<points>\d+)\}".to_string(),
+    })
+    .unwrap();
+
+    assert_eq!(
+      scorer.score("SP:5 Login page {8}"),
+      Some(Score {
+        estimated: Some(5),
+        correction: Some(8),
+      })
+    );
+    assert_eq!(scorer.score("[3] Login page"), None);
+    // Points from a field are still read
+    assert_eq!(scorer.score("(3) Login page").unwrap().estimated, Some(3));
+    assert_eq!(scorer.tally(vec!["SP:5 a", "SP:2 b {4}", "c"]), (9, 1, 5));
+
+    assert!(Scorer::new(&ScorePatterns {
+      estimate: r"SP:(\d+)".to_string(),
+      ..ScorePatterns::default()
+    })
+    .is_err());
+  }
+
+  #[test]
+  fn group_decks_groups_by_prefix() {
+    let deck = |name: &str| Deck {
+      list_name: name.to_string(),
+      size: 1,
+      score: 1,
+      unscored: 0,
+      estimated: 1,
+    };
+    let decks = vec![
+      deck("Sprint 42 / To Do"),
+      deck("Backlog"),
+      deck("Sprint 43 / To Do"),
+      deck("Sprint 42 / Done"),
+    ];
+
+    let groups = group_decks(&decks, "/");
+    let names: Vec<(Option<String>, usize)> = groups
+      .iter()
+      .map(|(name, decks)| (name.clone(), decks.len()))
+      .collect();
+
+    assert_eq!(
+      names,
+      vec![
+        (Some("Sprint 42".to_string()), 2),
+        (None, 1),
+        (Some("Sprint 43".to_string()), 1)
+      ]
+    );
+  }
+
+  #[test]
+  fn delta_table_compares_group_subtotals() {
+    let deck = |name: &str, size: usize| Deck {
+      list_name: name.to_string(),
+      size,
+      score: size as i32,
+      unscored: 0,
+      estimated: size as i32,
+    };
+    let decks = vec![deck("Sprint 42 / To Do", 2), deck("Sprint 42 / Done", 3)];
+    let old_decks = vec![deck("Sprint 42 / To Do", 1), deck("Sprint 42 / Done", 1)];
+    let options = TableOptions {
+      group_separator: Some("/".to_string()),
+      ..TableOptions::default()
+    };
+
+    let table = delta_table(&decks, &old_decks, &options);
+    let subtotal = table.get_row(2).unwrap();
+    assert_eq!(
+      subtotal.get_cell(0).unwrap().get_content(),
+      "Sprint 42 subtotal"
+    );
+    assert_eq!(subtotal.get_cell(1).unwrap().get_content(), "5 (3)");
+    assert_eq!(table.len(), 4);
+  }
+
+  #[test]
+  fn it_writes_decks_as_csv_with_a_total() {
+    let deck = |name: &str, score: i32| Deck {
+      list_name: name.to_string(),
+      size: 2,
+      score,
+      unscored: 1,
+      estimated: score,
+    };
+    let decks = vec![
+      deck("To Do", 5),
+      deck("Archive", 8),
+      deck("Done, finally", 3),
+    ];
+
+    assert_eq!(
+      decks_as_csv(
+        &decks,
+        &ListFilter::new(Some("Archive"), false).unwrap(),
+        &CsvDialect::default()
+      ),
+      vec![
+        "List,Cards,Score,Estimated,Unscored",
+        "To Do,2,5,5,1",
+        "\"Done, finally\",2,3,3,1",
+        "TOTAL,4,8,8,2",
+      ]
+    );
+  }
 }
@@ -1,19 +1,20 @@
 /// A set of helper functions for dealing with generating burndown charts
 use card_counter::{
-  commands::burndown::BurndownOptions,
+  commands::burndown::{BurndownOptions, StateMatcher},
   database::{
     aws::Aws,
-    config::{trello_auth_from_env, Config},
+    config::{ChartStyle, Config, DateFormat, Timezone},
     Database, DateRange,
   },
   errors::*,
-  kanban::{trello::TrelloClient, Kanban},
+  kanban::Kanban,
+  score::ListFilter,
 };
 use chrono::prelude::*;
 use log::info;
 use std::{str::FromStr, string::ParseError};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct BurndownConfig {
   pub start: Option<String>,
   pub end: Option<String>,
@@ -38,26 +39,23 @@ impl BurndownConfig {
     BurndownConfig {
       start: Some(
         Utc
-          .timestamp(two_weeks_ago, 0)
+          .timestamp_opt(two_weeks_ago, 0)
+          .unwrap()
+          .format("%Y-%m-%d")
+          .to_string(),
+      ),
+      end: Some(
+        Utc
+          .timestamp_opt(today, 0)
+          .unwrap()
           .format("%Y-%m-%d")
           .to_string(),
       ),
-      end: Some(Utc.timestamp(today, 0).format("%Y-%m-%d").to_string()),
       board_id,
     }
   }
 }
 
-impl Default for BurndownConfig {
-  fn default() -> Self {
-    Self {
-      start: None,
-      end: None,
-      board_id: None,
-    }
-  }
-}
-
 impl FromStr for BurndownConfig {
   type Err = ParseError;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -83,22 +81,20 @@ impl FromStr for BurndownConfig {
 // character string, but we store the index in dynamodb as the board's
 // full id, a 24 character string. So we need to make sure we have the
 // full id to work.
-pub async fn get_full_board_id(board_id: String) -> Result<String> {
-  let client = TrelloClient {
-    client: reqwest::Client::new(),
-    auth: trello_auth_from_env().unwrap(),
-  };
-
+pub async fn get_full_board_id(kanban: &dyn Kanban, board_id: String) -> Result<String> {
   if board_id.len() == 24 {
     Ok(board_id)
   } else {
-    Ok(client.get_board(&board_id).await?.id)
+    Ok(kanban.get_board(&board_id).await?.id)
   }
 }
 
+/// Without a CONFIG_TABLE to look teams up in, everything has to come from the environment
 pub fn validate_env_vars() -> Result<()> {
-  if std::env::var("BUCKET_NAME").is_err() {
-    panic!("Unable to find env variable BUCKET_NAME");
+  if std::env::var("CONFIG_TABLE").is_err() && std::env::var("BUCKET_NAME").is_err() {
+    return Err(eyre!(
+      "Unable to find env variable CONFIG_TABLE or BUCKET_NAME"
+    ));
   }
   Ok(())
 }
@@ -113,14 +109,25 @@ pub async fn generate_burndown_chart(
   let range = DateRange::from_strs(start, end);
   let options = BurndownOptions {
     board_id: board_id.to_string(),
+    board_name: None,
     range,
     client,
-    filter: Some("NoBurn".into()),
+    filter: ListFilter::new(Some("NoBurn"), false)?,
+    states: StateMatcher::default(),
+    fill_missing_days: true,
+    timezone: Timezone::Utc,
   };
   info!("{:?}", options.board_id);
   info!("{:?}", options.range);
   let burndown = options.into_burndown().await?;
-  burndown.as_svg()
+  burndown.as_svg(
+    &DateFormat::default(),
+    &ChartStyle::default(),
+    &[],
+    None,
+    false,
+    false,
+  )
 }
 
 #[cfg(test)]
@@ -1,11 +1,13 @@
 mod burndown_helpers;
 mod slack_helpers;
+mod tenant_helpers;
 use burndown_helpers::*;
 use slack_helpers::*;
+use tenant_helpers::*;
 
 use card_counter::errors::*;
 
-use std::{collections::HashMap, str::FromStr};
+use std::str::FromStr;
 
 use aws_lambda_events::encodings::Body;
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
@@ -59,13 +61,25 @@ fn default_gateway_response(body: SlackBlock) -> ApiGatewayProxyResponse {
 
 /// you can invoke the lambda with a JSON payload, which is parsed using the CustomEvent struct.
 async fn my_handler(event: SlackCommand) -> Result<SlackBlock> {
+  let tenant = match tenant_for(event.team_id.as_deref(), event.channel_id.as_deref()).await? {
+    Some(tenant) => tenant,
+    None => {
+      return Ok(SlackBlock {
+        blocks: vec![SlackMessage::markdown(
+          "card-counter hasn't been set up for this workspace yet.".to_string(),
+        )],
+        response_type: None,
+      })
+    }
+  };
+
   // If we use the two_weeks method we should tell them what command we ran for them
   let mut using_two_weeks = false;
   let command = match event.text.trim().is_empty() {
     true => {
       using_two_weeks = true;
       Ok(BurndownConfig::for_two_weeks_ago(
-        std::env::var("DEFAULT_BOARD_ID").ok(),
+        tenant.default_board_id.clone(),
       ))
     }
     false => BurndownConfig::from_str(&event.text),
@@ -90,7 +104,17 @@ async fn my_handler(event: SlackCommand) -> Result<SlackBlock> {
   }
   let start = config.start.unwrap();
   let end = config.end.unwrap();
-  let board_id = get_full_board_id(config.board_id.unwrap()).await?;
+  let board_id = match tenant.resolve_board(&config.board_id.unwrap()) {
+    Ok(board_id) => board_id,
+    Err(e) => {
+      return Ok(SlackBlock {
+        blocks: vec![SlackMessage::markdown(e.to_string())],
+        response_type: None,
+      })
+    }
+  };
+  let kanban = tenant.kanban()?;
+  let board_id = get_full_board_id(kanban.as_ref(), board_id).await?;
   let chart: String = match generate_burndown_chart(&start, &end, &board_id).await {
     Ok(chart) => chart,
     Err(e) => {
@@ -99,10 +123,7 @@ async fn my_handler(event: SlackCommand) -> Result<SlackBlock> {
     }
   };
 
-  let bucket = match std::env::var("BUCKET_NAME") {
-    Ok(bucket) => bucket,
-    Err(_) => panic!("Unable to find env variable BUCKET_NAME"),
-  };
+  let bucket = tenant.bucket;
 
   let date_range = format!("{}_{}", &start, &end);
   upload_chart_to_s3(&chart, &bucket, &date_range).await?;
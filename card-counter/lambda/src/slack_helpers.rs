@@ -3,6 +3,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+// Mirrors everything Slack sends with a slash command, even though only some of it is used
+#[allow(unused)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct SlackCommand {
   pub token: Option<String>,
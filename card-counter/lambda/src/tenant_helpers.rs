@@ -0,0 +1,201 @@
+/// Helper functions for looking up the configuration for the Slack team and channel a command
+/// came from
+use card_counter::{
+  database::config::{trello_auth_from_env, Config, KanbanBoard},
+  errors::*,
+  kanban::{init_kanban_board_from_config, Kanban},
+};
+use rusoto_core::Region;
+use rusoto_dynamodb::{AttributeValue, DynamoDb, DynamoDbClient, GetItemInput};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// The channel_id used for a team's fallback configuration
+pub const ANY_CHANNEL: &str = "*";
+
+/// The lambda is only allowed to write to buckets starting with TENANT_BUCKET_PREFIX, see
+/// template.yaml
+const TENANT_BUCKET_PREFIX: &str = "TENANT_BUCKET_PREFIX";
+
+/// Everything the lambda needs to answer a command for one Slack team, or one channel in a team.
+/// These are stored in the DynamoDB table named by CONFIG_TABLE, keyed on team_id and
+/// channel_id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantConfig {
+  pub team_id: String,
+  pub channel_id: String,
+  pub bucket: String,
+  pub default_board_id: Option<String>,
+  // Short names for boards, ex: "web" -> "3em95wSl". When any are given only these boards can
+  // be charted from the team
+  #[serde(default)]
+  pub boards: HashMap<String, String>,
+  pub kanban: KanbanBoard,
+}
+
+impl TenantConfig {
+  /// Builds the configuration for a single team from the TRELLO_API_KEY, TRELLO_API_TOKEN,
+  /// BUCKET_NAME, and DEFAULT_BOARD_ID environment variables
+  pub fn from_env() -> Result<TenantConfig> {
+    Ok(TenantConfig {
+      team_id: ANY_CHANNEL.to_string(),
+      channel_id: ANY_CHANNEL.to_string(),
+      bucket: std::env::var("BUCKET_NAME")
+        .wrap_err_with(|| "Unable to find env variable BUCKET_NAME")?,
+      default_board_id: std::env::var("DEFAULT_BOARD_ID").ok(),
+      boards: HashMap::new(),
+      kanban: KanbanBoard::Trello(trello_auth_from_env()?),
+    })
+  }
+
+  /// Finds the configuration for a channel, falling back to the configuration for its team when
+  /// the channel doesn't have its own.
+  pub async fn lookup(
+    client: &DynamoDbClient,
+    table_name: &str,
+    team_id: &str,
+    channel_id: Option<&str>,
+  ) -> Result<Option<TenantConfig>> {
+    if let Some(channel_id) = channel_id {
+      if let Some(config) = get_tenant(client, table_name, team_id, channel_id).await? {
+        return Ok(Some(config));
+      }
+    }
+    get_tenant(client, table_name, team_id, ANY_CHANNEL).await
+  }
+
+  /// Turns what the user typed into a board id, resolving any of the team's short names. Boards
+  /// the team hasn't listed are refused when it has listed any.
+  pub fn resolve_board(&self, board: &str) -> Result<String> {
+    if let Some(board_id) = self.boards.get(board) {
+      return Ok(board_id.clone());
+    }
+    if self.boards.is_empty() || self.boards.values().any(|board_id| board_id == board) {
+      Ok(board.to_string())
+    } else {
+      Err(eyre!("Board {} isn't available in this channel", board))
+    }
+  }
+
+  /// Errors when the team's bucket falls outside of the buckets the lambda is allowed to write to
+  pub fn check_bucket(&self, prefix: &str) -> Result<()> {
+    if self.bucket.starts_with(prefix) {
+      Ok(())
+    } else {
+      Err(eyre!(
+        "The bucket for team {} needs to start with {}",
+        self.team_id,
+        prefix
+      ))
+    }
+  }
+
+  pub fn kanban(&self) -> Result<Box<dyn Kanban>> {
+    init_kanban_board_from_config(&Config {
+      kanban: self.kanban.clone(),
+      ..Config::default()
+    })
+  }
+}
+
+async fn get_tenant(
+  client: &DynamoDbClient,
+  table_name: &str,
+  team_id: &str,
+  channel_id: &str,
+) -> Result<Option<TenantConfig>> {
+  let mut key = HashMap::new();
+  key.insert(
+    "team_id".to_string(),
+    AttributeValue {
+      s: Some(team_id.to_string()),
+      ..Default::default()
+    },
+  );
+  key.insert(
+    "channel_id".to_string(),
+    AttributeValue {
+      s: Some(channel_id.to_string()),
+      ..Default::default()
+    },
+  );
+
+  let item = client
+    .get_item(GetItemInput {
+      table_name: table_name.to_string(),
+      key,
+      ..Default::default()
+    })
+    .await
+    .wrap_err_with(|| format!("Unable to read configuration for team {}", team_id))?
+    .item;
+
+  item
+    .map(|item| {
+      serde_dynamodb::from_hashmap(item)
+        .wrap_err_with(|| format!("Unable to parse configuration for team {}", team_id))
+    })
+    .transpose()
+}
+
+/// Looks up the configuration for the team and channel a command came from. When CONFIG_TABLE
+/// isn't set the lambda serves a single team configured through environment variables.
+pub async fn tenant_for(
+  team_id: Option<&str>,
+  channel_id: Option<&str>,
+) -> Result<Option<TenantConfig>> {
+  let table_name = match std::env::var("CONFIG_TABLE") {
+    Ok(table_name) => table_name,
+    Err(_) => return TenantConfig::from_env().map(Some),
+  };
+
+  let team_id = match team_id {
+    Some(team_id) => team_id,
+    None => return Ok(None),
+  };
+  let client = DynamoDbClient::new(Region::default());
+  let tenant = TenantConfig::lookup(&client, &table_name, team_id, channel_id).await?;
+  if let (Some(tenant), Ok(prefix)) = (&tenant, std::env::var(TENANT_BUCKET_PREFIX)) {
+    tenant.check_bucket(&prefix)?;
+  }
+  Ok(tenant)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use card_counter::database::config::JiraAuth;
+
+  #[test]
+  fn it_only_allows_a_teams_boards() {
+    let mut boards = HashMap::new();
+    boards.insert("web".to_string(), "3em95wSl".to_string());
+    let config = TenantConfig {
+      team_id: "T1".to_string(),
+      channel_id: ANY_CHANNEL.to_string(),
+      bucket: "charts".to_string(),
+      default_board_id: None,
+      boards,
+      kanban: KanbanBoard::Jira(JiraAuth {
+        username: "user".to_string(),
+        api_token: "token".to_string(),
+        url: "https://example.atlassian.net".to_string(),
+        ..JiraAuth::default()
+      }),
+    };
+
+    assert_eq!(config.resolve_board("web").unwrap(), "3em95wSl");
+    assert_eq!(config.resolve_board("3em95wSl").unwrap(), "3em95wSl");
+    assert!(config.resolve_board("someone-elses").is_err());
+
+    let config = TenantConfig {
+      boards: HashMap::new(),
+      ..config
+    };
+    assert_eq!(config.resolve_board("anything").unwrap(), "anything");
+
+    assert!(config.check_bucket("card-counter-").is_err());
+    assert!(config.check_bucket("char").is_ok());
+  }
+}